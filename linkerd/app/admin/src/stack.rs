@@ -96,7 +96,11 @@ impl Config {
         let admin = svc::stack(move |_| admin.clone())
             .push(metrics.proxy.http_endpoint.to_layer::<classify::Response, _, Permitted>())
             .push_map_target(|(permit, http)| Permitted { permit, http })
-            .push(inbound::policy::NewHttpPolicy::layer(metrics.http_authz.clone()))
+            // External authorization is not supported on the admin server.
+            .push(inbound::policy::NewHttpPolicy::layer(
+                metrics.http_authz.clone(),
+                Default::default(),
+            ))
             .push(Rescue::layer())
             .push_on_service(http::BoxResponse::layer())
             .unlift_new()
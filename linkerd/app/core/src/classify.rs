@@ -2,7 +2,10 @@ use crate::profiles;
 pub use classify::gate;
 use linkerd_error::Error;
 use linkerd_proxy_client_policy as client_policy;
-use linkerd_proxy_http::{classify, HasH2Reason, ResponseTimeoutError};
+use linkerd_proxy_http::{
+    classify, DeadlineExceededError, HasH2Reason, ResponseStreamTimeoutError, ResponseTimeoutError,
+    StreamIdleTimeoutError,
+};
 use std::borrow::Cow;
 use tonic as grpc;
 use tracing::trace;
@@ -43,6 +46,9 @@ pub enum Class {
     Http(Result<http::StatusCode>),
     Grpc(Result<grpc::Code>),
     Error(Cow<'static, str>),
+    /// Classifies an opaque connection, which fails if it could not be
+    /// established or encountered an error before any data was received.
+    Opaque(Result<()>),
 }
 
 // === impl Request ===
@@ -157,7 +163,11 @@ impl classify::ClassifyResponse for Response {
     }
 
     fn error(self, err: &Error) -> Self::Class {
-        let msg = if err.is::<ResponseTimeoutError>() {
+        let msg = if err.is::<ResponseTimeoutError>()
+            || err.is::<ResponseStreamTimeoutError>()
+            || err.is::<DeadlineExceededError>()
+            || err.is::<StreamIdleTimeoutError>()
+        {
             "timeout".into()
         } else {
             h2_error(err).into()
@@ -240,7 +250,7 @@ impl Class {
     pub fn is_failure(&self) -> bool {
         matches!(
             self,
-            Class::Http(Err(_)) | Class::Grpc(Err(_)) | Class::Error(_),
+            Class::Http(Err(_)) | Class::Grpc(Err(_)) | Class::Error(_) | Class::Opaque(Err(_)),
         )
     }
 }
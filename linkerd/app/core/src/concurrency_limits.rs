@@ -0,0 +1,154 @@
+//! Shares adaptive concurrency limits between the services for each target.
+
+use crate::{
+    metrics::{FmtLabels, Gauge, Metric},
+    svc::{
+        self, AdaptiveLimit, AdaptiveLimitConfig, NewAdaptiveConcurrencyLimit, WeakAdaptiveLimit,
+    },
+};
+use parking_lot::Mutex;
+use std::{collections::HashMap, fmt, hash::Hash, sync::Arc};
+
+/// Holds an adaptive concurrency limit for each `K`-typed key, so that all of
+/// the services for a key share the same limit.
+///
+/// Limits are only held weakly: a key's limit is dropped once none of its
+/// services remain, so that limits do not accumulate as targets come and go.
+#[derive(Debug)]
+pub struct AdaptiveLimits<K>(Arc<Mutex<HashMap<K, WeakAdaptiveLimit>>>);
+
+/// Extracts the shared [`AdaptiveLimit`] for a target's key.
+#[derive(Clone, Debug)]
+pub struct ExtractLimit<K, X> {
+    config: AdaptiveLimitConfig,
+    extract: X,
+    limits: AdaptiveLimits<K>,
+}
+
+// === impl AdaptiveLimits ===
+
+impl<K> Default for AdaptiveLimits<K> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+
+impl<K> Clone for AdaptiveLimits<K> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<K: Hash + Eq> AdaptiveLimits<K> {
+    /// Returns a layer that limits requests to each target with an adaptive
+    /// concurrency limit, if one is configured. Targets are keyed by their
+    /// `K`-typed parameter.
+    pub fn to_layer<N>(
+        &self,
+        config: Option<AdaptiveLimitConfig>,
+    ) -> impl svc::layer::Layer<
+        N,
+        Service = svc::Either<NewAdaptiveConcurrencyLimit<ExtractLimit<K, ()>, N>, N>,
+    > + Clone {
+        self.to_layer_via(config, ())
+    }
+
+    /// Like [`AdaptiveLimits::to_layer`], but targets are keyed by `extract`.
+    pub fn to_layer_via<N, X: Clone>(
+        &self,
+        config: Option<AdaptiveLimitConfig>,
+        extract: X,
+    ) -> impl svc::layer::Layer<
+        N,
+        Service = svc::Either<NewAdaptiveConcurrencyLimit<ExtractLimit<K, X>, N>, N>,
+    > + Clone {
+        let limits = self.clone();
+        svc::layer::mk(move |inner| match config {
+            Some(config) => svc::Either::A(NewAdaptiveConcurrencyLimit::new(
+                ExtractLimit {
+                    config,
+                    extract: extract.clone(),
+                    limits: limits.clone(),
+                },
+                inner,
+            )),
+            None => svc::Either::B(inner),
+        })
+    }
+
+    /// Formats the current limit of each key as a gauge.
+    pub fn fmt_metric(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        metric: Metric<'_, &str, Gauge>,
+    ) -> fmt::Result
+    where
+        K: FmtLabels,
+    {
+        let mut limits = self.0.lock();
+        limits.retain(|_, limit| limit.upgrade().is_some());
+        if limits.is_empty() {
+            return Ok(());
+        }
+
+        metric.fmt_help(f)?;
+        for (key, limit) in limits.iter() {
+            if let Some(limit) = limit.upgrade() {
+                let gauge = Gauge::from(limit.limit() as u64);
+                metric.fmt_metric_labeled(f, &gauge, key)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// === impl ExtractLimit ===
+
+impl<K, X, T> svc::ExtractParam<AdaptiveLimit, T> for ExtractLimit<K, X>
+where
+    K: Hash + Eq,
+    X: svc::ExtractParam<K, T>,
+{
+    fn extract_param(&self, target: &T) -> AdaptiveLimit {
+        let key = self.extract.extract_param(target);
+        let mut limits = self.limits.0.lock();
+        if let Some(limit) = limits.get(&key).and_then(WeakAdaptiveLimit::upgrade) {
+            return limit;
+        }
+
+        // Drop the keys whose services have all been dropped before adding a
+        // new limit.
+        limits.retain(|_, limit| limit.upgrade().is_some());
+        let limit = AdaptiveLimit::new(self.config);
+        limits.insert(key, limit.downgrade());
+        limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use svc::ExtractParam;
+
+    #[test]
+    fn shares_limits_until_dropped() {
+        let limits = AdaptiveLimits::<&'static str>::default();
+        let extract = ExtractLimit {
+            config: AdaptiveLimitConfig::default(),
+            extract: |key: &&'static str| *key,
+            limits: limits.clone(),
+        };
+
+        let a0 = extract.extract_param(&"a");
+        let a1 = extract.extract_param(&"a");
+        let _b = extract.extract_param(&"b");
+        assert_eq!(limits.0.lock().len(), 2);
+
+        // Once all of a key's services are dropped, its limit is evicted.
+        drop((a0, a1));
+        let _c = extract.extract_param(&"c");
+        let mut keys = limits.0.lock().keys().copied().collect::<Vec<_>>();
+        keys.sort_unstable();
+        assert_eq!(keys, ["b", "c"]);
+    }
+}
@@ -1,7 +1,7 @@
 pub use crate::exp_backoff::ExponentialBackoff;
 use crate::{
     proxy::http::{self, h1, h2},
-    svc::{queue, CloneParam, ExtractParam, Param},
+    svc::{queue, AdaptiveLimitConfig, CloneParam, ExtractParam, Param},
     transport::{Keepalive, ListenAddr},
 };
 use std::time::Duration;
@@ -27,6 +27,11 @@ pub struct ProxyConfig {
     pub server: ServerConfig,
     pub connect: ConnectConfig,
     pub max_in_flight_requests: usize,
+
+    /// When set, requests are additionally limited by an adaptive concurrency
+    /// limit that is bounded by `max_in_flight_requests`.
+    pub adaptive_concurrency_limit: Option<AdaptiveLimitConfig>,
+
     pub detect_protocol_timeout: Duration,
 }
 
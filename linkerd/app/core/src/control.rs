@@ -49,6 +49,12 @@ impl svc::Param<http::balance::EwmaConfig> for ControlAddr {
     }
 }
 
+impl svc::Param<Option<http::balance::SlowStartConfig>> for ControlAddr {
+    fn param(&self) -> Option<http::balance::SlowStartConfig> {
+        None
+    }
+}
+
 impl fmt::Display for ControlAddr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Display::fmt(&self.addr, f)
@@ -127,6 +127,16 @@ impl SyntheticHttpResponse {
         }
     }
 
+    pub fn deadline_exceeded(msg: impl ToString) -> Self {
+        Self {
+            close_connection: false,
+            http_status: http::StatusCode::GATEWAY_TIMEOUT,
+            grpc_status: tonic::Code::DeadlineExceeded,
+            message: Cow::Owned(msg.to_string()),
+            location: None,
+        }
+    }
+
     pub fn unavailable(msg: impl ToString) -> Self {
         Self {
             close_connection: true,
@@ -177,6 +187,16 @@ impl SyntheticHttpResponse {
         }
     }
 
+    pub fn rate_limited(msg: impl ToString) -> Self {
+        Self {
+            http_status: http::StatusCode::TOO_MANY_REQUESTS,
+            grpc_status: tonic::Code::ResourceExhausted,
+            close_connection: false,
+            message: Cow::Owned(msg.to_string()),
+            location: None,
+        }
+    }
+
     pub fn redirect(http_status: http::StatusCode, location: &http::Uri) -> Self {
         Self {
             http_status,
@@ -14,6 +14,7 @@
 use thiserror::Error;
 
 pub mod classify;
+pub mod concurrency_limits;
 pub mod config;
 pub mod control;
 pub mod disco_cache;
@@ -33,6 +34,7 @@ pub use linkerd_addr::{self as addr, Addr, AddrMatch, IpMatch, NameAddr, NameMat
 pub use linkerd_conditional::Conditional;
 pub use linkerd_detect as detect;
 pub use linkerd_dns;
+pub use linkerd_errno as errno;
 pub use linkerd_error::{cause_ref, is_caused_by, Error, Infallible, Recover, Result};
 pub use linkerd_exp_backoff as exp_backoff;
 pub use linkerd_http_metrics as http_metrics;
@@ -431,6 +431,12 @@ impl FmtLabels for Class {
                 "classification=\"failure\",grpc_status=\"\",error=\"{}\"",
                 msg
             ),
+
+            Class::Opaque(res) => write!(
+                f,
+                "classification=\"{}\",grpc_status=\"\",error=\"\"",
+                class(res.is_ok())
+            ),
         }
     }
 }
@@ -77,7 +77,7 @@ where
     #[inline]
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         // If the client ID is the same as the gateway's, then we're in a loop.
-        if *self.client_id == *self.local_id {
+        if self.client_id.dns_name() == Some(&*self.local_id) {
             return Poll::Ready(Err(GatewayLoop.into()));
         }
 
@@ -131,6 +131,7 @@ async fn upgraded_request_remains_relative_form() {
                         }),
                     }]))]),
                 },
+                authz_mode: Default::default(),
             };
             let (policy, tx) = inbound::policy::AllowPolicy::for_test(self.param(), policy);
             tokio::spawn(async move {
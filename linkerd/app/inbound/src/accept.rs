@@ -4,7 +4,10 @@ use crate::{
 };
 use linkerd_app_core::{
     io, svc,
-    transport::addrs::{ClientAddr, OrigDstAddr, Remote},
+    transport::{
+        addrs::{ClientAddr, OrigDstAddr, Remote},
+        proxy_protocol,
+    },
     Error,
 };
 use std::fmt::Debug;
@@ -78,6 +81,31 @@ impl<N> Inbound<N> {
                 .push(svc::ArcNewService::layer())
         })
     }
+
+    /// Builds a stack that reads PROXY protocol headers from connections on
+    /// the configured ports, so that the client and destination addresses they
+    /// describe are used for authorization, routing, and telemetry.
+    pub(crate) fn push_proxy_protocol<T, I, NSvc>(self) -> Inbound<svc::ArcNewTcp<T, I>>
+    where
+        T: svc::Param<Remote<ClientAddr>> + svc::Param<OrigDstAddr>,
+        T: Clone + Send + Sync + 'static,
+        I: io::AsyncRead + Send + Unpin + 'static,
+        N: svc::NewService<proxy_protocol::Addrs<T>, Service = NSvc>,
+        N: Clone + Send + Sync + Unpin + 'static,
+        NSvc: svc::Service<proxy_protocol::ProxyProtocolIo<I>, Response = ()>,
+        NSvc: Send + 'static,
+        NSvc::Error: Into<Error>,
+        NSvc::Future: Send,
+    {
+        self.map_stack(|cfg, _, accept| {
+            accept
+                .push(proxy_protocol::NewProxyProtocol::layer(
+                    cfg.proxy_protocol.clone(),
+                ))
+                .push_on_service(svc::BoxService::layer())
+                .push(svc::ArcNewService::layer())
+        })
+    }
 }
 
 // === impl Accept ===
@@ -142,6 +170,7 @@ mod tests {
                     kind: "server".into(),
                     name: "testsrv".into(),
                 }),
+                authz_mode: Default::default(),
             },
             None,
         );
@@ -405,7 +405,7 @@ impl svc::Param<Option<identity::Name>> for Http {
                 tls::ServerTls::Established {
                     client_id: Some(id),
                     ..
-                } => Some(id.clone().0),
+                } => id.dns_name().cloned(),
                 _ => None,
             })
     }
@@ -230,6 +230,7 @@ pub mod fuzz {
                         kind: "server".into(),
                         name: "testsrv".into(),
                     }),
+                    authz_mode: Default::default(),
                 },
             );
             policy
@@ -255,7 +255,10 @@ impl<C> Inbound<C> {
                     LogicalPerRequest::from((permit.clone(), t.clone()))
                 }))
                 .check_new_service::<(policy::HttpRoutePermit, T), http::Request<http::BoxBody>>()
-                .push(policy::NewHttpPolicy::layer(rt.metrics.http_authz.clone()))
+                .push(policy::NewHttpPolicy::layer(
+                    rt.metrics.http_authz.clone(),
+                    rt.ext_authz.clone(),
+                ))
                 // Used by tap.
                 .push_http_insert_target::<tls::ConditionalServerTls>()
                 .push_http_insert_target::<Remote<ClientAddr>>()
@@ -66,6 +66,7 @@ impl<H> Inbound<H> {
         self.map_stack(|config, rt, http| {
             let ProxyConfig {
                 max_in_flight_requests,
+                adaptive_concurrency_limit,
                 ..
             } = config.proxy;
 
@@ -91,6 +92,13 @@ impl<H> Inbound<H> {
                         // limit is reached.
                         .push(svc::LoadShed::layer()),
                 )
+                // Limit the number of in-flight requests to each server with
+                // an adaptive limit, when configured, shedding any excess.
+                .push(
+                    rt.metrics
+                        .http_concurrency_limits
+                        .to_layer(adaptive_concurrency_limit),
+                )
                 .push(svc::NewMapErr::layer_from_target::<ServerError, _>())
                 .push_on_service(svc::MapErr::layer_boxed())
                 .push(rt.metrics.http_errors.to_layer())
@@ -209,10 +217,45 @@ impl errors::HttpRescue<Error> for ServerRescue {
             return Ok(errors::SyntheticHttpResponse::permission_denied(error));
         }
 
+        if errors::is_caused_by::<policy::HttpRouteUnauthenticated>(&*error) {
+            return Ok(errors::SyntheticHttpResponse::unauthenticated(error));
+        }
+
+        if errors::is_caused_by::<policy::HttpRouteAuthnUnavailable>(&*error) {
+            return Ok(errors::SyntheticHttpResponse::unavailable(error));
+        }
+
+        if errors::is_caused_by::<policy::HttpRouteRateLimited>(&*error) {
+            return Ok(errors::SyntheticHttpResponse::rate_limited(error));
+        }
+
+        if let Some(policy::HttpRouteExtAuthzDenied { status }) =
+            errors::cause_ref::<policy::HttpRouteExtAuthzDenied>(&*error)
+        {
+            if *status == http::StatusCode::FORBIDDEN {
+                return Ok(errors::SyntheticHttpResponse::permission_denied(error));
+            }
+            return Ok(errors::SyntheticHttpResponse::response(
+                *status,
+                error.to_string(),
+            ));
+        }
+
+        // When the authorization service fails and the route does not permit
+        // requests on failure, requests fail as unavailable rather than being
+        // denied, since the client's request may well be authorized.
+        if errors::is_caused_by::<policy::HttpRouteExtAuthzFailed>(&*error) {
+            return Ok(errors::SyntheticHttpResponse::unavailable(error));
+        }
+
         if errors::is_caused_by::<policy::HttpRouteInvalidRedirect>(&*error) {
             tracing::warn!(%error);
             return Ok(errors::SyntheticHttpResponse::unexpected_error());
         }
+        if errors::is_caused_by::<policy::HttpRouteInvalidUrlRewrite>(&*error) {
+            tracing::warn!(%error);
+            return Ok(errors::SyntheticHttpResponse::unexpected_error());
+        }
         if let Some(policy::HttpRouteRedirect { status, location }) =
             errors::cause_ref::<policy::HttpRouteRedirect>(&*error)
         {
@@ -632,6 +632,7 @@ impl svc::Param<policy::AllowPolicy> for Target {
                     kind: "server".into(),
                     name: "testsrv".into(),
                 }),
+                authz_mode: Default::default(),
             },
         );
         policy
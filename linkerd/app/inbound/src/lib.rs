@@ -43,6 +43,10 @@ pub struct Config {
     pub policy: policy::Config,
     pub allowed_ips: transport::AllowIps,
 
+    /// Configures server policy features that the policy API cannot yet
+    /// express.
+    pub local_policy: policy::LocalConfig,
+
     /// Configures the timeout after which the proxy will revert to skipping
     /// service profile routing instrumentation.
     pub profile_skip_timeout: Duration,
@@ -53,6 +57,10 @@ pub struct Config {
 
     /// Configures how HTTP requests are buffered *for each inbound port*.
     pub http_request_queue: QueueConfig,
+
+    /// Configures the ports on which connections are expected to begin with a
+    /// PROXY protocol header.
+    pub proxy_protocol: transport::proxy_protocol::Config,
 }
 
 #[derive(Clone)]
@@ -69,6 +77,7 @@ struct Runtime {
     tap: tap::Registry,
     span_sink: OpenCensusSink,
     drain: drain::Watch,
+    ext_authz: policy::ExtAuthzClient,
 }
 
 /// Indicates the name to be used to route gateway connections.
@@ -116,7 +125,10 @@ impl<S> Inbound<S> {
     pub fn authorize_http<N>(
         &self,
     ) -> impl svc::layer::Layer<N, Service = policy::NewHttpPolicy<N>> + Clone {
-        policy::NewHttpPolicy::layer(self.runtime.metrics.http_authz.clone())
+        policy::NewHttpPolicy::layer(
+            self.runtime.metrics.http_authz.clone(),
+            self.runtime.ext_authz.clone(),
+        )
     }
 
     /// A helper for gateways to instrument policy checks.
@@ -156,6 +168,7 @@ impl Inbound<()> {
             tap: runtime.tap,
             span_sink: runtime.span_sink,
             drain: runtime.drain,
+            ext_authz: Default::default(),
         };
         Self {
             config,
@@ -164,6 +177,14 @@ impl Inbound<()> {
         }
     }
 
+    /// Configures the client used by routes' external authorization filters.
+    ///
+    /// If no client is configured, external authorization checks fail.
+    pub fn with_ext_authz(mut self, client: policy::ExtAuthzClient) -> Self {
+        self.runtime.ext_authz = client;
+        self
+    }
+
     #[cfg(any(test, feature = "test-util"))]
     pub fn for_test() -> (Self, drain::Signal) {
         let (rt, drain) = test_util::runtime();
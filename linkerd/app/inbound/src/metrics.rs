@@ -9,6 +9,7 @@
 //! `DashMap` as we migrate other metrics registries.
 
 pub(crate) mod authz;
+pub(crate) mod concurrency;
 pub(crate) mod error;
 
 pub use linkerd_app_core::metrics::*;
@@ -22,6 +23,8 @@ pub struct InboundMetrics {
     pub(crate) tcp_authz: authz::TcpAuthzMetrics,
     pub tcp_errors: error::TcpErrorMetrics,
 
+    pub(crate) http_concurrency_limits: concurrency::HttpConcurrencyLimits,
+
     /// Holds metrics that are common to both inbound and outbound proxies. These metrics are
     /// reported separately
     pub proxy: Proxy,
@@ -34,6 +37,7 @@ impl InboundMetrics {
             http_errors: error::HttpErrorMetrics::default(),
             tcp_authz: authz::TcpAuthzMetrics::default(),
             tcp_errors: error::TcpErrorMetrics::default(),
+            http_concurrency_limits: concurrency::HttpConcurrencyLimits::default(),
             proxy,
         }
     }
@@ -47,6 +51,8 @@ impl FmtMetrics for InboundMetrics {
         self.tcp_authz.fmt_metrics(f)?;
         self.tcp_errors.fmt_metrics(f)?;
 
+        self.http_concurrency_limits.fmt_metrics(f)?;
+
         // XXX: Proxy metrics are reported elsewhere.
 
         Ok(())
@@ -17,9 +17,15 @@ metrics! {
     inbound_http_authz_deny_total: Counter {
         "The total number of inbound HTTP requests that could not be processed due to a proxy error."
     },
+    inbound_http_authz_audit_total: Counter {
+        "The total number of inbound HTTP requests that would have been denied but were permitted by an audit policy"
+    },
     inbound_http_route_not_found_total: Counter {
         "The total number of inbound HTTP requests that could not be associated with a route"
     },
+    inbound_http_local_rate_limit_total: Counter {
+        "The total number of inbound HTTP requests that were refused by a local rate limit"
+    },
 
     inbound_tcp_authz_allow_total: Counter {
         "The total number of inbound TCP connections that were authorized"
@@ -27,6 +33,9 @@ metrics! {
     inbound_tcp_authz_deny_total: Counter {
         "The total number of inbound TCP connections that were denied"
     },
+    inbound_tcp_authz_audit_total: Counter {
+        "The total number of inbound TCP connections that would have been denied but were permitted by an audit policy"
+    },
     inbound_tcp_authz_terminate_total: Counter {
         "The total number of inbound TCP connections that were terminated due to an authorization change"
     }
@@ -42,13 +51,16 @@ pub(crate) struct TcpAuthzMetrics(Arc<TcpInner>);
 struct HttpInner {
     allow: Mutex<HashMap<RouteAuthzKey, Counter>>,
     deny: Mutex<HashMap<RouteKey, Counter>>,
+    audit: Mutex<HashMap<RouteKey, Counter>>,
     route_not_found: Mutex<HashMap<ServerKey, Counter>>,
+    rate_limit: Mutex<HashMap<RouteAuthzKey, Counter>>,
 }
 
 #[derive(Debug, Default)]
 struct TcpInner {
     allow: Mutex<HashMap<ServerAuthzKey, Counter>>,
     deny: Mutex<HashMap<ServerKey, Counter>>,
+    audit: Mutex<HashMap<ServerKey, Counter>>,
     terminate: Mutex<HashMap<ServerKey, Counter>>,
 }
 
@@ -98,6 +110,24 @@ impl HttpAuthzMetrics {
             .or_default()
             .incr();
     }
+
+    pub fn audit(&self, labels: RouteLabels, dst: OrigDstAddr, tls: tls::ConditionalServerTls) {
+        self.0
+            .audit
+            .lock()
+            .entry(RouteKey::new(labels, dst, tls))
+            .or_default()
+            .incr();
+    }
+
+    pub fn rate_limit(&self, permit: &HttpRoutePermit, tls: tls::ConditionalServerTls) {
+        self.0
+            .rate_limit
+            .lock()
+            .entry(RouteAuthzKey::from_permit(permit, tls))
+            .or_default()
+            .incr();
+    }
 }
 
 impl FmtMetrics for HttpAuthzMetrics {
@@ -127,6 +157,19 @@ impl FmtMetrics for HttpAuthzMetrics {
         }
         drop(deny);
 
+        let audit = self.0.audit.lock();
+        if !audit.is_empty() {
+            inbound_http_authz_audit_total.fmt_help(f)?;
+            inbound_http_authz_audit_total.fmt_scopes(
+                f,
+                audit
+                    .iter()
+                    .map(|(k, c)| ((k.target, (&k.labels, TlsAccept(&k.tls))), c)),
+                |c| c,
+            )?;
+        }
+        drop(audit);
+
         let route_not_found = self.0.route_not_found.lock();
         if !route_not_found.is_empty() {
             inbound_http_route_not_found_total.fmt_help(f)?;
@@ -140,6 +183,19 @@ impl FmtMetrics for HttpAuthzMetrics {
         }
         drop(route_not_found);
 
+        let rate_limit = self.0.rate_limit.lock();
+        if !rate_limit.is_empty() {
+            inbound_http_local_rate_limit_total.fmt_help(f)?;
+            inbound_http_local_rate_limit_total.fmt_scopes(
+                f,
+                rate_limit
+                    .iter()
+                    .map(|(k, c)| ((k.target, (&k.labels, TlsAccept(&k.tls))), c)),
+                |c| c,
+            )?;
+        }
+        drop(rate_limit);
+
         Ok(())
     }
 }
@@ -165,6 +221,15 @@ impl TcpAuthzMetrics {
             .incr();
     }
 
+    pub fn audit(&self, policy: &AllowPolicy, tls: tls::ConditionalServerTls) {
+        self.0
+            .audit
+            .lock()
+            .entry(ServerKey::from_policy(policy, tls))
+            .or_default()
+            .incr();
+    }
+
     pub fn terminate(&self, policy: &AllowPolicy, tls: tls::ConditionalServerTls) {
         self.0
             .terminate
@@ -191,6 +256,13 @@ impl FmtMetrics for TcpAuthzMetrics {
         }
         drop(deny);
 
+        let audit = self.0.audit.lock();
+        if !audit.is_empty() {
+            inbound_tcp_authz_audit_total.fmt_help(f)?;
+            inbound_tcp_authz_audit_total.fmt_scopes(f, &*audit, |c| c)?;
+        }
+        drop(audit);
+
         let terminate = self.0.terminate.lock();
         if !terminate.is_empty() {
             inbound_tcp_authz_terminate_total.fmt_help(f)?;
@@ -0,0 +1,37 @@
+use linkerd_app_core::{
+    concurrency_limits::{AdaptiveLimits, ExtractLimit},
+    metrics::{metrics, FmtMetrics, Gauge, ServerLabel},
+    svc::{self, AdaptiveLimitConfig, NewAdaptiveConcurrencyLimit},
+};
+
+metrics! {
+    inbound_http_concurrency_limit: Gauge {
+        "The current adaptive concurrency limit of inbound HTTP requests for each server"
+    }
+}
+
+/// Holds the adaptive concurrency limit for each inbound server.
+#[derive(Clone, Debug, Default)]
+pub struct HttpConcurrencyLimits(AdaptiveLimits<ServerLabel>);
+
+// === impl HttpConcurrencyLimits ===
+
+impl HttpConcurrencyLimits {
+    /// Returns a layer that limits requests to each server with an adaptive
+    /// concurrency limit, if one is configured.
+    pub fn to_layer<N>(
+        &self,
+        config: Option<AdaptiveLimitConfig>,
+    ) -> impl svc::layer::Layer<
+        N,
+        Service = svc::Either<NewAdaptiveConcurrencyLimit<ExtractLimit<ServerLabel, ()>, N>, N>,
+    > + Clone {
+        self.0.to_layer(config)
+    }
+}
+
+impl FmtMetrics for HttpConcurrencyLimits {
+    fn fmt_metrics(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt_metric(f, inbound_http_concurrency_limit)
+    }
+}
@@ -3,7 +3,10 @@ mod tcp;
 
 pub(crate) use self::{http::HttpErrorMetrics, tcp::TcpErrorMetrics};
 use crate::{
-    policy::{HttpRouteNotFound, HttpRouteUnauthorized, ServerUnauthorized},
+    policy::{
+        HttpRouteExtAuthzDenied, HttpRouteNotFound, HttpRouteRateLimited, HttpRouteUnauthorized,
+        ServerUnauthorized,
+    },
     GatewayDomainInvalid, GatewayIdentityRequired, GatewayLoop,
 };
 use linkerd_app_core::{
@@ -34,6 +37,8 @@ impl ErrorKind {
         if err.is::<ServerUnauthorized>()
             || err.is::<HttpRouteUnauthorized>()
             || err.is::<HttpRouteNotFound>()
+            || err.is::<HttpRouteRateLimited>()
+            || err.is::<HttpRouteExtAuthzDenied>()
         {
             return None;
         }
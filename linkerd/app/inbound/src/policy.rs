@@ -2,6 +2,7 @@ mod api;
 mod config;
 pub mod defaults;
 mod http;
+mod local;
 mod store;
 mod tcp;
 
@@ -9,24 +10,30 @@ pub(crate) use self::store::Store;
 pub use self::{
     config::Config,
     http::{
-        HttpInvalidPolicy, HttpRouteInvalidRedirect, HttpRouteNotFound, HttpRouteRedirect,
+        ExtAuthzClient, HttpInvalidPolicy, HttpRouteAuthnUnavailable, HttpRouteExtAuthzDenied,
+        HttpRouteExtAuthzFailed, HttpRouteInvalidRedirect, HttpRouteInvalidUrlRewrite,
+        HttpRouteNotFound, HttpRouteRateLimited, HttpRouteRedirect, HttpRouteUnauthenticated,
         HttpRouteUnauthorized, NewHttpPolicy,
     },
+    local::{AuditConfig, LocalConfig, UrlRewriteConfig},
     tcp::NewTcpPolicy,
 };
 
 pub use linkerd_app_core::metrics::ServerLabel;
 use linkerd_app_core::{
+    identity as id,
     metrics::{RouteAuthzLabels, ServerAuthzLabels},
     tls,
     transport::{ClientAddr, OrigDstAddr, Remote},
 };
 use linkerd_idle_cache::Cached;
 pub use linkerd_proxy_server_policy::{
-    authz::Suffix,
+    authz::{JwtAuthentication, Suffix},
+    ext_authz::{ExtAuthz, FailureMode as ExtAuthzFailureMode},
     grpc::Route as GrpcRoute,
     http::{filter::Redirection, Route as HttpRoute},
-    route, Authentication, Authorization, Meta, Protocol, RoutePolicy, ServerPolicy,
+    route, Authentication, Authorization, AuthzMode, LocalRateLimit, Meta, Protocol, RateLimitKey,
+    RoutePolicy, ServerPolicy,
 };
 use std::sync::Arc;
 use thiserror::Error;
@@ -90,6 +97,7 @@ impl From<DefaultPolicy> for ServerPolicy {
             DefaultPolicy::Deny => ServerPolicy {
                 protocol: Protocol::Opaque(Arc::new([])),
                 meta: Meta::new_default("deny"),
+                authz_mode: AuthzMode::Enforce,
             },
         }
     }
@@ -150,12 +158,16 @@ impl AllowPolicy {
     }
 }
 
+fn is_permitted_network(authz: &Authorization, client_addr: Remote<ClientAddr>) -> bool {
+    authz.networks.iter().any(|n| n.contains(&client_addr.ip()))
+}
+
 fn is_authorized(
     authz: &Authorization,
     client_addr: Remote<ClientAddr>,
     tls: &tls::ConditionalServerTls,
 ) -> bool {
-    if !authz.networks.iter().any(|n| n.contains(&client_addr.ip())) {
+    if !is_permitted_network(authz, client_addr) {
         return false;
     }
 
@@ -172,15 +184,55 @@ fn is_authorized(
         Authentication::TlsAuthenticated {
             ref identities,
             ref suffixes,
+            ref spiffe,
         } => match tls {
             tls::ConditionalServerTls::Some(tls::ServerTls::Established {
                 client_id: Some(tls::server::ClientId(ref id)),
                 ..
             }) => {
-                identities.contains(id.as_str()) || suffixes.iter().any(|s| s.contains(id.as_str()))
+                if identities.contains(id.as_str()) {
+                    return true;
+                }
+                match id {
+                    id::Id::Dns(name) => suffixes.iter().any(|s| s.contains(name.as_str())),
+                    // Name suffixes do not apply to SPIFFE IDs, though SPIFFE
+                    // IDs are permitted by wildcard suffixes.
+                    id::Id::Spiffe(id) => {
+                        suffixes.iter().any(|s| s.is_wildcard())
+                            || spiffe
+                                .iter()
+                                .any(|m| m.contains(id.trust_domain(), id.path()))
+                    }
+                }
             }
             _ => false,
         },
+
+        // JWTs are validated for each HTTP request, so they never authorize
+        // connections.
+        Authentication::Jwt(_) => false,
+    }
+}
+
+/// Returns the metadata used to label requests and connections that are
+/// permitted by an audit policy rather than an authorization.
+fn audit_meta() -> Arc<Meta> {
+    Meta::new_default("audit")
+}
+
+/// Lists the names of authorizations that were considered for a request or
+/// connection that would have been denied.
+fn authz_names(authzs: &[Authorization]) -> Vec<&str> {
+    authzs.iter().map(|a| a.meta.name()).collect()
+}
+
+fn client_id(tls: &tls::ConditionalServerTls) -> Option<&id::Id> {
+    match tls {
+        tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+            client_id: Some(tls::server::ClientId(ref id)),
+            ..
+        }) => Some(id),
+        _ => None,
     }
 }
 
@@ -188,11 +240,19 @@ fn is_authorized(
 
 impl ServerPermit {
     fn new(dst: OrigDstAddr, server: &ServerPolicy, authz: &Authorization) -> Self {
+        Self::with_meta(dst, server, authz.meta.clone())
+    }
+
+    fn audit(dst: OrigDstAddr, server: &ServerPolicy) -> Self {
+        Self::with_meta(dst, server, audit_meta())
+    }
+
+    fn with_meta(dst: OrigDstAddr, server: &ServerPolicy, authz: Arc<Meta>) -> Self {
         Self {
             dst,
             protocol: server.protocol.clone(),
             labels: ServerAuthzLabels {
-                authz: authz.meta.clone(),
+                authz,
                 server: ServerLabel(server.meta.clone()),
             },
         }
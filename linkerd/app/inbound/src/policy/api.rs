@@ -1,3 +1,4 @@
+use super::LocalConfig;
 use futures::prelude::*;
 use linkerd2_proxy_api::inbound::{
     self as api, inbound_server_policies_client::InboundServerPoliciesClient as Client,
@@ -16,6 +17,7 @@ use std::{sync::Arc, time};
 pub(super) struct Api<S> {
     workload: Arc<str>,
     detect_timeout: time::Duration,
+    local: Arc<LocalConfig>,
     client: Client<S>,
 }
 
@@ -34,10 +36,16 @@ where
     S::ResponseBody:
         http::HttpBody<Data = tonic::codegen::Bytes, Error = Error> + Default + Send + 'static,
 {
-    pub(super) fn new(workload: Arc<str>, detect_timeout: time::Duration, client: S) -> Self {
+    pub(super) fn new(
+        workload: Arc<str>,
+        detect_timeout: time::Duration,
+        local: LocalConfig,
+        client: S,
+    ) -> Self {
         Self {
             workload,
             detect_timeout,
+            local: Arc::new(local),
             client: Client::new(client),
         }
     }
@@ -73,6 +81,7 @@ where
             workload: self.workload.as_ref().to_owned(),
         };
         let detect_timeout = self.detect_timeout;
+        let local = self.local.clone();
         let mut client = self.client.clone();
         Box::pin(async move {
             let rsp = client.watch_port(tonic::Request::new(req)).await?;
@@ -82,12 +91,15 @@ where
                         // If the server returned an invalid server policy, we
                         // default to using an invalid policy that causes all
                         // requests to report an internal error.
-                        let policy = ServerPolicy::try_from(up).unwrap_or_else(|error| {
-                            tracing::warn!(%error, "Server misconfigured");
-                            INVALID_POLICY
-                                .get_or_init(|| ServerPolicy::invalid(detect_timeout))
-                                .clone()
-                        });
+                        let policy = match ServerPolicy::try_from(up) {
+                            Ok(policy) => local.apply(policy),
+                            Err(error) => {
+                                tracing::warn!(%error, "Server misconfigured");
+                                INVALID_POLICY
+                                    .get_or_init(|| ServerPolicy::invalid(detect_timeout))
+                                    .clone()
+                            }
+                        };
                         tracing::debug!(?policy);
                         policy
                     })
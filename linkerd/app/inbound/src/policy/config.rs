@@ -1,4 +1,4 @@
-use super::{api::Api, DefaultPolicy, GetPolicy, Protocol, ServerPolicy, Store};
+use super::{api::Api, DefaultPolicy, GetPolicy, LocalConfig, Protocol, ServerPolicy, Store};
 use linkerd_app_core::{exp_backoff::ExponentialBackoff, proxy::http, Error};
 use rangemap::RangeInclusiveSet;
 use std::{
@@ -36,6 +36,7 @@ impl Config {
         workload: Arc<str>,
         client: C,
         backoff: ExponentialBackoff,
+        local: LocalConfig,
     ) -> impl GetPolicy + Clone + Send + Sync + 'static
     where
         C: tonic::client::GrpcService<tonic::body::BoxBody, Error = Error>,
@@ -50,7 +51,13 @@ impl Config {
                 ports,
                 cache_max_idle_age,
                 opaque_ports,
-            } => Store::spawn_fixed(default, cache_max_idle_age, ports, opaque_ports),
+            } => {
+                let default = local.apply_default(default);
+                let ports = ports
+                    .into_iter()
+                    .map(|(port, policy)| (port, local.apply(policy)));
+                Store::spawn_fixed(default, cache_max_idle_age, ports, opaque_ports)
+            }
 
             Self::Discover {
                 default,
@@ -58,6 +65,7 @@ impl Config {
                 cache_max_idle_age,
                 opaque_ports,
             } => {
+                let default = local.apply_default(default);
                 let watch = {
                     let detect_timeout = match default {
                         DefaultPolicy::Allow(ServerPolicy {
@@ -66,7 +74,7 @@ impl Config {
                         }) => timeout,
                         _ => Duration::from_secs(10),
                     };
-                    Api::new(workload, detect_timeout, client).into_watch(backoff)
+                    Api::new(workload, detect_timeout, local, client).into_watch(backoff)
                 };
                 Store::spawn_discover(default, cache_max_idle_age, watch, ports, opaque_ports)
             }
@@ -1,6 +1,6 @@
 use linkerd_app_core::{IpNet, Ipv4Net, Ipv6Net};
 use linkerd_proxy_server_policy::{
-    authz::Suffix, http, Authentication, Authorization, Meta, Protocol, ServerPolicy,
+    authz::Suffix, http, Authentication, Authorization, AuthzMode, Meta, Protocol, ServerPolicy,
 };
 use std::{sync::Arc, time::Duration};
 
@@ -53,6 +53,7 @@ fn authenticated() -> Authentication {
     Authentication::TlsAuthenticated {
         identities: Default::default(),
         suffixes: vec![Suffix::from(vec![])],
+        spiffe: vec![],
     }
 }
 
@@ -79,5 +80,6 @@ fn mk(
     ServerPolicy {
         meta: Meta::new_default(name),
         protocol,
+        authz_mode: AuthzMode::Enforce,
     }
 }
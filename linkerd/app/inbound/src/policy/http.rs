@@ -11,12 +11,20 @@ use linkerd_app_core::{
     transport::{ClientAddr, OrigDstAddr, Remote},
     Error, Result,
 };
-use linkerd_proxy_server_policy::{grpc, http, route::RouteMatch};
-use std::{sync::Arc, task};
+use linkerd_proxy_server_policy::{
+    grpc, http, route::RouteMatch, Authentication, ExtAuthz, LocalRateLimit,
+};
+use std::{future::Future, pin::Pin, sync::Arc, task};
 
+mod ext_authz;
+mod jwt;
+mod rate_limit;
 #[cfg(test)]
 mod tests;
 
+pub use self::ext_authz::ExtAuthzClient;
+use self::{jwt::JwtValidator, rate_limit::RateLimits};
+
 /// A middleware that enforces policy on each HTTP request.
 ///
 /// This enforcement is done lazily on each request so that policy updates are
@@ -27,6 +35,9 @@ mod tests;
 #[derive(Clone, Debug)]
 pub struct NewHttpPolicy<N> {
     metrics: HttpAuthzMetrics,
+    rate_limits: RateLimits,
+    jwt: JwtValidator,
+    ext_authz: ExtAuthzClient,
     inner: N,
 }
 
@@ -36,6 +47,9 @@ pub struct HttpPolicyService<T, N> {
     connection: ConnectionMeta,
     policy: AllowPolicy,
     metrics: HttpAuthzMetrics,
+    rate_limits: RateLimits,
+    jwt: JwtValidator,
+    ext_authz: ExtAuthzClient,
     inner: N,
 }
 
@@ -54,6 +68,10 @@ pub struct HttpRouteNotFound(());
 #[error("invalid redirect: {0}")]
 pub struct HttpRouteInvalidRedirect(#[from] pub http::filter::InvalidRedirect);
 
+#[derive(Debug, thiserror::Error)]
+#[error("invalid URL rewrite: {0}")]
+pub struct HttpRouteInvalidUrlRewrite(#[from] pub http::filter::InvalidUrlRewrite);
+
 #[derive(Debug, thiserror::Error)]
 #[error("request redirected to {location}")]
 pub struct HttpRouteRedirect {
@@ -65,6 +83,28 @@ pub struct HttpRouteRedirect {
 #[error("unauthorized request on route")]
 pub struct HttpRouteUnauthorized(());
 
+#[derive(Debug, thiserror::Error)]
+#[error("unauthenticated request on route")]
+pub struct HttpRouteUnauthenticated(#[source] jwt::InvalidJwt);
+
+#[derive(Debug, thiserror::Error)]
+#[error("route authentication unavailable: {0}")]
+pub struct HttpRouteAuthnUnavailable(#[source] jwt::InvalidJwt);
+
+#[derive(Debug, thiserror::Error)]
+#[error("request rate limited on route")]
+pub struct HttpRouteRateLimited(());
+
+#[derive(Debug, thiserror::Error)]
+#[error("request denied by external authorization with {status}")]
+pub struct HttpRouteExtAuthzDenied {
+    pub status: ::http::StatusCode,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("external authorization failed: {0}")]
+pub struct HttpRouteExtAuthzFailed(#[source] Error);
+
 #[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
 #[error("HTTP request configured to fail with {status}: {message}")]
 pub struct HttpRouteInjectedFailure {
@@ -86,9 +126,19 @@ pub struct HttpInvalidPolicy(&'static str);
 // === impl NewHttpPolicy ===
 
 impl<N> NewHttpPolicy<N> {
-    pub fn layer(metrics: HttpAuthzMetrics) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+    pub fn layer(
+        metrics: HttpAuthzMetrics,
+        ext_authz: ExtAuthzClient,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        // Rate limits and JWKS are shared by all of the services built by this
+        // layer.
+        let rate_limits = RateLimits::default();
+        let jwt = JwtValidator::default();
         svc::layer::mk(move |inner| Self {
             metrics: metrics.clone(),
+            rate_limits: rate_limits.clone(),
+            jwt: jwt.clone(),
+            ext_authz: ext_authz.clone(),
             inner,
         })
     }
@@ -108,11 +158,15 @@ where
         let tls = target.param();
         let policy: AllowPolicy = target.param();
         let dst = policy.dst_addr();
+        self.jwt.preload(&policy.borrow());
         HttpPolicyService {
             target,
             policy,
             connection: ConnectionMeta { client, dst, tls },
             metrics: self.metrics.clone(),
+            rate_limits: self.rate_limits.clone(),
+            jwt: self.jwt.clone(),
+            ext_authz: self.ext_authz.clone(),
             inner: self.inner.clone(),
         }
     }
@@ -122,7 +176,7 @@ where
 
 macro_rules! err {
     ($e:expr) => {
-        return future::Either::Right(future::err($e))
+        return future::Either::Left(future::Either::Right(future::err($e)))
     };
 }
 
@@ -137,16 +191,21 @@ macro_rules! try_fut {
 
 impl<B, T, N, S> svc::Service<::http::Request<B>> for HttpPolicyService<T, N>
 where
+    B: Send + 'static,
     T: Clone,
     N: svc::NewService<(HttpRoutePermit, T), Service = S>,
-    S: svc::Service<::http::Request<B>>,
+    S: svc::Service<::http::Request<B>> + Send + 'static,
     S::Error: Into<Error>,
+    S::Future: Send,
 {
     type Response = S::Response;
     type Error = Error;
     type Future = future::Either<
-        future::ErrInto<svc::stack::Oneshot<S, ::http::Request<B>>, Error>,
-        future::Ready<Result<Self::Response>>,
+        future::Either<
+            future::ErrInto<svc::stack::Oneshot<S, ::http::Request<B>>, Error>,
+            future::Ready<Result<Self::Response>>,
+        >,
+        Pin<Box<dyn Future<Output = Result<Self::Response>> + Send + 'static>>,
     >;
 
     #[inline]
@@ -157,38 +216,92 @@ where
     fn call(&mut self, mut req: ::http::Request<B>) -> Self::Future {
         // Find an appropriate route for the request and ensure that it's
         // authorized.
-        let permit = match self.policy.routes() {
+        let (permit, audited, ext_authz) = match self.policy.routes() {
             None => err!(self.mk_route_not_found()),
             Some(Routes::Http(routes)) => {
-                let (permit, mtch, route) = try_fut!(self.authorize(&routes, &req));
-                try_fut!(apply_http_filters(mtch, route, &mut req));
-                permit
+                let (permit, mtch, route, audited) = try_fut!(self.authorize(&routes, &req));
+                let rate_limit = |limit: &LocalRateLimit| self.rate_limit(&permit, limit);
+                try_fut!(apply_http_filters(mtch, route, &mut req, rate_limit));
+                let ext_authz = route
+                    .filters
+                    .iter()
+                    .filter_map(|f| match f {
+                        http::Filter::ExtAuthz(ea) => Some(ea.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                (permit, audited, ext_authz)
             }
             Some(Routes::Grpc(routes)) => {
-                let (permit, _, route) = try_fut!(self.authorize(&routes, &req));
-                try_fut!(apply_grpc_filters(route, &mut req));
-                permit
+                let (permit, _, route, audited) = try_fut!(self.authorize(&routes, &req));
+                let rate_limit = |limit: &LocalRateLimit| self.rate_limit(&permit, limit);
+                try_fut!(apply_grpc_filters(route, &mut req, rate_limit));
+                let ext_authz = route
+                    .filters
+                    .iter()
+                    .filter_map(|f| match f {
+                        grpc::Filter::ExtAuthz(ea) => Some(ea.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                (permit, audited, ext_authz)
             }
         };
 
-        future::Either::Left(
-            self.inner
-                .new_service((permit, self.target.clone()))
-                .oneshot(req)
-                .err_into::<Error>(),
-        )
+        // Requests are only counted as allowed once they have passed all of
+        // the route's checks, including external authorization.
+        if ext_authz.is_empty() {
+            if !audited {
+                self.metrics.allow(&permit, self.connection.tls.clone());
+            }
+            return future::Either::Left(future::Either::Left(
+                self.inner
+                    .new_service((permit, self.target.clone()))
+                    .oneshot(req)
+                    .err_into::<Error>(),
+            ));
+        }
+
+        // External authorization checks are asynchronous, so the inner service
+        // is only called once the checks have completed.
+        let client = self.ext_authz.clone();
+        let connection = self.connection.clone();
+        let metrics = self.metrics.clone();
+        let inner = self
+            .inner
+            .new_service((permit.clone(), self.target.clone()));
+        future::Either::Right(Box::pin(async move {
+            let route = &permit.labels.route;
+            for config in ext_authz {
+                if let Err(error) =
+                    check_ext_authz(&client, &config, route, &connection, &mut req).await
+                {
+                    metrics.deny(route.clone(), connection.dst, connection.tls.clone());
+                    return Err(error);
+                }
+            }
+            if !audited {
+                metrics.allow(&permit, connection.tls.clone());
+            }
+            inner.oneshot(req).await.map_err(Into::into)
+        }))
     }
 }
 
 impl<T, N> HttpPolicyService<T, N> {
     /// Finds a matching route for the given request and checks that a
     /// sufficient authorization is present, returning a permit describing the
-    /// authorization.
+    /// authorization and whether it was only permitted by an audit policy.
     fn authorize<'m, M: super::route::Match + 'm, P, B>(
         &self,
         routes: &'m [super::route::Route<M, RoutePolicy<P>>],
         req: &::http::Request<B>,
-    ) -> Result<(HttpRoutePermit, RouteMatch<M::Summary>, &'m RoutePolicy<P>)> {
+    ) -> Result<(
+        HttpRoutePermit,
+        RouteMatch<M::Summary>,
+        &'m RoutePolicy<P>,
+        bool,
+    )> {
         let (r#match, route) =
             super::route::find(routes, req).ok_or_else(|| self.mk_route_not_found())?;
 
@@ -197,12 +310,77 @@ impl<T, N> HttpPolicyService<T, N> {
             server: self.policy.server_label(),
         };
 
-        let authz = match route
+        // If a JWT authorization applies to the client but the request's token
+        // is rejected, the request is denied as unauthenticated. If the token
+        // cannot be validated because keys have not been loaded, the request
+        // fails with a server error instead.
+        let mut unauthenticated = None;
+        let mut authn_unavailable = None;
+        let (authz, audited) = match route
             .authorizations
             .iter()
-            .find(|a| super::is_authorized(a, self.connection.client, &self.connection.tls))
-        {
-            Some(authz) => authz,
+            .find(|a| match a.authentication {
+                Authentication::Jwt(ref authn) => {
+                    if !super::is_permitted_network(a, self.connection.client) {
+                        return false;
+                    }
+                    match self.jwt.validate(authn, req.headers()) {
+                        Ok(()) => true,
+                        Err(error @ jwt::InvalidJwt::JwksUnavailable(_)) => {
+                            authn_unavailable.get_or_insert(error);
+                            false
+                        }
+                        Err(error) => {
+                            tracing::debug!(
+                                authz.group = %a.meta.group(),
+                                authz.kind = %a.meta.kind(),
+                                authz.name = %a.meta.name(),
+                                %error,
+                                "Request token rejected",
+                            );
+                            unauthenticated.get_or_insert(error);
+                            false
+                        }
+                    }
+                }
+                _ => super::is_authorized(a, self.connection.client, &self.connection.tls),
+            }) {
+            Some(authz) => (authz.meta.clone(), false),
+            None if route.authz_mode.is_audit() || self.policy.borrow().authz_mode.is_audit() => {
+                // In audit mode, the request is permitted so that the impact
+                // of the policy can be observed before it is enforced.
+                tracing::info!(
+                    server.group = %labels.server.0.group(),
+                    server.kind = %labels.server.0.kind(),
+                    server.name = %labels.server.0.name(),
+                    route.group = %labels.route.group(),
+                    route.kind = %labels.route.kind(),
+                    route.name = %labels.route.name(),
+                    authz.names = ?super::authz_names(&route.authorizations),
+                    client.tls = ?self.connection.tls,
+                    client.id = ?super::client_id(&self.connection.tls),
+                    client.ip = %self.connection.client.ip(),
+                    unauthenticated = unauthenticated.is_some(),
+                    "Request would have been denied",
+                );
+                self.metrics.audit(
+                    labels.clone(),
+                    self.connection.dst,
+                    self.connection.tls.clone(),
+                );
+                (super::audit_meta(), true)
+            }
+            None if authn_unavailable.is_some() => {
+                let error = authn_unavailable.expect("checked above");
+                tracing::warn!(
+                    route.group = %labels.route.group(),
+                    route.kind = %labels.route.kind(),
+                    route.name = %labels.route.name(),
+                    %error,
+                    "Request cannot be authenticated",
+                );
+                return Err(HttpRouteAuthnUnavailable(error).into());
+            }
             None => {
                 tracing::info!(
                     server.group = %labels.server.0.group(),
@@ -230,6 +408,9 @@ impl<T, N> HttpPolicyService<T, N> {
                 }
                 self.metrics
                     .deny(labels, self.connection.dst, self.connection.tls.clone());
+                if let Some(error) = unauthenticated {
+                    return Err(HttpRouteUnauthenticated(error).into());
+                }
                 return Err(HttpRouteUnauthorized(()).into());
             }
         };
@@ -237,7 +418,7 @@ impl<T, N> HttpPolicyService<T, N> {
         let permit = {
             let labels = RouteAuthzLabels {
                 route: labels,
-                authz: authz.meta.clone(),
+                authz,
             };
             tracing::debug!(
                 server.group = %labels.route.server.0.group(),
@@ -259,8 +440,35 @@ impl<T, N> HttpPolicyService<T, N> {
             }
         };
 
-        self.metrics.allow(&permit, self.connection.tls.clone());
-        Ok((permit, r#match, route))
+        Ok((permit, r#match, route, audited))
+    }
+
+    /// Takes a token from the route's rate limit, failing the request if the
+    /// limit has been exceeded.
+    fn rate_limit(&self, permit: &HttpRoutePermit, limit: &LocalRateLimit) -> Result<()> {
+        if self.rate_limits.acquire(
+            &permit.labels.route,
+            limit,
+            self.connection.client,
+            &self.connection.tls,
+        ) {
+            return Ok(());
+        }
+
+        let labels = &permit.labels.route;
+        tracing::info!(
+            server.group = %labels.server.0.group(),
+            server.kind = %labels.server.0.kind(),
+            server.name = %labels.server.0.name(),
+            route.group = %labels.route.group(),
+            route.kind = %labels.route.kind(),
+            route.name = %labels.route.name(),
+            client.tls = ?self.connection.tls,
+            client.ip = %self.connection.client.ip(),
+            "Request rate limited",
+        );
+        self.metrics.rate_limit(permit, self.connection.tls.clone());
+        Err(HttpRouteRateLimited(()).into())
     }
 
     fn mk_route_not_found(&self) -> Error {
@@ -271,10 +479,51 @@ impl<T, N> HttpPolicyService<T, N> {
     }
 }
 
+async fn check_ext_authz<B>(
+    client: &ExtAuthzClient,
+    config: &ExtAuthz,
+    route: &RouteLabels,
+    connection: &ConnectionMeta,
+    req: &mut ::http::Request<B>,
+) -> Result<()> {
+    let res = client.check(config, route, connection, req).await;
+    match &res {
+        // The authorization service could not be used, so this is logged
+        // separately from the service's denials.
+        Err(error) if error.is::<HttpRouteExtAuthzFailed>() => tracing::warn!(
+            server.group = %route.server.0.group(),
+            server.kind = %route.server.0.kind(),
+            server.name = %route.server.0.name(),
+            route.group = %route.route.group(),
+            route.kind = %route.route.kind(),
+            route.name = %route.route.name(),
+            client.tls = ?connection.tls,
+            client.ip = %connection.client.ip(),
+            %error,
+            "External authorization failed; denying request",
+        ),
+        Err(error) => tracing::info!(
+            server.group = %route.server.0.group(),
+            server.kind = %route.server.0.kind(),
+            server.name = %route.server.0.name(),
+            route.group = %route.route.group(),
+            route.kind = %route.route.kind(),
+            route.name = %route.route.name(),
+            client.tls = ?connection.tls,
+            client.ip = %connection.client.ip(),
+            %error,
+            "Request denied by external authorization",
+        ),
+        Ok(()) => {}
+    }
+    res
+}
+
 fn apply_http_filters<B>(
     r#match: http::RouteMatch,
     route: &http::Policy,
     req: &mut ::http::Request<B>,
+    mut rate_limit: impl FnMut(&LocalRateLimit) -> Result<()>,
 ) -> Result<()> {
     // TODO Do any metrics apply here?
     for filter in &route.filters {
@@ -303,6 +552,18 @@ fn apply_http_filters<B>(
                 rh.apply(req.headers_mut());
             }
 
+            http::Filter::UrlRewrite(rewrite) => {
+                rewrite
+                    .apply(req, &r#match)
+                    .map_err(HttpRouteInvalidUrlRewrite)?;
+            }
+
+            http::Filter::RateLimit(limit) => rate_limit(limit)?,
+
+            // External authorization is checked after all other filters have
+            // been applied.
+            http::Filter::ExtAuthz(_) => {}
+
             http::Filter::InternalError(msg) => {
                 return Err(HttpInvalidPolicy(msg).into());
             }
@@ -312,7 +573,11 @@ fn apply_http_filters<B>(
     Ok(())
 }
 
-fn apply_grpc_filters<B>(route: &grpc::Policy, req: &mut ::http::Request<B>) -> Result<()> {
+fn apply_grpc_filters<B>(
+    route: &grpc::Policy,
+    req: &mut ::http::Request<B>,
+    mut rate_limit: impl FnMut(&LocalRateLimit) -> Result<()>,
+) -> Result<()> {
     for filter in &route.filters {
         match filter {
             grpc::Filter::InjectFailure(fail) => {
@@ -325,6 +590,12 @@ fn apply_grpc_filters<B>(route: &grpc::Policy, req: &mut ::http::Request<B>) ->
                 rh.apply(req.headers_mut());
             }
 
+            grpc::Filter::RateLimit(limit) => rate_limit(limit)?,
+
+            // External authorization is checked after all other filters have
+            // been applied.
+            grpc::Filter::ExtAuthz(_) => {}
+
             grpc::Filter::InternalError(msg) => {
                 return Err(HttpInvalidPolicy(msg).into());
             }
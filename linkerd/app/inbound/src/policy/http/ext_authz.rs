@@ -0,0 +1,521 @@
+use super::{ConnectionMeta, HttpRouteExtAuthzDenied, HttpRouteExtAuthzFailed};
+use linkerd_app_core::{metrics::RouteLabels, proxy::http::HttpBody, svc, tls, Error, Result};
+use linkerd_proxy_server_policy::{ext_authz::FailureMode, ExtAuthz};
+use parking_lot::Mutex;
+use std::{collections::HashMap, fmt, net::IpAddr, sync::Arc};
+use tokio::time;
+
+/// A client for an external authorization service.
+///
+/// The service must implement Envoy's `envoy.service.auth.v3.Authorization`
+/// gRPC API. Only the subset of request attributes and response fields
+/// described by [`proto`] are supported.
+///
+/// Decisions are cached for routes that configure a cache TTL. The cache is
+/// shared by all of the services that use a client.
+#[derive(Clone, Default)]
+pub struct ExtAuthzClient(Option<Arc<Inner>>);
+
+type Client = svc::BoxCloneService<
+    ::http::Request<tonic::body::BoxBody>,
+    ::http::Response<tonic::body::BoxBody>,
+    Error,
+>;
+
+struct Inner {
+    client: Mutex<Client>,
+    cache: Mutex<HashMap<Attributes, Cached>>,
+}
+
+/// The request attributes sent to the authorization service.
+///
+/// Attributes also serve as the decision cache's key, so they must not include
+/// anything that varies between otherwise-equivalent requests (like the
+/// client's port).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Attributes {
+    route: RouteLabels,
+    client_ip: IpAddr,
+    client_id: Option<tls::ClientId>,
+    dst: std::net::SocketAddr,
+    method: ::http::Method,
+    version: ::http::Version,
+    scheme: String,
+    authority: String,
+    path: String,
+    headers: Vec<(::http::header::HeaderName, String)>,
+}
+
+#[derive(Clone, Debug)]
+enum Decision {
+    Allow {
+        headers: Vec<(::http::header::HeaderName, ::http::HeaderValue, bool)>,
+        remove: Vec<::http::header::HeaderName>,
+    },
+    Deny(::http::StatusCode),
+}
+
+#[derive(Clone, Debug)]
+struct Cached {
+    decision: Decision,
+    expires: time::Instant,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("no external authorization service is configured")]
+struct NotConfigured;
+
+#[derive(Debug, thiserror::Error)]
+#[error("external authorization service did not respond in {0:?}")]
+struct CheckTimeout(time::Duration);
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid external authorization response: {0}")]
+struct InvalidResponse(&'static str);
+
+/// Limits the number of decisions cached by each client.
+const MAX_CACHED_DECISIONS: usize = 10_000;
+
+// === impl ExtAuthzClient ===
+
+impl ExtAuthzClient {
+    pub fn new<C, B>(client: C) -> Self
+    where
+        C: svc::Service<
+            ::http::Request<tonic::body::BoxBody>,
+            Response = ::http::Response<B>,
+            Error = Error,
+        >,
+        C: Clone + Send + 'static,
+        C::Future: Send + 'static,
+        B: HttpBody<Data = bytes::Bytes> + Send + 'static,
+        B::Error: Into<Error>,
+    {
+        use svc::ServiceExt;
+
+        let client = client.map_response(|rsp: ::http::Response<B>| {
+            rsp.map(|body| {
+                body.map_err(|e| tonic::Status::from_error(e.into()))
+                    .boxed_unsync()
+            })
+        });
+        Self(Some(Arc::new(Inner {
+            client: Mutex::new(svc::BoxCloneService::new(client)),
+            cache: Default::default(),
+        })))
+    }
+
+    /// Checks a request with the authorization service, modifying the
+    /// request's headers if the service requires it.
+    ///
+    /// Fails with [`HttpRouteExtAuthzDenied`] when the service denies the
+    /// request, or with [`HttpRouteExtAuthzFailed`] when the service fails and
+    /// the route does not permit requests on failure.
+    pub(super) async fn check<B>(
+        &self,
+        config: &ExtAuthz,
+        route: &RouteLabels,
+        conn: &ConnectionMeta,
+        req: &mut ::http::Request<B>,
+    ) -> Result<()> {
+        let attrs = Attributes::new(config, route, conn, req);
+
+        if config.cache_ttl.is_some() {
+            if let Some(decision) = self.cached(&attrs) {
+                tracing::trace!(?decision, "Using cached decision");
+                return decision.apply(req);
+            }
+        }
+
+        let decision = match self.request(config, &attrs).await {
+            Ok(decision) => decision,
+            Err(error) => {
+                return match config.failure_mode {
+                    FailureMode::Allow => {
+                        tracing::info!(%error, "External authorization failed; allowing request");
+                        Ok(())
+                    }
+                    FailureMode::Deny => Err(HttpRouteExtAuthzFailed(error).into()),
+                };
+            }
+        };
+        tracing::debug!(?decision, "External authorization decision");
+
+        if let Some(ttl) = config.cache_ttl {
+            self.cache(attrs, decision.clone(), ttl);
+        }
+        decision.apply(req)
+    }
+
+    async fn request(&self, config: &ExtAuthz, attrs: &Attributes) -> Result<Decision> {
+        let inner = self.0.as_ref().ok_or(NotConfigured)?;
+        let mut client = tonic::client::Grpc::new(inner.client.lock().clone());
+        let req = tonic::Request::new(attrs.to_proto());
+        let check = async move {
+            client.ready().await?;
+            let rsp = client
+                .unary(
+                    req,
+                    ::http::uri::PathAndQuery::from_static(
+                        "/envoy.service.auth.v3.Authorization/Check",
+                    ),
+                    tonic::codec::ProstCodec::default(),
+                )
+                .await?;
+            Decision::try_from(rsp.into_inner()).map_err(Error::from)
+        };
+        time::timeout(config.timeout, check)
+            .await
+            .map_err(|_| CheckTimeout(config.timeout))?
+    }
+
+    fn cached(&self, attrs: &Attributes) -> Option<Decision> {
+        let inner = self.0.as_ref()?;
+        let mut cache = inner.cache.lock();
+        let cached = cache.get(attrs)?;
+        if cached.expires > time::Instant::now() {
+            return Some(cached.decision.clone());
+        }
+        cache.remove(attrs);
+        None
+    }
+
+    fn cache(&self, attrs: Attributes, decision: Decision, ttl: time::Duration) {
+        let inner = match self.0.as_ref() {
+            Some(inner) => inner,
+            None => return,
+        };
+        let now = time::Instant::now();
+        let mut cache = inner.cache.lock();
+        if cache.len() >= MAX_CACHED_DECISIONS {
+            cache.retain(|_, c| c.expires > now);
+            if cache.len() >= MAX_CACHED_DECISIONS {
+                tracing::debug!("Decision cache is full");
+                return;
+            }
+        }
+        cache.insert(
+            attrs,
+            Cached {
+                decision,
+                expires: now + ttl,
+            },
+        );
+    }
+}
+
+impl fmt::Debug for ExtAuthzClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtAuthzClient")
+            .field("configured", &self.0.is_some())
+            .finish()
+    }
+}
+
+// === impl Attributes ===
+
+impl Attributes {
+    fn new<B>(
+        config: &ExtAuthz,
+        route: &RouteLabels,
+        conn: &ConnectionMeta,
+        req: &::http::Request<B>,
+    ) -> Self {
+        let uri = req.uri();
+        let authority = uri
+            .authority()
+            .map(|a| a.as_str())
+            .or_else(|| {
+                req.headers()
+                    .get(::http::header::HOST)
+                    .and_then(|h| h.to_str().ok())
+            })
+            .unwrap_or_default();
+
+        // Multiple header values are joined with commas, as described by
+        // RFC 9110.
+        let headers = config
+            .headers
+            .iter()
+            .filter_map(|name| {
+                let values = req
+                    .headers()
+                    .get_all(name)
+                    .iter()
+                    .filter_map(|v| v.to_str().ok())
+                    .collect::<Vec<_>>();
+                if values.is_empty() {
+                    return None;
+                }
+                Some((name.clone(), values.join(",")))
+            })
+            .collect();
+
+        Self {
+            route: route.clone(),
+            client_ip: conn.client.ip(),
+            client_id: conn.tls.value().and_then(|tls| tls.client_id()).cloned(),
+            dst: *conn.dst,
+            method: req.method().clone(),
+            version: req.version(),
+            scheme: uri.scheme_str().unwrap_or("http").to_string(),
+            authority: authority.to_string(),
+            path: uri
+                .path_and_query()
+                .map(|pq| pq.as_str())
+                .unwrap_or("/")
+                .to_string(),
+            headers,
+        }
+    }
+
+    fn to_proto(&self) -> proto::CheckRequest {
+        use proto::attribute_context as ctx;
+
+        let address = |ip: IpAddr, port: u16| proto::Address {
+            socket_address: Some(proto::SocketAddress {
+                address: ip.to_string(),
+                port_value: port.into(),
+            }),
+        };
+
+        let protocol = match self.version {
+            ::http::Version::HTTP_10 => "HTTP/1.0",
+            ::http::Version::HTTP_11 => "HTTP/1.1",
+            ::http::Version::HTTP_2 => "HTTP/2",
+            _ => "",
+        };
+
+        proto::CheckRequest {
+            attributes: Some(proto::AttributeContext {
+                source: Some(ctx::Peer {
+                    address: Some(address(self.client_ip, 0)),
+                    principal: self
+                        .client_id
+                        .as_ref()
+                        .map(|id| id.as_str().to_string())
+                        .unwrap_or_default(),
+                }),
+                destination: Some(ctx::Peer {
+                    address: Some(address(self.dst.ip(), self.dst.port())),
+                    principal: String::new(),
+                }),
+                request: Some(ctx::Request {
+                    http: Some(ctx::HttpRequest {
+                        method: self.method.to_string(),
+                        headers: self
+                            .headers
+                            .iter()
+                            .map(|(n, v)| (n.to_string(), v.clone()))
+                            .collect(),
+                        path: self.path.clone(),
+                        host: self.authority.clone(),
+                        scheme: self.scheme.clone(),
+                        protocol: protocol.to_string(),
+                    }),
+                }),
+            }),
+        }
+    }
+}
+
+// === impl Decision ===
+
+impl Decision {
+    fn apply<B>(&self, req: &mut ::http::Request<B>) -> Result<()> {
+        match self {
+            Self::Allow { headers, remove } => {
+                for (name, value, append) in headers {
+                    if *append {
+                        req.headers_mut().append(name.clone(), value.clone());
+                    } else {
+                        req.headers_mut().insert(name.clone(), value.clone());
+                    }
+                }
+                for name in remove {
+                    req.headers_mut().remove(name);
+                }
+                Ok(())
+            }
+            Self::Deny(status) => Err(HttpRouteExtAuthzDenied { status: *status }.into()),
+        }
+    }
+}
+
+impl TryFrom<proto::CheckResponse> for Decision {
+    type Error = InvalidResponse;
+
+    fn try_from(rsp: proto::CheckResponse) -> Result<Self, Self::Error> {
+        // As with Envoy, a response without a status permits the request.
+        let code = rsp.status.map(|s| s.code).unwrap_or(0);
+        if code != tonic::Code::Ok as i32 {
+            // Denials must be client or server errors, so that a denied
+            // request can't be mistaken for a successful (or redirected) one.
+            let status = rsp
+                .denied_response
+                .and_then(|d| d.status)
+                .and_then(|s| u16::try_from(s.code).ok())
+                .and_then(|s| ::http::StatusCode::from_u16(s).ok())
+                .filter(|s| s.is_client_error() || s.is_server_error())
+                .unwrap_or(::http::StatusCode::FORBIDDEN);
+            return Ok(Self::Deny(status));
+        }
+
+        let ok = rsp.ok_response.unwrap_or_default();
+        let headers = ok
+            .headers
+            .into_iter()
+            .map(|opt| {
+                let proto::HeaderValue { key, value } =
+                    opt.header.ok_or(InvalidResponse("missing header"))?;
+                let name = ::http::header::HeaderName::from_bytes(key.as_bytes())
+                    .map_err(|_| InvalidResponse("invalid header name"))?;
+                let value = ::http::HeaderValue::from_str(&value)
+                    .map_err(|_| InvalidResponse("invalid header value"))?;
+                Ok((name, value, opt.append.unwrap_or(false)))
+            })
+            .collect::<Result<Vec<_>, InvalidResponse>>()?;
+        let remove = ok
+            .headers_to_remove
+            .into_iter()
+            .map(|key| {
+                ::http::header::HeaderName::from_bytes(key.as_bytes())
+                    .map_err(|_| InvalidResponse("invalid header name"))
+            })
+            .collect::<Result<Vec<_>, InvalidResponse>>()?;
+        Ok(Self::Allow { headers, remove })
+    }
+}
+
+/// A subset of the messages used by Envoy's external authorization API.
+///
+/// Field numbers match `envoy/service/auth/v3/external_auth.proto` and
+/// `envoy/service/auth/v3/attribute_context.proto`; fields that the proxy
+/// does not use are omitted.
+pub(super) mod proto {
+    use std::collections::HashMap;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct CheckRequest {
+        #[prost(message, optional, tag = "1")]
+        pub attributes: Option<AttributeContext>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct AttributeContext {
+        #[prost(message, optional, tag = "1")]
+        pub source: Option<attribute_context::Peer>,
+        #[prost(message, optional, tag = "2")]
+        pub destination: Option<attribute_context::Peer>,
+        #[prost(message, optional, tag = "4")]
+        pub request: Option<attribute_context::Request>,
+    }
+
+    pub mod attribute_context {
+        use super::*;
+
+        #[derive(Clone, PartialEq, prost::Message)]
+        pub struct Peer {
+            #[prost(message, optional, tag = "1")]
+            pub address: Option<Address>,
+            #[prost(string, tag = "4")]
+            pub principal: String,
+        }
+
+        #[derive(Clone, PartialEq, prost::Message)]
+        pub struct Request {
+            #[prost(message, optional, tag = "2")]
+            pub http: Option<HttpRequest>,
+        }
+
+        #[derive(Clone, PartialEq, prost::Message)]
+        pub struct HttpRequest {
+            #[prost(string, tag = "2")]
+            pub method: String,
+            #[prost(map = "string, string", tag = "3")]
+            pub headers: HashMap<String, String>,
+            #[prost(string, tag = "4")]
+            pub path: String,
+            #[prost(string, tag = "5")]
+            pub host: String,
+            #[prost(string, tag = "6")]
+            pub scheme: String,
+            #[prost(string, tag = "10")]
+            pub protocol: String,
+        }
+    }
+
+    /// `envoy.config.core.v3.Address`, which only supports socket addresses
+    /// here.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Address {
+        #[prost(message, optional, tag = "1")]
+        pub socket_address: Option<SocketAddress>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SocketAddress {
+        #[prost(string, tag = "2")]
+        pub address: String,
+        #[prost(uint32, tag = "3")]
+        pub port_value: u32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct CheckResponse {
+        #[prost(message, optional, tag = "1")]
+        pub status: Option<RpcStatus>,
+        #[prost(message, optional, tag = "2")]
+        pub denied_response: Option<DeniedHttpResponse>,
+        #[prost(message, optional, tag = "3")]
+        pub ok_response: Option<OkHttpResponse>,
+    }
+
+    /// `google.rpc.Status`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RpcStatus {
+        #[prost(int32, tag = "1")]
+        pub code: i32,
+        #[prost(string, tag = "2")]
+        pub message: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct DeniedHttpResponse {
+        #[prost(message, optional, tag = "1")]
+        pub status: Option<HttpStatus>,
+    }
+
+    /// `envoy.type.v3.HttpStatus`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct HttpStatus {
+        #[prost(int32, tag = "1")]
+        pub code: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct OkHttpResponse {
+        #[prost(message, repeated, tag = "2")]
+        pub headers: Vec<HeaderValueOption>,
+        #[prost(string, repeated, tag = "5")]
+        pub headers_to_remove: Vec<String>,
+    }
+
+    /// `envoy.config.core.v3.HeaderValueOption`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct HeaderValueOption {
+        #[prost(message, optional, tag = "1")]
+        pub header: Option<HeaderValue>,
+        #[prost(message, optional, tag = "2")]
+        pub append: Option<bool>,
+    }
+
+    /// `envoy.config.core.v3.HeaderValue`
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct HeaderValue {
+        #[prost(string, tag = "1")]
+        pub key: String,
+        #[prost(string, tag = "2")]
+        pub value: String,
+    }
+}
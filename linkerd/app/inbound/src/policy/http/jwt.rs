@@ -0,0 +1,586 @@
+use linkerd_proxy_server_policy::{authz::JwtAuthentication, Authentication, ServerPolicy};
+use parking_lot::Mutex;
+use ring::signature;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{sync::watch, time};
+
+/// Validates JWT bearer tokens against JWKS files.
+///
+/// Each JWKS file is read by a background task, so requests never wait on the
+/// filesystem, and all of a server's requests share its parsed keys. Files are
+/// reloaded every [`JwtValidator::RELOAD_INTERVAL`] so that key rotations are
+/// honored without restarting the proxy.
+#[derive(Clone, Debug)]
+pub(crate) struct JwtValidator {
+    jwks: Arc<Mutex<HashMap<PathBuf, Keys>>>,
+    reload_interval: Duration,
+}
+
+/// The keys most recently loaded from a JWKS file, if it has been loaded.
+type Keys = watch::Receiver<Option<Arc<[Jwk]>>>;
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum InvalidJwt {
+    #[error("missing bearer token")]
+    Missing,
+
+    #[error("malformed token")]
+    Malformed,
+
+    #[error("no key validates the token's signature")]
+    Signature,
+
+    #[error("token has expired")]
+    Expired,
+
+    #[error("token is not yet valid")]
+    NotYetValid,
+
+    #[error("token has an unexpected issuer")]
+    Issuer,
+
+    #[error("token has an unexpected audience")]
+    Audience,
+
+    #[error("token does not include the required {0} claim")]
+    Claim(String),
+
+    /// The JWKS file has not been loaded, so no token can be validated. This
+    /// is a server error rather than a client error.
+    #[error("JWKS {} has not been loaded", .0.display())]
+    JwksUnavailable(PathBuf),
+}
+
+#[derive(Debug)]
+struct Jwk {
+    kid: Option<String>,
+    key: Key,
+}
+
+#[derive(Debug)]
+enum Key {
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    P256(Vec<u8>),
+    P384(Vec<u8>),
+    Ed25519(Vec<u8>),
+}
+
+/// A JSON object, as in a token's header or claims.
+type Claims = serde_json::Map<String, serde_json::Value>;
+
+// === impl JwtValidator ===
+
+impl Default for JwtValidator {
+    fn default() -> Self {
+        Self {
+            jwks: Default::default(),
+            reload_interval: Self::RELOAD_INTERVAL,
+        }
+    }
+}
+
+impl JwtValidator {
+    /// The amount of time between reads of a JWKS file.
+    const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+    /// Tokens are accepted for this long before they become valid and after
+    /// they expire to tolerate clock skew.
+    const LEEWAY: Duration = Duration::from_secs(60);
+
+    /// Starts loading the JWKS files referenced by the policy's routes, so
+    /// that keys are available before requests are authenticated.
+    pub(crate) fn preload(&self, policy: &ServerPolicy) {
+        use linkerd_proxy_server_policy::Protocol;
+
+        let authzs = match policy.protocol {
+            Protocol::Detect { ref http, .. }
+            | Protocol::Http1(ref http)
+            | Protocol::Http2(ref http) => http
+                .iter()
+                .flat_map(|r| r.rules.iter())
+                .flat_map(|r| r.policy.authorizations.iter())
+                .collect::<Vec<_>>(),
+            Protocol::Grpc(ref grpc) => grpc
+                .iter()
+                .flat_map(|r| r.rules.iter())
+                .flat_map(|r| r.policy.authorizations.iter())
+                .collect(),
+            Protocol::Opaque(_) | Protocol::Tls(_) => return,
+        };
+        for authz in authzs {
+            if let Authentication::Jwt(ref authn) = authz.authentication {
+                self.keys(&authn.jwks_path);
+            }
+        }
+    }
+
+    /// Validates the bearer token in the request's `authorization` header.
+    pub(crate) fn validate(
+        &self,
+        authn: &JwtAuthentication,
+        headers: &::http::HeaderMap,
+    ) -> Result<(), InvalidJwt> {
+        let token = headers
+            .get(::http::header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(bearer_token)
+            .ok_or(InvalidJwt::Missing)?;
+        let keys = self
+            .keys(&authn.jwks_path)
+            .borrow()
+            .clone()
+            .ok_or_else(|| InvalidJwt::JwksUnavailable(authn.jwks_path.clone()))?;
+        let claims = verify(token, &keys)?;
+        check_claims(&claims, authn, SystemTime::now())
+    }
+
+    /// Returns the keys loaded from the given JWKS file, spawning a task to
+    /// load it if it is not already being loaded.
+    fn keys(&self, path: &Path) -> Keys {
+        let mut jwks = self.jwks.lock();
+        if let Some(keys) = jwks.get(path) {
+            return keys.clone();
+        }
+
+        let (tx, rx) = watch::channel(None);
+        tokio::spawn(load_jwks(path.to_path_buf(), tx, self.reload_interval));
+        jwks.insert(path.to_path_buf(), rx.clone());
+        rx
+    }
+}
+
+// === impl Jwk ===
+
+impl Jwk {
+    fn parse(jwk: &serde_json::Map<String, serde_json::Value>) -> Option<Self> {
+        let param = |name: &str| jwk.get(name).and_then(serde_json::Value::as_str);
+        let bytes = |name: &str| param(name).and_then(decode);
+
+        let key = match (param("kty")?, param("crv")) {
+            ("RSA", _) => Key::Rsa {
+                n: bytes("n")?,
+                e: bytes("e")?,
+            },
+            ("EC", Some(crv @ ("P-256" | "P-384"))) => {
+                // Keys are verified as uncompressed points.
+                let mut point = vec![0x04];
+                point.extend(bytes("x")?);
+                point.extend(bytes("y")?);
+                if crv == "P-256" {
+                    Key::P256(point)
+                } else {
+                    Key::P384(point)
+                }
+            }
+            ("OKP", Some("Ed25519")) => Key::Ed25519(bytes("x")?),
+            _ => return None,
+        };
+
+        Some(Self {
+            kid: param("kid").map(Into::into),
+            key,
+        })
+    }
+
+    fn verify(&self, alg: &str, msg: &[u8], sig: &[u8]) -> bool {
+        use signature::{RsaPublicKeyComponents, UnparsedPublicKey};
+
+        let rsa = |params: &'static signature::RsaParameters| match self.key {
+            Key::Rsa { ref n, ref e } => RsaPublicKeyComponents { n, e }
+                .verify(params, msg, sig)
+                .is_ok(),
+            _ => false,
+        };
+
+        match (alg, &self.key) {
+            ("RS256", _) => rsa(&signature::RSA_PKCS1_2048_8192_SHA256),
+            ("RS384", _) => rsa(&signature::RSA_PKCS1_2048_8192_SHA384),
+            ("RS512", _) => rsa(&signature::RSA_PKCS1_2048_8192_SHA512),
+            ("PS256", _) => rsa(&signature::RSA_PSS_2048_8192_SHA256),
+            ("PS384", _) => rsa(&signature::RSA_PSS_2048_8192_SHA384),
+            ("PS512", _) => rsa(&signature::RSA_PSS_2048_8192_SHA512),
+            ("ES256", Key::P256(point)) => {
+                UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                    .verify(msg, sig)
+                    .is_ok()
+            }
+            ("ES384", Key::P384(point)) => {
+                UnparsedPublicKey::new(&signature::ECDSA_P384_SHA384_FIXED, point)
+                    .verify(msg, sig)
+                    .is_ok()
+            }
+            ("EdDSA", Key::Ed25519(x)) => UnparsedPublicKey::new(&signature::ED25519, x)
+                .verify(msg, sig)
+                .is_ok(),
+            // Notably, unsigned (`none`) and HMAC-signed tokens are never
+            // accepted.
+            _ => false,
+        }
+    }
+}
+
+/// Reads the JWKS file periodically, publishing its keys whenever it is
+/// read successfully.
+///
+/// When the file cannot be read, the last valid keys continue to be used
+/// until it is fixed.
+async fn load_jwks(
+    path: PathBuf,
+    tx: watch::Sender<Option<Arc<[Jwk]>>>,
+    reload_interval: Duration,
+) {
+    loop {
+        let read = {
+            let path = path.clone();
+            tokio::task::spawn_blocking(move || read_jwks(&path)).await
+        };
+        match read {
+            Ok(Ok(keys)) => {
+                tracing::debug!(path = %path.display(), keys = keys.len(), "Loaded JWKS");
+                tx.send_replace(Some(keys));
+            }
+            Ok(Err(error)) => {
+                tracing::warn!(path = %path.display(), %error, "Failed to load JWKS");
+            }
+            Err(error) => {
+                tracing::warn!(path = %path.display(), %error, "JWKS task failed");
+            }
+        }
+        tokio::select! {
+            _ = time::sleep(reload_interval) => {}
+            // Stop reloading once the validator has been dropped.
+            _ = tx.closed() => return,
+        }
+    }
+}
+
+fn read_jwks(path: &Path) -> std::io::Result<Arc<[Jwk]>> {
+    let jwks: serde_json::Value = serde_json::from_slice(&std::fs::read(path)?)?;
+    let keys = jwks
+        .get("keys")
+        .and_then(serde_json::Value::as_array)
+        .ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "JWKS must include keys")
+        })?
+        .iter()
+        .filter_map(|jwk| {
+            let key = jwk.as_object().and_then(Jwk::parse);
+            if key.is_none() {
+                tracing::debug!(?jwk, "Ignoring unsupported JWK");
+            }
+            key
+        })
+        .collect();
+    Ok(keys)
+}
+
+/// Verifies the token's signature, returning its claims.
+fn verify(token: &str, keys: &[Jwk]) -> Result<Claims, InvalidJwt> {
+    let mut parts = token.split('.');
+    let (header, claims, sig) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(header), Some(claims), Some(sig), None) => (header, claims, sig),
+        _ => return Err(InvalidJwt::Malformed),
+    };
+    // The signature covers the encoded header and claims.
+    let msg = &token[..header.len() + 1 + claims.len()];
+
+    let header = decode_json(header)?;
+    let alg = header
+        .get("alg")
+        .and_then(serde_json::Value::as_str)
+        .ok_or(InvalidJwt::Malformed)?;
+    let kid = header.get("kid").and_then(serde_json::Value::as_str);
+    let sig = decode(sig).ok_or(InvalidJwt::Malformed)?;
+    let verified = keys
+        .iter()
+        .filter(|k| kid.is_none() || k.kid.as_deref() == kid)
+        .any(|k| k.verify(alg, msg.as_bytes(), &sig));
+    if !verified {
+        return Err(InvalidJwt::Signature);
+    }
+
+    decode_json(claims)
+}
+
+fn check_claims(
+    claims: &Claims,
+    authn: &JwtAuthentication,
+    now: SystemTime,
+) -> Result<(), InvalidJwt> {
+    let now = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let time = |name: &str| claims.get(name).and_then(serde_json::Value::as_f64);
+
+    // Tokens must expire.
+    match time("exp") {
+        Some(exp) if now.as_secs_f64() < exp + JwtValidator::LEEWAY.as_secs_f64() => {}
+        _ => return Err(InvalidJwt::Expired),
+    }
+    if let Some(nbf) = time("nbf") {
+        if now.as_secs_f64() + JwtValidator::LEEWAY.as_secs_f64() < nbf {
+            return Err(InvalidJwt::NotYetValid);
+        }
+    }
+
+    if let Some(ref issuer) = authn.issuer {
+        if claims.get("iss").and_then(serde_json::Value::as_str) != Some(issuer.as_str()) {
+            return Err(InvalidJwt::Issuer);
+        }
+    }
+
+    if !authn.audiences.is_empty()
+        && !authn
+            .audiences
+            .iter()
+            .any(|aud| claim_includes(claims.get("aud"), aud))
+    {
+        return Err(InvalidJwt::Audience);
+    }
+
+    for (name, value) in &authn.claims {
+        if !claim_includes(claims.get(name), value) {
+            return Err(InvalidJwt::Claim(name.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the token from an `authorization` header value that uses the
+/// `Bearer` scheme. Auth schemes are case-insensitive, and the token may be
+/// separated from the scheme by any amount of whitespace.
+fn bearer_token(value: &str) -> Option<&str> {
+    let (scheme, token) = value.trim().split_once(char::is_whitespace)?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    let token = token.trim_start();
+    if token.is_empty() {
+        return None;
+    }
+    Some(token)
+}
+
+/// Returns true if the claim is the given string or an array that includes
+/// it.
+fn claim_includes(claim: Option<&serde_json::Value>, value: &str) -> bool {
+    match claim {
+        Some(serde_json::Value::String(s)) => s == value,
+        Some(serde_json::Value::Array(values)) => values.iter().any(|v| v.as_str() == Some(value)),
+        _ => false,
+    }
+}
+
+fn decode(part: &str) -> Option<Vec<u8>> {
+    base64::decode_config(part, base64::URL_SAFE_NO_PAD).ok()
+}
+
+fn decode_json(part: &str) -> Result<Claims, InvalidJwt> {
+    let bytes = decode(part).ok_or(InvalidJwt::Malformed)?;
+    serde_json::from_slice(&bytes).map_err(|_| InvalidJwt::Malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::KeyPair;
+    use std::collections::BTreeMap;
+
+    const SEED: [u8; 32] = [7; 32];
+    const OTHER_SEED: [u8; 32] = [8; 32];
+
+    fn encode(bytes: &[u8]) -> String {
+        base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+    }
+
+    fn jwks(seed: [u8; 32]) -> String {
+        let key = signature::Ed25519KeyPair::from_seed_unchecked(&seed).unwrap();
+        serde_json::json!({
+            "keys": [
+                // Unsupported keys are ignored.
+                { "kty": "oct", "k": "c2VjcmV0" },
+                {
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "kid": "test",
+                    "x": encode(key.public_key().as_ref()),
+                },
+            ],
+        })
+        .to_string()
+    }
+
+    fn token(seed: [u8; 32], claims: serde_json::Value) -> String {
+        let key = signature::Ed25519KeyPair::from_seed_unchecked(&seed).unwrap();
+        let header = encode(br#"{"alg":"EdDSA","kid":"test"}"#);
+        let claims = encode(claims.to_string().as_bytes());
+        let sig = key.sign(format!("{header}.{claims}").as_bytes());
+        format!("{header}.{claims}.{}", encode(sig.as_ref()))
+    }
+
+    fn exp(secs: i64) -> i64 {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        now.as_secs() as i64 + secs
+    }
+
+    fn headers(token: &str) -> ::http::HeaderMap {
+        let mut headers = ::http::HeaderMap::new();
+        headers.insert(
+            ::http::header::AUTHORIZATION,
+            format!("Bearer {token}").parse().unwrap(),
+        );
+        headers
+    }
+
+    fn write_jwks(file: &tempfile::NamedTempFile, seed: [u8; 32]) {
+        std::fs::write(file.path(), jwks(seed)).unwrap();
+    }
+
+    fn authn(path: &Path) -> JwtAuthentication {
+        JwtAuthentication {
+            jwks_path: path.to_path_buf(),
+            issuer: Some("https://issuer.example.com".to_string()),
+            audiences: Some("svc".to_string()).into_iter().collect(),
+            claims: BTreeMap::from([("groups".to_string(), "admin".to_string())]),
+        }
+    }
+
+    /// Waits for the validator to publish keys from the JWKS file.
+    async fn loaded(jwt: &JwtValidator, path: &Path) {
+        let mut keys = jwt.keys(path);
+        time::timeout(time::Duration::from_secs(5), async move {
+            while keys.borrow_and_update().is_none() {
+                keys.changed().await.expect("JWKS must load");
+            }
+        })
+        .await
+        .expect("JWKS must load");
+    }
+
+    #[test]
+    fn bearer_tokens() {
+        assert_eq!(bearer_token("Bearer abc.def.ghi"), Some("abc.def.ghi"));
+        assert_eq!(bearer_token("bearer abc.def.ghi"), Some("abc.def.ghi"));
+        assert_eq!(bearer_token("BEARER   abc.def.ghi "), Some("abc.def.ghi"));
+        assert_eq!(bearer_token("Bearer\tabc.def.ghi"), Some("abc.def.ghi"));
+        assert_eq!(bearer_token("Bearer "), None);
+        assert_eq!(bearer_token("Bearerabc.def.ghi"), None);
+        assert_eq!(bearer_token("Basic dXNlcjpwYXNz"), None);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn validates() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        write_jwks(&file, SEED);
+        let authn = authn(file.path());
+        let jwt = JwtValidator::default();
+
+        let valid = serde_json::json!({
+            "iss": "https://issuer.example.com",
+            "aud": ["other", "svc"],
+            "exp": exp(60),
+            "groups": ["users", "admin"],
+        });
+
+        // Tokens cannot be validated until the JWKS has been loaded.
+        assert!(matches!(
+            jwt.validate(&authn, &headers(&token(SEED, valid.clone()))),
+            Err(InvalidJwt::JwksUnavailable(_))
+        ));
+        loaded(&jwt, file.path()).await;
+        jwt.validate(&authn, &headers(&token(SEED, valid.clone())))
+            .expect("token must be valid");
+
+        assert!(matches!(
+            jwt.validate(&authn, &::http::HeaderMap::new()),
+            Err(InvalidJwt::Missing)
+        ));
+        assert!(matches!(
+            jwt.validate(&authn, &headers("garbage")),
+            Err(InvalidJwt::Malformed)
+        ));
+        assert!(matches!(
+            jwt.validate(&authn, &headers(&token(OTHER_SEED, valid.clone()))),
+            Err(InvalidJwt::Signature)
+        ));
+
+        let with = |name: &str, value: serde_json::Value| {
+            let mut claims = valid.clone();
+            claims[name] = value;
+            jwt.validate(&authn, &headers(&token(SEED, claims)))
+        };
+        assert!(matches!(
+            with("exp", exp(-120).into()),
+            Err(InvalidJwt::Expired)
+        ));
+        assert!(matches!(
+            with("nbf", exp(120).into()),
+            Err(InvalidJwt::NotYetValid)
+        ));
+        assert!(matches!(
+            with("iss", "https://evil.example.com".into()),
+            Err(InvalidJwt::Issuer)
+        ));
+        assert!(matches!(
+            with("aud", "other".into()),
+            Err(InvalidJwt::Audience)
+        ));
+        assert!(matches!(
+            with("groups", "users".into()),
+            Err(InvalidJwt::Claim(_))
+        ));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn reloads_jwks() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        write_jwks(&file, SEED);
+        let authn = authn(file.path());
+        let jwt = JwtValidator {
+            reload_interval: time::Duration::from_millis(10),
+            ..Default::default()
+        };
+
+        let token = |seed| {
+            token(
+                seed,
+                serde_json::json!({
+                    "iss": "https://issuer.example.com",
+                    "aud": "svc",
+                    "exp": exp(60),
+                    "groups": "admin",
+                }),
+            )
+        };
+        loaded(&jwt, file.path()).await;
+        jwt.validate(&authn, &headers(&token(SEED)))
+            .expect("token must be valid");
+
+        // When the file cannot be read, the last valid keys continue to be
+        // used.
+        std::fs::write(file.path(), "garbage").unwrap();
+        time::sleep(time::Duration::from_millis(50)).await;
+        jwt.validate(&authn, &headers(&token(SEED)))
+            .expect("token must be valid");
+
+        // Rotate the key.
+        write_jwks(&file, OTHER_SEED);
+        time::timeout(time::Duration::from_secs(5), async {
+            while jwt.validate(&authn, &headers(&token(OTHER_SEED))).is_err() {
+                time::sleep(time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("JWKS must reload");
+        assert!(matches!(
+            jwt.validate(&authn, &headers(&token(SEED))),
+            Err(InvalidJwt::Signature)
+        ));
+        jwt.validate(&authn, &headers(&token(OTHER_SEED)))
+            .expect("token must be valid");
+    }
+}
@@ -0,0 +1,180 @@
+use linkerd_app_core::{
+    metrics::RouteLabels,
+    tls,
+    transport::{ClientAddr, Remote},
+};
+use linkerd_proxy_server_policy::{LocalRateLimit, RateLimitKey};
+use parking_lot::Mutex;
+use std::{collections::HashMap, net::IpAddr, sync::Arc};
+use tokio::time;
+
+/// Holds the token buckets for all of a server's local rate limits, so that
+/// limits are shared by all connections to the server.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RateLimits(Arc<Mutex<Inner>>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    buckets: HashMap<Key, Bucket>,
+    /// The number of buckets at which idle buckets are next removed.
+    sweep_at: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct Key {
+    route: RouteLabels,
+    limit: LocalRateLimit,
+    client: Client,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Client {
+    Any,
+    Identity(Option<tls::ClientId>),
+    Ip(IpAddr),
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: time::Instant,
+}
+
+// === impl RateLimits ===
+
+impl RateLimits {
+    /// Buckets are not swept until at least this many have been created.
+    const MIN_SWEEP: usize = 1_000;
+
+    /// Takes a token from the bucket for the given route and client, returning
+    /// false if the request should be refused.
+    pub(crate) fn acquire(
+        &self,
+        route: &RouteLabels,
+        limit: &LocalRateLimit,
+        client: Remote<ClientAddr>,
+        tls: &tls::ConditionalServerTls,
+    ) -> bool {
+        let client = match limit.key {
+            RateLimitKey::Route => Client::Any,
+            RateLimitKey::ClientIp => Client::Ip(client.ip()),
+            RateLimitKey::ClientIdentity => Client::Identity(match tls {
+                tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                    client_id, ..
+                }) => client_id.clone(),
+                _ => None,
+            }),
+        };
+        let key = Key {
+            route: route.clone(),
+            limit: limit.clone(),
+            client,
+        };
+
+        let now = time::Instant::now();
+        let mut inner = self.0.lock();
+        inner.sweep(now);
+        let bucket = inner.buckets.entry(key).or_insert_with(|| Bucket {
+            tokens: f64::from(limit.burst),
+            updated: now,
+        });
+        bucket.acquire(limit, now)
+    }
+}
+
+// === impl Inner ===
+
+impl Inner {
+    /// Removes buckets that have refilled completely, since they are
+    /// equivalent to new buckets.
+    fn sweep(&mut self, now: time::Instant) {
+        if self.buckets.len() < self.sweep_at.max(RateLimits::MIN_SWEEP) {
+            return;
+        }
+
+        self.buckets
+            .retain(|Key { limit, .. }, bucket| !bucket.refill(limit, now));
+        // Wait for the map to double in size before sweeping again, so that
+        // the cost of sweeping is amortized over new buckets.
+        self.sweep_at = self.buckets.len() * 2;
+        tracing::trace!(buckets = self.buckets.len(), "Swept rate limits");
+    }
+}
+
+// === impl Bucket ===
+
+impl Bucket {
+    /// Adds the tokens accrued since the bucket was last updated, returning
+    /// true if the bucket is full.
+    fn refill(&mut self, limit: &LocalRateLimit, now: time::Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.updated);
+        let burst = f64::from(limit.burst);
+        self.tokens =
+            burst.min(self.tokens + elapsed.as_secs_f64() * f64::from(limit.requests_per_second));
+        self.updated = now;
+        self.tokens >= burst
+    }
+
+    fn acquire(&mut self, limit: &LocalRateLimit, now: time::Instant) -> bool {
+        self.refill(limit, now);
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::metrics::ServerLabel;
+    use linkerd_proxy_server_policy::Meta;
+
+    fn route() -> RouteLabels {
+        RouteLabels {
+            server: ServerLabel(Meta::new_default("test")),
+            route: Meta::new_default("test"),
+        }
+    }
+
+    fn client(ip: [u8; 4]) -> Remote<ClientAddr> {
+        Remote(ClientAddr((ip, 30120).into()))
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn refills() {
+        let limits = RateLimits::default();
+        let limit = LocalRateLimit {
+            requests_per_second: 2,
+            burst: 2,
+            key: RateLimitKey::Route,
+        };
+        let tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
+
+        let acquire = |ip| limits.acquire(&route(), &limit, client(ip), &tls);
+        assert!(acquire([192, 0, 2, 1]));
+        assert!(acquire([192, 0, 2, 2]));
+        assert!(!acquire([192, 0, 2, 1]));
+
+        time::sleep(time::Duration::from_millis(500)).await;
+        assert!(acquire([192, 0, 2, 2]));
+        assert!(!acquire([192, 0, 2, 1]));
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn per_client_ip() {
+        let limits = RateLimits::default();
+        let limit = LocalRateLimit {
+            requests_per_second: 1,
+            burst: 1,
+            key: RateLimitKey::ClientIp,
+        };
+        let tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
+
+        let acquire = |ip| limits.acquire(&route(), &limit, client(ip), &tls);
+        assert!(acquire([192, 0, 2, 1]));
+        assert!(!acquire([192, 0, 2, 1]));
+        assert!(acquire([192, 0, 2, 2]));
+    }
+}
@@ -30,6 +30,7 @@ macro_rules! new_svc {
                     kind: "Server".into(),
                     name: "testsrv".into(),
                 }),
+                authz_mode: Default::default(),
             },
         );
         let svc = HttpPolicyService {
@@ -37,6 +38,9 @@ macro_rules! new_svc {
             policy,
             connection: $conn,
             metrics: HttpAuthzMetrics::default(),
+            rate_limits: Default::default(),
+            jwt: Default::default(),
+            ext_authz: Default::default(),
             inner: |(permit, _): (HttpRoutePermit, ())| {
                 svc::mk(move |req: ::http::Request<hyper::Body>| {
                     futures::future::ready($rsp(permit.clone(), req))
@@ -90,6 +94,7 @@ async fn http_route() {
                     }]),
                     filters: vec![],
                     meta: rmeta.clone(),
+                    authz_mode: Default::default(),
                 },
             },
             Rule {
@@ -101,6 +106,7 @@ async fn http_route() {
                     authorizations: Arc::new([]),
                     filters: vec![],
                     meta: rmeta.clone(),
+                    authz_mode: Default::default(),
                 },
             }
         ],
@@ -174,6 +180,7 @@ async fn http_route() {
                         }]),
                         filters: vec![],
                         meta: rmeta.clone(),
+                        authz_mode: Default::default(),
                     },
                 },
                 Rule {
@@ -193,10 +200,12 @@ async fn http_route() {
                         }]),
                         filters: vec![],
                         meta: rmeta.clone(),
+                        authz_mode: Default::default(),
                     },
                 },
             ],
         }])),
+        authz_mode: Default::default(),
     })
     .expect("must send");
 
@@ -270,6 +279,7 @@ async fn http_filter_header() {
                     ..filter::ModifyHeader::default()
                 })],
                 meta: rmeta.clone(),
+                authz_mode: Default::default(),
             },
         }],
     }]));
@@ -338,6 +348,7 @@ async fn http_filter_inject_failure() {
                     },
                 })],
                 meta: rmeta.clone(),
+                authz_mode: Default::default(),
             },
         }],
     }]));
@@ -363,6 +374,76 @@ async fn http_filter_inject_failure() {
     );
 }
 
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn http_filter_rate_limit() {
+    use linkerd_proxy_server_policy::{
+        http::{r#match::MatchRequest, Filter, Policy, Route, Rule},
+        LocalRateLimit, RateLimitKey,
+    };
+
+    let rmeta = Arc::new(Meta::Resource {
+        group: "gateway.networking.k8s.io".into(),
+        kind: "httproute".into(),
+        name: "testrt".into(),
+    });
+    let proto = Protocol::Http1(Arc::new([Route {
+        hosts: vec![],
+        rules: vec![Rule {
+            matches: vec![MatchRequest::default()],
+            policy: Policy {
+                authorizations: Arc::new([Authorization {
+                    authentication: Authentication::Unauthenticated,
+                    networks: vec![std::net::IpAddr::from([192, 168, 3, 3]).into()],
+                    meta: Arc::new(Meta::Resource {
+                        group: "policy.linkerd.io".into(),
+                        kind: "AuthorizationPolicy".into(),
+                        name: "test".into(),
+                    }),
+                }]),
+                filters: vec![Filter::RateLimit(LocalRateLimit {
+                    requests_per_second: 1,
+                    burst: 2,
+                    key: RateLimitKey::ClientIdentity,
+                })],
+                meta: rmeta.clone(),
+                authz_mode: Default::default(),
+            },
+        }],
+    }]));
+    let (mut svc, _tx) = new_svc!(proto);
+
+    // The burst is permitted.
+    for _ in 0..2 {
+        svc.call(
+            ::http::Request::builder()
+                .body(hyper::Body::default())
+                .unwrap(),
+        )
+        .await
+        .expect("serves");
+    }
+
+    // But subsequent requests are refused until the bucket is refilled.
+    assert!(svc
+        .call(
+            ::http::Request::builder()
+                .body(hyper::Body::default())
+                .unwrap(),
+        )
+        .await
+        .expect_err("fails")
+        .is::<HttpRouteRateLimited>());
+
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    svc.call(
+        ::http::Request::builder()
+            .body(hyper::Body::default())
+            .unwrap(),
+    )
+    .await
+    .expect("serves");
+}
+
 #[tokio::test(flavor = "current_thread")]
 async fn grpc_route() {
     use linkerd_proxy_server_policy::grpc::{
@@ -398,6 +479,7 @@ async fn grpc_route() {
                     }]),
                     filters: vec![],
                     meta: rmeta.clone(),
+                    authz_mode: Default::default(),
                 },
             },
             Rule {
@@ -412,6 +494,7 @@ async fn grpc_route() {
                     authorizations: Arc::new([]),
                     filters: vec![],
                     meta: rmeta.clone(),
+                    authz_mode: Default::default(),
                 },
             }
         ],
@@ -499,6 +582,7 @@ async fn grpc_filter_header() {
                     ..http::filter::ModifyHeader::default()
                 })],
                 meta: rmeta.clone(),
+                authz_mode: Default::default(),
             },
         }],
     }]));
@@ -575,6 +659,7 @@ async fn grpc_filter_inject_failure() {
                     },
                 })],
                 meta: rmeta.clone(),
+                authz_mode: Default::default(),
             },
         }],
     }]));
@@ -601,3 +686,343 @@ async fn grpc_filter_inject_failure() {
         }
     );
 }
+
+#[tokio::test(flavor = "current_thread")]
+async fn http_route_jwt_unauthenticated() {
+    use linkerd_proxy_server_policy::{
+        authz::JwtAuthentication,
+        http::{r#match::MatchRequest, Policy, Route, Rule},
+    };
+
+    let rmeta = Arc::new(Meta::Resource {
+        group: "gateway.networking.k8s.io".into(),
+        kind: "httproute".into(),
+        name: "testrt".into(),
+    });
+    let authz = |client: [u8; 4]| Authorization {
+        authentication: Authentication::Jwt(JwtAuthentication {
+            jwks_path: "/var/run/linkerd/jwks.json".into(),
+            issuer: None,
+            audiences: Default::default(),
+            claims: Default::default(),
+        }),
+        networks: vec![std::net::IpAddr::from(client).into()],
+        meta: Arc::new(Meta::Resource {
+            group: "policy.linkerd.io".into(),
+            kind: "AuthorizationPolicy".into(),
+            name: "jwt".into(),
+        }),
+    };
+    let proto = |client| {
+        Protocol::Http1(Arc::new([Route {
+            hosts: vec![],
+            rules: vec![Rule {
+                matches: vec![MatchRequest::default()],
+                policy: Policy {
+                    authorizations: Arc::new([authz(client)]),
+                    filters: vec![],
+                    meta: rmeta.clone(),
+                    authz_mode: Default::default(),
+                },
+            }],
+        }]))
+    };
+    let inner = |_: HttpRoutePermit,
+                 _: ::http::Request<hyper::Body>|
+     -> Result<::http::Response<hyper::Body>> { unreachable!() };
+    let (mut svc, tx) = new_svc!(proto([192, 168, 3, 3]), conn!(), inner);
+
+    // Requests without a token are unauthenticated.
+    let err = svc
+        .call(
+            ::http::Request::builder()
+                .body(hyper::Body::default())
+                .unwrap(),
+        )
+        .await
+        .expect_err("fails");
+    assert!(err.is::<HttpRouteUnauthenticated>(), "{err}");
+
+    // Clients from other networks are unauthorized, regardless of their
+    // tokens.
+    tx.send_modify(|p| p.protocol = proto([192, 168, 3, 5]));
+    let err = svc
+        .call(
+            ::http::Request::builder()
+                .body(hyper::Body::default())
+                .unwrap(),
+        )
+        .await
+        .expect_err("fails");
+    assert!(err.is::<HttpRouteUnauthorized>(), "{err}");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn http_route_audit() {
+    use linkerd_proxy_server_policy::{
+        http::{r#match::MatchRequest, Policy, Route, Rule},
+        AuthzMode,
+    };
+
+    let rmeta = Arc::new(Meta::Resource {
+        group: "gateway.networking.k8s.io".into(),
+        kind: "httproute".into(),
+        name: "testrt".into(),
+    });
+    let proto = |authz_mode| {
+        Protocol::Http1(Arc::new([Route {
+            hosts: vec![],
+            rules: vec![Rule {
+                matches: vec![MatchRequest::default()],
+                policy: Policy {
+                    authorizations: Arc::new([Authorization {
+                        authentication: Authentication::Unauthenticated,
+                        networks: vec![std::net::IpAddr::from([192, 168, 3, 5]).into()],
+                        meta: Arc::new(Meta::Resource {
+                            group: "policy.linkerd.io".into(),
+                            kind: "AuthorizationPolicy".into(),
+                            name: "other".into(),
+                        }),
+                    }]),
+                    filters: vec![],
+                    meta: rmeta.clone(),
+                    authz_mode,
+                },
+            }],
+        }]))
+    };
+    let (mut svc, tx) = new_svc!(proto(AuthzMode::Enforce));
+
+    // Clients that do not match any authorization are denied when the policy
+    // is enforced.
+    let err = svc
+        .call(
+            ::http::Request::builder()
+                .body(hyper::Body::default())
+                .unwrap(),
+        )
+        .await
+        .expect_err("fails");
+    assert!(err.is::<HttpRouteUnauthorized>(), "{err}");
+
+    // Routes in audit mode permit the request.
+    tx.send_modify(|p| p.protocol = proto(AuthzMode::Audit));
+    let rsp = svc
+        .call(
+            ::http::Request::builder()
+                .body(hyper::Body::default())
+                .unwrap(),
+        )
+        .await
+        .expect("serves");
+    let permit = rsp
+        .extensions()
+        .get::<HttpRoutePermit>()
+        .expect("permitted");
+    assert_eq!(permit.labels.route.route, rmeta);
+    assert_eq!(permit.labels.authz, Meta::new_default("audit"));
+
+    // Servers in audit mode permit requests on all of their routes.
+    tx.send_modify(|p| {
+        p.protocol = proto(AuthzMode::Enforce);
+        p.authz_mode = AuthzMode::Audit;
+    });
+    let rsp = svc
+        .call(
+            ::http::Request::builder()
+                .body(hyper::Body::default())
+                .unwrap(),
+        )
+        .await
+        .expect("serves");
+    let permit = rsp
+        .extensions()
+        .get::<HttpRoutePermit>()
+        .expect("permitted");
+    assert_eq!(permit.labels.authz, Meta::new_default("audit"));
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn http_route_ext_authz() {
+    use super::ext_authz::proto;
+    use linkerd_proxy_server_policy::{
+        ext_authz::FailureMode,
+        http::{r#match::MatchRequest, Filter, Policy, Route, Rule},
+    };
+    use prost::Message;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let rmeta = Arc::new(Meta::Resource {
+        group: "gateway.networking.k8s.io".into(),
+        kind: "httproute".into(),
+        name: "testrt".into(),
+    });
+    let proto = |failure_mode| {
+        Protocol::Http1(Arc::new([Route {
+            hosts: vec![],
+            rules: vec![Rule {
+                matches: vec![MatchRequest::default()],
+                policy: Policy {
+                    authorizations: Arc::new([Authorization {
+                        authentication: Authentication::Unauthenticated,
+                        networks: vec![std::net::IpAddr::from([192, 168, 3, 3]).into()],
+                        meta: Arc::new(Meta::Resource {
+                            group: "policy.linkerd.io".into(),
+                            kind: "AuthorizationPolicy".into(),
+                            name: "test".into(),
+                        }),
+                    }]),
+                    filters: vec![Filter::ExtAuthz(ExtAuthz {
+                        timeout: std::time::Duration::from_secs(1),
+                        failure_mode,
+                        cache_ttl: Some(std::time::Duration::from_secs(10)),
+                        headers: vec![::http::header::HeaderName::from_static("x-user")],
+                    })],
+                    meta: rmeta.clone(),
+                    authz_mode: Default::default(),
+                },
+            }],
+        }]))
+    };
+    let inner = |_: HttpRoutePermit, req: ::http::Request<hyper::Body>| {
+        let mut rsp = ::http::Response::builder();
+        if let Some(v) = req.headers().get("x-authz") {
+            rsp = rsp.header("x-authz", v);
+        }
+        Ok::<_, Infallible>(rsp.body(hyper::Body::default()).unwrap())
+    };
+    let (mut svc, tx) = new_svc!(proto(FailureMode::Deny), conn!(), inner);
+
+    // The authorization service permits requests from admins, adding a header.
+    let checks = Arc::new(AtomicUsize::new(0));
+    svc.ext_authz = ExtAuthzClient::new({
+        let checks = checks.clone();
+        tower::service_fn(move |req: ::http::Request<tonic::body::BoxBody>| {
+            checks.fetch_add(1, Ordering::SeqCst);
+            async move {
+                let body = hyper::body::to_bytes(req.into_body()).await?;
+                let check = proto::CheckRequest::decode(&body[5..])?;
+                let attrs = check.attributes.unwrap();
+                assert_eq!(attrs.source.unwrap().principal, "foo.bar.bah");
+                let http = attrs.request.unwrap().http.unwrap();
+                assert_eq!(http.path, "/foo");
+
+                let user = http.headers.get("x-user").map(|s| s.as_str());
+                let rsp = if user == Some("admin") {
+                    proto::CheckResponse {
+                        ok_response: Some(proto::OkHttpResponse {
+                            headers: vec![proto::HeaderValueOption {
+                                header: Some(proto::HeaderValue {
+                                    key: "x-authz".into(),
+                                    value: "ok".into(),
+                                }),
+                                append: None,
+                            }],
+                            headers_to_remove: vec![],
+                        }),
+                        ..Default::default()
+                    }
+                } else {
+                    // Guests are denied with the status they request.
+                    let status = user
+                        .and_then(|u| u.strip_prefix("guest-"))
+                        .and_then(|s| s.parse().ok())
+                        .map(|code| proto::DeniedHttpResponse {
+                            status: Some(proto::HttpStatus { code }),
+                        });
+                    proto::CheckResponse {
+                        status: Some(proto::RpcStatus {
+                            code: tonic::Code::PermissionDenied as i32,
+                            message: "denied".into(),
+                        }),
+                        denied_response: status,
+                        ..Default::default()
+                    }
+                };
+                let rsp = rsp.encode_to_vec();
+                let mut frame = vec![0];
+                frame.extend_from_slice(&(rsp.len() as u32).to_be_bytes());
+                frame.extend_from_slice(&rsp);
+                Ok::<_, Error>(
+                    ::http::Response::builder()
+                        .header("content-type", "application/grpc")
+                        .body(hyper::Body::from(frame))
+                        .unwrap(),
+                )
+            }
+        })
+    });
+    let req = |user: &str| {
+        ::http::Request::builder()
+            .uri("/foo")
+            .header("x-user", user)
+            .body(hyper::Body::default())
+            .unwrap()
+    };
+
+    let rsp = svc.call(req("admin")).await.expect("serves");
+    assert_eq!(rsp.headers().get("x-authz").unwrap(), "ok");
+    assert_eq!(checks.load(Ordering::SeqCst), 1);
+    assert_eq!(authz_metric(&svc.metrics, "allow"), 1);
+
+    // Decisions are cached.
+    let rsp = svc.call(req("admin")).await.expect("serves");
+    assert_eq!(rsp.headers().get("x-authz").unwrap(), "ok");
+    assert_eq!(checks.load(Ordering::SeqCst), 1);
+
+    let err = svc.call(req("guest")).await.expect_err("fails");
+    assert_eq!(
+        err.downcast_ref::<HttpRouteExtAuthzDenied>()
+            .expect("must be denied")
+            .status,
+        ::http::StatusCode::FORBIDDEN
+    );
+    assert_eq!(checks.load(Ordering::SeqCst), 2);
+    assert_eq!(authz_metric(&svc.metrics, "allow"), 2);
+    assert_eq!(authz_metric(&svc.metrics, "deny"), 1);
+
+    // Denials may use any client or server error status. Other statuses are
+    // replaced with a 403 so that denials can't be mistaken for responses.
+    for (user, status) in [
+        ("guest-429", ::http::StatusCode::TOO_MANY_REQUESTS),
+        ("guest-503", ::http::StatusCode::SERVICE_UNAVAILABLE),
+        ("guest-200", ::http::StatusCode::FORBIDDEN),
+        ("guest-302", ::http::StatusCode::FORBIDDEN),
+    ] {
+        let err = svc.call(req(user)).await.expect_err("fails");
+        assert_eq!(
+            err.downcast_ref::<HttpRouteExtAuthzDenied>()
+                .expect("must be denied")
+                .status,
+            status,
+            "{user}"
+        );
+    }
+    assert_eq!(authz_metric(&svc.metrics, "deny"), 5);
+
+    // When the authorization service is unavailable, requests are denied
+    // unless the route permits requests on failure.
+    svc.ext_authz = ExtAuthzClient::default();
+    let err = svc.call(req("admin")).await.expect_err("fails");
+    assert!(err.is::<HttpRouteExtAuthzFailed>(), "{err}");
+    assert_eq!(authz_metric(&svc.metrics, "deny"), 6);
+
+    tx.send_modify(|p| p.protocol = proto(FailureMode::Allow));
+    let rsp = svc.call(req("admin")).await.expect("serves");
+    assert!(rsp.headers().get("x-authz").is_none());
+    assert_eq!(authz_metric(&svc.metrics, "allow"), 3);
+}
+
+/// Sums the `inbound_http_authz_<kind>_total` counters.
+fn authz_metric(metrics: &HttpAuthzMetrics, kind: &str) -> u64 {
+    use linkerd_app_core::metrics::FmtMetrics;
+
+    let prefix = format!("inbound_http_authz_{kind}_total{{");
+    metrics
+        .as_display()
+        .to_string()
+        .lines()
+        .filter(|l| l.starts_with(&prefix))
+        .filter_map(|l| l.rsplit(' ').next()?.parse::<u64>().ok())
+        .sum()
+}
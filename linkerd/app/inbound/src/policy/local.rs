@@ -0,0 +1,406 @@
+use super::{DefaultPolicy, Protocol, ServerPolicy};
+use linkerd_app_core::{Ipv4Net, Ipv6Net};
+use linkerd_proxy_server_policy::{
+    authz::JwtAuthentication,
+    grpc,
+    http::{self, filter::UrlRewrite, r#match::MatchPath},
+    Authentication, Authorization, AuthzMode, ExtAuthz, LocalRateLimit, Meta, RoutePolicy,
+};
+use std::sync::Arc;
+
+/// Configures server policy features that the policy API cannot yet express.
+///
+/// This configuration is applied to every discovered, fixed, and default
+/// policy. Each feature is disabled unless it is configured.
+#[derive(Clone, Debug, Default)]
+pub struct LocalConfig {
+    /// Limits the rate of requests on HTTP and gRPC routes that do not
+    /// configure a rate limit.
+    pub rate_limit: Option<LocalRateLimit>,
+
+    /// Rewrites requests on HTTP routes that match a path prefix.
+    pub url_rewrites: Vec<UrlRewriteConfig>,
+
+    /// Authorizes requests on HTTP and gRPC routes from clients on any network
+    /// that present a valid JWT, in addition to the route's authorizations.
+    pub jwt: Option<JwtAuthentication>,
+
+    /// Checks requests on HTTP and gRPC routes that do not configure external
+    /// authorization with the external authorization service.
+    pub ext_authz: Option<ExtAuthz>,
+
+    /// Permits the connections and requests that would be denied on the named
+    /// servers and routes, so that their policies may be observed before they
+    /// are enforced.
+    pub audit: AuditConfig,
+}
+
+/// Names the servers and routes whose policies are audited rather than
+/// enforced. The name `*` matches all servers or routes.
+#[derive(Clone, Debug, Default)]
+pub struct AuditConfig {
+    pub servers: Vec<String>,
+    pub routes: Vec<String>,
+}
+
+/// Rewrites requests on the HTTP route rules that only match `prefix`.
+///
+/// The rewrite may replace the matched prefix, since these rules always match
+/// a path prefix.
+#[derive(Clone, Debug)]
+pub struct UrlRewriteConfig {
+    pub prefix: String,
+    pub rewrite: UrlRewrite,
+}
+
+// === impl LocalConfig ===
+
+impl LocalConfig {
+    pub(crate) fn apply(&self, policy: ServerPolicy) -> ServerPolicy {
+        let protocol = match policy.protocol {
+            Protocol::Detect {
+                http,
+                timeout,
+                tcp_authorizations,
+            } => Protocol::Detect {
+                http: self.http_routes(&http),
+                timeout,
+                tcp_authorizations,
+            },
+            Protocol::Http1(http) => Protocol::Http1(self.http_routes(&http)),
+            Protocol::Http2(http) => Protocol::Http2(self.http_routes(&http)),
+            Protocol::Grpc(grpc) => Protocol::Grpc(self.grpc_routes(&grpc)),
+            protocol @ (Protocol::Tls(_) | Protocol::Opaque(_)) => protocol,
+        };
+
+        let authz_mode = if AuditConfig::matches(&self.audit.servers, &policy.meta) {
+            AuthzMode::Audit
+        } else {
+            policy.authz_mode
+        };
+
+        ServerPolicy {
+            protocol,
+            authz_mode,
+            ..policy
+        }
+    }
+
+    pub(crate) fn apply_default(&self, default: DefaultPolicy) -> DefaultPolicy {
+        match default {
+            DefaultPolicy::Allow(policy) => DefaultPolicy::Allow(self.apply(policy)),
+            DefaultPolicy::Deny => DefaultPolicy::Deny,
+        }
+    }
+
+    fn http_routes(&self, routes: &[http::Route]) -> Arc<[http::Route]> {
+        map_routes(routes, |route| {
+            for rule in route.rules.iter_mut() {
+                self.authorizations(&mut rule.policy.authorizations);
+                self.authz_mode(&mut rule.policy);
+                self.http_filters(&mut rule.policy.filters);
+                self.url_rewrite(rule);
+            }
+        })
+    }
+
+    fn http_filters(&self, filters: &mut Vec<http::Filter>) {
+        if let Some(limit) = self.rate_limit.as_ref() {
+            if !filters
+                .iter()
+                .any(|f| matches!(f, http::Filter::RateLimit(_)))
+            {
+                filters.push(http::Filter::RateLimit(limit.clone()));
+            }
+        }
+        if let Some(ext_authz) = self.ext_authz.as_ref() {
+            if !filters
+                .iter()
+                .any(|f| matches!(f, http::Filter::ExtAuthz(_)))
+            {
+                filters.push(http::Filter::ExtAuthz(ext_authz.clone()));
+            }
+        }
+    }
+
+    fn authorizations(&self, authzs: &mut Arc<[Authorization]>) {
+        if let Some(jwt) = self.jwt.as_ref() {
+            let authentication = Authentication::Jwt(jwt.clone());
+            if authzs.iter().any(|a| a.authentication == authentication) {
+                return;
+            }
+            let jwt = Authorization {
+                networks: vec![Ipv4Net::default().into(), Ipv6Net::default().into()],
+                authentication,
+                meta: Meta::new_default("jwt"),
+            };
+            *authzs = authzs.iter().cloned().chain(Some(jwt)).collect();
+        }
+    }
+
+    fn authz_mode<F>(&self, policy: &mut RoutePolicy<F>) {
+        if AuditConfig::matches(&self.audit.routes, &policy.meta) {
+            policy.authz_mode = AuthzMode::Audit;
+        }
+    }
+
+    fn url_rewrite(&self, rule: &mut http::Rule) {
+        if rule
+            .policy
+            .filters
+            .iter()
+            .any(|f| matches!(f, http::Filter::UrlRewrite(_)))
+        {
+            return;
+        }
+        if let Some(config) = self
+            .url_rewrites
+            .iter()
+            .find(|config| config.matches(&rule.matches))
+        {
+            rule.policy
+                .filters
+                .push(http::Filter::UrlRewrite(config.rewrite.clone()));
+        }
+    }
+
+    fn grpc_routes(&self, routes: &[grpc::Route]) -> Arc<[grpc::Route]> {
+        map_routes(routes, |route| {
+            for rule in route.rules.iter_mut() {
+                self.authorizations(&mut rule.policy.authorizations);
+                self.authz_mode(&mut rule.policy);
+                self.grpc_filters(&mut rule.policy.filters);
+            }
+        })
+    }
+
+    fn grpc_filters(&self, filters: &mut Vec<grpc::Filter>) {
+        if let Some(limit) = self.rate_limit.as_ref() {
+            if !filters
+                .iter()
+                .any(|f| matches!(f, grpc::Filter::RateLimit(_)))
+            {
+                filters.push(grpc::Filter::RateLimit(limit.clone()));
+            }
+        }
+        if let Some(ext_authz) = self.ext_authz.as_ref() {
+            if !filters
+                .iter()
+                .any(|f| matches!(f, grpc::Filter::ExtAuthz(_)))
+            {
+                filters.push(grpc::Filter::ExtAuthz(ext_authz.clone()));
+            }
+        }
+    }
+}
+
+// === impl AuditConfig ===
+
+impl AuditConfig {
+    fn matches(names: &[String], meta: &Meta) -> bool {
+        names.iter().any(|n| n == "*" || n == meta.name())
+    }
+}
+
+// === impl UrlRewriteConfig ===
+
+impl UrlRewriteConfig {
+    fn matches(&self, matches: &[http::r#match::MatchRequest]) -> bool {
+        !matches.is_empty()
+            && matches.iter().all(|m| match m.path {
+                Some(MatchPath::Prefix(ref prefix)) => *prefix == self.prefix,
+                _ => false,
+            })
+    }
+}
+
+fn map_routes<R: Clone>(routes: &[R], f: impl Fn(&mut R)) -> Arc<[R]> {
+    routes
+        .iter()
+        .cloned()
+        .map(|mut route| {
+            f(&mut route);
+            route
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_proxy_server_policy::{Meta, RateLimitKey};
+
+    fn http_policy() -> ServerPolicy {
+        ServerPolicy {
+            protocol: Protocol::Http1(Arc::new([http::default(Arc::new([]))])),
+            meta: Meta::new_default("test"),
+            authz_mode: Default::default(),
+        }
+    }
+
+    fn http_filters(policy: &ServerPolicy) -> &[http::Filter] {
+        match policy.protocol {
+            Protocol::Http1(ref routes) => &routes[0].rules[0].policy.filters,
+            ref protocol => panic!("unexpected protocol: {protocol:?}"),
+        }
+    }
+
+    #[test]
+    fn rate_limits_routes() {
+        let limit = LocalRateLimit {
+            requests_per_second: 10,
+            burst: 20,
+            key: RateLimitKey::ClientIdentity,
+        };
+        let local = LocalConfig {
+            rate_limit: Some(limit.clone()),
+            ..Default::default()
+        };
+
+        let policy = local.apply(http_policy());
+        assert_eq!(
+            http_filters(&policy),
+            &[http::Filter::RateLimit(limit.clone())]
+        );
+
+        // Applying the configuration again does not add another limit.
+        let policy = local.apply(policy);
+        assert_eq!(http_filters(&policy), &[http::Filter::RateLimit(limit)]);
+    }
+
+    #[test]
+    fn rewrites_prefix_routes() {
+        use http::{filter::ModifyPath, r#match::MatchRequest};
+
+        let rewrite = UrlRewrite {
+            authority: None,
+            path: Some(ModifyPath::ReplacePrefixMatch("/legacy".to_string())),
+        };
+        let local = LocalConfig {
+            url_rewrites: vec![UrlRewriteConfig {
+                prefix: "/new".to_string(),
+                rewrite: rewrite.clone(),
+            }],
+            ..Default::default()
+        };
+
+        // Rules that do not match the prefix are not rewritten.
+        let policy = local.apply(http_policy());
+        assert!(http_filters(&policy).is_empty());
+
+        let mut policy = http_policy();
+        if let Protocol::Http1(ref mut routes) = policy.protocol {
+            let mut route = routes[0].clone();
+            route.rules[0].matches = vec![MatchRequest {
+                path: Some(MatchPath::Prefix("/new".to_string())),
+                ..Default::default()
+            }];
+            *routes = Arc::new([route]);
+        }
+        let policy = local.apply(policy);
+        assert_eq!(
+            http_filters(&policy),
+            &[http::Filter::UrlRewrite(rewrite.clone())]
+        );
+
+        // Applying the configuration again does not add another rewrite.
+        let policy = local.apply(policy);
+        assert_eq!(http_filters(&policy), &[http::Filter::UrlRewrite(rewrite)]);
+    }
+
+    #[test]
+    fn authorizes_jwt_routes() {
+        let jwt = JwtAuthentication {
+            jwks_path: "/var/run/jwks.json".into(),
+            issuer: Some("issuer".to_string()),
+            audiences: Default::default(),
+            claims: Default::default(),
+        };
+        let local = LocalConfig {
+            jwt: Some(jwt.clone()),
+            ..Default::default()
+        };
+
+        let authentications = |policy: &ServerPolicy| match policy.protocol {
+            Protocol::Http1(ref routes) => routes[0].rules[0]
+                .policy
+                .authorizations
+                .iter()
+                .map(|a| a.authentication.clone())
+                .collect::<Vec<_>>(),
+            ref protocol => panic!("unexpected protocol: {protocol:?}"),
+        };
+
+        let policy = local.apply(http_policy());
+        assert_eq!(
+            authentications(&policy),
+            vec![Authentication::Jwt(jwt.clone())]
+        );
+
+        // Applying the configuration again does not add another authorization.
+        let policy = local.apply(policy);
+        assert_eq!(authentications(&policy), vec![Authentication::Jwt(jwt)]);
+    }
+
+    #[test]
+    fn checks_routes_with_ext_authz() {
+        use linkerd_proxy_server_policy::ext_authz::FailureMode;
+
+        let ext_authz = ExtAuthz {
+            timeout: std::time::Duration::from_secs(1),
+            failure_mode: FailureMode::Deny,
+            cache_ttl: None,
+            headers: vec![],
+        };
+        let local = LocalConfig {
+            ext_authz: Some(ext_authz.clone()),
+            ..Default::default()
+        };
+
+        let policy = local.apply(http_policy());
+        assert_eq!(
+            http_filters(&policy),
+            &[http::Filter::ExtAuthz(ext_authz.clone())]
+        );
+
+        // Applying the configuration again does not add another check.
+        let policy = local.apply(policy);
+        assert_eq!(http_filters(&policy), &[http::Filter::ExtAuthz(ext_authz)]);
+    }
+
+    #[test]
+    fn audits_named_servers_and_routes() {
+        let route_mode = |policy: &ServerPolicy| match policy.protocol {
+            Protocol::Http1(ref routes) => routes[0].rules[0].policy.authz_mode,
+            ref protocol => panic!("unexpected protocol: {protocol:?}"),
+        };
+
+        let local = LocalConfig {
+            audit: AuditConfig {
+                servers: vec!["other".to_string()],
+                routes: vec!["default".to_string()],
+            },
+            ..Default::default()
+        };
+        let policy = local.apply(http_policy());
+        assert_eq!(policy.authz_mode, AuthzMode::Enforce);
+        assert_eq!(route_mode(&policy), AuthzMode::Audit);
+
+        let local = LocalConfig {
+            audit: AuditConfig {
+                servers: vec!["*".to_string()],
+                routes: vec![],
+            },
+            ..Default::default()
+        };
+        let policy = local.apply(http_policy());
+        assert_eq!(policy.authz_mode, AuthzMode::Audit);
+        assert_eq!(route_mode(&policy), AuthzMode::Enforce);
+    }
+
+    #[test]
+    fn unconfigured_is_noop() {
+        let policy = http_policy();
+        assert_eq!(LocalConfig::default().apply(policy.clone()), policy);
+    }
+}
@@ -37,6 +37,10 @@ pub struct Authorized<S> {
     client: Remote<ClientAddr>,
     tls: tls::ConditionalServerTls,
     metrics: TcpAuthzMetrics,
+
+    /// Set when the connection is only permitted by an audit policy, so that
+    /// it is only logged and counted when it becomes audited.
+    audited: bool,
 }
 
 // === impl NewTcpPolicy ===
@@ -69,8 +73,9 @@ where
             let p = policy.server.borrow();
             tracing::trace!(policy = ?p, "Authorizing connection");
             check_authorized(&p, policy.dst, client, &tls)
+                .map_err(|deny| (deny, check_audit(&p, policy.dst, client, &tls)))
         };
-        match authorized {
+        let (permit, audited) = match authorized {
             Ok(permit) => {
                 tracing::debug!(?permit, ?tls, %client, "Connection authorized");
 
@@ -78,17 +83,13 @@ where
                 // connection. So we can just increment the counter here since the service can only
                 // be used at most once.
                 self.metrics.allow(&permit, tls.clone());
-
-                let inner = self.inner.new_service((permit, target));
-                TcpPolicy::Authorized(Authorized {
-                    inner,
-                    policy,
-                    client,
-                    tls,
-                    metrics: self.metrics.clone(),
-                })
+                (permit, false)
+            }
+            Err((_, Some(permit))) => {
+                self.metrics.audit(&policy, tls.clone());
+                (permit, true)
             }
-            Err(deny) => {
+            Err((deny, None)) => {
                 let meta = policy.meta();
                 tracing::info!(
                     server.group = %meta.group(),
@@ -98,9 +99,19 @@ where
                     "Connection denied"
                 );
                 self.metrics.deny(&policy, tls);
-                TcpPolicy::Unauthorized(deny)
+                return TcpPolicy::Unauthorized(deny);
             }
-        }
+        };
+
+        let inner = self.inner.new_service((permit, target));
+        TcpPolicy::Authorized(Authorized {
+            inner,
+            policy,
+            client,
+            tls,
+            metrics: self.metrics.clone(),
+            audited,
+        })
     }
 }
 
@@ -138,6 +149,7 @@ where
             tls,
             policy,
             metrics,
+            audited,
         } = match self {
             Self::Authorized(a) => a,
             Self::Unauthorized(_deny) => unreachable!("poll_ready must be called"),
@@ -149,6 +161,7 @@ where
         let tls = tls.clone();
         let mut policy = policy.clone();
         let metrics = metrics.clone();
+        let mut audited = *audited;
 
         let call = inner.call(io);
         future::Either::Left(Box::pin(async move {
@@ -157,18 +170,38 @@ where
                 tokio::select! {
                     res = &mut call => return res.map_err(Into::into),
                     _ = policy.changed() => {
-                        if let Err(denied) = check_authorized(&policy.server.borrow(), policy.dst, client, &tls) {
-                            let meta = policy.meta();
-                            tracing::info!(
-                                server.group = %meta.group(),
-                                server.kind = %meta.kind(),
-                                server.name = %meta.name(),
-                                ?tls,
-                                %client,
-                                "Connection terminated due to policy change",
-                            );
-                            metrics.terminate(&policy, tls);
-                            return Err(denied.into());
+                        let authorized = {
+                            let p = policy.server.borrow();
+                            match check_authorized(&p, policy.dst, client, &tls) {
+                                Ok(_) => Ok(false),
+                                // A connection that is still permitted by an
+                                // audit policy has already been logged and
+                                // counted.
+                                Err(_) if audited && p.authz_mode.is_audit() => Ok(true),
+                                Err(deny) => match check_audit(&p, policy.dst, client, &tls) {
+                                    Some(_) => {
+                                        metrics.audit(&policy, tls.clone());
+                                        Ok(true)
+                                    }
+                                    None => Err(deny),
+                                },
+                            }
+                        };
+                        match authorized {
+                            Ok(a) => audited = a,
+                            Err(denied) => {
+                                let meta = policy.meta();
+                                tracing::info!(
+                                    server.group = %meta.group(),
+                                    server.kind = %meta.kind(),
+                                    server.name = %meta.name(),
+                                    ?tls,
+                                    %client,
+                                    "Connection terminated due to policy change",
+                                );
+                                metrics.terminate(&policy, tls);
+                                return Err(denied.into());
+                            }
                         }
                     }
                 };
@@ -203,3 +236,37 @@ fn check_authorized(
         server: server.meta.clone(),
     })
 }
+
+/// Permits an unauthorized connection if the server's policy is in audit
+/// mode, logging the connection that would have been denied.
+fn check_audit(
+    server: &ServerPolicy,
+    dst: OrigDstAddr,
+    client: Remote<ClientAddr>,
+    tls: &tls::ConditionalServerTls,
+) -> Option<ServerPermit> {
+    if !server.authz_mode.is_audit() {
+        return None;
+    }
+
+    let authzs = match server.protocol {
+        Protocol::Detect {
+            tcp_authorizations: ref authzs,
+            ..
+        }
+        | Protocol::Tls(ref authzs)
+        | Protocol::Opaque(ref authzs) => super::authz_names(authzs),
+        _ => vec![],
+    };
+    tracing::info!(
+        server.group = %server.meta.group(),
+        server.kind = %server.meta.kind(),
+        server.name = %server.meta.name(),
+        authz.names = ?authzs,
+        ?tls,
+        client.id = ?super::client_id(tls),
+        %client,
+        "Connection would have been denied",
+    );
+    Some(ServerPermit::audit(dst, server))
+}
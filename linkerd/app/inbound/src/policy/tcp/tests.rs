@@ -2,7 +2,8 @@ use super::*;
 use crate::policy::*;
 use linkerd_app_core::{proxy::http, Error};
 use linkerd_proxy_server_policy::{
-    authz::Suffix, Authentication, Authorization, Protocol, ServerPolicy,
+    authz::{SpiffeMatch, Suffix},
+    Authentication, Authorization, Protocol, ServerPolicy,
 };
 use std::{collections::BTreeSet, sync::Arc};
 
@@ -29,6 +30,7 @@ async fn unauthenticated_allowed() {
             kind: "server".into(),
             name: "test".into(),
         }),
+        authz_mode: Default::default(),
     };
 
     let tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
@@ -63,6 +65,7 @@ async fn authenticated_identity() {
                 authentication: Authentication::TlsAuthenticated {
                     suffixes: vec![],
                     identities: vec![client_id().to_string()].into_iter().collect(),
+                    spiffe: vec![],
                 },
                 networks: vec!["192.0.2.0/24".parse().unwrap()],
                 meta: Arc::new(Meta::Resource {
@@ -78,6 +81,7 @@ async fn authenticated_identity() {
             kind: "server".into(),
             name: "test".into(),
         }),
+        authz_mode: Default::default(),
     };
 
     let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
@@ -126,6 +130,7 @@ async fn authenticated_suffix() {
                 authentication: Authentication::TlsAuthenticated {
                     identities: BTreeSet::default(),
                     suffixes: vec![Suffix::from(vec!["cluster".into(), "local".into()])],
+                    spiffe: vec![],
                 },
                 networks: vec!["192.0.2.0/24".parse().unwrap()],
                 meta: Arc::new(Meta::Resource {
@@ -141,6 +146,7 @@ async fn authenticated_suffix() {
             kind: "server".into(),
             name: "test".into(),
         }),
+        authz_mode: Default::default(),
     };
 
     let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
@@ -180,6 +186,61 @@ async fn authenticated_suffix() {
         .expect_err("policy must require a client identity");
 }
 
+#[tokio::test(flavor = "current_thread")]
+async fn authenticated_spiffe() {
+    let policy = ServerPolicy {
+        protocol: Protocol::Opaque(
+            vec![Authorization {
+                authentication: Authentication::TlsAuthenticated {
+                    identities: BTreeSet::default(),
+                    // DNS-like suffixes must not match SPIFFE IDs.
+                    suffixes: vec![Suffix::from(vec!["api".into()])],
+                    spiffe: vec![SpiffeMatch {
+                        trust_domain: "example.org".into(),
+                        path_prefix: "/ns/testns".into(),
+                    }],
+                },
+                networks: vec!["192.0.2.0/24".parse().unwrap()],
+                meta: Arc::new(Meta::Resource {
+                    group: "policy.linkerd.io".into(),
+                    kind: "serverauthorization".into(),
+                    name: "tls-auth".into(),
+                }),
+            }]
+            .into(),
+        ),
+        meta: Arc::new(Meta::Resource {
+            group: "policy.linkerd.io".into(),
+            kind: "server".into(),
+            name: "test".into(),
+        }),
+        authz_mode: Default::default(),
+    };
+
+    let tls = |id: &str| {
+        tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+            client_id: Some(id.parse().unwrap()),
+            negotiated_protocol: None,
+        })
+    };
+    check_authorized(
+        &policy,
+        orig_dst_addr(),
+        client_addr(),
+        &tls("spiffe://example.org/ns/testns/sa/api"),
+    )
+    .expect("SPIFFE ID in the trust domain must be permitted");
+
+    for id in [
+        "spiffe://example.com/ns/testns/sa/web.api",
+        "spiffe://example.org/ns/testns2/sa/api",
+        "spiffe://example.org/ns/other/sa/api",
+    ] {
+        check_authorized(&policy, orig_dst_addr(), client_addr(), &tls(id))
+            .expect_err("SPIFFE ID must be rejected");
+    }
+}
+
 #[tokio::test(flavor = "current_thread")]
 async fn tls_unauthenticated() {
     let policy = ServerPolicy {
@@ -200,6 +261,7 @@ async fn tls_unauthenticated() {
             kind: "server".into(),
             name: "test".into(),
         }),
+        authz_mode: Default::default(),
     };
 
     let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
@@ -236,6 +298,147 @@ async fn tls_unauthenticated() {
         .expect_err("policy must require a TLS termination identity");
 }
 
+#[tokio::test(flavor = "current_thread")]
+async fn audit() {
+    let mut policy = ServerPolicy {
+        protocol: Protocol::Opaque(
+            vec![Authorization {
+                authentication: Authentication::TlsUnauthenticated,
+                networks: vec!["192.0.2.0/24".parse().unwrap()],
+                meta: Arc::new(Meta::Resource {
+                    group: "policy.linkerd.io".into(),
+                    kind: "serverauthorization".into(),
+                    name: "tls-unauth".into(),
+                }),
+            }]
+            .into(),
+        ),
+        meta: Arc::new(Meta::Resource {
+            group: "policy.linkerd.io".into(),
+            kind: "server".into(),
+            name: "test".into(),
+        }),
+        authz_mode: AuthzMode::Enforce,
+    };
+
+    let tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
+    check_authorized(&policy, orig_dst_addr(), client_addr(), &tls)
+        .expect_err("plaintext connection must be denied");
+    assert_eq!(
+        check_audit(&policy, orig_dst_addr(), client_addr(), &tls),
+        None,
+        "enforced policies must not permit unauthorized connections"
+    );
+
+    policy.authz_mode = AuthzMode::Audit;
+    assert_eq!(
+        check_audit(&policy, orig_dst_addr(), client_addr(), &tls)
+            .expect("audited connection must be permitted"),
+        ServerPermit {
+            dst: orig_dst_addr(),
+            protocol: policy.protocol.clone(),
+            labels: ServerAuthzLabels {
+                authz: Meta::new_default("audit"),
+                server: ServerLabel(Arc::new(Meta::Resource {
+                    group: "policy.linkerd.io".into(),
+                    kind: "server".into(),
+                    name: "test".into()
+                })),
+            }
+        }
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn audit_on_change() {
+    #[derive(Clone)]
+    struct Target(AllowPolicy);
+
+    impl svc::Param<AllowPolicy> for Target {
+        fn param(&self) -> AllowPolicy {
+            self.0.clone()
+        }
+    }
+
+    impl svc::Param<Remote<ClientAddr>> for Target {
+        fn param(&self) -> Remote<ClientAddr> {
+            client_addr()
+        }
+    }
+
+    impl svc::Param<tls::ConditionalServerTls> for Target {
+        fn param(&self) -> tls::ConditionalServerTls {
+            tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello)
+        }
+    }
+
+    let authz = |name: &str| Authorization {
+        authentication: Authentication::TlsUnauthenticated,
+        networks: vec!["192.0.2.0/24".parse().unwrap()],
+        meta: Arc::new(Meta::Resource {
+            group: "policy.linkerd.io".into(),
+            kind: "serverauthorization".into(),
+            name: name.into(),
+        }),
+    };
+    let (policy, tx) = AllowPolicy::for_test(
+        orig_dst_addr(),
+        ServerPolicy {
+            protocol: Protocol::Opaque(vec![authz("a")].into()),
+            meta: Arc::new(Meta::Resource {
+                group: "policy.linkerd.io".into(),
+                kind: "server".into(),
+                name: "test".into(),
+            }),
+            authz_mode: AuthzMode::Audit,
+        },
+    );
+
+    let metrics = TcpAuthzMetrics::default();
+    let new_policy = NewTcpPolicy {
+        inner: |_: (ServerPermit, Target)| {
+            svc::mk(|_: ()| futures::future::pending::<Result<(), Error>>())
+        },
+        metrics: metrics.clone(),
+    };
+    let mut svc = svc::NewService::new_service(&new_policy, Target(policy));
+    futures::future::poll_fn(|cx| svc::Service::<()>::poll_ready(&mut svc, cx))
+        .await
+        .expect("audited connection must be permitted");
+    let conn = tokio::spawn(svc::Service::call(&mut svc, ()));
+    tokio::task::yield_now().await;
+    assert_eq!(tcp_authz_metric(&metrics, "audit"), 1);
+
+    // Policy updates that continue to audit the connection are not counted
+    // again.
+    tx.send_modify(|p| p.protocol = Protocol::Opaque(vec![authz("a"), authz("b")].into()));
+    tokio::task::yield_now().await;
+    assert_eq!(tcp_authz_metric(&metrics, "audit"), 1);
+    assert!(!conn.is_finished());
+
+    // Once the policy is enforced, the connection is terminated.
+    tx.send_modify(|p| p.authz_mode = AuthzMode::Enforce);
+    conn.await
+        .expect("task must not panic")
+        .expect_err("connection must be terminated");
+    assert_eq!(tcp_authz_metric(&metrics, "audit"), 1);
+    assert_eq!(tcp_authz_metric(&metrics, "terminate"), 1);
+}
+
+/// Sums the `inbound_tcp_authz_<kind>_total` counters.
+fn tcp_authz_metric(metrics: &TcpAuthzMetrics, kind: &str) -> u64 {
+    use linkerd_app_core::metrics::FmtMetrics;
+
+    let prefix = format!("inbound_tcp_authz_{kind}_total{{");
+    metrics
+        .as_display()
+        .to_string()
+        .lines()
+        .filter(|l| l.starts_with(&prefix))
+        .filter_map(|l| l.rsplit(' ').next()?.parse::<u64>().ok())
+        .sum()
+}
+
 fn client_id() -> tls::ClientId {
     "testsa.testns.serviceaccount.identity.linkerd.cluster.local"
         .parse()
@@ -32,7 +32,12 @@ impl Inbound<()> {
         C::ResponseBody: Default + Send + 'static,
         C::Future: Send,
     {
-        self.config.policy.clone().build(workload, client, backoff)
+        self.config.policy.clone().build(
+            workload,
+            client,
+            backoff,
+            self.config.local_policy.clone(),
+        )
     }
 
     pub async fn serve<A, I, G, GSvc, P>(
@@ -48,7 +53,11 @@ impl Inbound<()> {
         I: Debug + Unpin + Send + Sync + 'static,
         G: svc::NewService<direct::GatewayTransportHeader, Service = GSvc>,
         G: Clone + Send + Sync + Unpin + 'static,
-        GSvc: svc::Service<direct::GatewayIo<io::ScopedIo<I>>, Response = ()> + Send + 'static,
+        GSvc: svc::Service<
+                direct::GatewayIo<transport::proxy_protocol::ProxyProtocolIo<io::ScopedIo<I>>>,
+                Response = (),
+            > + Send
+            + 'static,
         GSvc::Error: Into<Error>,
         GSvc::Future: Send,
         P: profiles::GetProfile<Error = Error>,
@@ -100,6 +109,7 @@ impl Inbound<()> {
             .push_http_tcp_server()
             .push_detect(forward)
             .push_accept(addr.port(), policies, direct)
+            .push_proxy_protocol()
             .into_inner();
 
         serve::serve(listen, server, shutdown).await;
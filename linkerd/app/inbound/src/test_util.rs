@@ -46,6 +46,7 @@ pub fn default_config() -> Config {
                 kind: "server".into(),
                 name: "testsrv".into(),
             }),
+            authz_mode: Default::default(),
         }
         .into(),
         ports: Default::default(),
@@ -77,15 +78,18 @@ pub fn default_config() -> Config {
                 h2_settings: h2::Settings::default(),
             },
             max_in_flight_requests: 10_000,
+            adaptive_concurrency_limit: None,
             detect_protocol_timeout: Duration::from_secs(10),
         },
         allowed_ips: Default::default(),
+        local_policy: Default::default(),
         http_request_queue: config::QueueConfig {
             capacity: 10_000,
             failfast_timeout: Duration::from_secs(1),
         },
         discovery_idle_timeout: Duration::from_secs(20),
         profile_skip_timeout: Duration::from_secs(1),
+        proxy_protocol: Default::default(),
     }
 }
 
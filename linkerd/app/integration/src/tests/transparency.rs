@@ -1308,6 +1308,10 @@ mod proxy_to_proxy {
         }
 
         async fn mk(srv: server::Listening) -> Self {
+            Self::mk_with_env(srv, TestEnv::default(), TestEnv::default()).await
+        }
+
+        async fn mk_with_env(srv: server::Listening, in_env: TestEnv, out_env: TestEnv) -> Self {
             let dstctl = controller::new();
             let srv_addr = srv.addr;
             let dst = format!("transparency.test.svc.cluster.local:{}", srv_addr.port());
@@ -1317,7 +1321,11 @@ mod proxy_to_proxy {
                 .run()
                 .instrument(tracing::info_span!("dstctl", "inbound"))
                 .await;
-            let inbound = proxy::new().controller(dstctl).inbound(srv).run().await;
+            let inbound = proxy::new()
+                .controller(dstctl)
+                .inbound(srv)
+                .run_with_test_env(in_env)
+                .await;
 
             let dstctl = controller::new();
             let _profile_out =
@@ -1332,7 +1340,7 @@ mod proxy_to_proxy {
             let outbound = proxy::new()
                 .controller(dstctl)
                 .outbound_ip(srv_addr)
-                .run()
+                .run_with_test_env(out_env)
                 .await;
 
             let addr = outbound.outbound;
@@ -1375,6 +1383,92 @@ mod proxy_to_proxy {
         // ensure panics from the server are propagated
         proxies.join_servers().await;
     }
+
+    /// Tests that HTTP/1.1 upgrades are tunneled over extended CONNECT
+    /// streams when both proxies enable them.
+    #[tokio::test]
+    async fn http11_upgrade_extended_connect() {
+        let _trace = trace_init();
+
+        let mut in_env = TestEnv::default();
+        in_env.put(app::env::ENV_HTTP2_EXTENDED_CONNECT, "true".to_string());
+        let mut out_env = TestEnv::default();
+        out_env.put(app::env::ENV_HTTP2_EXTENDED_CONNECT, "true".to_string());
+        http11_upgrade(in_env, out_env).await;
+    }
+
+    /// Tests that HTTP/1.1 upgrades fall back to a dedicated HTTP/1.1
+    /// connection when the inbound proxy does not advertise support for
+    /// extended CONNECT streams.
+    #[tokio::test]
+    async fn http11_upgrade_extended_connect_fallback() {
+        let _trace = trace_init();
+
+        let mut out_env = TestEnv::default();
+        out_env.put(app::env::ENV_HTTP2_EXTENDED_CONNECT, "true".to_string());
+        http11_upgrade(TestEnv::default(), out_env).await;
+    }
+
+    async fn http11_upgrade(in_env: TestEnv, out_env: TestEnv) {
+        let upgrade_req = "\
+                           GET /chat HTTP/1.1\r\n\
+                           Host: transparency.test.svc.cluster.local\r\n\
+                           Connection: upgrade\r\n\
+                           Upgrade: chatproto\r\n\
+                           \r\n\
+                           ";
+        let upgrade_res = "\
+                           HTTP/1.1 101 Switching Protocols\r\n\
+                           Upgrade: chatproto\r\n\
+                           Connection: upgrade\r\n\
+                           \r\n\
+                           ";
+        let upgrade_needle = "\r\nupgrade: chatproto\r\n";
+        let chatproto_req = "[chatproto-c]{send}: hi all\n";
+        let chatproto_res = "[chatproto-s]{recv}: welcome!\n";
+
+        let srv = server::tcp()
+            .accept_fut(move |mut sock| {
+                async move {
+                    let mut vec = vec![0; 512];
+                    let n = sock.read(&mut vec).await?;
+                    assert_contains!(s(&vec[..n]), upgrade_needle);
+                    sock.write_all(upgrade_res.as_bytes()).await?;
+
+                    let mut vec = vec![0; 512];
+                    let n = sock.read(&mut vec).await?;
+                    assert_eq!(s(&vec[..n]), chatproto_req);
+                    sock.write_all(chatproto_res.as_bytes()).await
+                }
+                .map(|res| res.expect("TCP server must not fail"))
+            })
+            .run()
+            .await;
+        let proxies = ProxyToProxy::mk_with_env(srv, in_env, out_env).await;
+
+        let client = client::tcp(proxies.inbound);
+        let tcp_client = client.connect().await;
+        tcp_client.write(upgrade_req).await;
+
+        let resp = tcp_client.read().await;
+        let resp_str = s(&resp);
+        assert!(
+            resp_str.starts_with("HTTP/1.1 101 Switching Protocols\r\n"),
+            "response not an upgrade: {:?}",
+            resp_str
+        );
+        assert_contains!(resp_str, upgrade_needle);
+
+        tcp_client.write(chatproto_req).await;
+        let chat_resp = tcp_client.read().await;
+        assert_eq!(s(&chat_resp), chatproto_res);
+
+        // TCP client must close first
+        tcp_client.shutdown().await;
+
+        // ensure panics from the server are propagated
+        proxies.join_servers().await;
+    }
 }
 
 #[tokio::test]
@@ -0,0 +1,117 @@
+use linkerd_app_core::{classify, proxy::http::classify::gate, svc};
+use linkerd_proxy_client_policy::FailureAccrual;
+use std::sync::Arc;
+use tracing::{trace_span, Instrument};
+
+mod consecutive_failures;
+mod success_rate;
+
+use self::consecutive_failures::ConsecutiveFailures;
+
+/// Params configuring a circuit breaker stack.
+#[derive(Clone, Debug)]
+pub(crate) struct Params {
+    accrual: FailureAccrual,
+    channel_capacity: usize,
+    /// Tracks the success rates of all endpoints in a backend, when
+    /// success-rate failure accrual is configured.
+    success_rate: Option<Arc<success_rate::Pool>>,
+}
+
+impl Params {
+    /// Returns params for a backend's circuit breakers.
+    ///
+    /// Success-rate failure accrual compares all of the backend's endpoints,
+    /// so a task that evaluates them is spawned here. It completes when the
+    /// params are dropped.
+    pub(crate) fn new(accrual: FailureAccrual, channel_capacity: usize) -> Self {
+        let success_rate = match accrual {
+            FailureAccrual::SuccessRate {
+                interval,
+                min_requests,
+                min_endpoints,
+                stdev_factor,
+                max_ejection_percent,
+                backoff,
+            } => Some(success_rate::Pool::spawn(success_rate::Config {
+                interval,
+                min_requests,
+                min_endpoints,
+                stdev_factor: stdev_factor.as_f64(),
+                max_ejection_percent,
+                backoff,
+            })),
+            _ => None,
+        };
+        Self {
+            accrual,
+            channel_capacity,
+            success_rate,
+        }
+    }
+}
+
+impl<T> svc::ExtractParam<gate::Params<classify::Class>, T> for Params {
+    fn extract_param(&self, _: &T) -> gate::Params<classify::Class> {
+        // Create a channel so that we can receive response summaries and
+        // control the gate.
+        let (prms, gate, rsps) = gate::Params::channel(self.channel_capacity);
+
+        match self.accrual {
+            FailureAccrual::None => {
+                // No failure accrual for this target; construct a gate
+                // that will never close.
+                tracing::trace!("No failure accrual policy enabled.");
+                prms
+            }
+            FailureAccrual::ConsecutiveFailures {
+                max_failures,
+                backoff,
+            } => {
+                tracing::trace!(
+                    max_failures,
+                    backoff = ?backoff,
+                    "Using consecutive-failures failure accrual policy.",
+                );
+
+                // 1. If the configured number of consecutive failures are encountered,
+                //    shut the gate.
+                // 2. After an ejection timeout, open the gate so that 1 request can be processed.
+                // 3. If that request succeeds, open the gate. If it fails, increase the
+                //    ejection timeout and repeat.
+                let breaker = ConsecutiveFailures::new(max_failures, backoff, gate, rsps);
+                tokio::spawn(
+                    breaker
+                        .run()
+                        .instrument(trace_span!("consecutive_failures").or_current()),
+                );
+
+                prms
+            }
+            FailureAccrual::SuccessRate { .. } => {
+                let pool = match self.success_rate {
+                    Some(ref pool) => pool,
+                    None => {
+                        tracing::warn!("No success rate pool configured");
+                        return prms;
+                    }
+                };
+                tracing::trace!("Using success-rate failure accrual policy.");
+
+                // 1. Record the endpoint's response classifications so that
+                //    its success rate may be compared with the rest of the
+                //    backend's endpoints.
+                // 2. If the endpoint is an outlier, shut the gate until the
+                //    ejection backoff elapses.
+                let breaker = pool.endpoint(gate, rsps);
+                tokio::spawn(
+                    breaker
+                        .run()
+                        .instrument(trace_span!("success_rate").or_current()),
+                );
+
+                prms
+            }
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use linkerd_app_core::{classify, exp_backoff::ExponentialBackoff, proxy::http::classify::gate};
+use parking_lot::Mutex;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+    Arc, Weak,
+};
+use tokio::{
+    sync::{mpsc, Notify},
+    time,
+};
+
+/// Configures success-rate outlier detection for a backend.
+#[derive(Copy, Clone, Debug)]
+pub struct Config {
+    pub interval: time::Duration,
+    pub min_requests: usize,
+    pub min_endpoints: usize,
+    pub stdev_factor: f64,
+    pub max_ejection_percent: u32,
+    pub backoff: ExponentialBackoff,
+}
+
+/// Tracks the success rates of all of a backend's endpoints so that endpoints
+/// whose success rate is a statistical outlier may be ejected.
+///
+/// Each interval, the success rate of every endpoint that served at least
+/// `min_requests` is compared against the mean success rate of those
+/// endpoints. Endpoints whose success rate is more than `stdev_factor`
+/// standard deviations below the mean are ejected, unless doing so would eject
+/// more than `max_ejection_percent` of the backend's endpoints.
+#[derive(Debug)]
+pub struct Pool {
+    config: Config,
+    endpoints: Mutex<Vec<Weak<Endpoint>>>,
+}
+
+/// Controls a single endpoint's gate based on the pool's ejection decisions.
+pub struct SuccessRate {
+    endpoint: Arc<Endpoint>,
+    backoff: ExponentialBackoff,
+    gate: gate::Tx,
+    rsps: mpsc::Receiver<classify::Class>,
+}
+
+#[derive(Debug, Default)]
+struct Endpoint {
+    successes: AtomicUsize,
+    failures: AtomicUsize,
+    ejected: AtomicBool,
+    /// The number of times the endpoint has recently been ejected. Decays
+    /// each interval in which the endpoint is not an outlier.
+    ejections: AtomicU32,
+    eject: Notify,
+}
+
+// === impl Pool ===
+
+impl Pool {
+    /// Returns a new pool and spawns a task that evaluates the pool's
+    /// endpoints each interval. The task completes when the pool is dropped.
+    pub fn spawn(config: Config) -> Arc<Self> {
+        let pool = Arc::new(Self {
+            config,
+            endpoints: Default::default(),
+        });
+
+        let weak = Arc::downgrade(&pool);
+        tokio::spawn(async move {
+            let mut interval =
+                time::interval_at(time::Instant::now() + config.interval, config.interval);
+            loop {
+                interval.tick().await;
+                match weak.upgrade() {
+                    Some(pool) => pool.evaluate(),
+                    None => return,
+                }
+            }
+        });
+
+        pool
+    }
+
+    /// Registers a new endpoint with the pool.
+    pub fn endpoint(&self, gate: gate::Tx, rsps: mpsc::Receiver<classify::Class>) -> SuccessRate {
+        let endpoint = Arc::new(Endpoint::default());
+        self.endpoints.lock().push(Arc::downgrade(&endpoint));
+        SuccessRate {
+            endpoint,
+            backoff: self.config.backoff,
+            gate,
+            rsps,
+        }
+    }
+
+    fn evaluate(&self) {
+        let endpoints = {
+            let mut endpoints = self.endpoints.lock();
+            endpoints.retain(|ep| ep.strong_count() > 0);
+            endpoints
+                .iter()
+                .filter_map(Weak::upgrade)
+                .collect::<Vec<_>>()
+        };
+
+        let max_ejected =
+            endpoints.len() * self.config.max_ejection_percent.min(100) as usize / 100;
+        let mut ejected = endpoints
+            .iter()
+            .filter(|ep| ep.ejected.load(Ordering::Acquire))
+            .count();
+
+        // Reset each endpoint's counters, recording the success rates of
+        // endpoints that served enough requests in this interval.
+        let mut rates = Vec::with_capacity(endpoints.len());
+        for ep in endpoints.iter() {
+            let successes = ep.successes.swap(0, Ordering::AcqRel);
+            let failures = ep.failures.swap(0, Ordering::AcqRel);
+            if ep.ejected.load(Ordering::Acquire) {
+                continue;
+            }
+            let total = successes + failures;
+            if total >= self.config.min_requests.max(1) {
+                rates.push((ep, successes as f64 / total as f64));
+            } else {
+                decay(ep);
+            }
+        }
+
+        if rates.len() < self.config.min_endpoints.max(1) {
+            tracing::trace!(
+                endpoints = rates.len(),
+                "Too few endpoints to detect outliers"
+            );
+            rates.iter().for_each(|(ep, _)| decay(ep));
+            return;
+        }
+
+        let n = rates.len() as f64;
+        let mean = rates.iter().map(|(_, rate)| rate).sum::<f64>() / n;
+        let variance = rates
+            .iter()
+            .map(|(_, rate)| (rate - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        let threshold = mean - self.config.stdev_factor * variance.sqrt();
+        tracing::debug!(%mean, stdev = %variance.sqrt(), %threshold, "Evaluated success rates");
+
+        // Eject the worst outliers first, in case the ejection limit is
+        // reached.
+        rates.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        for (ep, rate) in rates {
+            if rate < threshold && ejected < max_ejected {
+                tracing::debug!(%rate, "Ejecting outlier");
+                ejected += 1;
+                ep.ejected.store(true, Ordering::Release);
+                ep.ejections.fetch_add(1, Ordering::AcqRel);
+                ep.eject.notify_one();
+            } else {
+                decay(ep);
+            }
+        }
+    }
+}
+
+fn decay(ep: &Endpoint) {
+    let _ = ep
+        .ejections
+        .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1));
+}
+
+// === impl SuccessRate ===
+
+impl SuccessRate {
+    pub(super) async fn run(mut self) {
+        loop {
+            if self.open().await.is_err() {
+                return;
+            }
+
+            tracing::info!("Success rate outlier ejected");
+            if self.ejected().await.is_err() {
+                return;
+            }
+
+            tracing::info!("Success rate outlier restored");
+        }
+    }
+
+    /// Keep the gate open, recording response classifications, until the
+    /// pool ejects this endpoint.
+    async fn open(&mut self) -> Result<(), ()> {
+        tracing::debug!("Open");
+        self.gate.open();
+        loop {
+            tokio::select! {
+                rsp = self.rsps.recv() => {
+                    let class = rsp.ok_or(())?;
+                    tracing::trace!(?class, "Response");
+                    if class.is_success() {
+                        self.endpoint.successes.fetch_add(1, Ordering::AcqRel);
+                    } else {
+                        self.endpoint.failures.fetch_add(1, Ordering::AcqRel);
+                    }
+                }
+                _ = self.endpoint.eject.notified() => return Ok(()),
+                _ = self.gate.lost() => return Err(()),
+            }
+        }
+    }
+
+    /// Keep the gate shut for a backoff that grows with the number of recent
+    /// ejections.
+    async fn ejected(&mut self) -> Result<(), ()> {
+        let ejections = self.endpoint.ejections.load(Ordering::Acquire);
+        let backoff = self.backoff.delay(ejections.saturating_sub(1));
+        tracing::debug!(?backoff, ejections, "Shut");
+        self.gate.shut();
+
+        let sleep = time::sleep(backoff);
+        tokio::pin!(sleep);
+        loop {
+            tokio::select! {
+                _ = &mut sleep => break,
+                // Ignore responses while the endpoint is ejected.
+                _ = self.rsps.recv() => continue,
+                _ = self.gate.lost() => return Err(()),
+            }
+        }
+
+        self.endpoint.ejected.store(false, Ordering::Release);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_test::{assert_pending, task};
+
+    fn config(max_ejection_percent: u32) -> Config {
+        Config {
+            interval: time::Duration::from_secs(10),
+            min_requests: 10,
+            min_endpoints: 3,
+            stdev_factor: 1.0,
+            max_ejection_percent,
+            backoff: ExponentialBackoff::try_new(
+                time::Duration::from_secs(30),
+                time::Duration::from_secs(300),
+                // Don't jitter backoffs to ensure tests are deterministic.
+                0.0,
+            )
+            .expect("backoff params are valid"),
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn ejects_outliers() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let pool = Pool::spawn(config(50));
+        let mut endpoints = (0..4)
+            .map(|_| {
+                let (params, gate, rsps) = gate::Params::channel(100);
+                let task = task::spawn(pool.endpoint(gate, rsps).run());
+                (params, task)
+            })
+            .collect::<Vec<_>>();
+
+        // The last endpoint fails 30% of requests, while the others always
+        // succeed.
+        for (i, (params, task)) in endpoints.iter_mut().enumerate() {
+            for n in 0..20 {
+                let class = if i == 3 && n % 10 < 3 {
+                    classify::Class::Http(Err(http::StatusCode::BAD_GATEWAY))
+                } else {
+                    classify::Class::Http(Ok(http::StatusCode::OK))
+                };
+                params.responses.try_send(class).unwrap();
+            }
+            assert_pending!(task.poll());
+            assert!(params.gate.is_open());
+        }
+
+        // Wait for the pool to evaluate its endpoints.
+        time::sleep(time::Duration::from_secs(11)).await;
+        for (i, (params, task)) in endpoints.iter_mut().enumerate() {
+            assert_pending!(task.poll());
+            assert_eq!(params.gate.is_shut(), i == 3, "endpoint {i}");
+        }
+
+        // The outlier is restored after the ejection backoff.
+        time::sleep(time::Duration::from_secs(30)).await;
+        let (params, task) = &mut endpoints[3];
+        assert_pending!(task.poll());
+        assert!(params.gate.is_open());
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn limits_ejections() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        // With 5 endpoints, at most one may be ejected.
+        let pool = Pool::spawn(config(25));
+        let mut endpoints = (0..5)
+            .map(|_| {
+                let (params, gate, rsps) = gate::Params::channel(100);
+                let task = task::spawn(pool.endpoint(gate, rsps).run());
+                (params, task)
+            })
+            .collect::<Vec<_>>();
+
+        // Two endpoints fail every request.
+        for (i, (params, task)) in endpoints.iter_mut().enumerate() {
+            for _ in 0..20 {
+                let class = if i >= 3 {
+                    classify::Class::Http(Err(http::StatusCode::BAD_GATEWAY))
+                } else {
+                    classify::Class::Http(Ok(http::StatusCode::OK))
+                };
+                params.responses.try_send(class).unwrap();
+            }
+            assert_pending!(task.poll());
+        }
+
+        // Wait for the pool to evaluate its endpoints.
+        time::sleep(time::Duration::from_secs(11)).await;
+        let mut shut = 0;
+        for (params, task) in endpoints.iter_mut() {
+            assert_pending!(task.poll());
+            if params.gate.is_shut() {
+                shut += 1;
+            }
+        }
+        assert_eq!(shut, 1);
+    }
+}
@@ -246,19 +246,25 @@ fn policy_for_backend(
     static NO_OPAQ_FILTERS: Lazy<Arc<[policy::opaq::Filter]>> = Lazy::new(|| Arc::new([]));
 
     let opaque = policy::opaq::Opaque {
-        policy: Some(policy::opaq::Policy {
-            meta: meta.clone(),
-            filters: NO_OPAQ_FILTERS.clone(),
-            failure_policy: Default::default(),
-            request_timeout: None,
-            distribution: policy::RouteDistribution::FirstAvailable(Arc::new([
-                policy::RouteBackend {
-                    filters: NO_OPAQ_FILTERS.clone(),
-                    backend: backend.clone(),
-                    request_timeout: None,
-                },
-            ])),
-        }),
+        routes: Arc::new([policy::opaq::Route {
+            matches: vec![],
+            policy: policy::opaq::Policy {
+                meta: meta.clone(),
+                filters: NO_OPAQ_FILTERS.clone(),
+                failure_policy: Default::default(),
+                timeouts: Default::default(),
+                retry: None,
+                hedge: None,
+                distribution: policy::RouteDistribution::FirstAvailable(Arc::new([
+                    policy::RouteBackend {
+                        filters: NO_OPAQ_FILTERS.clone(),
+                        backend: backend.clone(),
+                        timeouts: Default::default(),
+                    },
+                ])),
+            },
+        }]),
+        failure_accrual: Default::default(),
     };
 
     let routes = Arc::new([policy::http::Route {
@@ -269,12 +275,14 @@ fn policy_for_backend(
                 meta: meta.clone(),
                 filters: NO_HTTP_FILTERS.clone(),
                 failure_policy: Default::default(),
-                request_timeout: None,
+                timeouts: Default::default(),
+                retry: None,
+                hedge: None,
                 distribution: policy::RouteDistribution::FirstAvailable(Arc::new([
                     policy::RouteBackend {
                         filters: NO_HTTP_FILTERS.clone(),
                         backend: backend.clone(),
-                        request_timeout: None,
+                        timeouts: Default::default(),
                     },
                 ])),
             },
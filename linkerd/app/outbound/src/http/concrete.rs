@@ -1,8 +1,8 @@
 //! A stack that (optionally) resolves a service to a set of endpoint replicas
 //! and distributes HTTP requests among them.
 
-use super::{balance, breaker, client, handle_proxy_error_headers};
-use crate::{http, stack_labels, BackendRef, Outbound, ParentRef};
+use super::{balance, client, handle_proxy_error_headers, logical::policy::NewDistinctEndpoint};
+use crate::{breaker, http, stack_labels, BackendRef, Outbound, ParentRef};
 use linkerd_app_core::{
     classify,
     metrics::{prefix_labels, EndpointLabels, OutboundEndpointLabels},
@@ -25,16 +25,35 @@ mod metrics;
 #[cfg(test)]
 mod tests;
 
-pub use self::metrics::BalancerMetrics;
+pub use self::metrics::{BackendConcurrencyLimits, BalancerMetrics};
 
 /// Parameter configuring dispatcher behavior.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Dispatch {
-    Balance(NameAddr, balance::EwmaConfig),
+    Balance(NameAddr, Load),
     Forward(Remote<ServerAddr>, Metadata),
     Fail { message: Arc<str> },
 }
 
+/// Configures how a balancer distributes requests over its endpoints.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Load {
+    /// Dispatches requests using P2C over each endpoint's peak EWMA latency.
+    PeakEwma(balance::EwmaConfig),
+
+    /// Dispatches requests using P2C over each endpoint's number of
+    /// outstanding requests.
+    LeastRequest,
+
+    /// Dispatches requests to endpoints in proportion to their discovered
+    /// weights.
+    WeightedRoundRobin,
+
+    /// Dispatches requests to endpoints on a hash ring, so that requests with
+    /// the same key are served by the same endpoint.
+    ConsistentHash(balance::HashKey),
+}
+
 /// A backend dispatcher explicitly fails all requests.
 #[derive(Debug, thiserror::Error)]
 #[error("{0}")]
@@ -63,10 +82,25 @@ pub struct Endpoint<T> {
 #[derive(Clone, Debug, PartialEq, Eq)]
 struct Balance<T> {
     addr: NameAddr,
-    ewma: balance::EwmaConfig,
+    load: Load,
     parent: T,
 }
 
+/// A target configuring a peak-EWMA balancer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct PeakEwmaBalance<T> {
+    ewma: balance::EwmaConfig,
+    slow_start: Option<balance::SlowStartConfig>,
+    balance: Balance<T>,
+}
+
+/// A target configuring a consistent-hash balancer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ConsistentHashBalance<T> {
+    key: balance::HashKey,
+    balance: Balance<T>,
+}
+
 // === impl Outbound ===
 
 impl<N> Outbound<N> {
@@ -121,7 +155,10 @@ impl<N> Outbound<N> {
                         .stack
                         .layer(stack_labels("http", "forward")),
                 )
-                .instrument(|e: &Endpoint<T>| info_span!("forward", addr = %e.addr));
+                .instrument(|e: &Endpoint<T>| info_span!("forward", addr = %e.addr))
+                // Fails copies of a hedged request that would be sent to the
+                // same endpoint.
+                .push(NewDistinctEndpoint::layer_via(|e: &Endpoint<T>| e.addr));
 
             let fail = svc::ArcNewService::new(|message: Arc<str>| {
                 svc::mk(move |_| {
@@ -137,8 +174,8 @@ impl<N> Outbound<N> {
                     move |parent: T| -> Result<_, Infallible> {
                         // 这里的 T 是 Concrete<Http<Sidecar>>
                         Ok(match parent.param() {
-                            Dispatch::Balance(addr, ewma) => {
-                                svc::Either::A(svc::Either::A(Balance { addr, ewma, parent }))
+                            Dispatch::Balance(addr, load) => {
+                                svc::Either::A(svc::Either::A(Balance { addr, load, parent }))
                             }
                             Dispatch::Forward(addr, metadata) => svc::Either::A(svc::Either::B({
                                 let is_local = inbound_ips.contains(&addr.ip());
@@ -158,6 +195,13 @@ impl<N> Outbound<N> {
                 // TODO(ver) Configure this queue from the target (i.e. from
                 // discovery).
                 .push(svc::NewQueue::layer_via(config.http_request_queue))
+                // Limit the number of in-flight requests to each backend with
+                // an adaptive limit, when configured, shedding any excess.
+                .push(
+                    rt.metrics
+                        .http_concurrency_limits
+                        .to_layer(config.proxy.adaptive_concurrency_limit),
+                )
                 .push(svc::ArcNewService::layer())
         })
     }
@@ -165,12 +209,6 @@ impl<N> Outbound<N> {
 
 // === impl Balance ===
 
-impl<T> svc::Param<http::balance::EwmaConfig> for Balance<T> {
-    fn param(&self) -> http::balance::EwmaConfig {
-        self.ewma
-    }
-}
-
 impl<T> Balance<T>
 where
     // Parent target.
@@ -210,6 +248,10 @@ where
     {
         let classify_channel_capacity = config.http_request_queue.capacity;
         let inbound_ips = config.inbound_ips.clone();
+        let local_policy = config.local_policy.clone();
+        let zone_affinity = config.zone_affinity.clone();
+        let zone_label = config.zone_label.clone();
+        let slow_start = config.slow_start;
         let metrics = rt.metrics.clone();
 
         let resolve = svc::MapTargetLayer::new(|t: Self| -> ConcreteAddr { ConcreteAddr(t.addr) })
@@ -219,9 +261,15 @@ where
             let endpoint = svc::stack(inner)
                 .push_map_target({
                     let inbound_ips = inbound_ips.clone();
+                    let local_policy = local_policy.clone();
                     move |((addr, metadata), target): ((SocketAddr, Metadata), Self)| {
                         tracing::trace!(%addr, ?metadata, ?target, "Resolved endpoint");
                         let is_local = inbound_ips.contains(&addr.ip());
+                        let metadata = if local_policy.sends_proxy_header(&target.addr) {
+                            metadata.with_proxy_protocol(true)
+                        } else {
+                            metadata
+                        };
                         // 把 （(SocketAddr, Metadata), Balance<Http<Sidecar>>） 转换为 Endpoint
                         Endpoint {
                             addr: Remote(ServerAddr(addr)),
@@ -239,13 +287,25 @@ where
                 .lift_new_with_target()
                 .push(
                     http::NewClassifyGateSet::<classify::Response, _, _, _>::layer_via({
-                        move |target: &Self| breaker::Params {
-                            accrual: target.parent.param(),
-                            // TODO configure channel capacities from target.
-                            channel_capacity: classify_channel_capacity,
+                        move |target: &Self| {
+                            breaker::Params::new(
+                                target.parent.param(),
+                                // TODO configure channel capacities from target.
+                                classify_channel_capacity,
+                            )
                         }
                     }),
                 )
+                // Prefers endpoints in the proxy's own zone, when configured.
+                // This wraps the failure accrual gate so that endpoints that
+                // are unavailable due to failures trigger spillover.
+                .push(balance::NewZoneAffinity::layer_via(
+                    {
+                        let zone_affinity = zone_affinity.clone();
+                        move |_: &Self| zone_affinity.clone()
+                    },
+                    crate::ExtractEndpointZone(zone_label.clone()),
+                ))
                 .push(balance::NewGaugeEndpoints::layer_via({
                     let metrics = metrics.http_balancer.clone();
                     move |target: &Self| {
@@ -255,14 +315,66 @@ where
                 .push_on_service(svc::OnServiceLayer::new(
                     metrics.proxy.stack.layer(stack_labels("http", "endpoint")),
                 ))
+                // Fails copies of a hedged request that would be sent to the
+                // same endpoint. This wraps the failure accrual gate so that
+                // endpoints are not penalized for these failures.
+                .push_on_service(NewDistinctEndpoint::layer_via(
+                    |(addr, _): &(SocketAddr, Metadata)| Remote(ServerAddr(*addr)),
+                ))
                 .push_on_service(svc::NewInstrumentLayer::new(
                     |(addr, _): &(SocketAddr, _)| info_span!("endpoint", %addr),
                 ));
 
+            let least_request = endpoint
+                .clone()
+                .push(http::NewBalanceLeastRequest::layer(resolve.clone()))
+                .push_on_service(http::BoxResponse::layer());
+
+            let round_robin = endpoint
+                .clone()
+                .push(http::NewBalanceWeightedRoundRobin::layer_via(
+                    |md: &Metadata| balance::Weight(md.weight()),
+                    resolve.clone(),
+                ))
+                .push_on_service(http::BoxResponse::layer());
+
+            let consistent_hash = endpoint
+                .clone()
+                .push_map_target(|t: ConsistentHashBalance<T>| t.balance)
+                .push(http::NewBalanceConsistentHash::layer(
+                    svc::MapTargetLayer::new(|t: ConsistentHashBalance<T>| t.balance)
+                        .layer(resolve.clone()),
+                ))
+                .push_on_service(http::BoxResponse::layer());
+
             endpoint
-                .push(http::NewBalancePeakEwma::layer(resolve.clone()))
-                .push(svc::NewMapErr::layer_from_target::<BalanceError, _>())
+                .push_map_target(|t: PeakEwmaBalance<T>| t.balance)
+                .push(http::NewBalancePeakEwma::layer(
+                    svc::MapTargetLayer::new(|t: PeakEwmaBalance<T>| t.balance)
+                        .layer(resolve.clone()),
+                ))
                 .push_on_service(http::BoxResponse::layer())
+                .push_switch(Ok::<_, Infallible>, least_request.into_inner())
+                .push_switch(Ok::<_, Infallible>, round_robin.into_inner())
+                .push_switch(
+                    move |t: Self| -> Result<_, Infallible> {
+                        use svc::Either::{A, B};
+                        Ok(match t.load.clone() {
+                            Load::PeakEwma(ewma) => A(A(A(PeakEwmaBalance {
+                                ewma,
+                                slow_start,
+                                balance: t,
+                            }))),
+                            Load::LeastRequest => A(A(B(t))),
+                            Load::WeightedRoundRobin => A(B(t)),
+                            Load::ConsistentHash(key) => {
+                                B(ConsistentHashBalance { key, balance: t })
+                            }
+                        })
+                    },
+                    consistent_hash.into_inner(),
+                )
+                .push(svc::NewMapErr::layer_from_target::<BalanceError, _>())
                 .push_on_service(metrics.proxy.stack.layer(stack_labels("http", "balance")))
                 .instrument(|t: &Self| {
                     let BackendRef(meta) = t.parent.param();
@@ -279,6 +391,28 @@ where
     }
 }
 
+// === impl PeakEwmaBalance ===
+
+impl<T> svc::Param<http::balance::EwmaConfig> for PeakEwmaBalance<T> {
+    fn param(&self) -> http::balance::EwmaConfig {
+        self.ewma
+    }
+}
+
+impl<T> svc::Param<Option<http::balance::SlowStartConfig>> for PeakEwmaBalance<T> {
+    fn param(&self) -> Option<http::balance::SlowStartConfig> {
+        self.slow_start
+    }
+}
+
+// === impl ConsistentHashBalance ===
+
+impl<T> svc::Param<http::balance::HashKey> for ConsistentHashBalance<T> {
+    fn param(&self) -> http::balance::HashKey {
+        self.key.clone()
+    }
+}
+
 // === impl BalanceError ===
 
 impl<T> From<(&Balance<T>, Error)> for BalanceError
@@ -322,6 +456,16 @@ impl<T> svc::Param<Option<http::AuthorityOverride>> for Endpoint<T> {
     }
 }
 
+impl<T> svc::Param<crate::tcp::proxy_header::SendProxyHeader> for Endpoint<T> {
+    fn param(&self) -> crate::tcp::proxy_header::SendProxyHeader {
+        // Headers are only sent to endpoints that are not meshed, since a
+        // meshed endpoint's proxy would not expect one.
+        crate::tcp::proxy_header::SendProxyHeader(
+            !self.is_local && self.metadata.proxy_protocol() && self.metadata.identity().is_none(),
+        )
+    }
+}
+
 impl<T> svc::Param<handle_proxy_error_headers::CloseServerConnection> for Endpoint<T> {
     fn param(&self) -> handle_proxy_error_headers::CloseServerConnection {
         handle_proxy_error_headers::CloseServerConnection(
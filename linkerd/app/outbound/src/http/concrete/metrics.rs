@@ -1,8 +1,9 @@
 use crate::{BackendRef, ParentRef};
 use ahash::AHashMap;
 use linkerd_app_core::{
+    concurrency_limits::{AdaptiveLimits, ExtractLimit},
     metrics::{metrics, FmtLabels, FmtMetrics, Gauge},
-    svc::http::balance,
+    svc::{self, http::balance, AdaptiveLimitConfig, NewAdaptiveConcurrencyLimit},
 };
 use parking_lot::Mutex;
 use std::{fmt::Write, sync::Arc};
@@ -10,6 +11,9 @@ use std::{fmt::Write, sync::Arc};
 metrics! {
     outbound_http_balancer_endpoints: Gauge {
         "The number of endpoints currently in a HTTP request balancer"
+    },
+    outbound_http_backend_concurrency_limit: Gauge {
+        "The current adaptive concurrency limit of outbound HTTP requests for each backend"
     }
 }
 
@@ -18,8 +22,16 @@ pub struct BalancerMetrics {
     balancers: Arc<Mutex<AHashMap<Labels, balance::EndpointsGauges>>>,
 }
 
+/// Holds the adaptive concurrency limit for each HTTP backend.
+#[derive(Clone, Debug, Default)]
+pub struct BackendConcurrencyLimits(AdaptiveLimits<Labels>);
+
+/// Extracts a backend's metric labels from its target.
+#[derive(Clone, Debug)]
+pub(super) struct ExtractLabels(());
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
-struct Labels(ParentRef, BackendRef);
+pub(super) struct Labels(ParentRef, BackendRef);
 
 struct Ready<'l>(&'l Labels);
 struct Pending<'l>(&'l Labels);
@@ -58,6 +70,43 @@ impl FmtMetrics for BalancerMetrics {
     }
 }
 
+// === impl BackendConcurrencyLimits ===
+
+impl BackendConcurrencyLimits {
+    /// Returns a layer that limits requests to each backend with an adaptive
+    /// concurrency limit, if one is configured.
+    pub(super) fn to_layer<N>(
+        &self,
+        config: Option<AdaptiveLimitConfig>,
+    ) -> impl svc::layer::Layer<
+        N,
+        Service = svc::Either<
+            NewAdaptiveConcurrencyLimit<ExtractLimit<Labels, ExtractLabels>, N>,
+            N,
+        >,
+    > + Clone {
+        self.0.to_layer_via(config, ExtractLabels(()))
+    }
+}
+
+impl FmtMetrics for BackendConcurrencyLimits {
+    fn fmt_metrics(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0
+            .fmt_metric(f, outbound_http_backend_concurrency_limit)
+    }
+}
+
+// === impl ExtractLabels ===
+
+impl<T> svc::ExtractParam<Labels, T> for ExtractLabels
+where
+    T: svc::Param<ParentRef> + svc::Param<BackendRef>,
+{
+    fn extract_param(&self, target: &T) -> Labels {
+        Labels(target.param(), target.param())
+    }
+}
+
 impl FmtLabels for Labels {
     fn fmt_labels(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let Labels(parent, backend) = self;
@@ -43,10 +43,10 @@ async fn gauges_endpoints() {
         .new_service(Balance {
             addr,
             parent: Target,
-            ewma: EwmaConfig {
+            load: Load::PeakEwma(EwmaConfig {
                 default_rtt: time::Duration::from_millis(100),
                 decay: time::Duration::from_secs(10),
-            },
+            }),
         });
 
     let ready = Arc::new(tokio::sync::Notify::new());
@@ -114,6 +114,79 @@ async fn gauges_endpoints() {
     assert_eq!(gauge.ready.value(), 0);
 }
 
+#[tokio::test(flavor = "current_thread")]
+async fn consistent_hash_pins_requests() {
+    let _trace = trace::test::trace_init();
+    let (rt, _shutdown) = runtime();
+    let outbound = Outbound::new(default_config(), rt);
+
+    let addr = "mysvc.myns.svc.cluster.local:80"
+        .parse::<NameAddr>()
+        .unwrap();
+    let ep0 = SocketAddr::new([192, 0, 2, 41].into(), 8080);
+    let ep1 = SocketAddr::new([192, 0, 2, 42].into(), 8080);
+
+    let resolve = support::resolver::<Metadata>();
+    let mut resolve_tx = resolve.endpoint_tx(addr.clone());
+
+    let (svc0, mut handle0) = tower_test::mock::pair();
+    let (svc1, mut handle1) = tower_test::mock::pair();
+
+    let stk = move |ep: Endpoint<_>| {
+        if *ep.addr == ep0 {
+            return svc0.clone();
+        }
+        if *ep.addr == ep1 {
+            return svc1.clone();
+        }
+        panic!("unexpected endpoint: {:?}", ep)
+    };
+
+    static USER: http::HeaderName = http::HeaderName::from_static("x-user");
+    let mut svc = svc::stack(stk)
+        .push(Balance::layer(&outbound.config, &outbound.runtime, resolve))
+        .into_inner()
+        .new_service(Balance {
+            addr,
+            parent: Target,
+            load: Load::ConsistentHash(balance::HashKey::Header(USER.clone())),
+        });
+
+    resolve_tx
+        .add(vec![(ep0, Metadata::default()), (ep1, Metadata::default())])
+        .unwrap();
+    handle0.allow(3);
+    handle1.allow(3);
+
+    // Every request with the same key is dispatched to the same endpoint.
+    let mut owners = Vec::new();
+    for _ in 0..3 {
+        svc.ready().await.unwrap();
+        let req = http::Request::builder()
+            .header(&USER, "alice")
+            .body(http::BoxBody::default())
+            .unwrap();
+        let rsp = svc.call(req);
+        let owner = tokio::select! {
+            biased;
+            Some((_, tx)) = handle0.next_request() => {
+                tx.send_response(http::Response::default());
+                ep0
+            }
+            Some((_, tx)) = handle1.next_request() => {
+                tx.send_response(http::Response::default());
+                ep1
+            }
+        };
+        rsp.await.unwrap();
+        owners.push(owner);
+    }
+    assert!(
+        owners.iter().all(|o| *o == owners[0]),
+        "requests were not pinned: {owners:?}"
+    );
+}
+
 #[derive(Clone, Debug)]
 struct Target;
 
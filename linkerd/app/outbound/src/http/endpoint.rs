@@ -4,7 +4,10 @@ use super::{
     handle_proxy_error_headers::{self, NewHandleProxyErrorHeaders},
     NewRequireIdentity,
 };
-use crate::{tcp::tagged_transport, Outbound};
+use crate::{
+    tcp::{proxy_header::SendProxyHeader, tagged_transport},
+    Outbound,
+};
 use linkerd_app_core::{
     classify, config, errors, http_tracing, metrics,
     proxy::{api_resolve::ProtocolHint, http, tap},
@@ -130,6 +133,10 @@ impl<N> Outbound<N> {
                 // is only done when the `Closable` parameter is set to true.
                 // This module always strips error headers from responses.
                 .push(NewHandleProxyErrorHeaders::layer())
+                // Reduces the `grpc-timeout` of requests with deadlines by the
+                // time spent in the proxy, failing requests whose deadlines
+                // have already elapsed rather than dispatching them.
+                .push_on_service(http::PropagateDeadline::layer())
                 // Handle connection-level errors eagerly so that we can report 5XX failures in tap
                 // and metrics. HTTP error metrics are not incremented here so that errors are not
                 // double-counted--i.e., endpoint metrics track these responses and error metrics
@@ -260,6 +267,13 @@ impl<T: svc::Param<Option<http::AuthorityOverride>>> svc::Param<Option<http::Aut
     }
 }
 
+impl<T: svc::Param<SendProxyHeader>> svc::Param<SendProxyHeader> for Connect<T> {
+    #[inline]
+    fn param(&self) -> SendProxyHeader {
+        self.inner.param()
+    }
+}
+
 impl<T: svc::Param<transport::labels::Key>> svc::Param<transport::labels::Key> for Connect<T> {
     #[inline]
     fn param(&self) -> transport::labels::Key {
@@ -108,13 +108,16 @@ impl<N> Outbound<N> {
         NSvc: Clone + Send + Sync + 'static,
         NSvc::Future: Send,
     {
-        self.map_stack(|_config, rt, concrete| {
+        self.map_stack(|config, rt, concrete| {
             // For each `T` target, watch its `Profile`, rebuilding a
             // router stack.
             let watch = concrete
                 // Share the concrete stack with each router stack.
                 .lift_new()
-                .push_on_service(RouterParams::layer(rt.metrics.clone()))
+                .push_on_service(RouterParams::layer(
+                    rt.metrics.clone(),
+                    config.deadline_header.clone(),
+                ))
                 // Rebuild the inner router stack every time the watch changes.
                 .push(svc::NewSpawnWatch::<Routes, _>::layer_into::<RouterParams<T>>());
 
@@ -133,6 +136,7 @@ where
 {
     fn layer<N, S>(
         metrics: OutboundMetrics,
+        deadline_header: Option<http::HeaderName>,
     ) -> impl svc::Layer<
         N,
         Service = svc::ArcNewService<
@@ -157,8 +161,10 @@ where
         S::Future: Send,
     {
         svc::layer::mk(move |concrete: N| {
-            let policy = svc::stack(concrete.clone())
-                .push(policy::Policy::layer(metrics.http_route_backends.clone()));
+            let policy = svc::stack(concrete.clone()).push(policy::Policy::layer(
+                metrics.http_route_backends.clone(),
+                deadline_header.clone(),
+            ));
             let profile =
                 svc::stack(concrete.clone()).push(profile::Params::layer(metrics.proxy.clone()));
             svc::stack(concrete)
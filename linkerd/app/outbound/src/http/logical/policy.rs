@@ -7,6 +7,7 @@ mod router;
 #[cfg(test)]
 mod tests;
 
+pub(crate) use self::route::hedge::NewDistinctEndpoint;
 pub use self::{
     route::{backend::RouteBackendMetrics, errors},
     router::{GrpcParams, HttpParams},
@@ -51,6 +52,7 @@ where
     /// services.
     pub(super) fn layer<N, S>(
         route_backend_metrics: RouteBackendMetrics,
+        deadline_header: Option<http::HeaderName>,
     ) -> impl svc::Layer<
         N,
         Service = svc::ArcNewService<
@@ -76,9 +78,14 @@ where
         S::Future: Send,
     {
         svc::layer::mk(move |inner: N| {
-            let http =
-                svc::stack(inner.clone()).push(router::Http::layer(route_backend_metrics.clone()));
-            let grpc = svc::stack(inner).push(router::Grpc::layer(route_backend_metrics.clone()));
+            let http = svc::stack(inner.clone()).push(router::Http::layer(
+                route_backend_metrics.clone(),
+                deadline_header.clone(),
+            ));
+            let grpc = svc::stack(inner).push(router::Grpc::layer(
+                route_backend_metrics.clone(),
+                deadline_header.clone(),
+            ));
 
             http.push_switch(
                 |pp: Policy<T>| {
@@ -8,6 +8,9 @@ use std::{fmt::Debug, hash::Hash, sync::Arc};
 
 pub(crate) mod backend;
 pub(crate) mod filters;
+pub(crate) mod hedge;
+pub(crate) mod mirror;
+pub(crate) mod retry;
 
 pub(crate) use self::backend::{Backend, MatchedBackend};
 pub use self::filters::errors;
@@ -28,9 +31,12 @@ pub(crate) struct Route<T, F, E> {
     pub(super) addr: Addr,
     pub(super) route_ref: RouteRef,
     pub(super) filters: Arc<[F]>,
+    pub(super) mirrors: Arc<[mirror::Mirror<T>]>,
     pub(super) distribution: BackendDistribution<T, F>,
     pub(super) failure_policy: E,
-    pub(super) request_timeout: Option<std::time::Duration>,
+    pub(super) timeouts: policy::RouteTimeouts,
+    pub(super) retry: Option<policy::RouteRetry<E>>,
+    pub(super) hedge: Option<policy::RouteHedge>,
 }
 
 pub(crate) type MatchedRoute<T, M, F, E> = Matched<M, Route<T, F, E>>;
@@ -76,6 +82,8 @@ where
     // Assert that filters can be applied.
     Self: filters::Apply,
     Self: svc::Param<classify::Request>,
+    Self: svc::Param<Option<retry::RetryPolicy>>,
+    Self: svc::Param<Option<hedge::HedgePolicy>>,
     MatchedBackend<T, M, F>: filters::Apply,
     backend::ExtractMetrics: svc::ExtractParam<backend::RequestCount, MatchedBackend<T, M, F>>,
 {
@@ -84,6 +92,7 @@ where
     /// backends are expected to be cached/shared by the inner stack.
     pub(crate) fn layer<N, S>(
         backend_metrics: backend::RouteBackendMetrics,
+        deadline_header: Option<http::HeaderName>,
     ) -> impl svc::Layer<
         N,
         Service = svc::ArcNewService<
@@ -108,8 +117,9 @@ where
         S: Clone + Send + Sync + 'static,
         S::Future: Send,
     {
-        svc::layer::mk(move |inner| {
-            svc::stack(inner)
+        svc::layer::mk(move |inner: N| {
+            let deadline_header = deadline_header.clone();
+            svc::stack(inner.clone())
                 // Distribute requests across route backends, applying policies
                 // and filters for each of the route-backends.
                 .push(MatchedBackend::layer(backend_metrics.clone()))
@@ -119,10 +129,49 @@ where
                 // consideration, so we must eagerly fail requests to prevent
                 // leaking tasks onto the runtime.
                 .push_on_service(svc::LoadShed::layer())
+                // Sets an optional timeout on each attempt, so that a slow
+                // attempt may be retried.
+                .push(http::NewTimeout::layer_via(|rt: &Self| {
+                    http::ResponseTimeout(rt.params.retry.as_ref().and_then(|r| r.timeout))
+                }))
+                // Sets an optional hedging policy, so that a second copy of a
+                // slow attempt may be sent. Hedging is applied within retries,
+                // since a hedged request may not be sent until the original
+                // request's body has been released.
+                .push(hedge::NewHedge::layer())
+                // Depending on whether or not the request can be retried, it
+                // may have one of two `Body` types. This layer unifies any
+                // `Body` type into `BoxBody`.
+                .push_on_service(http::BoxRequest::erased())
+                // Sets an optional retry policy.
+                .push(retry::NewRetryPolicy::layer())
+                // Copies a sample of requests to the route's mirror backends,
+                // which are obtained from the inner (cached) backend stack.
+                .push(mirror::NewRequestMirror::<T, _, _>::layer(
+                    inner,
+                    backend_metrics.clone(),
+                ))
                 // TODO(ver) attach the `E` typed failure policy to requests.
                 .push(filters::NewApplyFilters::<Self, _, _>::layer())
-                // Sets an optional request timeout.
+                // Sets an optional timeout on response headers.
                 .push(http::NewTimeout::layer())
+                // Sets optional timeouts on the total request duration and on
+                // idle response streams. Unlike the response timeout, these
+                // continue to apply while the response body is streamed.
+                .push(http::NewStreamTimeouts::layer())
+                // Bounds requests by the deadline set by their callers, if
+                // any, so that requests are not processed after their callers
+                // have given up on them.
+                .push(http::NewEnforceDeadline::layer_via(move |rt: &Self| {
+                    let policy::RouteTimeouts {
+                        response, request, ..
+                    } = rt.params.timeouts;
+                    http::DeadlineParams {
+                        timeout: response.into_iter().chain(request).min(),
+                        header: deadline_header.clone(),
+                    }
+                }))
+                .push_on_service(http::BoxResponse::layer())
                 .push(classify::NewClassify::layer())
                 .push(svc::NewMapErr::layer_with(|rt: &Self| {
                     let route = rt.params.route_ref.clone();
@@ -145,9 +194,28 @@ impl<T: Clone, M, F, E> svc::Param<BackendDistribution<T, F>> for MatchedRoute<T
     }
 }
 
+impl<T: Clone, M, F, E> svc::Param<mirror::Params<T>> for MatchedRoute<T, M, F, E> {
+    fn param(&self) -> mirror::Params<T> {
+        mirror::Params {
+            route_ref: self.params.route_ref.clone(),
+            mirrors: self.params.mirrors.clone(),
+            timeout: self.params.timeouts.response,
+        }
+    }
+}
+
 impl<T, M, F, E> svc::Param<http::timeout::ResponseTimeout> for MatchedRoute<T, M, F, E> {
     fn param(&self) -> http::timeout::ResponseTimeout {
-        http::timeout::ResponseTimeout(self.params.request_timeout)
+        http::timeout::ResponseTimeout(self.params.timeouts.response)
+    }
+}
+
+impl<T, M, F, E> svc::Param<http::StreamTimeouts> for MatchedRoute<T, M, F, E> {
+    fn param(&self) -> http::StreamTimeouts {
+        http::StreamTimeouts {
+            total: self.params.timeouts.request,
+            idle: self.params.timeouts.idle,
+        }
     }
 }
 
@@ -171,6 +239,32 @@ impl<T> svc::Param<classify::Request> for Http<T> {
     }
 }
 
+impl<T> svc::Param<Option<retry::RetryPolicy>> for Http<T> {
+    fn param(&self) -> Option<retry::RetryPolicy> {
+        let rt = self.params.retry.as_ref()?;
+        retry::RetryPolicy::new(
+            classify::Request::ClientPolicy(classify::ClientPolicy::Http(rt.conditions.clone())),
+            rt.max_retries,
+            rt.max_request_bytes,
+            rt.backoff,
+            rt.budget,
+        )
+    }
+}
+
+impl<T> svc::Param<Option<hedge::HedgePolicy>> for Http<T> {
+    fn param(&self) -> Option<hedge::HedgePolicy> {
+        let hedge = self.params.hedge.as_ref()?;
+        Some(hedge::HedgePolicy {
+            classify: svc::Param::<classify::Request>::param(self),
+            delay: hedge.delay,
+            max_percent: hedge.max_percent,
+            max_request_bytes: hedge.max_request_bytes,
+            idempotent_only: !hedge.idempotent,
+        })
+    }
+}
+
 impl<T> filters::Apply for Grpc<T> {
     #[inline]
     fn apply_request<B>(&self, req: &mut ::http::Request<B>) -> Result<()> {
@@ -190,3 +284,31 @@ impl<T> svc::Param<classify::Request> for Grpc<T> {
         ))
     }
 }
+
+impl<T> svc::Param<Option<retry::RetryPolicy>> for Grpc<T> {
+    fn param(&self) -> Option<retry::RetryPolicy> {
+        let rt = self.params.retry.as_ref()?;
+        retry::RetryPolicy::new(
+            classify::Request::ClientPolicy(classify::ClientPolicy::Grpc(rt.conditions.clone())),
+            rt.max_retries,
+            rt.max_request_bytes,
+            rt.backoff,
+            rt.budget,
+        )
+    }
+}
+
+impl<T> svc::Param<Option<hedge::HedgePolicy>> for Grpc<T> {
+    fn param(&self) -> Option<hedge::HedgePolicy> {
+        // gRPC requests are always `POST`s, so gRPC routes are only hedged
+        // when they mark their requests as idempotent.
+        let hedge = self.params.hedge.as_ref().filter(|h| h.idempotent)?;
+        Some(hedge::HedgePolicy {
+            classify: svc::Param::<classify::Request>::param(self),
+            delay: hedge.delay,
+            max_percent: hedge.max_percent,
+            max_request_bytes: hedge.max_request_bytes,
+            idempotent_only: false,
+        })
+    }
+}
@@ -9,14 +9,14 @@ mod count_reqs;
 mod metrics;
 
 pub use self::count_reqs::RequestCount;
-pub use self::metrics::RouteBackendMetrics;
+pub use self::metrics::{MirrorCounters, RouteBackendMetrics};
 
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub(crate) struct Backend<T, F> {
     pub(crate) route_ref: RouteRef,
     pub(crate) concrete: Concrete<T>,
     pub(crate) filters: Arc<[F]>,
-    pub(crate) request_timeout: Option<std::time::Duration>,
+    pub(crate) timeouts: policy::RouteTimeouts,
 }
 
 pub(crate) type MatchedBackend<T, M, F> = super::Matched<M, Backend<T, F>>;
@@ -47,7 +47,7 @@ impl<T: Clone, F> Clone for Backend<T, F> {
             route_ref: self.route_ref.clone(),
             filters: self.filters.clone(),
             concrete: self.concrete.clone(),
-            request_timeout: self.request_timeout,
+            timeouts: self.timeouts,
         }
     }
 }
@@ -119,6 +119,8 @@ where
                 )
                 .push(filters::NewApplyFilters::<Self, _, _>::layer())
                 .push(http::NewTimeout::layer())
+                .push(http::NewStreamTimeouts::layer())
+                .push_on_service(http::BoxResponse::layer())
                 .push(count_reqs::NewCountRequests::layer_via(ExtractMetrics {
                     metrics: metrics.clone(),
                 }))
@@ -139,7 +141,16 @@ where
 
 impl<T, M, F> svc::Param<http::ResponseTimeout> for MatchedBackend<T, M, F> {
     fn param(&self) -> http::ResponseTimeout {
-        http::ResponseTimeout(self.params.request_timeout)
+        http::ResponseTimeout(self.params.timeouts.response)
+    }
+}
+
+impl<T, M, F> svc::Param<http::StreamTimeouts> for MatchedBackend<T, M, F> {
+    fn param(&self) -> http::StreamTimeouts {
+        http::StreamTimeouts {
+            total: self.params.timeouts.request,
+            idle: self.params.timeouts.idle,
+        }
     }
 }
 
@@ -11,6 +11,15 @@ metrics! {
     },
     outbound_grpc_route_backend_requests_total: Counter {
         "The total number of outbound requests dispatched to a gRPC route backend"
+    },
+    outbound_http_route_mirror_requests_total: Counter {
+        "The total number of outbound requests mirrored to a HTTP route backend"
+    },
+    outbound_http_route_mirror_failures_total: Counter {
+        "The total number of outbound requests mirrored to a HTTP route backend that failed"
+    },
+    outbound_http_route_mirror_dropped_total: Counter {
+        "The total number of outbound requests not mirrored to a HTTP route backend because too many mirrored requests were in flight"
     }
 }
 
@@ -18,6 +27,15 @@ metrics! {
 pub struct RouteBackendMetrics {
     http: Arc<Mutex<AHashMap<Labels, Arc<Counter>>>>,
     grpc: Arc<Mutex<AHashMap<Labels, Arc<Counter>>>>,
+    mirror: Arc<Mutex<AHashMap<Labels, MirrorCounters>>>,
+}
+
+/// Counts requests mirrored to a route backend.
+#[derive(Clone, Debug, Default)]
+pub struct MirrorCounters {
+    pub requests: Arc<Counter>,
+    pub failures: Arc<Counter>,
+    pub dropped: Arc<Counter>,
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
@@ -41,6 +59,19 @@ impl RouteBackendMetrics {
             .or_default()
             .clone()
     }
+
+    pub fn http_mirror_counters(
+        &self,
+        pr: ParentRef,
+        rr: RouteRef,
+        br: BackendRef,
+    ) -> MirrorCounters {
+        self.mirror
+            .lock()
+            .entry(Labels(pr, rr, br))
+            .or_default()
+            .clone()
+    }
 }
 
 impl FmtMetrics for RouteBackendMetrics {
@@ -59,6 +90,20 @@ impl FmtMetrics for RouteBackendMetrics {
         }
         drop(grpc);
 
+        let mirror = self.mirror.lock();
+        if !mirror.is_empty() {
+            outbound_http_route_mirror_requests_total.fmt_help(f)?;
+            outbound_http_route_mirror_requests_total
+                .fmt_scopes(f, mirror.iter(), |c| &c.requests)?;
+            outbound_http_route_mirror_failures_total.fmt_help(f)?;
+            outbound_http_route_mirror_failures_total
+                .fmt_scopes(f, mirror.iter(), |c| &c.failures)?;
+            outbound_http_route_mirror_dropped_total.fmt_help(f)?;
+            outbound_http_route_mirror_dropped_total
+                .fmt_scopes(f, mirror.iter(), |c| &c.dropped)?;
+        }
+        drop(mirror);
+
         Ok(())
     }
 }
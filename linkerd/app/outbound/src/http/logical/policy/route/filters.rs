@@ -48,6 +48,10 @@ pub mod errors {
     #[error("invalid redirect: {0}")]
     pub struct HttpRouteInvalidRedirect(#[from] pub http::filter::InvalidRedirect);
 
+    #[derive(Debug, thiserror::Error)]
+    #[error("invalid URL rewrite: {0}")]
+    pub struct HttpRouteInvalidUrlRewrite(#[from] pub http::filter::InvalidUrlRewrite);
+
     #[derive(Debug, thiserror::Error)]
     #[error("request redirected to {location}")]
     pub struct HttpRouteRedirect {
@@ -111,10 +115,17 @@ pub fn apply_http_request<B>(
                 rh.apply(req.headers_mut());
             }
 
+            http::Filter::UrlRewrite(rewrite) => {
+                rewrite
+                    .apply(req, r#match)
+                    .map_err(errors::HttpRouteInvalidUrlRewrite)?;
+            }
+
             http::Filter::InternalError(msg) => {
                 return Err(errors::HttpInvalidPolicy(msg).into());
             }
             http::Filter::ResponseHeaders(_) => {} // ResponseHeaders filter does not apply to requests.
+            http::Filter::RequestMirror(_) => {} // RequestMirror filter is applied by the route's mirror middleware.
         }
     }
 
@@ -131,8 +142,10 @@ pub fn apply_http_response<B>(
             http::Filter::InjectFailure(_) => {} // InjectFailure filter does not apply to responses.
             http::Filter::Redirect(_) => {}      // Redirect filter does not apply to responses.
             http::Filter::RequestHeaders(_) => {} // RequestHeaders filter does not apply to responses.
+            http::Filter::UrlRewrite(_) => {}     // UrlRewrite filter does not apply to responses.
             http::Filter::InternalError(_) => {} // InternalError filter does not apply to responses.
             http::Filter::ResponseHeaders(rh) => rh.apply(rsp.headers_mut()),
+            http::Filter::RequestMirror(_) => {} // RequestMirror filter does not apply to responses.
         }
     }
 
@@ -159,6 +172,7 @@ pub fn apply_grpc_request<B>(
             grpc::Filter::InternalError(msg) => {
                 return Err(errors::HttpInvalidPolicy(msg).into());
             }
+            grpc::Filter::ResponseHeaders(_) => {} // ResponseHeaders filter does not apply to requests.
         }
     }
 
@@ -167,13 +181,14 @@ pub fn apply_grpc_request<B>(
 
 pub fn apply_grpc_response<B>(
     filters: &[grpc::Filter],
-    _rsp: &mut ::http::Response<B>,
+    rsp: &mut ::http::Response<B>,
 ) -> Result<()> {
     for filter in filters {
         match filter {
             grpc::Filter::InjectFailure(_) => {} // InjectFailure filter does not apply to responses.
             grpc::Filter::RequestHeaders(_) => {} // RequestHeaders filter does not apply to responses.
             grpc::Filter::InternalError(_) => {} // InternalError filter does not apply to responses.
+            grpc::Filter::ResponseHeaders(rh) => rh.apply(rsp.headers_mut()),
         }
     }
 
@@ -0,0 +1,678 @@
+use futures::{
+    future::{self, Either},
+    TryFutureExt,
+};
+use linkerd_app_core::{
+    classify,
+    proxy::http::{self, ClientHandle, HttpBody},
+    svc::{self, ServiceExt},
+    transport::addrs::*,
+    Error, Result,
+};
+use linkerd_http_classify::{Classify, ClassifyEos, ClassifyResponse};
+use linkerd_http_retry::{with_trailers::WithTrailers, ReplayBody};
+use linkerd_proxy_client_policy::HedgeDelay;
+use linkerd_retry as retry;
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::{
+    sync::oneshot,
+    time::{self, Duration, Instant},
+};
+
+/// A hedging policy configured by a client policy route.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct HedgePolicy {
+    pub(crate) classify: classify::Request,
+    pub(crate) delay: HedgeDelay,
+    pub(crate) max_percent: u32,
+    pub(crate) max_request_bytes: usize,
+
+    /// When set, only requests with idempotent methods are hedged. This is
+    /// unset for routes that mark all of their requests as idempotent.
+    pub(crate) idempotent_only: bool,
+}
+
+/// Builds [`Hedge`] services for route targets that configure hedging.
+#[derive(Clone, Debug)]
+pub(crate) struct NewHedge<N> {
+    inner: N,
+}
+
+/// Sends a second copy of a request when it has not received a response
+/// within the route's hedging delay, returning the first successful response.
+///
+/// The hedged request is dispatched through the route's backends like any
+/// other request. Both copies carry [`HedgedEndpoints`], so that
+/// [`DistinctEndpoint`] fails a copy that would be sent to the endpoint that is
+/// serving the other.
+#[derive(Clone, Debug)]
+pub(crate) struct Hedge<S> {
+    inner: S,
+    policy: Option<Arc<Hedging>>,
+}
+
+#[derive(Debug)]
+struct Hedging {
+    policy: HedgePolicy,
+    budget: retry::Budget,
+    latencies: Mutex<Latencies>,
+}
+
+/// Tracks a route's recently observed response latencies.
+///
+/// Latencies are recorded into coarse buckets, over the current and prior
+/// windows, so that a percentile may be estimated cheaply.
+#[derive(Debug)]
+struct Latencies {
+    current: [u64; BOUNDS_MS.len() + 1],
+    prior: [u64; BOUNDS_MS.len() + 1],
+    window_start: Instant,
+}
+
+/// Records the endpoints to which the copies of a hedged request are sent.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HedgedEndpoints(Arc<Mutex<Vec<SocketAddr>>>);
+
+/// Builds [`DistinctEndpoint`] services for endpoint targets, whose addresses
+/// are extracted by `X`.
+#[derive(Clone, Debug)]
+pub(crate) struct NewDistinctEndpoint<X, N> {
+    inner: N,
+    extract: X,
+}
+
+/// Fails requests that are copies of a hedged request when another copy was
+/// already sent to this endpoint, so that the hedged request is never served
+/// by the original request's endpoint.
+///
+/// This must be applied outside of an endpoint's failure accrual, so that the
+/// endpoint is not penalized for these failures.
+#[derive(Clone, Debug)]
+pub(crate) struct DistinctEndpoint<S> {
+    inner: S,
+    addr: SocketAddr,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("endpoint {0} is already serving a copy of this hedged request")]
+pub(crate) struct DuplicateEndpointError(SocketAddr);
+
+/// A request body that notifies a receiver when it is dropped.
+#[pin_project]
+struct NotifyDrop<B> {
+    #[pin]
+    inner: B,
+    _released: oneshot::Sender<()>,
+}
+
+type ResponseFuture = Pin<Box<dyn Future<Output = Result<http::Response<http::BoxBody>>> + Send>>;
+
+/// A response and whether it is classified as a failure.
+type Classified = Result<(bool, http::Response<WithTrailers<http::BoxBody>>)>;
+
+/// The upper bounds of each latency bucket, in milliseconds. Latencies
+/// exceeding the last bound are never hedged.
+const BOUNDS_MS: [u64; 21] = [
+    1, 2, 3, 4, 5, 10, 20, 30, 40, 50, 100, 200, 300, 400, 500, 1_000, 2_000, 3_000, 4_000, 5_000,
+    10_000,
+];
+
+/// The duration over which latencies are observed.
+const LATENCY_WINDOW: Duration = Duration::from_secs(10);
+
+/// The number of observed latencies required to estimate a percentile.
+const MIN_LATENCIES: u64 = 20;
+
+/// The duration over which the hedging budget is accrued.
+const BUDGET_TTL: Duration = Duration::from_secs(10);
+
+// === impl NewHedge ===
+
+impl<N> NewHedge<N> {
+    pub(crate) fn layer() -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewHedge<N>
+where
+    T: svc::Param<Option<HedgePolicy>>,
+    N: svc::NewService<T>,
+{
+    type Service = Hedge<N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let policy = target.param().map(|policy| {
+            let percent = policy.max_percent.min(100) as f32 / 100.0;
+            let budget = retry::Budget::new(BUDGET_TTL, 0, percent);
+            Arc::new(Hedging {
+                policy,
+                budget,
+                latencies: Mutex::new(Latencies::new(Instant::now())),
+            })
+        });
+        Hedge {
+            inner: self.inner.new_service(target),
+            policy,
+        }
+    }
+}
+
+// === impl Hedge ===
+
+impl<S> svc::Service<http::Request<http::BoxBody>> for Hedge<S>
+where
+    S: svc::Service<
+        http::Request<http::BoxBody>,
+        Response = http::Response<http::BoxBody>,
+        Error = Error,
+    >,
+    S: Clone + Send + 'static,
+    S::Future: Send,
+{
+    type Response = http::Response<http::BoxBody>;
+    type Error = Error;
+    type Future = Either<S::Future, ResponseFuture>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<http::BoxBody>) -> Self::Future {
+        let hedging = match self.policy.as_ref() {
+            Some(hedging) if hedging.is_hedgeable(&req) => hedging.clone(),
+            _ => return Either::Left(self.inner.call(req)),
+        };
+
+        let (mut head, body) = req.into_parts();
+        let body = match ReplayBody::try_new(body, hedging.policy.max_request_bytes) {
+            Ok(body) => body,
+            Err(body) => {
+                tracing::debug!(
+                    size = body.size_hint().lower(),
+                    "Body is too large to hedge"
+                );
+                return Either::Left(self.inner.call(http::Request::from_parts(head, body)));
+            }
+        };
+
+        hedging.budget.deposit();
+        let start = Instant::now();
+        let delay = hedging.delay();
+        head.extensions.insert(HedgedEndpoints::default());
+        let classify = hedging.policy.classify.classify(&clone_request(&head, ()));
+        let (hedge, ()) = clone_request(&head, ()).into_parts();
+        let replay = body.clone();
+        let (body, released) = NotifyDrop::new(body);
+        let original = self
+            .inner
+            .call(http::Request::from_parts(head, http::BoxBody::new(body)));
+        let inner = self.inner.clone();
+
+        Either::Right(Box::pin(async move {
+            let original = classified(original, classify.clone());
+            tokio::pin!(original);
+
+            let delay = match delay {
+                Some(delay) => delay,
+                None => return hedging.original(start, original.await),
+            };
+            let released = async move {
+                time::sleep(delay).await;
+                // Clones of a `ReplayBody` may not be polled concurrently, so
+                // the hedged request may not be sent until the original
+                // request's body has been released. Endpoints release request
+                // bodies once they have been sent; hedging is applied within
+                // retries, so that no other body holds this one.
+                let _ = released.await;
+            };
+            tokio::pin!(released);
+            if let Either::Left((res, _)) = future::select(&mut original, released).await {
+                return hedging.original(start, res);
+            }
+
+            if replay.is_capped() {
+                tracing::debug!("Body is too large to hedge");
+                return hedging.original(start, original.await);
+            }
+            if hedging.budget.withdraw().is_err() {
+                tracing::debug!("Hedging budget exhausted");
+                return hedging.original(start, original.await);
+            }
+
+            tracing::debug!(?delay, "Hedging request");
+            let hedge = inner.oneshot(clone_request(&hedge, http::BoxBody::new(replay)));
+            let hedge = classified(hedge, classify);
+            tokio::pin!(hedge);
+
+            // Use the first successful response, canceling the other request.
+            // If the first response is a failure, wait for the other. If both
+            // fail, the original's response is used, since the hedged request
+            // may have failed only because it could not reach another
+            // endpoint.
+            match future::select(original, hedge).await {
+                Either::Left((res, hedge)) => {
+                    if is_success(&res) {
+                        return hedging.original(start, res);
+                    }
+                    let hedged = hedge.await;
+                    if is_success(&hedged) {
+                        return respond(hedged);
+                    }
+                    respond(res)
+                }
+                Either::Right((hedged, original)) => {
+                    if is_success(&hedged) {
+                        // The original request is canceled, having taken at
+                        // least this long.
+                        hedging.record(start);
+                        return respond(hedged);
+                    }
+                    let res = original.await;
+                    if is_success(&res) {
+                        return hedging.original(start, res);
+                    }
+                    respond(res)
+                }
+            }
+        }))
+    }
+}
+
+/// Waits for a response's trailers, if it may only consist of trailers, and
+/// classifies whether it is a failure.
+async fn classified<F>(rsp: F, classify: classify::Response) -> Classified
+where
+    F: Future<Output = Result<http::Response<http::BoxBody>>>,
+{
+    let rsp = WithTrailers::map_response(rsp.await?).await;
+    let is_failure = classify.start(&rsp).eos(rsp.body().trailers()).is_failure();
+    Ok((is_failure, rsp))
+}
+
+fn is_success(res: &Classified) -> bool {
+    matches!(res, Ok((false, _)))
+}
+
+fn respond(res: Classified) -> Result<http::Response<http::BoxBody>> {
+    let (_, rsp) = res?;
+    Ok(rsp.map(http::BoxBody::new))
+}
+
+fn clone_request<B>(head: &::http::request::Parts, body: B) -> http::Request<B> {
+    let mut clone = http::Request::new(body);
+    *clone.method_mut() = head.method.clone();
+    *clone.uri_mut() = head.uri.clone();
+    *clone.headers_mut() = head.headers.clone();
+    *clone.version_mut() = head.version;
+
+    // The HTTP server sets a ClientHandle with the client's address and a
+    // means to close the server-side connection.
+    if let Some(client_handle) = head.extensions.get::<ClientHandle>().cloned() {
+        clone.extensions_mut().insert(client_handle);
+    }
+
+    // Hedged requests are bounded by the original request's deadline.
+    if let Some(deadline) = head.extensions.get::<http::RequestDeadline>().cloned() {
+        clone.extensions_mut().insert(deadline);
+    }
+
+    if let Some(endpoints) = head.extensions.get::<HedgedEndpoints>().cloned() {
+        clone.extensions_mut().insert(endpoints);
+    }
+
+    clone
+}
+
+// === impl Hedging ===
+
+impl Hedging {
+    fn is_hedgeable<B>(&self, req: &http::Request<B>) -> bool {
+        !self.policy.idempotent_only || req.method().is_idempotent()
+    }
+
+    /// Returns the delay after which a request is hedged, if it may be hedged.
+    fn delay(&self) -> Option<Duration> {
+        match self.policy.delay {
+            HedgeDelay::Fixed(delay) => Some(delay),
+            HedgeDelay::Percentile(p) => self.latencies.lock().percentile(p, Instant::now()),
+        }
+    }
+
+    /// Returns the original request's response, recording its latency if it
+    /// succeeded.
+    fn original(&self, start: Instant, res: Classified) -> Result<http::Response<http::BoxBody>> {
+        if is_success(&res) {
+            self.record(start);
+        }
+        respond(res)
+    }
+
+    /// Records the original request's latency.
+    ///
+    /// Only the original request's latency is recorded, whichever request's
+    /// response is used: a hedged request's latency says nothing about how
+    /// long the original request would have taken, so the latencies that
+    /// determine the hedging delay would otherwise be skewed by hedging itself.
+    /// When a hedged request's response is used, the original request is
+    /// canceled and recorded as having taken as long as it was outstanding.
+    fn record(&self, start: Instant) {
+        let now = Instant::now();
+        self.latencies
+            .lock()
+            .record(now.saturating_duration_since(start), now);
+    }
+}
+
+// === impl HedgedEndpoints ===
+
+impl HedgedEndpoints {
+    /// Records that a copy of the request is sent to `addr`, returning false
+    /// if another copy was already sent to it.
+    fn claim(&self, addr: SocketAddr) -> bool {
+        let mut endpoints = self.0.lock();
+        if endpoints.contains(&addr) {
+            return false;
+        }
+        endpoints.push(addr);
+        true
+    }
+}
+
+// === impl NewDistinctEndpoint ===
+
+impl<X: Clone, N> NewDistinctEndpoint<X, N> {
+    pub(crate) fn layer_via(extract: X) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            extract: extract.clone(),
+        })
+    }
+}
+
+impl<T, X, N> svc::NewService<T> for NewDistinctEndpoint<X, N>
+where
+    X: svc::ExtractParam<Remote<ServerAddr>, T>,
+    N: svc::NewService<T>,
+{
+    type Service = DistinctEndpoint<N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let Remote(ServerAddr(addr)) = self.extract.extract_param(&target);
+        DistinctEndpoint {
+            inner: self.inner.new_service(target),
+            addr,
+        }
+    }
+}
+
+// === impl DistinctEndpoint ===
+
+impl<B, S> svc::Service<http::Request<B>> for DistinctEndpoint<S>
+where
+    S: svc::Service<http::Request<B>>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = Either<future::ErrInto<S::Future, Error>, future::Ready<Result<S::Response>>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        if let Some(endpoints) = req.extensions().get::<HedgedEndpoints>() {
+            if !endpoints.claim(self.addr) {
+                tracing::debug!(addr = %self.addr, "Endpoint is already serving a copy of this request");
+                return Either::Right(future::err(DuplicateEndpointError(self.addr).into()));
+            }
+        }
+        Either::Left(self.inner.call(req).err_into())
+    }
+}
+
+// === impl Latencies ===
+
+impl Latencies {
+    fn new(now: Instant) -> Self {
+        Self {
+            current: [0; BOUNDS_MS.len() + 1],
+            prior: [0; BOUNDS_MS.len() + 1],
+            window_start: now,
+        }
+    }
+
+    fn record(&mut self, latency: Duration, now: Instant) {
+        self.rotate(now);
+        let ms = latency.as_millis() as u64;
+        let bucket = BOUNDS_MS
+            .iter()
+            .position(|&bound| ms <= bound)
+            .unwrap_or(BOUNDS_MS.len());
+        self.current[bucket] += 1;
+    }
+
+    /// Estimates the given percentile of the latencies observed over the
+    /// current and prior windows, as the upper bound of the bucket in which it
+    /// falls.
+    fn percentile(&mut self, percentile: u8, now: Instant) -> Option<Duration> {
+        self.rotate(now);
+        let counts = self.current.iter().zip(&self.prior).map(|(c, p)| c + p);
+        let total = counts.clone().sum::<u64>();
+        if total < MIN_LATENCIES {
+            return None;
+        }
+
+        let rank = ((total * u64::from(percentile.min(100)) + 99) / 100).max(1);
+        let mut seen = 0;
+        for (count, bound) in counts.zip(BOUNDS_MS) {
+            seen += count;
+            if seen >= rank {
+                return Some(Duration::from_millis(bound));
+            }
+        }
+        None
+    }
+
+    fn rotate(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed < LATENCY_WINDOW {
+            return;
+        }
+        self.prior = if elapsed < LATENCY_WINDOW * 2 {
+            self.current
+        } else {
+            [0; BOUNDS_MS.len() + 1]
+        };
+        self.current = [0; BOUNDS_MS.len() + 1];
+        self.window_start = now;
+    }
+}
+
+// === impl NotifyDrop ===
+
+impl<B> NotifyDrop<B> {
+    fn new(inner: B) -> (Self, oneshot::Receiver<()>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            Self {
+                inner,
+                _released: tx,
+            },
+            rx,
+        )
+    }
+}
+
+impl<B: HttpBody> HttpBody for NotifyDrop<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        self.project().inner.poll_data(cx)
+    }
+
+    #[inline]
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<::http::HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use svc::Service;
+
+    #[test]
+    fn latency_percentiles() {
+        let start = Instant::now();
+        let mut latencies = Latencies::new(start);
+        for _ in 0..90 {
+            latencies.record(Duration::from_millis(8), start);
+        }
+        for _ in 0..10 {
+            latencies.record(Duration::from_millis(250), start);
+        }
+        assert_eq!(
+            latencies.percentile(50, start),
+            Some(Duration::from_millis(10))
+        );
+        assert_eq!(
+            latencies.percentile(95, start),
+            Some(Duration::from_millis(300))
+        );
+
+        // Latencies from the prior window are still considered.
+        let now = start + LATENCY_WINDOW;
+        assert_eq!(
+            latencies.percentile(95, now),
+            Some(Duration::from_millis(300))
+        );
+
+        // Latencies are forgotten after two windows.
+        let now = now + LATENCY_WINDOW;
+        assert_eq!(latencies.percentile(95, now), None);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn records_original_latencies() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let (inner, mut handle) = tower_test::mock::pair();
+        let hedging = Arc::new(Hedging {
+            policy: HedgePolicy {
+                classify: classify::Request::default(),
+                delay: HedgeDelay::Fixed(Duration::from_millis(10)),
+                max_percent: 100,
+                max_request_bytes: 64 * 1024,
+                idempotent_only: true,
+            },
+            budget: retry::Budget::new(BUDGET_TTL, 0, 1.0),
+            latencies: Mutex::new(Latencies::new(Instant::now())),
+        });
+        let recorded = || hedging.latencies.lock().current.iter().sum::<u64>();
+        let svc = Hedge {
+            inner,
+            policy: Some(hedging.clone()),
+        };
+        handle.allow(3);
+
+        // The original request's latency is recorded when it succeeds.
+        let rsp = tokio::spawn(
+            svc.clone()
+                .oneshot(http::Request::new(http::BoxBody::default())),
+        );
+        let (_, tx) = handle.next_request().await.expect("request");
+        tx.send_response(http::Response::new(http::BoxBody::default()));
+        rsp.await.expect("task").expect("response");
+        assert_eq!(recorded(), 1);
+
+        // When the hedged request's response is used, the canceled original
+        // request's latency is recorded.
+        let rsp = tokio::spawn(svc.oneshot(http::Request::new(http::BoxBody::default())));
+        let (_, _original) = handle.next_request().await.expect("request");
+        let (req, tx) = handle.next_request().await.expect("hedged request");
+        assert!(req.extensions().get::<HedgedEndpoints>().is_some());
+        tx.send_response(http::Response::new(http::BoxBody::default()));
+        rsp.await.expect("task").expect("response");
+        assert_eq!(recorded(), 2);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn distinct_endpoints() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let (inner, mut handle) = tower_test::mock::pair::<http::Request<()>, ()>();
+        handle.allow(3);
+        let endpoints = HedgedEndpoints::default();
+        let hedged = || {
+            let mut req = http::Request::new(());
+            req.extensions_mut().insert(endpoints.clone());
+            req
+        };
+        let mut ep0 = DistinctEndpoint {
+            inner: inner.clone(),
+            addr: ([192, 0, 2, 10], 8080).into(),
+        };
+        let mut ep1 = DistinctEndpoint {
+            inner,
+            addr: ([192, 0, 2, 11], 8080).into(),
+        };
+
+        let _original = ep0.ready().await.unwrap().call(hedged());
+        let err = ep0
+            .ready()
+            .await
+            .unwrap()
+            .call(hedged())
+            .await
+            .expect_err("copies must not be sent to the same endpoint");
+        assert!(err.is::<DuplicateEndpointError>());
+        let _hedge = ep1.ready().await.unwrap().call(hedged());
+
+        // Other requests are unaffected.
+        let _other = ep0.ready().await.unwrap().call(http::Request::new(()));
+
+        for _ in 0..3 {
+            handle.next_request().await.expect("request");
+        }
+    }
+
+    #[test]
+    fn requires_latencies() {
+        let start = Instant::now();
+        let mut latencies = Latencies::new(start);
+        for _ in 0..MIN_LATENCIES - 1 {
+            latencies.record(Duration::from_millis(8), start);
+        }
+        assert_eq!(latencies.percentile(50, start), None);
+    }
+}
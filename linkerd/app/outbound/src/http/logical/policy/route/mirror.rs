@@ -0,0 +1,371 @@
+use super::{
+    super::Concrete,
+    backend::{MirrorCounters, RouteBackendMetrics},
+};
+use crate::{BackendRef, RouteRef};
+use linkerd_app_core::{
+    proxy::http::{self, ClientHandle, HttpBody},
+    svc::{self, ServiceExt},
+    Error, Result,
+};
+use linkerd_http_retry::ReplayBody;
+use linkerd_http_route::http::filter::Distribution;
+use linkerd_proxy_client_policy as policy;
+use pin_project::pin_project;
+use std::{
+    marker::PhantomData,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::{
+    sync::{oneshot, OwnedSemaphorePermit, Semaphore},
+    time,
+};
+use tracing::Instrument;
+
+/// A backend to which a sample of a route's requests are copied.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Mirror<T> {
+    pub(crate) concrete: Concrete<T>,
+    pub(crate) distribution: Distribution,
+    pub(crate) max_request_bytes: usize,
+    pub(crate) max_in_flight: usize,
+}
+
+/// Configures the mirrors for a route.
+#[derive(Clone, Debug)]
+pub(crate) struct Params<T> {
+    pub(crate) route_ref: RouteRef,
+    pub(crate) mirrors: Arc<[Mirror<T>]>,
+    pub(crate) timeout: Option<time::Duration>,
+}
+
+/// A route filter that may configure a request mirror.
+pub(crate) trait MirrorFilter {
+    fn request_mirror(&self) -> Option<&policy::http::RequestMirror>;
+}
+
+/// Builds [`RequestMirror`] services, obtaining a service for each mirror
+/// backend from a (cached) backend stack.
+#[derive(Clone, Debug)]
+pub(crate) struct NewRequestMirror<T, K, N> {
+    backends: K,
+    metrics: RouteBackendMetrics,
+    inner: N,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// Sends a copy of each sampled request to a route's mirror backends.
+///
+/// Mirrored requests are dispatched on a background task once the original
+/// request's body has been released, so their responses never affect the
+/// caller. Requests are not mirrored to a backend that already has its maximum
+/// number of mirrored requests in flight.
+#[derive(Clone, Debug)]
+pub(crate) struct RequestMirror<S, M> {
+    inner: S,
+    mirrors: Arc<[Mirrored<M>]>,
+    timeout: Option<time::Duration>,
+}
+
+#[derive(Clone, Debug)]
+struct Mirrored<M> {
+    service: M,
+    distribution: Distribution,
+    max_request_bytes: usize,
+    in_flight: Arc<Semaphore>,
+    backend_ref: BackendRef,
+    counters: MirrorCounters,
+}
+
+/// A request body that notifies a receiver when it is dropped.
+#[pin_project]
+struct NotifyDrop<B> {
+    #[pin]
+    inner: B,
+    _released: oneshot::Sender<()>,
+}
+
+// === impl MirrorFilter ===
+
+impl MirrorFilter for policy::http::Filter {
+    fn request_mirror(&self) -> Option<&policy::http::RequestMirror> {
+        match self {
+            Self::RequestMirror(mirror) => Some(mirror),
+            _ => None,
+        }
+    }
+}
+
+impl MirrorFilter for policy::grpc::Filter {
+    fn request_mirror(&self) -> Option<&policy::http::RequestMirror> {
+        // gRPC routes do not support request mirroring.
+        None
+    }
+}
+
+// === impl NewRequestMirror ===
+
+impl<T, K: Clone, N> NewRequestMirror<T, K, N> {
+    pub(crate) fn layer(
+        backends: K,
+        metrics: RouteBackendMetrics,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            backends: backends.clone(),
+            metrics: metrics.clone(),
+            inner,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<R, T, K, M, N> svc::NewService<R> for NewRequestMirror<T, K, N>
+where
+    R: svc::Param<Params<T>>,
+    T: Clone,
+    K: svc::NewService<Concrete<T>, Service = M>,
+    N: svc::NewService<R>,
+{
+    type Service = RequestMirror<N::Service, M>;
+
+    fn new_service(&self, target: R) -> Self::Service {
+        let Params {
+            route_ref,
+            mirrors,
+            timeout,
+        } = target.param();
+        let mirrors = mirrors
+            .iter()
+            .map(|mirror| Mirrored {
+                service: self.backends.new_service(mirror.concrete.clone()),
+                distribution: mirror.distribution.clone(),
+                max_request_bytes: mirror.max_request_bytes,
+                in_flight: Arc::new(Semaphore::new(mirror.max_in_flight)),
+                backend_ref: mirror.concrete.backend_ref.clone(),
+                counters: self.metrics.http_mirror_counters(
+                    mirror.concrete.parent_ref.clone(),
+                    route_ref.clone(),
+                    mirror.concrete.backend_ref.clone(),
+                ),
+            })
+            .collect();
+        RequestMirror {
+            inner: self.inner.new_service(target),
+            mirrors,
+            timeout,
+        }
+    }
+}
+
+// === impl RequestMirror ===
+
+impl<S, M> svc::Service<http::Request<http::BoxBody>> for RequestMirror<S, M>
+where
+    S: svc::Service<http::Request<http::BoxBody>>,
+    M: svc::Service<
+        http::Request<http::BoxBody>,
+        Response = http::Response<http::BoxBody>,
+        Error = Error,
+    >,
+    M: Clone + Send + 'static,
+    M::Future: Send,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<http::BoxBody>) -> Self::Future {
+        use rand::distributions::Distribution as _;
+
+        let mut rng = rand::thread_rng();
+        let mirrors = self
+            .mirrors
+            .iter()
+            .filter(|m| m.distribution.sample(&mut rng))
+            .filter_map(|m| match m.in_flight.clone().try_acquire_owned() {
+                Ok(permit) => Some((m.clone(), permit)),
+                Err(_) => {
+                    let backend = &m.backend_ref.0;
+                    tracing::debug!(?backend, "Too many mirrored requests in flight");
+                    m.counters.dropped.incr();
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        let max_request_bytes = match mirrors.iter().map(|(m, _)| m.max_request_bytes).min() {
+            Some(max) => max,
+            None => return self.inner.call(req),
+        };
+
+        let (head, body) = req.into_parts();
+        let body = match ReplayBody::try_new(body, max_request_bytes) {
+            Ok(body) => body,
+            Err(body) => {
+                tracing::debug!(
+                    size = body.size_hint().lower(),
+                    "Body is too large to mirror"
+                );
+                return self.inner.call(http::Request::from_parts(head, body));
+            }
+        };
+
+        let (mirror, ()) = clone_request(&head, ()).into_parts();
+        let replay = body.clone();
+        let (body, released) = NotifyDrop::new(body);
+        tokio::spawn(
+            dispatch(mirror, replay, mirrors, released, self.timeout)
+                .instrument(tracing::debug_span!("mirror").or_current()),
+        );
+
+        self.inner
+            .call(http::Request::from_parts(head, http::BoxBody::new(body)))
+    }
+}
+
+/// Sends the request to each mirror in turn, discarding responses.
+///
+/// Clones of a `ReplayBody` may not be polled concurrently, so each mirrored
+/// request waits for the prior request's body to be released. Each mirror's
+/// permit is held until its request completes.
+async fn dispatch<M>(
+    head: ::http::request::Parts,
+    body: ReplayBody<http::BoxBody>,
+    mirrors: Vec<(Mirrored<M>, OwnedSemaphorePermit)>,
+    released: oneshot::Receiver<()>,
+    timeout: Option<time::Duration>,
+) where
+    M: svc::Service<
+        http::Request<http::BoxBody>,
+        Response = http::Response<http::BoxBody>,
+        Error = Error,
+    >,
+{
+    let _ = released.await;
+    for (
+        Mirrored {
+            service,
+            backend_ref,
+            counters,
+            ..
+        },
+        permit,
+    ) in mirrors
+    {
+        if body.is_capped() {
+            tracing::debug!("Body is too large to mirror");
+            return;
+        }
+
+        let (body, released) = NotifyDrop::new(body.clone());
+        let rsp = service.oneshot(clone_request(&head, http::BoxBody::new(body)));
+        counters.requests.incr();
+        let res = match timeout {
+            Some(timeout) => time::timeout(timeout, discard(rsp))
+                .await
+                .unwrap_or_else(|e| Err(e.into())),
+            None => discard(rsp).await,
+        };
+        match res {
+            Ok(status) if !status.is_server_error() => {
+                tracing::trace!(backend = ?backend_ref.0, %status, "Mirrored request succeeded");
+            }
+            Ok(status) => {
+                tracing::debug!(backend = ?backend_ref.0, %status, "Mirrored request failed");
+                counters.failures.incr();
+            }
+            Err(error) => {
+                tracing::debug!(backend = ?backend_ref.0, %error, "Mirrored request failed");
+                counters.failures.incr();
+            }
+        }
+        let _ = released.await;
+        drop(permit);
+    }
+}
+
+/// Reads the response to completion, returning its status.
+async fn discard(
+    rsp: impl std::future::Future<Output = Result<http::Response<http::BoxBody>>>,
+) -> Result<http::StatusCode> {
+    let rsp = rsp.await?;
+    let status = rsp.status();
+    let mut body = rsp.into_body();
+    while let Some(data) = body.data().await {
+        data?;
+    }
+    Ok(status)
+}
+
+fn clone_request<B>(head: &::http::request::Parts, body: B) -> http::Request<B> {
+    let mut clone = http::Request::new(body);
+    *clone.method_mut() = head.method.clone();
+    *clone.uri_mut() = head.uri.clone();
+    *clone.headers_mut() = head.headers.clone();
+    *clone.version_mut() = head.version;
+
+    // The HTTP server sets a ClientHandle with the client's address and a
+    // means to close the server-side connection.
+    if let Some(client_handle) = head.extensions.get::<ClientHandle>().cloned() {
+        clone.extensions_mut().insert(client_handle);
+    }
+
+    // Mirrored requests are bounded by the original request's deadline.
+    if let Some(deadline) = head.extensions.get::<http::RequestDeadline>().cloned() {
+        clone.extensions_mut().insert(deadline);
+    }
+
+    clone
+}
+
+// === impl NotifyDrop ===
+
+impl<B> NotifyDrop<B> {
+    fn new(inner: B) -> (Self, oneshot::Receiver<()>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            Self {
+                inner,
+                _released: tx,
+            },
+            rx,
+        )
+    }
+}
+
+impl<B: HttpBody> HttpBody for NotifyDrop<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        self.project().inner.poll_data(cx)
+    }
+
+    #[inline]
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<::http::HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
@@ -0,0 +1,231 @@
+use futures::{future, FutureExt};
+use linkerd_app_core::{
+    classify,
+    exp_backoff::ExponentialBackoff,
+    proxy::http::{self, ClientHandle, EraseResponse, HttpBody, ResponseTimeoutError},
+    svc::{self, Either},
+    Error,
+};
+use linkerd_http_classify::{Classify, ClassifyEos, ClassifyResponse};
+use linkerd_http_retry::{
+    with_trailers::{self, WithTrailers},
+    ReplayBody,
+};
+use linkerd_proxy_client_policy::RetryBudget;
+use linkerd_retry as retry;
+use std::{sync::Arc, time::Duration};
+
+/// Builds [`RetryPolicy`]s for route targets that configure retries.
+#[derive(Clone, Debug, Default)]
+pub struct NewRetryPolicy(());
+
+/// A retry policy configured by a client policy route.
+///
+/// Each request may be retried up to a fixed number of times. As with
+/// ServiceProfile retries, the route's retries are also limited by a budget
+/// that is shared by all of its requests.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub(crate) classify: classify::Request,
+    pub(crate) remaining: usize,
+    pub(crate) max_request_bytes: usize,
+    pub(crate) backoff: Option<ExponentialBackoff>,
+    pub(crate) budget: Arc<retry::Budget>,
+    pub(crate) attempt: u32,
+}
+
+const MIN_BUDGET_TTL: Duration = Duration::from_secs(1);
+const MAX_BUDGET_TTL: Duration = Duration::from_secs(60);
+
+pub(crate) type NewRetry<N> = retry::NewRetry<NewRetryPolicy, N, EraseResponse<()>>;
+
+// === impl NewRetryPolicy ===
+
+impl NewRetryPolicy {
+    pub(crate) fn layer<N>() -> impl svc::layer::Layer<N, Service = NewRetry<N>> + Clone {
+        retry::layer(Self::default())
+            // Because we wrap the response body type on retries, we must
+            // include a `Proxy` middleware for unifying the response body types
+            // of the retry and non-retry services.
+            .with_proxy(EraseResponse::new(()))
+    }
+}
+
+impl<T> retry::NewPolicy<T> for NewRetryPolicy
+where
+    T: svc::Param<Option<RetryPolicy>>,
+{
+    type Policy = RetryPolicy;
+
+    fn new_policy(&self, target: &T) -> Option<Self::Policy> {
+        target.param()
+    }
+}
+
+// === impl RetryPolicy ===
+
+impl RetryPolicy {
+    pub(crate) fn new(
+        classify: classify::Request,
+        max_retries: usize,
+        max_request_bytes: usize,
+        backoff: Option<ExponentialBackoff>,
+        budget: RetryBudget,
+    ) -> Option<Self> {
+        if max_retries == 0 {
+            return None;
+        }
+        // Budgets must be accrued over a TTL between 1 and 60 seconds, and may
+        // permit no more than 10 retries per request.
+        let budget = retry::Budget::new(
+            budget.ttl.clamp(MIN_BUDGET_TTL, MAX_BUDGET_TTL),
+            budget.min_retries_per_second.min(i32::MAX as u32 - 1),
+            budget.retry_percent.min(1_000) as f32 / 100.0,
+        );
+        Some(Self {
+            classify,
+            remaining: max_retries,
+            max_request_bytes,
+            backoff,
+            budget: Arc::new(budget),
+            attempt: 0,
+        })
+    }
+
+    fn next(&self) -> Self {
+        Self {
+            remaining: self.remaining - 1,
+            attempt: self.attempt + 1,
+            ..self.clone()
+        }
+    }
+}
+
+impl<A, B> retry::Policy<http::Request<ReplayBody<A>>, http::Response<WithTrailers<B>>, Error>
+    for RetryPolicy
+where
+    A: HttpBody + Unpin,
+    A::Error: Into<Error>,
+    B: HttpBody + Unpin,
+{
+    type Future = future::BoxFuture<'static, Self>;
+
+    fn retry(
+        &self,
+        req: &http::Request<ReplayBody<A>>,
+        result: Result<&http::Response<WithTrailers<B>>, &Error>,
+    ) -> Option<Self::Future> {
+        if self.remaining == 0 {
+            tracing::debug!("Retries exhausted");
+            return None;
+        }
+
+        let retryable = match result {
+            // Only per-attempt timeouts are retryable. All other errors are
+            // assumed to be unsafe to retry.
+            Err(error) => error.is::<ResponseTimeoutError>(),
+            Ok(rsp) => {
+                let is_failure = self
+                    .classify
+                    .classify(req)
+                    .start(rsp)
+                    .eos(rsp.body().trailers())
+                    .is_failure();
+                // The request may not be replayed if its body exceeded the
+                // maximum buffer size.
+                let exceeded_max_len = req.body().is_capped();
+                tracing::trace!(is_failure, exceeded_max_len);
+                is_failure && !exceeded_max_len
+            }
+        };
+        if !retryable {
+            return None;
+        }
+
+        if self.budget.withdraw().is_err() {
+            tracing::debug!("Retry budget exhausted");
+            return None;
+        }
+
+        let next = self.next();
+        tracing::debug!(
+            attempt = next.attempt,
+            remaining = next.remaining,
+            "Retrying"
+        );
+        match self.backoff {
+            None => Some(future::ready(next).boxed()),
+            Some(backoff) => {
+                let delay = backoff.delay(self.attempt);
+                Some(tokio::time::sleep(delay).map(move |()| next).boxed())
+            }
+        }
+    }
+
+    fn clone_request(
+        &self,
+        req: &http::Request<ReplayBody<A>>,
+    ) -> Option<http::Request<ReplayBody<A>>> {
+        let mut clone = http::Request::new(req.body().clone());
+        *clone.method_mut() = req.method().clone();
+        *clone.uri_mut() = req.uri().clone();
+        *clone.headers_mut() = req.headers().clone();
+        *clone.version_mut() = req.version();
+
+        // The HTTP server sets a ClientHandle with the client's address and a
+        // means to close the server-side connection.
+        if let Some(client_handle) = req.extensions().get::<ClientHandle>().cloned() {
+            clone.extensions_mut().insert(client_handle);
+        }
+
+        // Retries are bounded by the original request's deadline.
+        if let Some(deadline) = req.extensions().get::<http::RequestDeadline>().cloned() {
+            clone.extensions_mut().insert(deadline);
+        }
+
+        Some(clone)
+    }
+}
+
+impl<A, B> retry::PrepareRetry<http::Request<A>, http::Response<B>, Error> for RetryPolicy
+where
+    A: HttpBody + Unpin,
+    A::Error: Into<Error>,
+    B: HttpBody + Unpin + Send + 'static,
+    B::Data: Unpin + Send,
+    B::Error: Unpin + Send,
+{
+    type RetryRequest = http::Request<ReplayBody<A>>;
+    type RetryResponse = http::Response<WithTrailers<B>>;
+    type ResponseFuture = future::Map<
+        with_trailers::WithTrailersFuture<B>,
+        fn(http::Response<WithTrailers<B>>) -> Result<http::Response<WithTrailers<B>>, Error>,
+    >;
+
+    fn prepare_request(
+        &self,
+        req: http::Request<A>,
+    ) -> Either<Self::RetryRequest, http::Request<A>> {
+        // Every request on the route accrues the budget, whether or not it
+        // may be retried.
+        self.budget.deposit();
+
+        let (head, body) = req.into_parts();
+        match ReplayBody::try_new(body, self.max_request_bytes) {
+            Ok(body) => Either::A(http::Request::from_parts(head, body)),
+            Err(body) => {
+                tracing::debug!(
+                    size = body.size_hint().lower(),
+                    "Body is too large to buffer"
+                );
+                Either::B(http::Request::from_parts(head, body))
+            }
+        }
+    }
+
+    /// If the response is HTTP/2, return a future that checks for a `TRAILERS`
+    /// frame immediately after the first frame of the response.
+    fn prepare_response(rsp: http::Response<B>) -> Self::ResponseFuture {
+        WithTrailers::map_response(rsp).map(Ok)
+    }
+}
@@ -62,7 +62,10 @@ where
         Key = route::MatchedRoute<T, M::Summary, F, E>,
         Error = NoRoute,
     >,
-    route::MatchedRoute<T, M::Summary, F, E>: route::filters::Apply + svc::Param<classify::Request>,
+    route::MatchedRoute<T, M::Summary, F, E>: route::filters::Apply
+        + svc::Param<classify::Request>
+        + svc::Param<Option<route::retry::RetryPolicy>>
+        + svc::Param<Option<route::hedge::HedgePolicy>>,
     route::MatchedBackend<T, M::Summary, F>: route::filters::Apply,
     route::backend::ExtractMetrics:
         svc::ExtractParam<route::backend::RequestCount, route::MatchedBackend<T, M::Summary, F>>,
@@ -71,6 +74,7 @@ where
     /// set of inner services so that.
     pub(super) fn layer<N, S>(
         route_backend_metrics: RouteBackendMetrics,
+        deadline_header: Option<http::HeaderName>,
     ) -> impl svc::Layer<
         N,
         Service = svc::ArcNewService<
@@ -103,7 +107,10 @@ where
                 .push(NewBackendCache::layer())
                 // Lazily cache a service for each `RouteParams` returned from the
                 // `SelectRoute` impl.
-                .push_on_service(route::MatchedRoute::layer(route_backend_metrics.clone()))
+                .push_on_service(route::MatchedRoute::layer(
+                    route_backend_metrics.clone(),
+                    deadline_header.clone(),
+                ))
                 .push(svc::NewOneshotRoute::<Self, (), _>::layer_cached())
                 .push(svc::ArcNewService::layer())
                 .into_inner()
@@ -115,7 +122,7 @@ impl<T, M, F, E> From<(Params<M, F, E>, T)> for Router<T, M, F, E>
 where
     T: Eq + Hash + Clone + Debug,
     M: Clone,
-    F: Clone,
+    F: Clone + route::mirror::MirrorFilter,
     E: Clone,
 {
     fn from((rts, parent): (Params<M, F, E>, T)) -> Self {
@@ -150,14 +157,34 @@ where
 
         let mk_dispatch = move |bke: &policy::Backend| match bke.dispatcher {
             policy::BackendDispatcher::BalanceP2c(
-                policy::Load::PeakEwma(policy::PeakEwma { decay, default_rtt }),
+                ref load,
                 policy::EndpointDiscovery::DestinationGet { ref path },
             ) => mk_concrete(
                 BackendRef(bke.meta.clone()),
                 concrete::Dispatch::Balance(
                     path.parse::<NameAddr>()
                         .expect("destination must be a nameaddr"),
-                    http::balance::EwmaConfig { decay, default_rtt },
+                    match *load {
+                        policy::Load::PeakEwma(policy::PeakEwma { decay, default_rtt }) => {
+                            concrete::Load::PeakEwma(http::balance::EwmaConfig {
+                                decay,
+                                default_rtt,
+                            })
+                        }
+                        policy::Load::LeastRequest => concrete::Load::LeastRequest,
+                        policy::Load::WeightedRoundRobin => concrete::Load::WeightedRoundRobin,
+                        policy::Load::ConsistentHash(policy::ConsistentHash { ref key }) => {
+                            concrete::Load::ConsistentHash(match key {
+                                policy::HashKey::Header(name) => {
+                                    http::balance::HashKey::Header(name.clone())
+                                }
+                                policy::HashKey::Cookie(name) => {
+                                    http::balance::HashKey::Cookie(name.clone())
+                                }
+                                policy::HashKey::ClientIp => http::balance::HashKey::ClientIp,
+                            })
+                        }
+                    },
                 ),
             ),
             policy::BackendDispatcher::Forward(addr, ref md) => mk_concrete(
@@ -179,7 +206,7 @@ where
                 route_ref: route_ref.clone(),
                 filters,
                 concrete,
-                request_timeout: rb.request_timeout,
+                timeouts: rb.timeouts,
             }
         };
 
@@ -205,22 +232,37 @@ where
                              filters,
                              distribution,
                              failure_policy,
-                             request_timeout,
+                             timeouts,
+                             retry,
+                             hedge,
                          }| {
             let route_ref = RouteRef(meta);
             let distribution = mk_distribution(&route_ref, &distribution);
+            let mirrors = filters
+                .iter()
+                .filter_map(route::mirror::MirrorFilter::request_mirror)
+                .map(|mirror| route::mirror::Mirror {
+                    concrete: mk_dispatch(&mirror.backend),
+                    distribution: mirror.distribution.clone(),
+                    max_request_bytes: mirror.max_request_bytes,
+                    max_in_flight: mirror.max_in_flight,
+                })
+                .collect();
             route::Route {
                 addr: addr.clone(),
                 parent: parent.clone(),
                 route_ref,
                 filters,
+                mirrors,
                 failure_policy,
                 distribution,
-                request_timeout,
+                timeouts,
+                retry,
+                hedge,
             }
         };
 
-        let routes = routes
+        let routes: Arc<[http_route::Route<M, route::Route<T, F, E>>]> = routes
             .iter()
             .map(|route| http_route::Route {
                 hosts: route.hosts.clone(),
@@ -236,7 +278,20 @@ where
             })
             .collect();
 
-        let backends = backends.iter().map(mk_dispatch).collect();
+        // Mirror backends are not referenced by any distribution, so they must
+        // be added to the set of cached backends explicitly.
+        let backends = backends
+            .iter()
+            .map(mk_dispatch)
+            .chain(routes.iter().flat_map(|route| {
+                route.rules.iter().flat_map(|rule| {
+                    rule.policy
+                        .mirrors
+                        .iter()
+                        .map(|mirror| mirror.concrete.clone())
+                })
+            }))
+            .collect();
 
         Self {
             routes,
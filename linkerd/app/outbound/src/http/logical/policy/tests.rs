@@ -4,6 +4,7 @@ use linkerd_app_core::{
     svc::NewService,
     svc::{Layer, ServiceExt},
     trace,
+    transport::addrs::*,
 };
 use linkerd_http_route as route;
 use linkerd_proxy_client_policy as policy;
@@ -48,11 +49,13 @@ async fn header_based_route() {
         }),
         filters: Arc::new([]),
         failure_policy: Default::default(),
-        request_timeout: None,
+        timeouts: Default::default(),
+        retry: None,
+        hedge: None,
         distribution: policy::RouteDistribution::FirstAvailable(Arc::new([policy::RouteBackend {
             filters: Arc::new([]),
             backend,
-            request_timeout: None,
+            timeouts: Default::default(),
         }])),
     };
 
@@ -116,7 +119,7 @@ async fn header_based_route() {
     });
 
     let metrics = RouteBackendMetrics::default();
-    let router = Policy::layer(metrics.clone())
+    let router = Policy::layer(metrics.clone(), None)
         .layer(inner)
         .new_service(Policy::from((routes, ())));
 
@@ -199,7 +202,9 @@ async fn http_filter_request_headers() {
                     policy: policy::RoutePolicy {
                         meta: policy::Meta::new_default("turtles"),
                         failure_policy: Default::default(),
-                        request_timeout: None,
+                        timeouts: Default::default(),
+                        retry: None,
+                        hedge: None,
                         filters: Arc::new([policy::http::Filter::RequestHeaders(
                             policy::http::filter::ModifyHeader {
                                 add: vec![(PIZZA.clone(), TUBULAR.clone())],
@@ -215,7 +220,7 @@ async fn http_filter_request_headers() {
                                         ..Default::default()
                                     },
                                 )]),
-                                request_timeout: None,
+                                timeouts: Default::default(),
                             },
                         ])),
                     },
@@ -226,7 +231,7 @@ async fn http_filter_request_headers() {
         }
     });
 
-    let router = Policy::layer(Default::default())
+    let router = Policy::layer(Default::default(), None)
         .layer(inner)
         .new_service(Policy::from((routes, ())));
 
@@ -250,3 +255,642 @@ async fn http_filter_request_headers() {
     // Hold the router to prevent inner services from being dropped.
     drop(router);
 }
+
+#[tokio::test(flavor = "current_thread")]
+async fn http_route_retries() {
+    let _trace = trace::test::trace_init();
+
+    // Stack that produces mock services.
+    let (inner, mut handle) = tower_test::mock::pair();
+    let inner = move |_: Concrete<()>| inner.clone();
+
+    let routes = http_route_params(
+        Some(policy::RouteRetry {
+            max_retries: 1,
+            max_request_bytes: 64 * 1024,
+            conditions: Default::default(),
+            timeout: None,
+            backoff: None,
+            budget: policy::RetryBudget {
+                retry_percent: 20,
+                min_retries_per_second: 10,
+                ttl: time::Duration::from_secs(10),
+            },
+        }),
+        None,
+    );
+
+    let router = Policy::layer(Default::default(), None)
+        .layer(inner)
+        .new_service(Policy::from((routes, ())));
+
+    handle.allow(2);
+    let req = http::Request::builder()
+        .body(http::BoxBody::default())
+        .unwrap();
+    let rsp = tokio::spawn(router.clone().oneshot(req));
+
+    let (_, tx) = time::timeout(time::Duration::from_secs(1), handle.next_request())
+        .await
+        .expect("timed out")
+        .expect("request");
+    tx.send_response(
+        http::Response::builder()
+            .status(http::StatusCode::INTERNAL_SERVER_ERROR)
+            .body(http::BoxBody::default())
+            .unwrap(),
+    );
+
+    let (_, tx) = time::timeout(time::Duration::from_secs(1), handle.next_request())
+        .await
+        .expect("timed out")
+        .expect("retried request");
+    tx.send_response(
+        http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(http::BoxBody::default())
+            .unwrap(),
+    );
+
+    let rsp = rsp.await.expect("task").expect("response");
+    assert_eq!(rsp.status(), http::StatusCode::OK);
+
+    // Hold the router to prevent inner services from being dropped.
+    drop(router);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn http_route_retry_budget() {
+    let _trace = trace::test::trace_init();
+
+    // Stack that produces mock services.
+    let (inner, mut handle) = tower_test::mock::pair();
+    let inner = move |_: Concrete<()>| inner.clone();
+
+    // The budget never permits a retry.
+    let routes = http_route_params(
+        Some(policy::RouteRetry {
+            max_retries: 1,
+            max_request_bytes: 64 * 1024,
+            conditions: Default::default(),
+            timeout: None,
+            backoff: None,
+            budget: policy::RetryBudget {
+                retry_percent: 0,
+                min_retries_per_second: 0,
+                ttl: time::Duration::from_secs(10),
+            },
+        }),
+        None,
+    );
+
+    let router = Policy::layer(Default::default(), None)
+        .layer(inner)
+        .new_service(Policy::from((routes, ())));
+
+    handle.allow(2);
+    let req = http::Request::builder()
+        .body(http::BoxBody::default())
+        .unwrap();
+    let rsp = tokio::spawn(router.clone().oneshot(req));
+
+    let (_, tx) = time::timeout(time::Duration::from_secs(1), handle.next_request())
+        .await
+        .expect("timed out")
+        .expect("request");
+    tx.send_response(
+        http::Response::builder()
+            .status(http::StatusCode::INTERNAL_SERVER_ERROR)
+            .body(http::BoxBody::default())
+            .unwrap(),
+    );
+
+    let rsp = time::timeout(time::Duration::from_secs(1), rsp)
+        .await
+        .expect("timed out")
+        .expect("task")
+        .expect("response");
+    assert_eq!(rsp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+
+    // Hold the router to prevent inner services from being dropped.
+    drop(router);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn http_route_hedges() {
+    let _trace = trace::test::trace_init();
+
+    // Stack that produces mock services.
+    let (inner, mut handle) = tower_test::mock::pair();
+    let inner = move |_: Concrete<()>| inner.clone();
+
+    let routes = http_route_params(
+        None,
+        Some(policy::RouteHedge {
+            delay: policy::HedgeDelay::Fixed(time::Duration::from_millis(10)),
+            max_percent: 100,
+            max_request_bytes: 64 * 1024,
+            idempotent: false,
+        }),
+    );
+
+    let router = Policy::layer(Default::default(), None)
+        .layer(inner)
+        .new_service(Policy::from((routes, ())));
+
+    handle.allow(2);
+    let req = http::Request::builder()
+        .body(http::BoxBody::default())
+        .unwrap();
+    let rsp = tokio::spawn(router.clone().oneshot(req));
+
+    // The original request does not receive a response.
+    let (_, _original) = time::timeout(time::Duration::from_secs(1), handle.next_request())
+        .await
+        .expect("timed out")
+        .expect("request");
+
+    let (_, tx) = time::timeout(time::Duration::from_secs(1), handle.next_request())
+        .await
+        .expect("timed out")
+        .expect("hedged request");
+    tx.send_response(
+        http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(http::BoxBody::default())
+            .unwrap(),
+    );
+
+    let rsp = time::timeout(time::Duration::from_secs(1), rsp)
+        .await
+        .expect("timed out")
+        .expect("task")
+        .expect("response");
+    assert_eq!(rsp.status(), http::StatusCode::OK);
+
+    // Hold the router to prevent inner services from being dropped.
+    drop(router);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn http_route_retries_and_hedges() {
+    let _trace = trace::test::trace_init();
+
+    // Stack that produces mock services.
+    let (inner, mut handle) = tower_test::mock::pair();
+    let inner = move |_: Concrete<()>| inner.clone();
+
+    let routes = http_route_params(
+        Some(policy::RouteRetry {
+            max_retries: 1,
+            max_request_bytes: 64 * 1024,
+            conditions: Default::default(),
+            timeout: None,
+            backoff: None,
+            budget: policy::RetryBudget {
+                retry_percent: 20,
+                min_retries_per_second: 10,
+                ttl: time::Duration::from_secs(10),
+            },
+        }),
+        Some(policy::RouteHedge {
+            delay: policy::HedgeDelay::Fixed(time::Duration::from_millis(10)),
+            max_percent: 100,
+            max_request_bytes: 64 * 1024,
+            idempotent: false,
+        }),
+    );
+
+    let router = Policy::layer(Default::default(), None)
+        .layer(inner)
+        .new_service(Policy::from((routes, ())));
+
+    handle.allow(2);
+    let req = http::Request::builder()
+        .body(http::BoxBody::new(hyper::Body::from("cowabunga")))
+        .unwrap();
+    let rsp = tokio::spawn(router.clone().oneshot(req));
+
+    // The original request's body is sent, but it does not receive a response.
+    let (req, _original) = time::timeout(time::Duration::from_secs(1), handle.next_request())
+        .await
+        .expect("timed out")
+        .expect("request");
+    let body = hyper::body::to_bytes(req.into_body()).await.expect("body");
+    assert_eq!(body, "cowabunga");
+
+    // The retry policy's copy of the body must not prevent the request from
+    // being hedged.
+    let (req, tx) = time::timeout(time::Duration::from_secs(1), handle.next_request())
+        .await
+        .expect("timed out")
+        .expect("hedged request");
+    let body = hyper::body::to_bytes(req.into_body()).await.expect("body");
+    assert_eq!(body, "cowabunga");
+    tx.send_response(
+        http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(http::BoxBody::default())
+            .unwrap(),
+    );
+
+    let rsp = time::timeout(time::Duration::from_secs(1), rsp)
+        .await
+        .expect("timed out")
+        .expect("task")
+        .expect("response");
+    assert_eq!(rsp.status(), http::StatusCode::OK);
+
+    // Hold the router to prevent inner services from being dropped.
+    drop(router);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn http_route_deadline() {
+    let _trace = trace::test::trace_init();
+
+    let addr = ([127, 0, 0, 1], 18080).into();
+    let backend = policy::Backend {
+        meta: policy::Meta::new_default("test"),
+        queue: policy::Queue {
+            capacity: 10,
+            failfast_timeout: time::Duration::from_secs(1),
+        },
+        dispatcher: policy::BackendDispatcher::Forward(addr, Default::default()),
+    };
+
+    // Stack that produces mock services.
+    let (inner, mut handle) = tower_test::mock::pair();
+    let inner = move |_: Concrete<()>| inner.clone();
+
+    let routes = Params::Http({
+        router::HttpParams {
+            addr: Addr::Socket(([127, 0, 0, 1], 8080).into()),
+            meta: ParentRef(policy::Meta::new_default("splinter")),
+            routes: Arc::new([policy::http::Route {
+                hosts: Default::default(),
+                rules: vec![policy::http::Rule {
+                    matches: vec![route::http::MatchRequest::default()],
+                    policy: policy::RoutePolicy {
+                        meta: policy::Meta::new_default("turtles"),
+                        failure_policy: Default::default(),
+                        timeouts: policy::RouteTimeouts {
+                            response: Some(time::Duration::from_secs(10)),
+                            ..Default::default()
+                        },
+                        retry: None,
+                        hedge: None,
+                        filters: Arc::new([]),
+                        distribution: policy::RouteDistribution::FirstAvailable(Arc::new([
+                            policy::RouteBackend {
+                                backend: backend.clone(),
+                                filters: Arc::new([]),
+                                timeouts: Default::default(),
+                            },
+                        ])),
+                    },
+                }],
+            }]),
+            backends: std::iter::once(backend).collect(),
+            failure_accrual: Default::default(),
+        }
+    });
+
+    let router = Policy::layer(
+        Default::default(),
+        Some(http::HeaderName::from_static("l5d-timeout")),
+    )
+    .layer(inner)
+    .new_service(Policy::from((routes, ())));
+
+    handle.allow(1);
+    let req = http::Request::builder()
+        .header("l5d-timeout", "100ms")
+        .body(http::BoxBody::default())
+        .unwrap();
+    let rsp = tokio::spawn(router.clone().oneshot(req));
+
+    // The request is dispatched without the timeout header and does not
+    // receive a response before its deadline.
+    let (req, _tx) = handle.next_request().await.expect("request");
+    assert!(!req.headers().contains_key("l5d-timeout"));
+
+    let error = rsp
+        .await
+        .expect("task")
+        .expect_err("request must exceed its deadline");
+    assert!(
+        linkerd_app_core::errors::is_caused_by::<http::DeadlineExceededError>(&*error),
+        "{error}"
+    );
+
+    // Hold the router to prevent inner services from being dropped.
+    drop(router);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn http_filter_request_mirror() {
+    let _trace = trace::test::trace_init();
+
+    let mk_backend = |name: &'static str, port: u16| policy::Backend {
+        meta: policy::Meta::new_default(name),
+        queue: policy::Queue {
+            capacity: 10,
+            failfast_timeout: time::Duration::from_secs(1),
+        },
+        dispatcher: policy::BackendDispatcher::Forward(
+            ([127, 0, 0, 1], port).into(),
+            Default::default(),
+        ),
+    };
+    let backend = mk_backend("test", 18080);
+    let mirror = mk_backend("mirror", 18081);
+
+    // Stack that produces mock services.
+    let (inner_backend, mut backend_handle) = tower_test::mock::pair();
+    let (inner_mirror, mut mirror_handle) = tower_test::mock::pair();
+    let inner = move |concrete: Concrete<()>| match concrete.target {
+        concrete::Dispatch::Forward(Remote(ServerAddr(addr)), ..) if addr.port() == 18080 => {
+            inner_backend.clone()
+        }
+        concrete::Dispatch::Forward(Remote(ServerAddr(addr)), ..) if addr.port() == 18081 => {
+            inner_mirror.clone()
+        }
+        target => panic!("unexpected target: {target:?}"),
+    };
+
+    let routes = Params::Http({
+        router::HttpParams {
+            addr: Addr::Socket(([127, 0, 0, 1], 8080).into()),
+            meta: ParentRef(policy::Meta::new_default("splinter")),
+            routes: Arc::new([policy::http::Route {
+                hosts: Default::default(),
+                rules: vec![policy::http::Rule {
+                    matches: vec![route::http::MatchRequest::default()],
+                    policy: policy::RoutePolicy {
+                        meta: policy::Meta::new_default("turtles"),
+                        failure_policy: Default::default(),
+                        timeouts: Default::default(),
+                        retry: None,
+                        hedge: None,
+                        filters: Arc::new([policy::http::Filter::RequestMirror(
+                            policy::http::RequestMirror {
+                                backend: mirror,
+                                distribution: Default::default(),
+                                max_request_bytes: 1024,
+                                max_in_flight: 1,
+                            },
+                        )]),
+                        distribution: policy::RouteDistribution::FirstAvailable(Arc::new([
+                            policy::RouteBackend {
+                                backend: backend.clone(),
+                                filters: Arc::new([]),
+                                timeouts: Default::default(),
+                            },
+                        ])),
+                    },
+                }],
+            }]),
+            backends: std::iter::once(backend).collect(),
+            failure_accrual: Default::default(),
+        }
+    });
+
+    let metrics = RouteBackendMetrics::default();
+    let router = Policy::layer(metrics.clone(), None)
+        .layer(inner)
+        .new_service(Policy::from((routes, ())));
+
+    backend_handle.allow(1);
+    mirror_handle.allow(1);
+    let req = http::Request::builder()
+        .body(http::BoxBody::new(hyper::Body::from("cowabunga")))
+        .unwrap();
+    let rsp = tokio::spawn(router.clone().oneshot(req));
+
+    // The original request is dispatched to the route's backend.
+    let (req, tx) = time::timeout(time::Duration::from_secs(1), backend_handle.next_request())
+        .await
+        .expect("timed out")
+        .expect("request");
+    let body = hyper::body::to_bytes(req.into_body()).await.expect("body");
+    assert_eq!(body, "cowabunga");
+    tx.send_response(
+        http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(http::BoxBody::default())
+            .unwrap(),
+    );
+    let rsp = rsp.await.expect("task").expect("response");
+    assert_eq!(rsp.status(), http::StatusCode::OK);
+
+    // Once the original request's body is released, a copy is sent to the
+    // mirror. Its response is discarded.
+    let (req, tx) = time::timeout(time::Duration::from_secs(1), mirror_handle.next_request())
+        .await
+        .expect("timed out")
+        .expect("mirrored request");
+    let body = hyper::body::to_bytes(req.into_body()).await.expect("body");
+    assert_eq!(body, "cowabunga");
+
+    // While the mirrored request is in flight, further requests are not
+    // mirrored.
+    backend_handle.allow(1);
+    let req = http::Request::builder()
+        .body(http::BoxBody::new(hyper::Body::from("cowabunga")))
+        .unwrap();
+    let rsp = tokio::spawn(router.clone().oneshot(req));
+    let (_, tx2) = time::timeout(time::Duration::from_secs(1), backend_handle.next_request())
+        .await
+        .expect("timed out")
+        .expect("request");
+    tx2.send_response(
+        http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(http::BoxBody::default())
+            .unwrap(),
+    );
+    let rsp = rsp.await.expect("task").expect("response");
+    assert_eq!(rsp.status(), http::StatusCode::OK);
+    let report = linkerd_app_core::metrics::FmtMetrics::as_display(&metrics).to_string();
+    let dropped = report
+        .lines()
+        .find(|l| l.starts_with("outbound_http_route_mirror_dropped_total{"))
+        .expect("dropped mirrors must be counted");
+    assert!(dropped.ends_with(" 1"), "{dropped}");
+
+    tx.send_response(
+        http::Response::builder()
+            .status(http::StatusCode::INTERNAL_SERVER_ERROR)
+            .body(http::BoxBody::default())
+            .unwrap(),
+    );
+
+    let failures = time::timeout(time::Duration::from_secs(1), async {
+        loop {
+            let report = linkerd_app_core::metrics::FmtMetrics::as_display(&metrics).to_string();
+            if let Some(line) = report
+                .lines()
+                .find(|l| l.starts_with("outbound_http_route_mirror_failures_total{"))
+            {
+                return line.to_string();
+            }
+            tokio::task::yield_now().await;
+        }
+    })
+    .await
+    .expect("timed out");
+    assert!(failures.contains(r#"backend_name="mirror""#), "{failures}");
+    assert!(failures.ends_with(" 1"), "{failures}");
+
+    // Hold the router to prevent inner services from being dropped.
+    drop(router);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn grpc_filter_headers() {
+    let _trace = trace::test::trace_init();
+
+    let addr = ([127, 0, 0, 1], 18080).into();
+    let backend = policy::Backend {
+        meta: policy::Meta::new_default("test"),
+        queue: policy::Queue {
+            capacity: 10,
+            failfast_timeout: time::Duration::from_secs(1),
+        },
+        dispatcher: policy::BackendDispatcher::Forward(addr, Default::default()),
+    };
+
+    // Stack that produces mock services.
+    let (inner, mut handle) = tower_test::mock::pair();
+    let inner = move |_: Concrete<()>| inner.clone();
+
+    static TENANT: http::HeaderName = http::HeaderName::from_static("x-tenant");
+    static SHELL: http::HeaderValue = http::HeaderValue::from_static("shell");
+    static SERVED_BY: http::HeaderName = http::HeaderName::from_static("x-served-by");
+    static LAIR: http::HeaderValue = http::HeaderValue::from_static("lair");
+    let routes = Params::Grpc({
+        router::GrpcParams {
+            addr: Addr::Socket(([127, 0, 0, 1], 8080).into()),
+            meta: ParentRef(policy::Meta::new_default("splinter")),
+            routes: Arc::new([policy::grpc::Route {
+                hosts: Default::default(),
+                rules: vec![policy::grpc::Rule {
+                    matches: vec![route::grpc::MatchRoute::default()],
+                    policy: policy::RoutePolicy {
+                        meta: policy::Meta::new_default("turtles"),
+                        failure_policy: Default::default(),
+                        timeouts: Default::default(),
+                        retry: None,
+                        hedge: None,
+                        filters: Arc::new([
+                            policy::grpc::Filter::RequestHeaders(
+                                policy::grpc::filter::ModifyHeader {
+                                    add: vec![(TENANT.clone(), SHELL.clone())],
+                                    ..Default::default()
+                                },
+                            ),
+                            policy::grpc::Filter::ResponseHeaders(
+                                policy::grpc::filter::ModifyHeader {
+                                    set: vec![(SERVED_BY.clone(), LAIR.clone())],
+                                    ..Default::default()
+                                },
+                            ),
+                        ]),
+                        distribution: policy::RouteDistribution::FirstAvailable(Arc::new([
+                            policy::RouteBackend {
+                                backend: backend.clone(),
+                                filters: Arc::new([]),
+                                timeouts: Default::default(),
+                            },
+                        ])),
+                    },
+                }],
+            }]),
+            backends: std::iter::once(backend).collect(),
+            failure_accrual: Default::default(),
+        }
+    });
+
+    let router = Policy::layer(Default::default(), None)
+        .layer(inner)
+        .new_service(Policy::from((routes, ())));
+
+    handle.allow(1);
+    let req = http::Request::builder()
+        .method(http::Method::POST)
+        .uri("http://foo.example.com/foo.Bar/Baz")
+        .header(http::header::CONTENT_TYPE, "application/grpc")
+        .body(http::BoxBody::default())
+        .unwrap();
+    let rsp = tokio::spawn(router.clone().oneshot(req));
+
+    let (req, tx) = time::timeout(time::Duration::from_secs(1), handle.next_request())
+        .await
+        .expect("timed out")
+        .expect("request");
+    assert_eq!(
+        req.headers().get_all(&TENANT).iter().collect::<Vec<_>>(),
+        vec![&SHELL],
+    );
+    tx.send_response(
+        http::Response::builder()
+            .header(&SERVED_BY, "dojo")
+            .body(http::BoxBody::default())
+            .unwrap(),
+    );
+
+    let rsp = rsp.await.expect("task").expect("response");
+    assert_eq!(
+        rsp.headers().get_all(&SERVED_BY).iter().collect::<Vec<_>>(),
+        vec![&LAIR],
+    );
+
+    // Hold the router to prevent inner services from being dropped.
+    drop(router);
+}
+
+/// Builds router parameters with a single HTTP route that forwards to a single
+/// backend.
+fn http_route_params(
+    retry: Option<policy::RouteRetry<policy::http::StatusRanges>>,
+    hedge: Option<policy::RouteHedge>,
+) -> Params {
+    let addr = ([127, 0, 0, 1], 18080).into();
+    let backend = policy::Backend {
+        meta: policy::Meta::new_default("test"),
+        queue: policy::Queue {
+            capacity: 10,
+            failfast_timeout: time::Duration::from_secs(1),
+        },
+        dispatcher: policy::BackendDispatcher::Forward(addr, Default::default()),
+    };
+
+    Params::Http(router::HttpParams {
+        addr: Addr::Socket(([127, 0, 0, 1], 8080).into()),
+        meta: ParentRef(policy::Meta::new_default("splinter")),
+        routes: Arc::new([policy::http::Route {
+            hosts: Default::default(),
+            rules: vec![policy::http::Rule {
+                matches: vec![route::http::MatchRequest::default()],
+                policy: policy::RoutePolicy {
+                    meta: policy::Meta::new_default("turtles"),
+                    failure_policy: Default::default(),
+                    timeouts: Default::default(),
+                    retry,
+                    hedge,
+                    filters: Arc::new([]),
+                    distribution: policy::RouteDistribution::FirstAvailable(Arc::new([
+                        policy::RouteBackend {
+                            backend: backend.clone(),
+                            filters: Arc::new([]),
+                            timeouts: Default::default(),
+                        },
+                    ])),
+                },
+            }],
+        }]),
+        backends: std::iter::once(backend).collect(),
+        failure_accrual: Default::default(),
+    })
+}
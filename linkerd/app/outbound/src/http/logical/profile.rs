@@ -145,7 +145,10 @@ where
             let concrete = Concrete {
                 parent_ref: ParentRef(parent_meta.clone()),
                 backend_ref: BackendRef(parent_meta),
-                target: concrete::Dispatch::Balance(addr.clone(), DEFAULT_EWMA),
+                target: concrete::Dispatch::Balance(
+                    addr.clone(),
+                    concrete::Load::PeakEwma(DEFAULT_EWMA),
+                ),
                 authority: Some(addr.as_http_authority()),
                 parent: parent.clone(),
                 failure_accrual: Default::default(),
@@ -161,7 +164,10 @@ where
                     backend_ref: BackendRef(
                         service_meta(&t.addr).unwrap_or_else(|| UNKNOWN_META.clone()),
                     ),
-                    target: concrete::Dispatch::Balance(t.addr.clone(), DEFAULT_EWMA),
+                    target: concrete::Dispatch::Balance(
+                        t.addr.clone(),
+                        concrete::Load::PeakEwma(DEFAULT_EWMA),
+                    ),
                     authority: Some(t.addr.as_http_authority()),
                     parent: parent.clone(),
                     failure_accrual: Default::default(),
@@ -175,7 +181,10 @@ where
                             service_meta(&addr).unwrap_or_else(|| UNKNOWN_META.clone()),
                         ),
                         authority: Some(addr.as_http_authority()),
-                        target: concrete::Dispatch::Balance(addr, DEFAULT_EWMA),
+                        target: concrete::Dispatch::Balance(
+                            addr,
+                            concrete::Load::PeakEwma(DEFAULT_EWMA),
+                        ),
                         parent: parent.clone(),
                         failure_accrual: Default::default(),
                     };
@@ -308,7 +308,14 @@ async fn route_request_timeout() {
         let backend = default_backend(&dest);
         // Set a request timeout for the route, and no backend request timeout
         // on the backend.
-        let route = timeout_route(backend.clone(), Some(REQUEST_TIMEOUT), None);
+        let route = timeout_route(
+            backend.clone(),
+            client_policy::RouteTimeouts {
+                response: Some(REQUEST_TIMEOUT),
+                ..Default::default()
+            },
+            Default::default(),
+        );
         watch::channel(Routes::Policy(policy::Params::Http(policy::HttpParams {
             addr: dest.into(),
             meta: ParentRef(client_policy::Meta::new_default("parent")),
@@ -372,8 +379,14 @@ async fn backend_request_timeout() {
         // Set both a route request timeout and a backend request timeout.
         let route = timeout_route(
             backend.clone(),
-            Some(ROUTE_REQUEST_TIMEOUT),
-            Some(BACKEND_REQUEST_TIMEOUT),
+            client_policy::RouteTimeouts {
+                response: Some(ROUTE_REQUEST_TIMEOUT),
+                ..Default::default()
+            },
+            client_policy::RouteTimeouts {
+                response: Some(BACKEND_REQUEST_TIMEOUT),
+                ..Default::default()
+            },
         );
         watch::channel(Routes::Policy(policy::Params::Http(policy::HttpParams {
             addr: dest.into(),
@@ -427,6 +440,117 @@ async fn backend_request_timeout() {
     ));
 }
 
+#[tokio::test(flavor = "current_thread")]
+async fn route_stream_timeouts() {
+    use http::HttpBody;
+    tokio::time::pause();
+    let _trace = trace::test::trace_init();
+    const IDLE_TIMEOUT: Duration = std::time::Duration::from_secs(2);
+    const TOTAL_TIMEOUT: Duration = std::time::Duration::from_secs(30);
+
+    let addr = SocketAddr::new([192, 0, 2, 41].into(), PORT);
+    let dest: NameAddr = format!("{AUTHORITY}:{PORT}")
+        .parse::<NameAddr>()
+        .expect("dest addr is valid");
+    let (svc, mut handle) = tower_test::mock::pair();
+    let connect = HttpConnect::default().service(addr, svc);
+    let resolve = support::resolver().endpoint_exists(dest.clone(), addr, Default::default());
+    let (rt, _shutdown) = runtime();
+    let stack = Outbound::new(default_config(), rt)
+        .with_stack(connect)
+        .push_http_cached(resolve)
+        .into_inner();
+
+    let (_route_tx, routes) = {
+        let backend = default_backend(&dest);
+        // Set idle and total timeouts for the route, but no response timeout.
+        let route = timeout_route(
+            backend.clone(),
+            client_policy::RouteTimeouts {
+                request: Some(TOTAL_TIMEOUT),
+                idle: Some(IDLE_TIMEOUT),
+                ..Default::default()
+            },
+            Default::default(),
+        );
+        watch::channel(Routes::Policy(policy::Params::Http(policy::HttpParams {
+            addr: dest.into(),
+            meta: ParentRef(client_policy::Meta::new_default("parent")),
+            backends: Arc::new([backend]),
+            routes: Arc::new([route]),
+            failure_accrual: client_policy::FailureAccrual::None,
+        })))
+    };
+    let target = Target {
+        num: 1,
+        version: http::Version::H2,
+        routes,
+    };
+    let svc = stack.new_service(target);
+
+    // A stream may outlive its idle timeout as long as data continues to flow.
+    handle.allow(1);
+    let rsp = send_req(svc.clone(), http::Request::get("/"));
+    let (mut tx, body) = hyper::Body::channel();
+    serve_req(
+        &mut handle,
+        http::Response::builder()
+            .status(StatusCode::OK)
+            .body(http::BoxBody::new(body))
+            .unwrap(),
+    )
+    .await;
+    let mut body = rsp.await.expect("request must succeed").into_body();
+    for _ in 0..5 {
+        tokio::time::sleep(IDLE_TIMEOUT / 2).await;
+        tx.send_data("hello".into())
+            .await
+            .expect("body must be open");
+        body.data()
+            .await
+            .expect("body must not end")
+            .expect("body must not fail");
+    }
+
+    // ...but fails once data stops flowing.
+    let error = body
+        .data()
+        .await
+        .expect("body must not end")
+        .expect_err("body must fail with a timeout");
+    assert!(errors::is_caused_by::<http::StreamIdleTimeoutError>(
+        error.as_ref()
+    ));
+    drop(tx);
+
+    // The total timeout bounds a stream even when data continues to flow.
+    handle.allow(1);
+    let rsp = send_req(svc.clone(), http::Request::get("/"));
+    let (mut tx, body) = hyper::Body::channel();
+    serve_req(
+        &mut handle,
+        http::Response::builder()
+            .status(StatusCode::OK)
+            .body(http::BoxBody::new(body))
+            .unwrap(),
+    )
+    .await;
+    let mut body = rsp.await.expect("request must succeed").into_body();
+    let error = loop {
+        tokio::time::sleep(IDLE_TIMEOUT / 2).await;
+        tx.send_data("hello".into())
+            .await
+            .expect("body must be open");
+        match body.data().await.expect("body must not end") {
+            Ok(_) => {}
+            Err(error) => break error,
+        }
+    };
+    assert!(errors::is_caused_by::<http::ResponseStreamTimeoutError>(
+        error.as_ref()
+    ));
+}
+
 #[derive(Clone, Debug)]
 struct Target {
     num: usize,
@@ -590,11 +714,13 @@ fn default_route(backend: client_policy::Backend) -> client_policy::http::Route
                 meta: Meta::new_default("test_route"),
                 filters: NO_FILTERS.clone(),
                 failure_policy: Default::default(),
-                request_timeout: None,
+                timeouts: Default::default(),
+                retry: None,
+                hedge: None,
                 distribution: RouteDistribution::FirstAvailable(Arc::new([RouteBackend {
                     filters: NO_FILTERS.clone(),
                     backend,
-                    request_timeout: None,
+                    timeouts: Default::default(),
                 }])),
             },
         }],
@@ -603,8 +729,8 @@ fn default_route(backend: client_policy::Backend) -> client_policy::http::Route
 
 fn timeout_route(
     backend: client_policy::Backend,
-    route_timeout: Option<Duration>,
-    backend_timeout: Option<Duration>,
+    route_timeouts: client_policy::RouteTimeouts,
+    backend_timeouts: client_policy::RouteTimeouts,
 ) -> client_policy::http::Route {
     use client_policy::{
         http::{self, Filter, Policy, Route, Rule},
@@ -620,11 +746,13 @@ fn timeout_route(
                 meta: Meta::new_default("test_route"),
                 filters: NO_FILTERS.clone(),
                 failure_policy: Default::default(),
-                request_timeout: route_timeout,
+                timeouts: route_timeouts,
+                retry: None,
+                hedge: None,
                 distribution: RouteDistribution::FirstAvailable(Arc::new([RouteBackend {
                     filters: NO_FILTERS.clone(),
                     backend,
-                    request_timeout: backend_timeout,
+                    timeouts: backend_timeouts,
                 }])),
             },
         }],
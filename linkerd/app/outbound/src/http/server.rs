@@ -145,6 +145,22 @@ impl errors::HttpRescue<Error> for ServerRescue {
             return Ok(errors::SyntheticHttpResponse::gateway_timeout(error));
         }
 
+        // A route's total request timeout elapsed, or its response stream
+        // went idle. If the response has already begun, this terminates the
+        // stream (e.g. with gRPC trailers).
+        if errors::is_caused_by::<http::ResponseStreamTimeoutError>(&*error) {
+            return Ok(errors::SyntheticHttpResponse::gateway_timeout(error));
+        }
+        if errors::is_caused_by::<http::StreamIdleTimeoutError>(&*error) {
+            return Ok(errors::SyntheticHttpResponse::gateway_timeout(error));
+        }
+
+        // The deadline set by a request's `grpc-timeout` or deadline header
+        // elapsed, so its caller is no longer waiting for a response.
+        if errors::is_caused_by::<http::DeadlineExceededError>(&*error) {
+            return Ok(errors::SyntheticHttpResponse::deadline_exceeded(error));
+        }
+
         // A request with a `l5d-require-id` header are dispatched to endpoints
         // with a different identity.
         if errors::is_caused_by::<IdentityRequired>(&*error) {
@@ -17,6 +17,10 @@ use thiserror::Error;
 use tokio::sync::watch;
 use tracing::Instrument;
 
+mod connect;
+
+use self::connect::ConnectTarget;
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct Http<T> {
     parent: T,
@@ -26,8 +30,23 @@ struct Http<T> {
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct Opaq<T>(Discovery<T>);
 
+/// An opaque target whose client policy routes connections by SNI.
+#[derive(Clone, Debug)]
+struct Tls {
+    orig_dst: OrigDstAddr,
+    logical: Option<profiles::LogicalAddr>,
+    policy: policy::Receiver,
+}
+
+/// An opaque target for a tunnel requested by an HTTP CONNECT request.
+#[derive(Clone, Debug)]
+struct Tunnel(opaq::Logical);
+
 #[derive(Clone, Debug)]
-struct SelectTarget<T>(Http<T>);
+struct SelectTarget<T> {
+    http: Http<T>,
+    forward_proxy: bool,
+}
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 enum RequestTarget {
@@ -56,6 +75,14 @@ struct PolicyRequired(OrigDstAddr);
 #[error("l5d-dst-override is not a valid host:port")]
 struct InvalidOverrideHeader;
 
+#[derive(Debug, Default, Error)]
+#[error("forward-proxy requests must be in absolute-form")]
+struct AbsoluteFormRequired;
+
+#[derive(Debug, Default, Error)]
+#[error("forward-proxy connections must use HTTP")]
+struct HttpRequired;
+
 const DST_OVERRIDE_HEADER: &str = "l5d-dst-override";
 
 // === impl Outbound ===
@@ -89,9 +116,23 @@ impl Outbound<()> {
         // on this ingress stack.
         let opaque = {
             let discover = discover.clone();
+            let tls = self.to_tcp_connect().push_tls_cached(resolve.clone());
             self.to_tcp_connect()
                 .push_opaq_cached(resolve.clone())
-                .map_stack(|_, _, stk| stk.push_map_target(Opaq))
+                .map_stack(|_, _, stk| {
+                    // Opaque connections with a TLS client policy are routed
+                    // by SNI.
+                    stk.push_switch(
+                        |parent: Opaq<T>| -> Result<_, Infallible> {
+                            if let Some(tls) = parent.tls() {
+                                return Ok(svc::Either::B(tls));
+                            }
+                            Ok(svc::Either::A(parent))
+                        },
+                        tls.into_inner(),
+                    )
+                    .push_map_target(Opaq)
+                })
                 .push_discover(svc::mk(move |OrigDstAddr(addr)| {
                     discover.clone().oneshot(DiscoverAddr(addr.into()))
                 }))
@@ -110,11 +151,75 @@ impl Outbound<()> {
             })
             .push_discover(discover);
 
-        http.push_ingress(opaque)
+        http.push_ingress(opaque, false)
             .push_tcp_instrument(|t: &T| tracing::info_span!("ingress", addr = %t.param()))
             .into_inner()
     }
 
+    /// Builds a "forward-proxy mode" proxy.
+    ///
+    /// Forward-proxy mode serves applications that explicitly use the proxy
+    /// (e.g. via `HTTP_PROXY`) instead of being transparently redirected to it.
+    /// Connections that begin with an HTTP/1.1 `CONNECT host:port` request are
+    /// tunneled to the requested address through the opaque stack, and the
+    /// tunnel is acknowledged once the target is connected. Otherwise,
+    /// each HTTP request is routed by the authority of its absolute-form URI (or
+    /// the l5d-dst-override header). In both cases, targets are discovered and
+    /// routed by the same profile and client policy discovery as ingress-mode.
+    pub fn mk_forward_proxy<T, I, R>(
+        &self,
+        profiles: impl profiles::GetProfile<Error = Error>,
+        policies: impl policy::GetPolicy,
+        resolve: R,
+    ) -> svc::ArcNewTcp<T, I>
+    where
+        // Target type for outbound forward-proxy connections.
+        T: svc::Param<OrigDstAddr>,
+        T: Clone + Send + Sync + 'static,
+        // Server-side socket.
+        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr,
+        I: Debug + Unpin + Send + Sync + 'static,
+        // Endpoint resolver.
+        R: Resolve<ConcreteAddr, Endpoint = Metadata, Error = Error>,
+    {
+        let discover = self.ingress_resolver(profiles, policies);
+
+        // CONNECT tunnels are routed through the opaque stack by the requested
+        // address.
+        let tunnel = self
+            .to_tcp_connect()
+            .push_opaq_cached(resolve.clone())
+            .map_stack(|_, _, stk| stk.push_filter(Tunnel::try_from))
+            .push_discover(discover.clone())
+            .into_inner();
+
+        let http = self
+            .to_tcp_connect()
+            .push_tcp_endpoint()
+            .push_http_tcp_client()
+            .push_http_cached(resolve)
+            .push_http_server()
+            .map_stack(|_, _, stk| {
+                stk.check_new_service::<Http<Logical>, _>()
+                    .push_filter(Http::try_from)
+            })
+            .push_discover(discover);
+
+        // There is no original destination address to fall back to, so
+        // connections that are not HTTP are refused.
+        http.push_ingress(svc::Fail::<_, HttpRequired>::default(), true)
+            .map_stack(|config, _, stk| {
+                stk.push(connect::NewConnectTunnel::layer(
+                    tunnel,
+                    config.proxy.detect_protocol_timeout,
+                ))
+                .push_on_service(svc::BoxService::layer())
+                .push(svc::ArcNewService::layer())
+            })
+            .push_tcp_instrument(|t: &T| tracing::info_span!("forward_proxy", addr = %t.param()))
+            .into_inner()
+    }
+
     fn ingress_resolver(
         &self,
         profiles: impl profiles::GetProfile<Error = Error>,
@@ -143,6 +248,7 @@ impl Outbound<()> {
         };
         svc::mk(move |DiscoverAddr(addr)| {
             tracing::debug!(%addr, "Discover");
+            let load = load.clone();
 
             let profile = profiles
                 .clone()
@@ -220,7 +326,7 @@ impl Outbound<()> {
                                 &PROFILE_META,
                                 detect_timeout,
                                 queue,
-                                load,
+                                load.clone(),
                                 logical,
                             );
                         }
@@ -245,9 +351,17 @@ impl<N> Outbound<N> {
     /// outbound traffic is HTTP and HTTP detection is **always** performed. If
     /// HTTP detection fails, we revert to using the provided `fallback` stack.
     ///
+    /// When `forward_proxy` is set, requests without the header are routed by
+    /// the authority of their absolute-form URI instead of the original
+    /// destination address.
+    ///
     /// The inner stack is used to create a service for each HTTP request. This
     /// stack must handle its own caching.
-    fn push_ingress<T, I, F, FSvc, NSvc>(self, fallback: F) -> Outbound<svc::ArcNewTcp<T, I>>
+    fn push_ingress<T, I, F, FSvc, NSvc>(
+        self,
+        fallback: F,
+        forward_proxy: bool,
+    ) -> Outbound<svc::ArcNewTcp<T, I>>
     where
         // Target type describing an outbound connection.
         T: svc::Param<OrigDstAddr>,
@@ -300,7 +414,10 @@ impl<N> Outbound<N> {
                         .push(http::strip_header::request::layer(DST_OVERRIDE_HEADER))
                 )
                 .lift_new()
-                .push(svc::NewOneshotRoute::layer_via(|t: &Http<T>| SelectTarget(t.clone())))
+                .push(svc::NewOneshotRoute::layer_via(move |t: &Http<T>| SelectTarget {
+                    http: t.clone(),
+                    forward_proxy,
+                }))
                 .check_new_service::<Http<T>, http::Request<_>>();
 
             // HTTP detection is **always** performed. If detection fails, then we
@@ -339,18 +456,35 @@ where
     T: svc::Param<OrigDstAddr>,
 {
     type Key = Http<RequestTarget>;
-    type Error = InvalidOverrideHeader;
+    type Error = Error;
 
     fn select(&self, req: &http::Request<B>) -> Result<Self::Key, Self::Error> {
         // Use either the override header or the original destination address.
-        let target = http::authority_from_header(req, DST_OVERRIDE_HEADER)
+        // Forward-proxy requests name their target in absolute-form instead of
+        // relying on the original destination address.
+        let override_target = http::authority_from_header(req, DST_OVERRIDE_HEADER)
             .map(|a| {
                 NameAddr::from_authority_with_default_port(&a, 80)
                     .map(RequestTarget::Named)
                     .map_err(|_| InvalidOverrideHeader)
             })
-            .transpose()?
-            .unwrap_or_else(|| RequestTarget::Orig((*self.0).param()));
+            .transpose()?;
+        let target = match override_target {
+            Some(target) => target,
+            None if self.forward_proxy => {
+                let scheme = req.uri().scheme().ok_or(AbsoluteFormRequired)?;
+                let authority = req.uri().authority().ok_or(AbsoluteFormRequired)?;
+                let default_port = if *scheme == ::http::uri::Scheme::HTTPS {
+                    443
+                } else {
+                    80
+                };
+                NameAddr::from_authority_with_default_port(authority, default_port)
+                    .map(RequestTarget::Named)
+                    .map_err(|_| AbsoluteFormRequired)?
+            }
+            None => RequestTarget::Orig((*self.http).param()),
+        };
 
         // Use the request's version.
         let version = match req.version() {
@@ -613,6 +747,24 @@ impl<T> std::ops::Deref for Opaq<T> {
     }
 }
 
+impl<T> Opaq<T>
+where
+    T: svc::Param<OrigDstAddr>,
+{
+    /// Returns a TLS target if the client policy routes connections by SNI.
+    fn tls(&self) -> Option<Tls> {
+        let policy = svc::Param::<policy::Receiver>::param(&self.0);
+        if !matches!(policy.borrow().protocol, policy::Protocol::Tls(_)) {
+            return None;
+        }
+        Some(Tls {
+            orig_dst: (*self.0).param(),
+            logical: svc::Param::<Option<profiles::LogicalAddr>>::param(&self.0),
+            policy,
+        })
+    }
+}
+
 impl<T> svc::Param<Remote<ServerAddr>> for Opaq<T>
 where
     T: svc::Param<OrigDstAddr>,
@@ -628,6 +780,11 @@ where
     T: svc::Param<OrigDstAddr>,
 {
     fn param(&self) -> opaq::Logical {
+        let policy = svc::Param::<policy::Receiver>::param(&self.0);
+        if opaq::should_route_with_policy(&policy.borrow()) {
+            return opaq::Logical::Policy(self.param(), policy);
+        }
+
         if let Some(profile) = svc::Param::<Option<profiles::Receiver>>::param(&self.0) {
             if let Some(profiles::LogicalAddr(addr)) = profile.logical_addr() {
                 return opaq::Logical::Route(addr, profile);
@@ -642,6 +799,85 @@ where
     }
 }
 
+// === impl Tls ===
+
+impl svc::Param<policy::Receiver> for Tls {
+    fn param(&self) -> policy::Receiver {
+        self.policy.clone()
+    }
+}
+
+impl svc::Param<Option<profiles::LogicalAddr>> for Tls {
+    fn param(&self) -> Option<profiles::LogicalAddr> {
+        self.logical.clone()
+    }
+}
+
+impl PartialEq for Tls {
+    fn eq(&self, other: &Self) -> bool {
+        self.orig_dst == other.orig_dst
+    }
+}
+
+impl Eq for Tls {}
+
+impl std::hash::Hash for Tls {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.orig_dst.hash(state);
+    }
+}
+
+// === impl Tunnel ===
+
+impl TryFrom<Discovery<ConnectTarget>> for Tunnel {
+    type Error = Error;
+
+    fn try_from(parent: Discovery<ConnectTarget>) -> std::result::Result<Self, Self::Error> {
+        let policy = svc::Param::<policy::Receiver>::param(&parent);
+        let ConnectTarget(addr) = (*parent).clone();
+
+        // Client policy routes are only used for socket addresses, since
+        // they are matched by destination port.
+        if let Addr::Socket(sa) = addr {
+            if opaq::should_route_with_policy(&policy.borrow()) {
+                return Ok(Tunnel(opaq::Logical::Policy(
+                    Remote(ServerAddr(sa)),
+                    policy,
+                )));
+            }
+        }
+
+        if let Some(profile) = svc::Param::<Option<profiles::Receiver>>::param(&parent) {
+            if let Some(profiles::LogicalAddr(laddr)) = profile.logical_addr() {
+                return Ok(Tunnel(opaq::Logical::Route(laddr, profile)));
+            }
+
+            if let Some((sa, metadata)) = profile.endpoint() {
+                return Ok(Tunnel(opaq::Logical::Forward(
+                    Remote(ServerAddr(sa)),
+                    metadata,
+                )));
+            }
+        }
+
+        // Names cannot be tunneled without discovery, since the proxy does not
+        // resolve arbitrary names.
+        match addr {
+            Addr::Socket(sa) => Ok(Tunnel(opaq::Logical::Forward(
+                Remote(ServerAddr(sa)),
+                Default::default(),
+            ))),
+            Addr::Name(name) => Err(DiscoveryRequired(name).into()),
+        }
+    }
+}
+
+impl svc::Param<opaq::Logical> for Tunnel {
+    fn param(&self) -> opaq::Logical {
+        self.0.clone()
+    }
+}
+
 // === impl RequestTarget ===
 
 impl From<RequestTarget> for Addr {
@@ -660,6 +896,38 @@ mod tests {
     use tokio::{io::AsyncReadExt, io::AsyncWriteExt, time};
     use tower_test::mock;
 
+    /// Forward-proxy requests are routed to the default port of their URI's
+    /// scheme when the URI does not include a port.
+    #[test]
+    fn forward_proxy_default_ports() {
+        use svc::router::SelectRoute;
+
+        let select = SelectTarget {
+            http: Http {
+                version: http::Version::Http1,
+                parent: OrigDstAddr(([127, 0, 0, 1], 4143).into()),
+            },
+            forward_proxy: true,
+        };
+        let target = |uri: &str| {
+            let req = ::http::Request::builder().uri(uri).body(()).unwrap();
+            select.select(&req).expect("must select a target").parent
+        };
+
+        for (uri, addr) in [
+            ("http://foo.example.com/", "foo.example.com:80"),
+            ("https://foo.example.com/", "foo.example.com:443"),
+            ("https://foo.example.com:8443/", "foo.example.com:8443"),
+        ] {
+            assert_eq!(
+                target(uri),
+                RequestTarget::Named(addr.parse().unwrap()),
+                "{}",
+                uri
+            );
+        }
+    }
+
     /// The ingress stack must not require that inner HTTP stack is immediately
     /// ready.
     #[tokio::test(flavor = "current_thread")]
@@ -676,7 +944,7 @@ mod tests {
         let (runtime, _drain) = crate::test_util::runtime();
         let svc = Outbound::new(config, runtime)
             .with_stack(move |_: _| not_ready_http.clone())
-            .push_ingress(move |_: _| not_ready_opaq.clone())
+            .push_ingress(move |_: _| not_ready_opaq.clone(), false)
             .into_inner()
             .new_service(OrigDstAddr(([127, 0, 0, 1], 80).into()));
 
@@ -714,7 +982,7 @@ mod tests {
         let (runtime, _drain) = crate::test_util::runtime();
         let svc = Outbound::new(config, runtime)
             .with_stack(move |_: _| not_ready_http.clone())
-            .push_ingress(move |_: _| not_ready_opaq.clone())
+            .push_ingress(move |_: _| not_ready_opaq.clone(), false)
             .into_inner()
             .new_service(OrigDstAddr(([127, 0, 0, 1], 80).into()));
 
@@ -0,0 +1,469 @@
+use super::DiscoverAddr;
+use bytes::{Buf, BytesMut};
+use futures::ready;
+use linkerd_app_core::{
+    errors,
+    io::{self, AsyncReadExt, AsyncWriteExt},
+    svc::{self, ServiceExt},
+    Addr, Error, Result,
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tokio::{sync::oneshot, time};
+use tracing::{debug, trace};
+
+/// The address requested by an HTTP/1.1 CONNECT request.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(super) struct ConnectTarget(pub(super) Addr);
+
+/// Tunnels connections that begin with an HTTP/1.1 CONNECT request through
+/// the `tunnel` stack. All other connections are handled by the inner stack.
+#[derive(Clone, Debug)]
+pub(super) struct NewConnectTunnel<F, N> {
+    tunnel: F,
+    inner: N,
+    timeout: time::Duration,
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct ConnectTunnel<T, F, N> {
+    target: T,
+    tunnel: F,
+    inner: N,
+    timeout: time::Duration,
+}
+
+/// The client's I/O for a CONNECT tunnel.
+///
+/// The tunnel is acknowledged before the I/O is first read or written, which
+/// the tunnel stack only does once it has connected to the target. If the I/O
+/// is dropped before the tunnel is acknowledged, it is returned so that an
+/// error response may be written instead.
+#[derive(Debug)]
+pub(super) struct TunnelIo<I> {
+    io: Option<I>,
+    written: usize,
+    unacknowledged: Option<oneshot::Sender<I>>,
+}
+
+#[derive(Debug, Error)]
+#[error("invalid CONNECT request")]
+pub(super) struct InvalidConnect(());
+
+const CONNECT: &[u8] = b"CONNECT ";
+
+/// The largest CONNECT request head that will be read.
+const MAX_HEAD_LEN: usize = 8 * 1024;
+
+const ESTABLISHED: &[u8] = b"HTTP/1.1 200 Connection established\r\n\r\n";
+
+const BAD_GATEWAY: &[u8] =
+    b"HTTP/1.1 502 Bad Gateway\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+
+const GATEWAY_TIMEOUT: &[u8] =
+    b"HTTP/1.1 504 Gateway Timeout\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+
+// === impl NewConnectTunnel ===
+
+impl<F: Clone, N> NewConnectTunnel<F, N> {
+    pub(super) fn layer(
+        tunnel: F,
+        timeout: time::Duration,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            tunnel: tunnel.clone(),
+            inner,
+            timeout,
+        })
+    }
+}
+
+impl<T, F: Clone, N: Clone> svc::NewService<T> for NewConnectTunnel<F, N> {
+    type Service = ConnectTunnel<T, F, N>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        ConnectTunnel {
+            target,
+            tunnel: self.tunnel.clone(),
+            inner: self.inner.clone(),
+            timeout: self.timeout,
+        }
+    }
+}
+
+// === impl ConnectTunnel ===
+
+impl<T, I, F, FSvc, N, NSvc> svc::Service<I> for ConnectTunnel<T, F, N>
+where
+    T: Clone + Send + 'static,
+    I: io::AsyncRead + io::AsyncWrite + Send + Unpin + 'static,
+    F: svc::NewService<ConnectTarget, Service = FSvc> + Clone + Send + 'static,
+    FSvc: svc::Service<TunnelIo<io::PrefixedIo<I>>, Response = (), Error = Error> + Send,
+    FSvc::Future: Send,
+    N: svc::NewService<T, Service = NSvc> + Clone + Send + 'static,
+    NSvc: svc::Service<io::PrefixedIo<I>, Response = (), Error = Error> + Send,
+    NSvc::Future: Send,
+{
+    type Response = ();
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut io: I) -> Self::Future {
+        let target = self.target.clone();
+        let tunnel = self.tunnel.clone();
+        let inner = self.inner.clone();
+        let timeout = self.timeout;
+        Box::pin(async move {
+            let mut buf = BytesMut::with_capacity(1024);
+            match time::timeout(timeout, read_connect(&mut io, &mut buf)).await {
+                Ok(Ok(Some(addr))) => {
+                    debug!(%addr, "Tunneling CONNECT request");
+                    let (io, unacknowledged) = TunnelIo::new(io::PrefixedIo::new(buf.freeze(), io));
+                    let svc = tunnel.new_service(ConnectTarget(addr));
+                    let error = match svc.oneshot(io).await {
+                        Ok(()) => return Ok(()),
+                        Err(error) => error,
+                    };
+
+                    // If the target could not be discovered or connected, the
+                    // tunnel was never acknowledged and the failure is
+                    // reported to the client.
+                    if let Ok(mut io) = unacknowledged.await {
+                        debug!(%error, "Failed to establish tunnel");
+                        let rsp = if is_timeout(&*error) {
+                            GATEWAY_TIMEOUT
+                        } else {
+                            BAD_GATEWAY
+                        };
+                        if let Err(error) = io.write_all(rsp).await {
+                            trace!(%error, "Failed to write response");
+                        }
+                    }
+                    return Err(error);
+                }
+                Ok(Ok(None)) => trace!("Not a CONNECT request"),
+                Ok(Err(error)) => return Err(error),
+                Err(_) => debug!("Timed out reading request"),
+            }
+
+            let svc = inner.new_service(target);
+            svc.oneshot(io::PrefixedIo::new(buf.freeze(), io)).await
+        })
+    }
+}
+
+/// Indicates whether a tunnel failed because its target could not be
+/// connected in time.
+fn is_timeout(error: &(dyn std::error::Error + 'static)) -> bool {
+    errors::is_caused_by::<errors::ConnectTimeout>(error)
+        || errors::is_caused_by::<errors::FailFastError>(error)
+        || errors::cause_ref::<io::Error>(error)
+            .map(|e| e.kind() == io::ErrorKind::TimedOut)
+            .unwrap_or(false)
+}
+
+/// Reads an HTTP/1.1 CONNECT request head from `io`.
+///
+/// Returns `None` as soon as the buffered bytes cannot be a CONNECT request.
+/// Any bytes read, except for a CONNECT request's head, are left in `buf`.
+async fn read_connect<I>(io: &mut I, buf: &mut BytesMut) -> Result<Option<Addr>>
+where
+    I: io::AsyncRead + Unpin,
+{
+    loop {
+        let n = buf.len().min(CONNECT.len());
+        if buf[..n] != CONNECT[..n] {
+            return Ok(None);
+        }
+
+        if n == CONNECT.len() {
+            let mut headers = [httparse::EMPTY_HEADER; 64];
+            let mut req = httparse::Request::new(&mut headers);
+            match req.parse(&buf[..]) {
+                Ok(httparse::Status::Complete(len)) => {
+                    let addr = req
+                        .path
+                        .and_then(|p| p.parse::<Addr>().ok())
+                        .ok_or(InvalidConnect(()))?;
+                    buf.advance(len);
+                    return Ok(Some(addr));
+                }
+                Ok(httparse::Status::Partial) if buf.len() < MAX_HEAD_LEN => {}
+                _ => return Err(InvalidConnect(()).into()),
+            }
+        }
+
+        if io.read_buf(buf).await? == 0 {
+            return Ok(None);
+        }
+    }
+}
+
+// === impl TunnelIo ===
+
+impl<I> TunnelIo<I> {
+    fn new(io: I) -> (Self, oneshot::Receiver<I>) {
+        let (tx, rx) = oneshot::channel();
+        let io = Self {
+            io: Some(io),
+            written: 0,
+            unacknowledged: Some(tx),
+        };
+        (io, rx)
+    }
+
+    fn io_mut(&mut self) -> &mut I {
+        self.io.as_mut().expect("I/O must be set until dropped")
+    }
+}
+
+impl<I: io::AsyncWrite + Unpin> TunnelIo<I> {
+    /// Writes the response acknowledging the tunnel, if it has not yet been
+    /// written.
+    fn poll_acknowledge(&mut self, cx: &mut Context<'_>) -> io::Poll<()> {
+        if self.unacknowledged.is_none() {
+            return Poll::Ready(Ok(()));
+        }
+
+        while self.written < ESTABLISHED.len() {
+            let written = self.written;
+            let n = ready!(Pin::new(self.io_mut()).poll_write(cx, &ESTABLISHED[written..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += n;
+        }
+        ready!(Pin::new(self.io_mut()).poll_flush(cx))?;
+
+        trace!("Acknowledged tunnel");
+        self.unacknowledged = None;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<I: io::AsyncRead + io::AsyncWrite + Unpin> io::AsyncRead for TunnelIo<I> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut io::ReadBuf<'_>,
+    ) -> io::Poll<()> {
+        ready!(self.poll_acknowledge(cx))?;
+        Pin::new(self.io_mut()).poll_read(cx, buf)
+    }
+}
+
+impl<I: io::AsyncWrite + Unpin> io::AsyncWrite for TunnelIo<I> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> io::Poll<usize> {
+        ready!(self.poll_acknowledge(cx))?;
+        Pin::new(self.io_mut()).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> io::Poll<usize> {
+        ready!(self.poll_acknowledge(cx))?;
+        Pin::new(self.io_mut()).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io
+            .as_ref()
+            .map(|io| io.is_write_vectored())
+            .unwrap_or(false)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        ready!(self.poll_acknowledge(cx))?;
+        Pin::new(self.io_mut()).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        ready!(self.poll_acknowledge(cx))?;
+        Pin::new(self.io_mut()).poll_shutdown(cx)
+    }
+}
+
+impl<I: io::PeerAddr> io::PeerAddr for TunnelIo<I> {
+    fn peer_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.io
+            .as_ref()
+            .expect("I/O must be set until dropped")
+            .peer_addr()
+    }
+}
+
+impl<I> Drop for TunnelIo<I> {
+    fn drop(&mut self) {
+        // Nothing has been written to the client, so it may still be sent an
+        // error response.
+        if self.written == 0 {
+            if let (Some(tx), Some(io)) = (self.unacknowledged.take(), self.io.take()) {
+                let _ = tx.send(io);
+            }
+        }
+    }
+}
+
+// === impl ConnectTarget ===
+
+impl svc::Param<DiscoverAddr> for ConnectTarget {
+    fn param(&self) -> DiscoverAddr {
+        DiscoverAddr(self.0.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+    use svc::{layer::Layer, NewService};
+
+    type ClientIo = TunnelIo<io::PrefixedIo<io::DuplexStream>>;
+
+    /// Tests that a tunnel is only acknowledged once the tunnel stack has
+    /// connected to the target.
+    #[tokio::test(flavor = "current_thread")]
+    async fn acknowledges_connected_tunnel() {
+        let _trace = linkerd_tracing::test::trace_init();
+        time::pause();
+
+        let tunnel = |_: ConnectTarget| {
+            svc::mk(|mut io: ClientIo| async move {
+                // Connecting to the target takes a while.
+                time::sleep(time::Duration::from_secs(1)).await;
+                io.write_all(b"hello").await?;
+                Ok::<_, Error>(())
+            })
+        };
+        let (mut client, task) = spawn_connect(tunnel).await;
+
+        // Nothing is written to the client before the target is connected.
+        let mut buf = BytesMut::new();
+        tokio::select! {
+            _ = time::sleep(time::Duration::from_millis(500)) => {}
+            _ = client.read_buf(&mut buf) => panic!("unexpected read"),
+        }
+
+        let mut rsp = Vec::new();
+        client.read_to_end(&mut rsp).await.expect("must read");
+        assert_eq!(&rsp[..ESTABLISHED.len()], ESTABLISHED);
+        assert_eq!(&rsp[ESTABLISHED.len()..], b"hello");
+        task.await.unwrap().expect("tunnel must succeed");
+    }
+
+    /// Tests that clients are sent a 502 response when the target cannot be
+    /// connected.
+    #[tokio::test(flavor = "current_thread")]
+    async fn reports_connect_failures() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let rsp = connect_error(io::ErrorKind::ConnectionRefused).await;
+        assert_eq!(rsp, BAD_GATEWAY);
+    }
+
+    /// Tests that clients are sent a 504 response when connecting to the
+    /// target times out.
+    #[tokio::test(flavor = "current_thread")]
+    async fn reports_connect_timeouts() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let rsp = connect_error(io::ErrorKind::TimedOut).await;
+        assert_eq!(rsp, GATEWAY_TIMEOUT);
+    }
+
+    /// Returns the response written to a client when the tunnel stack fails
+    /// to connect with the given error.
+    async fn connect_error(kind: io::ErrorKind) -> Vec<u8> {
+        let tunnel = move |_: ConnectTarget| {
+            svc::mk(move |_io: ClientIo| future::err::<(), Error>(io::Error::from(kind).into()))
+        };
+        let (mut client, task) = spawn_connect(tunnel).await;
+
+        let mut rsp = Vec::new();
+        client.read_to_end(&mut rsp).await.expect("must read");
+        task.await.unwrap().expect_err("tunnel must fail");
+        rsp
+    }
+
+    /// Spawns a `ConnectTunnel` serving a CONNECT request through `tunnel`.
+    async fn spawn_connect<F, FSvc>(
+        tunnel: F,
+    ) -> (io::DuplexStream, tokio::task::JoinHandle<Result<()>>)
+    where
+        F: svc::NewService<ConnectTarget, Service = FSvc> + Clone + Send + 'static,
+        FSvc: svc::Service<ClientIo, Response = (), Error = Error> + Send + 'static,
+        FSvc::Future: Send,
+    {
+        let inner = |_: ()| {
+            svc::mk(|_: io::PrefixedIo<io::DuplexStream>| {
+                future::err::<(), Error>(InvalidConnect(()).into())
+            })
+        };
+        let svc = NewConnectTunnel::layer(tunnel, time::Duration::from_secs(10))
+            .layer(inner)
+            .new_service(());
+
+        let (mut client, server) = io::duplex(1024);
+        let task = tokio::spawn(svc.oneshot(server));
+        client
+            .write_all(b"CONNECT foo.ns.svc.cluster.local:8080 HTTP/1.1\r\n\r\n")
+            .await
+            .expect("must write");
+        (client, task)
+    }
+
+    #[tokio::test]
+    async fn reads_connect() {
+        let mut io = tokio_test::io::Builder::new()
+            .read(b"CONNECT foo.ns.svc.cluster.local:8080 HTTP/1.1\r\n")
+            .read(b"Host: foo.ns.svc.cluster.local:8080\r\n\r\nhello")
+            .build();
+        let mut buf = BytesMut::new();
+        let addr = read_connect(&mut io, &mut buf)
+            .await
+            .expect("must read")
+            .expect("must be a CONNECT request");
+        assert_eq!(
+            addr,
+            "foo.ns.svc.cluster.local:8080".parse::<Addr>().unwrap()
+        );
+        assert_eq!(&buf[..], b"hello");
+    }
+
+    #[tokio::test]
+    async fn passes_other_requests() {
+        let mut io = tokio_test::io::Builder::new()
+            .read(b"GET http://foo.ns.svc.cluster.local/ HTTP/1.1\r\n\r\n")
+            .build();
+        let mut buf = BytesMut::new();
+        let addr = read_connect(&mut io, &mut buf).await.expect("must read");
+        assert!(addr.is_none());
+        assert_eq!(
+            &buf[..],
+            b"GET http://foo.ns.svc.cluster.local/ HTTP/1.1\r\n\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_connect() {
+        let mut io = tokio_test::io::Builder::new()
+            .read(b"CONNECT foo HTTP/1.1\r\n\r\n")
+            .build();
+        let mut buf = BytesMut::new();
+        let err = read_connect(&mut io, &mut buf)
+            .await
+            .expect_err("must fail");
+        assert!(err.is::<InvalidConnect>(), "unexpected error: {}", err);
+    }
+}
@@ -21,8 +21,7 @@ use linkerd_app_core::{
     },
     serve,
     svc::{self, ServiceExt},
-    tls,
-    transport::addrs::*,
+    transport::{addrs::*, proxy_protocol},
     AddrMatch, Error, ProxyRuntime, Result,
 };
 use std::{
@@ -34,6 +33,7 @@ use std::{
     time::Duration,
 };
 
+mod breaker;
 mod discover;
 pub mod http;
 mod ingress;
@@ -45,6 +45,7 @@ mod sidecar;
 pub mod tcp;
 #[cfg(any(test, feature = "test-util"))]
 pub mod test_util;
+pub mod tls;
 
 pub use self::{
     discover::{spawn_synthesized_profile_policy, synthesize_forward_policy, Discovery},
@@ -78,10 +79,42 @@ pub struct Config {
     // not perform per-target-address discovery. Non-HTTP connections are
     // forwarded without discovery/routing/mTLS.
     pub ingress_mode: bool,
+
+    /// In "forward-proxy mode", applications explicitly use the proxy via
+    /// HTTP CONNECT or absolute-form requests instead of being transparently
+    /// redirected to it. This takes precedence over ingress mode.
+    pub forward_proxy_mode: bool,
     pub inbound_ips: Arc<HashSet<IpAddr>>,
 
     // Whether the proxy may include informational headers on HTTP responses.
     pub emit_headers: bool,
+
+    /// Configures the ports on which connections are expected to begin with a
+    /// PROXY protocol header. This only applies in ingress mode.
+    pub proxy_protocol: proxy_protocol::Config,
+
+    /// Configures balancers to prefer endpoints in the proxy's own zone, if
+    /// set.
+    pub zone_affinity: Option<proxy::http::balance::ZoneAffinityConfig>,
+
+    /// The endpoint label that describes the zone in which each discovered
+    /// endpoint runs.
+    pub zone_label: Arc<str>,
+
+    /// Configures peak-EWMA balancers to ramp up traffic to newly discovered
+    /// endpoints, if set.
+    ///
+    /// This applies to all backends' peak-EWMA balancers. Other balancers
+    /// ignore it.
+    pub slow_start: Option<proxy::http::balance::SlowStartConfig>,
+
+    /// Configures a header, in addition to `grpc-timeout`, with which callers
+    /// may bound the duration of requests on HTTP and gRPC policy routes.
+    pub deadline_header: Option<proxy::http::HeaderName>,
+
+    /// Configures client policy features that the policy API cannot yet
+    /// express.
+    pub local_policy: policy::LocalConfig,
 }
 
 #[derive(Clone, Debug)]
@@ -100,7 +133,7 @@ struct Runtime {
     drain: drain::Watch,
 }
 
-pub type ConnectMeta = tls::ConnectMeta<Local<ClientAddr>>;
+pub type ConnectMeta = linkerd_app_core::tls::ConnectMeta<Local<ClientAddr>>;
 
 /// A reference to a frontend/apex resource, usually a service.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -149,12 +182,17 @@ impl Outbound<()> {
         C::ResponseBody: Default + Send + 'static,
         C::Future: Send,
     {
-        policy::Api::new(workload, Duration::from_secs(10), client)
-            .into_watch(backoff)
-            .map_result(|response| match response {
-                Err(e) => Err(e.into()),
-                Ok(rsp) => Ok(rsp.into_inner()),
-            })
+        policy::Api::new(
+            workload,
+            Duration::from_secs(10),
+            self.config.local_policy.clone(),
+            client,
+        )
+        .into_watch(backoff)
+        .map_result(|response| match response {
+            Err(e) => Err(e.into()),
+            Ok(rsp) => Ok(rsp.into_inner()),
+        })
     }
 
     #[cfg(any(test, feature = "test-util"))]
@@ -240,9 +278,20 @@ impl Outbound<()> {
         R: Resolve<ConcreteAddr, Endpoint = Metadata, Error = Error>,
     {
         let profiles = profiles::WithAllowlist::new(profiles, self.config.allow_discovery.clone());
-        if self.config.ingress_mode {
+        if self.config.forward_proxy_mode {
+            tracing::info!("Outbound routing in forward-proxy mode");
+            let server = self.mk_forward_proxy(profiles, policies, resolve);
+            let shutdown = self.runtime.drain.signaled();
+            serve::serve(listen, server, shutdown).await;
+        } else if self.config.ingress_mode {
             tracing::info!("Outbound routing in ingress-mode");
-            let server = self.mk_ingress(profiles, policies, resolve);
+            // Load balancers in front of an ingress may describe each
+            // connection's client with a PROXY protocol header.
+            let server = svc::stack(self.mk_ingress(profiles, policies, resolve))
+                .push(proxy_protocol::NewProxyProtocol::layer(
+                    self.config.proxy_protocol.clone(),
+                ))
+                .into_inner();
             let shutdown = self.runtime.drain.signaled();
             serve::serve(listen, server, shutdown).await;
         } else {
@@ -253,6 +302,16 @@ impl Outbound<()> {
     }
 }
 
+/// Extracts an endpoint's zone from the configured endpoint label.
+#[derive(Clone, Debug)]
+struct ExtractEndpointZone(Arc<str>);
+
+impl svc::ExtractParam<proxy::http::balance::EndpointZone, Metadata> for ExtractEndpointZone {
+    fn extract_param(&self, metadata: &Metadata) -> proxy::http::balance::EndpointZone {
+        proxy::http::balance::EndpointZone(metadata.labels().get(&*self.0).cloned())
+    }
+}
+
 fn stack_labels(proto: &'static str, name: &'static str) -> metrics::StackLabels {
     metrics::StackLabels::outbound(proto, name)
 }
@@ -9,8 +9,12 @@
 //! `DashMap` as we migrate other metrics registries.
 
 use crate::{
-    http::{concrete::BalancerMetrics, policy::RouteBackendMetrics},
+    http::{
+        concrete::{BackendConcurrencyLimits, BalancerMetrics},
+        policy::RouteBackendMetrics,
+    },
     policy,
+    tls::TlsRouteMetrics,
 };
 
 pub(crate) mod error;
@@ -24,6 +28,9 @@ pub struct OutboundMetrics {
 
     pub(crate) http_route_backends: RouteBackendMetrics,
     pub(crate) http_balancer: BalancerMetrics,
+    pub(crate) http_concurrency_limits: BackendConcurrencyLimits,
+
+    pub(crate) tls_routes: TlsRouteMetrics,
 
     /// Holds metrics that are common to both inbound and outbound proxies. These metrics are
     /// reported separately
@@ -38,6 +45,8 @@ impl OutboundMetrics {
             tcp_errors: error::Tcp::default(),
             http_route_backends: RouteBackendMetrics::default(),
             http_balancer: BalancerMetrics::default(),
+            http_concurrency_limits: BackendConcurrencyLimits::default(),
+            tls_routes: TlsRouteMetrics::default(),
         }
     }
 }
@@ -46,6 +55,8 @@ impl FmtMetrics for OutboundMetrics {
     fn fmt_metrics(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.http_route_backends.fmt_metrics(f)?;
         self.http_balancer.fmt_metrics(f)?;
+        self.http_concurrency_limits.fmt_metrics(f)?;
+        self.tls_routes.fmt_metrics(f)?;
         self.http_errors.fmt_metrics(f)?;
         self.tcp_errors.fmt_metrics(f)?;
 
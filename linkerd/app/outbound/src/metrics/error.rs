@@ -6,17 +6,23 @@ use crate::http::IdentityRequired;
 use linkerd_app_core::{
     errors::{FailFastError, LoadShedError},
     metrics::FmtLabels,
-    proxy::http::ResponseTimeoutError,
+    proxy::http::{
+        DeadlineExceededError, ResponseStreamTimeoutError, ResponseTimeoutError,
+        StreamIdleTimeoutError,
+    },
 };
 use std::fmt;
 
 /// Outbound proxy error types.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 enum ErrorKind {
+    DeadlineExceeded,
     FailFast,
     IdentityRequired,
     Io,
     ResponseTimeout,
+    ResponseStreamTimeout,
+    StreamIdleTimeout,
     Unexpected,
     LoadShed,
 }
@@ -33,6 +39,12 @@ impl ErrorKind {
             ErrorKind::FailFast
         } else if err.is::<ResponseTimeoutError>() {
             ErrorKind::ResponseTimeout
+        } else if err.is::<ResponseStreamTimeoutError>() {
+            ErrorKind::ResponseStreamTimeout
+        } else if err.is::<StreamIdleTimeoutError>() {
+            ErrorKind::StreamIdleTimeout
+        } else if err.is::<DeadlineExceededError>() {
+            ErrorKind::DeadlineExceeded
         } else if err.is::<LoadShedError>() {
             ErrorKind::LoadShed
         } else if let Some(e) = err.source() {
@@ -49,11 +61,14 @@ impl FmtLabels for ErrorKind {
             f,
             "error=\"{}\"",
             match self {
+                ErrorKind::DeadlineExceeded => "deadline exceeded",
                 ErrorKind::LoadShed => "loadshed",
                 ErrorKind::FailFast => "failfast",
                 ErrorKind::IdentityRequired => "identity required",
                 ErrorKind::Io => "i/o",
                 ErrorKind::ResponseTimeout => "response timeout",
+                ErrorKind::ResponseStreamTimeout => "response stream timeout",
+                ErrorKind::StreamIdleTimeout => "stream idle timeout",
                 ErrorKind::Unexpected => "unexpected",
             }
         )
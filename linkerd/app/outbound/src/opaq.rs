@@ -1,4 +1,7 @@
-use crate::{tcp, Outbound};
+use crate::{
+    policy::{self, FailureAccrual},
+    tcp, Outbound,
+};
 use linkerd_app_core::{
     io, profiles,
     proxy::{
@@ -11,7 +14,8 @@ use linkerd_app_core::{
 };
 use std::{fmt::Debug, hash::Hash};
 
-mod concrete;
+mod classify;
+pub(crate) mod concrete;
 mod logical;
 
 pub use self::logical::Logical;
@@ -63,6 +67,41 @@ impl<C> Outbound<C> {
     }
 }
 
+/// Returns the failure accrual policy configured for a client policy's opaque
+/// routes.
+pub(crate) fn failure_accrual(policy: &policy::ClientPolicy) -> FailureAccrual {
+    match policy.protocol {
+        policy::Protocol::Opaque(ref opaque) | policy::Protocol::Detect { ref opaque, .. } => {
+            opaque.failure_accrual
+        }
+        policy::Protocol::Tls(ref tls) => tls.failure_accrual,
+        policy::Protocol::Http1(_) | policy::Protocol::Http2(_) | policy::Protocol::Grpc(_) => {
+            FailureAccrual::None
+        }
+    }
+}
+
+/// Returns true if a client policy's opaque routes configure matches,
+/// filters, or failure accrual.
+///
+/// Service profiles cannot express these, so connections must be routed with
+/// the client policy.
+pub(crate) fn should_route_with_policy(policy: &policy::ClientPolicy) -> bool {
+    match policy.protocol {
+        policy::Protocol::Opaque(ref opaque) | policy::Protocol::Detect { ref opaque, .. } => {
+            opaque.failure_accrual != FailureAccrual::None
+                || opaque
+                    .routes
+                    .iter()
+                    .any(|rt| !rt.matches.is_empty() || !rt.policy.filters.is_empty())
+        }
+        policy::Protocol::Tls(_)
+        | policy::Protocol::Http1(_)
+        | policy::Protocol::Http2(_)
+        | policy::Protocol::Grpc(_) => false,
+    }
+}
+
 // === impl Opaq ===
 
 impl svc::Param<Logical> for Opaq {
@@ -0,0 +1,248 @@
+use futures::{future, Future, FutureExt, TryFutureExt};
+use linkerd_app_core::{classify, errno::Errno, io, proxy::http::classify::gate, svc, Error};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::sync::mpsc;
+
+/// A connection that reports its classification to an endpoint's failure
+/// accrual policy.
+pub type Io<I> = io::SensorIo<I, ClassifyIo>;
+
+/// A [`svc::NewService`] that builds a [`NewClassifyGate`] for each balancer.
+///
+/// `X` extracts a failure accrual strategy `P` from the balancer's target. The
+/// strategy is then used to build the [`gate::Params`] for each of the
+/// balancer's endpoints.
+#[derive(Clone, Debug)]
+pub struct NewClassifyGateSet<X, N> {
+    extract: X,
+    inner: N,
+}
+
+/// A [`svc::NewService`] that wraps each endpoint's connect service with a
+/// [`svc::Gate`] controlled by the endpoint's failure accrual policy.
+#[derive(Clone, Debug)]
+pub struct NewClassifyGate<P, N> {
+    params: P,
+    inner: N,
+}
+
+/// Classifies connection attempts made by an inner connect service.
+///
+/// Connections that cannot be established are classified as failures.
+/// Established connections are wrapped with a [`ClassifyIo`] sensor, so that
+/// connections that are reset before any data is received are also classified
+/// as failures.
+#[derive(Clone, Debug)]
+pub struct ClassifyConnect<S> {
+    responses: Option<mpsc::Sender<classify::Class>>,
+    inner: S,
+}
+
+/// Classifies a connection once it receives data, encounters an error, or is
+/// dropped.
+#[derive(Debug)]
+pub struct ClassifyIo {
+    responses: Option<mpsc::Sender<classify::Class>>,
+}
+
+// === impl NewClassifyGateSet ===
+
+impl<X: Clone, N> NewClassifyGateSet<X, N> {
+    pub fn layer_via(extract: X) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            extract: extract.clone(),
+            inner,
+        })
+    }
+}
+
+impl<T, P, X, N> svc::NewService<T> for NewClassifyGateSet<X, N>
+where
+    X: svc::ExtractParam<P, T>,
+    N: svc::NewService<T>,
+{
+    type Service = NewClassifyGate<P, N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let params = self.extract.extract_param(&target);
+        let inner = self.inner.new_service(target);
+        NewClassifyGate { params, inner }
+    }
+}
+
+// === impl NewClassifyGate ===
+
+impl<T, P, N> svc::NewService<T> for NewClassifyGate<P, N>
+where
+    P: svc::ExtractParam<gate::Params<classify::Class>, T>,
+    N: svc::NewService<T>,
+{
+    type Service = svc::Gate<ClassifyConnect<N::Service>>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let gate::Params { responses, gate } = self.params.extract_param(&target);
+        let inner = self.inner.new_service(target);
+        svc::Gate::new(
+            gate,
+            ClassifyConnect {
+                responses: Some(responses),
+                inner,
+            },
+        )
+    }
+}
+
+// === impl ClassifyConnect ===
+
+impl<S> ClassifyConnect<S> {
+    /// Returns a layer that wraps connect services without classifying their
+    /// connections, so that they produce the same connection type as
+    /// classified services.
+    pub fn layer_unclassified() -> impl svc::layer::Layer<S, Service = Self> + Clone {
+        svc::layer::mk(|inner| Self {
+            responses: None,
+            inner,
+        })
+    }
+}
+
+impl<S> svc::Service<()> for ClassifyConnect<S>
+where
+    S: svc::Service<()>,
+    S::Error: Into<Error>,
+    S::Future: Send + 'static,
+{
+    type Response = Io<S::Response>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Error>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, (): ()) -> Self::Future {
+        let mut sensor = ClassifyIo {
+            responses: self.responses.clone(),
+        };
+        Box::pin(
+            self.inner
+                .call(())
+                .err_into::<Error>()
+                .map(move |res| match res {
+                    Ok(io) => Ok(io::SensorIo::new(io, sensor)),
+                    Err(error) => {
+                        tracing::trace!(%error, "Connection failed");
+                        sensor.classify(false);
+                        Err(error)
+                    }
+                }),
+        )
+    }
+}
+
+// === impl ClassifyIo ===
+
+impl ClassifyIo {
+    fn classify(&mut self, success: bool) {
+        if let Some(responses) = self.responses.take() {
+            let class = classify::Class::Opaque(if success { Ok(()) } else { Err(()) });
+            // If the failure accrual policy is not keeping up, drop the
+            // classification rather than blocking the connection.
+            let _ = responses.try_send(class);
+        }
+    }
+}
+
+impl io::Sensor for ClassifyIo {
+    fn record_read(&mut self, sz: usize) {
+        // Once the endpoint has sent data, the connection is considered
+        // established and later errors do not indicate that the endpoint is
+        // unhealthy.
+        if sz > 0 {
+            self.classify(true);
+        }
+    }
+
+    fn record_write(&mut self, _: usize) {}
+
+    fn record_close(&mut self, _: Option<Errno>) {}
+
+    fn record_error<T>(&mut self, op: io::Poll<T>) -> io::Poll<T> {
+        if let Poll::Ready(Err(ref error)) = op {
+            if error.kind() != io::ErrorKind::WouldBlock {
+                tracing::trace!(%error, "Connection reset before receiving data");
+                self.classify(false);
+            }
+        }
+        op
+    }
+}
+
+impl Drop for ClassifyIo {
+    fn drop(&mut self) {
+        // Connections that are closed cleanly are considered successful, even
+        // if no data was received.
+        self.classify(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn classifies_connect_errors() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let mut connect = ClassifyConnect {
+            responses: Some(tx),
+            inner: svc::mk(|()| {
+                future::err::<io::DuplexStream, _>(io::Error::from(
+                    io::ErrorKind::ConnectionRefused,
+                ))
+            }),
+        };
+        assert!(svc::Service::call(&mut connect, ()).await.is_err());
+        assert_eq!(rx.recv().await, Some(classify::Class::Opaque(Err(()))));
+    }
+
+    #[tokio::test]
+    async fn classifies_early_resets() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let mut io = io::SensorIo::new(
+            tokio_test::io::Builder::new()
+                .read_error(io::Error::from(io::ErrorKind::ConnectionReset))
+                .build(),
+            ClassifyIo {
+                responses: Some(tx),
+            },
+        );
+        let mut buf = [0u8; 8];
+        assert!(io.read(&mut buf).await.is_err());
+        assert_eq!(rx.recv().await, Some(classify::Class::Opaque(Err(()))));
+    }
+
+    #[tokio::test]
+    async fn classifies_data_as_success() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let mut io = io::SensorIo::new(
+            tokio_test::io::Builder::new()
+                .read(b"hello")
+                .read_error(io::Error::from(io::ErrorKind::ConnectionReset))
+                .build(),
+            ClassifyIo {
+                responses: Some(tx),
+            },
+        );
+        let mut buf = [0u8; 8];
+        assert_eq!(io.read(&mut buf).await.unwrap(), 5);
+        assert!(io.read(&mut buf).await.is_err());
+        drop(io);
+        assert_eq!(rx.recv().await, Some(classify::Class::Opaque(Ok(()))));
+        assert_eq!(rx.recv().await, None);
+    }
+}
@@ -1,4 +1,9 @@
-use crate::{stack_labels, Outbound};
+use super::classify;
+use crate::{
+    breaker,
+    policy::{self, FailureAccrual},
+    stack_labels, Outbound,
+};
 use linkerd_app_core::{
     drain, io, metrics, profiles,
     proxy::{
@@ -46,6 +51,7 @@ pub struct Endpoint<T> {
 struct Balance<T> {
     addr: NameAddr,
     ewma: balance::EwmaConfig,
+    slow_start: Option<balance::SlowStartConfig>,
     parent: T,
 }
 
@@ -73,6 +79,7 @@ impl<C> Outbound<C> {
     where
         // Logical target.c
         T: svc::Param<Dispatch>,
+        T: svc::Param<FailureAccrual>,
         T: Clone + Debug + Send + Sync + 'static,
         // Server-side socket.
         I: io::AsyncRead + io::AsyncWrite + Debug + Send + Unpin + 'static,
@@ -101,6 +108,10 @@ impl<C> Outbound<C> {
 
             let forward = connect
                 .clone()
+                // Forwarded connections are not subject to failure accrual,
+                // but must produce the same connection type as balanced
+                // endpoints.
+                .push_on_service(classify::ClassifyConnect::layer_unclassified())
                 .push_on_service(
                     rt.metrics
                         .proxy
@@ -119,11 +130,21 @@ impl<C> Outbound<C> {
                 .instrument(|e: &Endpoint<T>| info_span!("endpoint", addr = %e.addr));
 
             let inbound_ips = config.inbound_ips.clone();
+            let local_policy = config.local_policy.clone();
+            let zone_affinity = config.zone_affinity.clone();
+            let zone_label = config.zone_label.clone();
+            let slow_start = config.slow_start;
+            let classify_channel_capacity = tcp_connection_queue.capacity;
             let balance = endpoint
                 .push_map_target(
                     move |((addr, metadata), target): ((SocketAddr, Metadata), Balance<T>)| {
                         tracing::trace!(%addr, ?metadata, ?target, "Resolved endpoint");
                         let is_local = inbound_ips.contains(&addr.ip());
+                        let metadata = if local_policy.sends_proxy_header(&target.addr) {
+                            metadata.with_proxy_protocol(true)
+                        } else {
+                            metadata
+                        };
                         Endpoint {
                             addr: Remote(ServerAddr(addr)),
                             metadata,
@@ -133,6 +154,20 @@ impl<C> Outbound<C> {
                     },
                 )
                 .lift_new_with_target()
+                // Connect failures and connections that are reset before any
+                // data is received are classified as failures, so that the
+                // backend's failure accrual policy may stop sending new
+                // connections to unhealthy endpoints.
+                .push(classify::NewClassifyGateSet::layer_via(
+                    move |target: &Balance<T>| {
+                        breaker::Params::new(target.parent.param(), classify_channel_capacity)
+                    },
+                ))
+                // Prefers endpoints in the proxy's own zone, when configured.
+                .push(balance::NewZoneAffinity::layer_via(
+                    move |_: &Balance<T>| zone_affinity.clone(),
+                    crate::ExtractEndpointZone(zone_label),
+                ))
                 .push(tcp::NewBalancePeakEwma::layer(resolve))
                 .push(svc::NewMapErr::layer_from_target::<ConcreteError, _>())
                 .push_on_service(
@@ -147,9 +182,12 @@ impl<C> Outbound<C> {
                 .push_switch(
                     move |parent: T| -> Result<_, Infallible> {
                         Ok(match parent.param() {
-                            Dispatch::Balance(addr, ewma) => {
-                                svc::Either::A(Balance { addr, ewma, parent })
-                            }
+                            Dispatch::Balance(addr, ewma) => svc::Either::A(Balance {
+                                addr,
+                                ewma,
+                                slow_start,
+                                parent,
+                            }),
                             Dispatch::Forward(addr, meta) => svc::Either::B(Endpoint {
                                 addr,
                                 is_local: false,
@@ -168,6 +206,52 @@ impl<C> Outbound<C> {
     }
 }
 
+// === impl Dispatch ===
+
+impl Dispatch {
+    /// Returns a dispatcher for a client policy backend.
+    ///
+    /// Opaque connections cannot be failed with a message, so `None` is
+    /// returned for `Fail` backends. Balanced backends always use peak EWMA, and
+    /// a warning is logged for backends that configure another load strategy.
+    pub(crate) fn from_backend(bke: &policy::Backend) -> Option<Self> {
+        const EWMA: balance::EwmaConfig = balance::EwmaConfig {
+            default_rtt: std::time::Duration::from_millis(30),
+            decay: std::time::Duration::from_secs(10),
+        };
+
+        match bke.dispatcher {
+            policy::BackendDispatcher::BalanceP2c(
+                ref load,
+                policy::EndpointDiscovery::DestinationGet { ref path },
+            ) => Some(Self::Balance(
+                path.parse::<NameAddr>()
+                    .expect("destination must be a nameaddr"),
+                match *load {
+                    policy::Load::PeakEwma(policy::PeakEwma { decay, default_rtt }) => {
+                        balance::EwmaConfig { decay, default_rtt }
+                    }
+                    // Opaque balancers only support the EWMA load metric, so
+                    // other strategies (which are only configured locally) are
+                    // not honored.
+                    ref load => {
+                        tracing::warn!(
+                            backend = ?bke.meta,
+                            ?load,
+                            "Opaque backends are balanced by peak EWMA; ignoring load strategy",
+                        );
+                        EWMA
+                    }
+                },
+            )),
+            policy::BackendDispatcher::Forward(addr, ref md) => {
+                Some(Self::Forward(Remote(ServerAddr(addr)), md.clone()))
+            }
+            policy::BackendDispatcher::Fail { .. } => None,
+        }
+    }
+}
+
 // === impl ConcreteError ===
 
 impl<T> From<(&Balance<T>, Error)> for ConcreteError {
@@ -195,6 +279,12 @@ impl<T> svc::Param<balance::EwmaConfig> for Balance<T> {
     }
 }
 
+impl<T> svc::Param<Option<balance::SlowStartConfig>> for Balance<T> {
+    fn param(&self) -> Option<balance::SlowStartConfig> {
+        self.slow_start
+    }
+}
+
 // === impl Endpoint ===
 
 impl<T> svc::Param<Remote<ServerAddr>> for Endpoint<T> {
@@ -226,6 +316,16 @@ impl<T> svc::Param<Option<AuthorityOverride>> for Endpoint<T> {
     }
 }
 
+impl<T> svc::Param<crate::tcp::proxy_header::SendProxyHeader> for Endpoint<T> {
+    fn param(&self) -> crate::tcp::proxy_header::SendProxyHeader {
+        // Headers are only sent to endpoints that are not meshed, since a
+        // meshed endpoint's proxy would not expect one.
+        crate::tcp::proxy_header::SendProxyHeader(
+            !self.is_local && self.metadata.proxy_protocol() && self.metadata.identity().is_none(),
+        )
+    }
+}
+
 impl<T> svc::Param<Option<SessionProtocol>> for Endpoint<T> {
     fn param(&self) -> Option<SessionProtocol> {
         None
@@ -1,5 +1,8 @@
 use super::concrete;
-use crate::Outbound;
+use crate::{
+    policy::{self, FailureAccrual},
+    Outbound,
+};
 use linkerd_app_core::{
     io,
     profiles::{self, Profile},
@@ -12,18 +15,25 @@ use linkerd_distribute as distribute;
 use std::{fmt::Debug, hash::Hash, time};
 use tokio::sync::watch;
 
+mod router;
 #[cfg(test)]
 mod tests;
 
 #[derive(Clone, Debug)]
 pub enum Logical {
     Route(NameAddr, profiles::Receiver),
+    /// Routes connections to the original destination with the client
+    /// policy's opaque routes.
+    Policy(Remote<ServerAddr>, policy::Receiver),
     Forward(Remote<ServerAddr>, Metadata),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Concrete<T> {
     target: concrete::Dispatch,
+    /// Only backends of client policies accrue failures. This is part of the
+    /// target so that backends are rebuilt when the policy changes it.
+    failure_accrual: FailureAccrual,
     parent: T,
 }
 
@@ -74,6 +84,10 @@ impl<N> Outbound<N> {
     /// services. Only available inner services are used for routing. When
     /// there are no available backends, requests are failed with a
     /// [`svc::stack::LoadShedError`].
+    ///
+    /// Targets routed by a client policy select a route for each connection
+    /// by its destination port and client address. The route's filters are
+    /// applied before the connection is dispatched.
     pub fn push_opaq_logical<T, I, NSvc>(
         self,
     ) -> Outbound<
@@ -87,7 +101,8 @@ impl<N> Outbound<N> {
         T: svc::Param<Logical>,
         T: Eq + Hash + Clone + Debug + Send + Sync + 'static,
         // Server-side socket.
-        I: io::AsyncRead + io::AsyncWrite + Debug + Send + Unpin + 'static,
+        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr,
+        I: Debug + Send + Unpin + 'static,
         // Concrete stack.
         N: svc::NewService<Concrete<T>, Service = NSvc> + Clone + Send + Sync + 'static,
         NSvc: svc::Service<I, Response = ()> + Clone + Send + Sync + 'static,
@@ -116,10 +131,31 @@ impl<N> Outbound<N> {
                 .push_on_service(route)
                 .push(svc::NewOneshotRoute::<Params<T>, _, _>::layer_cached());
 
+            // For each `router::Routable` target, watch its client policy,
+            // selecting a route for each connection. Each route's filters are
+            // applied before the connection is distributed to a backend.
+            let policy_route = svc::layers()
+                .lift_new()
+                .push(NewDistribute::layer())
+                .push_on_service(svc::LoadShed::layer())
+                .push(router::NewApplyFilters::layer());
+            let policy_router = svc::layers()
+                .lift_new()
+                .push(NewBackendCache::layer())
+                .push_on_service(policy_route)
+                .push(svc::NewOneshotRoute::<router::Router<T>, _, _>::layer_cached());
+            let policy = concrete
+                .clone()
+                .lift_new()
+                .push_on_service(policy_router)
+                .push(svc::NewSpawnWatch::<policy::ClientPolicy, _>::layer_into::<
+                    router::Router<T>,
+                >())
+                .push_switch(Ok::<_, Infallible>, concrete.clone().into_inner());
+
             // For each `Routable` target, watch its `Profile`, maintaining a
             // cache of all concrete services used by the router.
             concrete
-                .clone()
                 // Share the concrete stack with each router stack.
                 .lift_new()
                 // Rebuild this router stack every time the profile changes.
@@ -134,13 +170,23 @@ impl<N> Outbound<N> {
                                 parent,
                                 profile,
                             }),
-                            Logical::Forward(addr, meta) => svc::Either::B(Concrete {
-                                target: concrete::Dispatch::Forward(addr, meta),
-                                parent,
-                            }),
+                            Logical::Policy(addr, policy) => {
+                                svc::Either::B(svc::Either::A(router::Routable {
+                                    parent,
+                                    addr,
+                                    policy,
+                                }))
+                            }
+                            Logical::Forward(addr, meta) => {
+                                svc::Either::B(svc::Either::B(Concrete {
+                                    target: concrete::Dispatch::Forward(addr, meta),
+                                    failure_accrual: FailureAccrual::None,
+                                    parent,
+                                }))
+                            }
                         })
                     },
-                    concrete.into_inner(),
+                    policy.into_inner(),
                 )
                 .push(svc::ArcNewService::layer())
         })
@@ -171,6 +217,7 @@ where
         let (backends, distribution) = if profile.targets.is_empty() {
             let concrete = Concrete {
                 target: concrete::Dispatch::Balance(routable.addr, EWMA),
+                failure_accrual: FailureAccrual::None,
                 parent: routable.parent.clone(),
             };
             let backends = std::iter::once(concrete.clone()).collect();
@@ -182,6 +229,7 @@ where
                 .iter()
                 .map(|t| Concrete {
                     target: concrete::Dispatch::Balance(t.addr.clone(), EWMA),
+                    failure_accrual: FailureAccrual::None,
                     parent: routable.parent.clone(),
                 })
                 .collect();
@@ -189,6 +237,7 @@ where
                 |profiles::Target { addr, weight }| {
                     let concrete = Concrete {
                         target: concrete::Dispatch::Balance(addr, EWMA),
+                        failure_accrual: FailureAccrual::None,
                         parent: routable.parent.clone(),
                     };
                     (concrete, weight)
@@ -287,12 +336,19 @@ impl<T> svc::Param<concrete::Dispatch> for Concrete<T> {
     }
 }
 
+impl<T> svc::Param<FailureAccrual> for Concrete<T> {
+    fn param(&self) -> FailureAccrual {
+        self.failure_accrual
+    }
+}
+
 // === impl Logical ===
 
 impl std::cmp::PartialEq for Logical {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Route(laddr, _), Self::Route(raddr, _)) => laddr == raddr,
+            (Self::Policy(laddr, _), Self::Policy(raddr, _)) => laddr == raddr,
             (Self::Forward(laddr, lmeta), Self::Forward(raddr, rmeta)) => {
                 laddr == raddr && lmeta == rmeta
             }
@@ -309,6 +365,9 @@ impl std::hash::Hash for Logical {
             Self::Route(addr, _) => {
                 addr.hash(state);
             }
+            Self::Policy(addr, _) => {
+                addr.hash(state);
+            }
             Self::Forward(addr, meta) => {
                 addr.hash(state);
                 meta.hash(state);
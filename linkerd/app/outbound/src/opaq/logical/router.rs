@@ -0,0 +1,267 @@
+use super::{concrete, Concrete, Distribution};
+use crate::{policy, RouteRef};
+use futures::{future, TryFutureExt};
+use linkerd_app_core::{io, svc, transport::addrs::*, Error};
+use linkerd_distribute as distribute;
+use std::{
+    fmt::Debug,
+    hash::Hash,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::sync::watch;
+
+/// A target that routes connections with a client policy's opaque routes.
+#[derive(Clone, Debug)]
+pub(super) struct Routable<T> {
+    pub(super) parent: T,
+    pub(super) addr: Remote<ServerAddr>,
+    pub(super) policy: policy::Receiver,
+}
+
+/// Selects an opaque route for each connection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Router<T: Eq + Hash + Clone + Debug> {
+    parent: T,
+    addr: Remote<ServerAddr>,
+    routes: Arc<[policy::opaq::Route]>,
+    failure_accrual: policy::FailureAccrual,
+    backends: distribute::Backends<Concrete<T>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(super) struct RouteParams<T> {
+    parent: T,
+    route_ref: RouteRef,
+    filters: Arc<[policy::opaq::Filter]>,
+    distribution: Distribution<T>,
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct NewApplyFilters<N> {
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct ApplyFilters<S> {
+    filters: Arc<[policy::opaq::Filter]>,
+    inner: S,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("no route found for connection to {0}")]
+pub struct NoRoute(Remote<ServerAddr>);
+
+#[derive(Debug, thiserror::Error)]
+#[error("connection forbidden by route")]
+pub struct RouteForbidden(());
+
+#[derive(Debug, thiserror::Error)]
+#[error("injected failure: {0}")]
+pub struct InjectedFailure(Arc<str>);
+
+// === impl Routable ===
+
+impl<T> svc::Param<watch::Receiver<policy::ClientPolicy>> for Routable<T> {
+    fn param(&self) -> watch::Receiver<policy::ClientPolicy> {
+        self.policy.clone()
+    }
+}
+
+// === impl Router ===
+
+impl<T> From<(policy::ClientPolicy, Routable<T>)> for Router<T>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    fn from((policy, routable): (policy::ClientPolicy, Routable<T>)) -> Self {
+        let Routable { parent, addr, .. } = routable;
+
+        // Connections are only routed by policy when the policy has opaque
+        // routes. If the policy changes to another protocol, connections fail
+        // with a `NoRoute` error.
+        let routes = match policy.protocol {
+            policy::Protocol::Opaque(ref opaque) | policy::Protocol::Detect { ref opaque, .. } => {
+                opaque.routes.clone()
+            }
+            _ => Default::default(),
+        };
+        let failure_accrual = crate::opaq::failure_accrual(&policy);
+
+        let backends = policy
+            .backends
+            .iter()
+            .filter_map(concrete::Dispatch::from_backend)
+            .map(|target| Concrete {
+                target,
+                failure_accrual,
+                parent: parent.clone(),
+            })
+            .collect();
+
+        Self {
+            parent,
+            addr,
+            routes,
+            failure_accrual,
+            backends,
+        }
+    }
+}
+
+impl<T> Router<T>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    fn route_params(&self, route: &policy::opaq::Route) -> RouteParams<T> {
+        // Fail backends are omitted, since opaque connections cannot be
+        // failed with a message.
+        let mk_concrete = |bke: &policy::Backend| -> Option<Concrete<T>> {
+            Some(Concrete {
+                target: concrete::Dispatch::from_backend(bke)?,
+                failure_accrual: self.failure_accrual,
+                parent: self.parent.clone(),
+            })
+        };
+
+        let distribution = match route.policy.distribution {
+            policy::RouteDistribution::Empty => Distribution::Empty,
+            policy::RouteDistribution::FirstAvailable(ref backends) => {
+                Distribution::first_available(
+                    backends.iter().filter_map(|rb| mk_concrete(&rb.backend)),
+                )
+            }
+            policy::RouteDistribution::RandomAvailable(ref backends) => {
+                Distribution::random_available(
+                    backends
+                        .iter()
+                        .filter_map(|(rb, weight)| Some((mk_concrete(&rb.backend)?, *weight))),
+                )
+                .expect("distribution must be valid")
+            }
+        };
+
+        RouteParams {
+            parent: self.parent.clone(),
+            route_ref: RouteRef(route.policy.meta.clone()),
+            filters: route.policy.filters.clone(),
+            distribution,
+        }
+    }
+}
+
+impl<T> svc::Param<distribute::Backends<Concrete<T>>> for Router<T>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    fn param(&self) -> distribute::Backends<Concrete<T>> {
+        self.backends.clone()
+    }
+}
+
+impl<T, I> svc::router::SelectRoute<I> for Router<T>
+where
+    T: Eq + Hash + Clone + Debug,
+    I: io::PeerAddr,
+{
+    type Key = RouteParams<T>;
+    type Error = Error;
+
+    fn select(&self, io: &I) -> Result<Self::Key, Self::Error> {
+        let client = io.peer_addr()?;
+        let Remote(ServerAddr(dst)) = self.addr;
+        let route =
+            policy::opaq::find(&self.routes, dst, client.ip()).ok_or_else(|| NoRoute(self.addr))?;
+        tracing::debug!(route.meta = ?route.policy.meta, "Selected opaque route");
+        Ok(self.route_params(route))
+    }
+}
+
+// === impl RouteParams ===
+
+impl<T: Clone> svc::Param<Distribution<T>> for RouteParams<T> {
+    fn param(&self) -> Distribution<T> {
+        self.distribution.clone()
+    }
+}
+
+impl<T> svc::Param<RouteRef> for RouteParams<T> {
+    fn param(&self) -> RouteRef {
+        self.route_ref.clone()
+    }
+}
+
+impl<T> svc::Param<Arc<[policy::opaq::Filter]>> for RouteParams<T> {
+    fn param(&self) -> Arc<[policy::opaq::Filter]> {
+        self.filters.clone()
+    }
+}
+
+// === impl NewApplyFilters ===
+
+impl<N> NewApplyFilters<N> {
+    pub(super) fn layer() -> impl svc::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewApplyFilters<N>
+where
+    T: svc::Param<Arc<[policy::opaq::Filter]>>,
+    N: svc::NewService<T>,
+{
+    type Service = ApplyFilters<N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        ApplyFilters {
+            filters: target.param(),
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl ApplyFilters ===
+
+impl<S> ApplyFilters<S> {
+    fn apply(&self) -> Result<(), Error> {
+        for filter in &*self.filters {
+            match filter {
+                policy::opaq::Filter::Forbidden => return Err(RouteForbidden(()).into()),
+                policy::opaq::Filter::InjectFailure(fail) => {
+                    if let Some(message) = fail.apply() {
+                        return Err(InjectedFailure(message).into());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<I, S> svc::Service<I> for ApplyFilters<S>
+where
+    S: svc::Service<I>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::Ready<Result<S::Response, Error>>,
+        future::ErrInto<S::Future, Error>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, io: I) -> Self::Future {
+        if let Err(error) = self.apply() {
+            tracing::info!(%error, "Connection rejected by route filter");
+            return future::Either::Left(future::err(error));
+        }
+
+        future::Either::Right(self.inner.call(io).err_into::<Error>())
+    }
+}
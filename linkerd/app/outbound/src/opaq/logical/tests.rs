@@ -1,5 +1,5 @@
 use super::*;
-use crate::test_util::*;
+use crate::{tcp::proxy_header::SendProxyHeader, test_util::*};
 use io::AsyncWriteExt;
 use linkerd_app_core::{
     errors::{self, FailFastError},
@@ -7,7 +7,7 @@ use linkerd_app_core::{
     profiles::{self, Profile},
     svc::{self, NewService, ServiceExt},
 };
-use std::net::SocketAddr;
+use std::{net::SocketAddr, sync::Arc};
 use tokio::time;
 
 /// Tests that the logical stack forwards connections to services with a single endpoint.
@@ -189,6 +189,254 @@ async fn balances() {
     assert!(resolved.only_configured(), "Resolution must be reused");
 }
 
+/// Tests that connections routed by a client policy select a route by
+/// destination port and apply the route's filters.
+#[tokio::test]
+async fn policy_routes() {
+    let _trace = linkerd_tracing::test::trace_init();
+    time::pause();
+
+    let ep_addr = SocketAddr::new([192, 0, 2, 30].into(), 3333);
+    let backend = policy::Backend {
+        meta: policy::Meta::new_default("test"),
+        queue: policy::Queue {
+            capacity: 10,
+            failfast_timeout: time::Duration::from_secs(3),
+        },
+        dispatcher: policy::BackendDispatcher::Forward(ep_addr, Default::default()),
+    };
+    let mk_route = |name: &'static str,
+                    matches: Vec<policy::opaq::MatchConnection>,
+                    filters: Arc<[policy::opaq::Filter]>| policy::opaq::Route {
+        matches,
+        policy: policy::opaq::Policy {
+            meta: policy::Meta::new_default(name),
+            filters,
+            failure_policy: Default::default(),
+            timeouts: Default::default(),
+            retry: None,
+            hedge: None,
+            distribution: policy::RouteDistribution::FirstAvailable(Arc::new([
+                policy::RouteBackend {
+                    filters: Arc::new([]),
+                    backend: backend.clone(),
+                    timeouts: Default::default(),
+                },
+            ])),
+        },
+    };
+    // Connections to port 4444 are forbidden. All other connections are
+    // forwarded to the backend.
+    let routes = Arc::new([
+        mk_route("default", vec![], Arc::new([])),
+        mk_route(
+            "forbidden",
+            vec![policy::opaq::MatchConnection {
+                ports: Some(policy::opaq::PortRange {
+                    min: 4444,
+                    max: 4444,
+                }),
+                client_networks: vec![],
+            }],
+            Arc::new([policy::opaq::Filter::Forbidden]),
+        ),
+    ]);
+    let (_tx, rx) = tokio::sync::watch::channel(policy::ClientPolicy {
+        parent: policy::Meta::new_default("parent"),
+        protocol: policy::Protocol::Opaque(policy::opaq::Opaque {
+            routes,
+            failure_accrual: Default::default(),
+        }),
+        backends: Arc::new([backend.clone()]),
+    });
+
+    // Build the TCP logical stack with a mocked connector.
+    let (rt, _shutdown) = runtime();
+    let stack = Outbound::new(default_config(), rt)
+        .with_stack(svc::mk(move |ep: concrete::Endpoint<Concrete<Logical>>| {
+            let Remote(ServerAddr(ea)) = svc::Param::param(&ep);
+            assert_eq!(ea, ep_addr);
+            let mut io = support::io();
+            io.write(b"who r u?").read(b"ep0");
+            let local = Local(ClientAddr(([0, 0, 0, 0], 4444).into()));
+            future::ok::<_, support::io::Error>((io.build(), local))
+        }))
+        .push_opaq_concrete(support::resolver())
+        .push_opaq_logical()
+        .into_inner();
+
+    // Connections that match the forbidden route are refused.
+    let forbidden = Logical::Policy(
+        Remote(ServerAddr(([192, 0, 2, 10], 4444).into())),
+        rx.clone(),
+    );
+    let (io, task) = spawn_io();
+    let err = stack
+        .new_service(forbidden)
+        .oneshot(io)
+        .await
+        .expect_err("forbidden route must fail");
+    task.abort();
+    assert!(
+        errors::is_caused_by::<router::RouteForbidden>(&*err),
+        "unexpected error: {}",
+        err
+    );
+
+    // Other connections are forwarded to the backend.
+    let allowed = Logical::Policy(Remote(ServerAddr(([192, 0, 2, 10], 5555).into())), rx);
+    let (io, task) = spawn_io();
+    stack
+        .new_service(allowed)
+        .oneshot(io)
+        .await
+        .expect("forwarding must not fail");
+    let msg = task.await.unwrap().unwrap();
+    assert_eq!(msg, "ep0");
+}
+
+/// Tests that connections to the forwarded endpoints of backends that are
+/// configured to receive PROXY protocol headers are marked to receive one.
+#[tokio::test]
+async fn forward_proxy_header() {
+    let _trace = linkerd_tracing::test::trace_init();
+    time::pause();
+
+    let ep_addr = SocketAddr::new([192, 0, 2, 30].into(), 3306);
+    let backend = policy::Backend {
+        meta: Arc::new(policy::Meta::Resource {
+            group: "core".to_string(),
+            kind: "Service".to_string(),
+            namespace: "db".to_string(),
+            name: "mysql".to_string(),
+            section: None,
+            port: std::num::NonZeroU16::new(3306),
+        }),
+        queue: policy::Queue {
+            capacity: 10,
+            failfast_timeout: time::Duration::from_secs(3),
+        },
+        dispatcher: policy::BackendDispatcher::Forward(ep_addr, Metadata::default()),
+    };
+    let config = crate::Config {
+        local_policy: proxy_protocol_backends(),
+        ..default_config()
+    };
+    // Local configuration is applied to the policy as it is discovered.
+    let (_tx, rx) = tokio::sync::watch::channel(config.local_policy.apply(policy::ClientPolicy {
+        parent: policy::Meta::new_default("parent"),
+        protocol: policy::Protocol::Opaque(policy::opaq::Opaque {
+            routes: Arc::new([policy::opaq::Route {
+                matches: vec![],
+                policy: policy::opaq::Policy {
+                    meta: policy::Meta::new_default("default"),
+                    filters: Arc::new([]),
+                    failure_policy: Default::default(),
+                    timeouts: Default::default(),
+                    retry: None,
+                    hedge: None,
+                    distribution: policy::RouteDistribution::FirstAvailable(Arc::new([
+                        policy::RouteBackend {
+                            filters: Arc::new([]),
+                            backend: backend.clone(),
+                            timeouts: Default::default(),
+                        },
+                    ])),
+                },
+            }]),
+            failure_accrual: Default::default(),
+        }),
+        backends: Arc::new([backend]),
+    }));
+
+    let (rt, _shutdown) = runtime();
+    let stack = Outbound::new(config, rt)
+        .with_stack(svc::mk(move |ep: concrete::Endpoint<Concrete<Logical>>| {
+            let Remote(ServerAddr(ea)) = svc::Param::param(&ep);
+            assert_eq!(ea, ep_addr);
+            let SendProxyHeader(send) = svc::Param::param(&ep);
+            assert!(send, "endpoint must receive a PROXY protocol header");
+            let mut io = support::io();
+            io.write(b"who r u?").read(b"ep0");
+            let local = Local(ClientAddr(([0, 0, 0, 0], 4444).into()));
+            future::ok::<_, support::io::Error>((io.build(), local))
+        }))
+        .push_opaq_concrete(support::resolver())
+        .push_opaq_logical()
+        .into_inner();
+
+    let logical = Logical::Policy(Remote(ServerAddr(([192, 0, 2, 10], 5555).into())), rx);
+    let (io, task) = spawn_io();
+    stack
+        .new_service(logical)
+        .oneshot(io)
+        .await
+        .expect("forwarding must not fail");
+    let msg = task.await.unwrap().unwrap();
+    assert_eq!(msg, "ep0");
+}
+
+/// Tests that connections to the balanced, non-meshed endpoints of backends
+/// that are configured to receive PROXY protocol headers are marked to receive
+/// one, while other backends' endpoints are not.
+#[tokio::test]
+async fn balance_proxy_header() {
+    let _trace = linkerd_tracing::test::trace_init();
+    time::pause();
+
+    for (name, expected) in [
+        ("mysql.db.svc.cluster.local:3306", true),
+        ("xyz.example.com:3306", false),
+    ] {
+        let laddr = name.parse::<NameAddr>().unwrap();
+        let (_tx, rx) = tokio::sync::watch::channel(Profile {
+            addr: Some(profiles::LogicalAddr(laddr.clone())),
+            ..Default::default()
+        });
+        let logical = Logical::Route(laddr.clone(), rx.into());
+
+        let ep_addr = SocketAddr::new([192, 0, 2, 30].into(), 3306);
+        let resolve = support::resolver().endpoint_exists(laddr, ep_addr, Default::default());
+
+        let (rt, _shutdown) = runtime();
+        let config = crate::Config {
+            local_policy: proxy_protocol_backends(),
+            ..default_config()
+        };
+        let stack = Outbound::new(config, rt)
+            .with_stack(svc::mk(move |ep: concrete::Endpoint<Concrete<Logical>>| {
+                let SendProxyHeader(send) = svc::Param::param(&ep);
+                assert_eq!(send, expected, "{name}");
+                let mut io = support::io();
+                io.write(b"hola").read(b"mundo");
+                let local = Local(ClientAddr(([0, 0, 0, 0], 4444).into()));
+                future::ok::<_, support::io::Error>((io.build(), local))
+            }))
+            .push_opaq_concrete(resolve)
+            .push_opaq_logical()
+            .into_inner();
+
+        let mut io = support::io();
+        io.read(b"hola").write(b"mundo");
+        stack
+            .new_service(logical)
+            .oneshot(io.build())
+            .await
+            .expect("forwarding must not fail");
+    }
+}
+
+fn proxy_protocol_backends() -> policy::LocalConfig {
+    policy::LocalConfig {
+        proxy_protocol_backends: vec![policy::ParentService {
+            namespace: "db".to_string(),
+            name: "mysql".to_string(),
+            port: std::num::NonZeroU16::new(3306).unwrap(),
+        }],
+        ..Default::default()
+    }
+}
+
 /// Balancer test helper that runs client I/O on a task.
 fn spawn_io() -> (
     io::DuplexStream,
@@ -204,3 +452,60 @@ fn spawn_io() -> (
     });
     (server_io, task)
 }
+
+/// Tests that policy-routed backends accrue failures as configured by each
+/// update of the client policy.
+#[test]
+fn policy_backends_accrue_failures() {
+    let backend = policy::Backend {
+        meta: policy::Meta::new_default("test"),
+        queue: policy::Queue {
+            capacity: 10,
+            failfast_timeout: time::Duration::from_secs(3),
+        },
+        dispatcher: policy::BackendDispatcher::Forward(
+            SocketAddr::new([192, 0, 2, 30].into(), 3333),
+            Default::default(),
+        ),
+    };
+    let mk_policy = |failure_accrual: FailureAccrual| policy::ClientPolicy {
+        parent: policy::Meta::new_default("parent"),
+        protocol: policy::Protocol::Opaque(policy::opaq::Opaque {
+            routes: Arc::new([]),
+            failure_accrual,
+        }),
+        backends: Arc::new([backend.clone()]),
+    };
+
+    let (_tx, rx) = watch::channel(mk_policy(FailureAccrual::None));
+    let addr = Remote(ServerAddr(SocketAddr::new([192, 0, 2, 3].into(), 4444)));
+    let logical = Logical::Policy(addr, rx.clone());
+    let routable = router::Routable {
+        parent: logical.clone(),
+        addr,
+        policy: rx,
+    };
+
+    let backends = |failure_accrual: FailureAccrual| {
+        let router = router::Router::from((mk_policy(failure_accrual), routable.clone()));
+        svc::Param::<distribute::Backends<Concrete<Logical>>>::param(&router)
+    };
+    let expected = |failure_accrual: FailureAccrual| {
+        std::iter::once(Concrete {
+            target: concrete::Dispatch::from_backend(&backend).unwrap(),
+            failure_accrual,
+            parent: logical.clone(),
+        })
+        .collect::<distribute::Backends<_>>()
+    };
+
+    let consecutive = FailureAccrual::ConsecutiveFailures {
+        max_failures: 3,
+        backoff: Default::default(),
+    };
+    assert_eq!(
+        backends(FailureAccrual::None),
+        expected(FailureAccrual::None)
+    );
+    assert_eq!(backends(consecutive), expected(consecutive));
+}
@@ -7,8 +7,14 @@ use std::future::Future;
 use tokio::sync::watch;
 
 mod api;
+mod local;
 
 pub(crate) use self::api::Api;
+pub use self::local::{
+    FailureAccrualConfig, GrpcResponseHeadersConfig, HedgeConfig, LoadConfig, LocalConfig,
+    MirrorConfig, OpaqueFilterConfig, ParentService, RetryConfig, RouteScope, TimeoutsConfig,
+    TlsRouteConfig, UrlRewriteConfig,
+};
 
 pub type Receiver = watch::Receiver<ClientPolicy>;
 
@@ -1,3 +1,4 @@
+use super::{ClientPolicy, LocalConfig};
 use futures::prelude::*;
 use linkerd2_proxy_api::outbound::{
     self as api, outbound_policies_client::OutboundPoliciesClient as Client,
@@ -8,7 +9,6 @@ use linkerd_app_core::{
     svc::Service,
     Addr, Error, Recover, Result,
 };
-use linkerd_proxy_client_policy::ClientPolicy;
 use linkerd_tonic_watch::StreamWatch;
 use std::{sync::Arc, time};
 
@@ -16,6 +16,7 @@ use std::{sync::Arc, time};
 pub(crate) struct Api<S> {
     workload: Arc<str>,
     detect_timeout: time::Duration,
+    local: Arc<LocalConfig>,
     client: Client<S>,
 }
 
@@ -33,10 +34,16 @@ where
     S::ResponseBody:
         http::HttpBody<Data = tonic::codegen::Bytes, Error = Error> + Default + Send + 'static,
 {
-    pub(crate) fn new(workload: Arc<str>, detect_timeout: time::Duration, client: S) -> Self {
+    pub(crate) fn new(
+        workload: Arc<str>,
+        detect_timeout: time::Duration,
+        local: LocalConfig,
+        client: S,
+    ) -> Self {
         Self {
             workload,
             detect_timeout,
+            local: Arc::new(local),
             client: Client::new(client),
         }
     }
@@ -79,6 +86,7 @@ where
             }
         };
         let detect_timeout = self.detect_timeout;
+        let local = self.local.clone();
         let mut client = self.client.clone();
         Box::pin(async move {
             let rsp = client.watch(tonic::Request::new(req)).await?;
@@ -88,12 +96,15 @@ where
                         // If the server returned an invalid client policy, we
                         // default to using an invalid policy that causes all
                         // requests to report an internal error.
-                        let policy = ClientPolicy::try_from(up).unwrap_or_else(|error| {
-                            tracing::warn!(%error, "Client policy misconfigured");
-                            INVALID_POLICY
-                                .get_or_init(|| ClientPolicy::invalid(detect_timeout))
-                                .clone()
-                        });
+                        let policy = match ClientPolicy::try_from(up) {
+                            Ok(policy) => local.apply(policy),
+                            Err(error) => {
+                                tracing::warn!(%error, "Client policy misconfigured");
+                                INVALID_POLICY
+                                    .get_or_init(|| ClientPolicy::invalid(detect_timeout))
+                                    .clone()
+                            }
+                        };
                         tracing::debug!(?policy);
                         policy
                     })
@@ -0,0 +1,1271 @@
+use linkerd_app_core::{exp_backoff::ExponentialBackoff, NameAddr};
+use linkerd_proxy_client_policy::{
+    grpc, http, opaq, route::MatchHost, tls, Backend, BackendDispatcher, ClientPolicy,
+    FailureAccrual, Load, Meta, Protocol, RetryBudget, RouteBackend, RouteDistribution, RouteHedge,
+    RoutePolicy, RouteRetry, RouteTimeouts,
+};
+use std::{num::NonZeroU16, sync::Arc, time};
+
+/// Configures client policy features that the policy API cannot yet express.
+///
+/// This configuration is applied to every policy discovered from the policy
+/// API. Each feature is disabled unless it is configured, and applies only to
+/// the parent services, routes, or backends that it names.
+///
+/// The policy API always takes precedence: local configuration only sets
+/// values that a policy leaves unset (retries, hedging, timeouts, and failure
+/// accrual) or adds to a policy's routes and filters (mirrors, URL rewrites,
+/// response headers, TLS routes, and opaque filters). Balancer loads are the
+/// exception, since the policy API can only configure peak EWMA: a configured
+/// backend's load replaces it.
+#[derive(Clone, Debug, Default)]
+pub struct LocalConfig {
+    /// Retries failed requests on the configured HTTP and gRPC routes, unless
+    /// they configure retries.
+    pub retries: Vec<RetryConfig>,
+
+    /// Hedges slow requests on the configured HTTP and gRPC routes, unless
+    /// they configure hedging.
+    pub hedges: Vec<HedgeConfig>,
+
+    /// Bounds requests on the configured HTTP and gRPC routes that do not
+    /// configure each timeout.
+    pub timeouts: Vec<TimeoutsConfig>,
+
+    /// Overrides the load balancing strategy of the configured backends.
+    ///
+    /// Only HTTP and gRPC balancers honor this; opaque balancers are always
+    /// balanced by peak EWMA.
+    pub loads: Vec<LoadConfig>,
+
+    /// Configures failure accrual for the configured parent services'
+    /// policies that do not configure it.
+    pub failure_accrual: Vec<FailureAccrualConfig>,
+
+    /// Mirrors requests on the HTTP routes of the configured parent services.
+    pub mirrors: Vec<MirrorConfig>,
+
+    /// Rewrites requests on the HTTP routes of the configured parent services.
+    pub url_rewrites: Vec<UrlRewriteConfig>,
+
+    /// Modifies response metadata on the gRPC routes of the configured parent
+    /// services.
+    pub grpc_response_headers: Vec<GrpcResponseHeadersConfig>,
+
+    /// Routes the connections of the configured parent services by the server
+    /// name in their TLS ClientHello.
+    pub tls_routes: Vec<TlsRouteConfig>,
+
+    /// Filters the opaque connections of the configured parent services.
+    pub opaque_filters: Vec<OpaqueFilterConfig>,
+
+    /// Prefaces connections to the non-meshed endpoints of the configured
+    /// backend services with a PROXY protocol header.
+    ///
+    /// Balancers are matched by the backend service's cluster-local DNS name,
+    /// so this also applies to the backends of service profiles.
+    pub proxy_protocol_backends: Vec<ParentService>,
+}
+
+/// Configures retries for the routes in `routes`, unless they otherwise
+/// configure them.
+///
+/// A request is retried when its response matches the route's retry
+/// conditions or, if none are configured for the route's protocol, when the
+/// route's failure policy classifies the response as a failure.
+#[derive(Clone, Debug)]
+pub struct RetryConfig {
+    pub routes: RouteScope,
+    pub http_conditions: Option<http::StatusRanges>,
+    pub grpc_conditions: Option<grpc::Codes>,
+    pub max_retries: usize,
+    pub max_request_bytes: usize,
+    pub timeout: Option<time::Duration>,
+    pub backoff: Option<ExponentialBackoff>,
+    pub budget: RetryBudget,
+}
+
+/// Configures hedging for the routes in `routes`, unless they otherwise
+/// configure it.
+#[derive(Clone, Debug)]
+pub struct HedgeConfig {
+    pub routes: RouteScope,
+    pub hedge: RouteHedge,
+}
+
+/// Bounds requests on the routes in `routes` by each of `timeouts` that the
+/// route does not configure.
+#[derive(Clone, Debug)]
+pub struct TimeoutsConfig {
+    pub routes: RouteScope,
+    pub timeouts: RouteTimeouts,
+}
+
+/// Configures failure accrual for a parent service's policies, unless they
+/// configure it.
+#[derive(Clone, Debug)]
+pub struct FailureAccrualConfig {
+    pub parent: ParentService,
+    pub accrual: FailureAccrual,
+}
+
+/// Identifies the parent service of the policies that local configuration
+/// applies to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParentService {
+    pub namespace: String,
+    pub name: String,
+    pub port: NonZeroU16,
+}
+
+/// Identifies the routes that local configuration applies to: all of a parent
+/// service's routes or, if `route` is set, only its routes with that name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RouteScope {
+    pub parent: ParentService,
+    pub route: Option<String>,
+}
+
+/// Balances a backend service's endpoints by `load` instead of the peak EWMA
+/// strategy that the policy API configures.
+///
+/// Backends are identified by their service, like parent services.
+#[derive(Clone, Debug)]
+pub struct LoadConfig {
+    pub backend: ParentService,
+    pub load: Load,
+}
+
+/// Mirrors a sample of the requests on a parent service's HTTP routes.
+///
+/// gRPC routes are not mirrored.
+#[derive(Clone, Debug)]
+pub struct MirrorConfig {
+    pub parent: ParentService,
+    pub mirror: http::RequestMirror,
+}
+
+/// Rewrites requests on a parent service's HTTP route rules that only match
+/// `prefix`.
+///
+/// The rewrite may replace the matched prefix, since these rules always match
+/// a path prefix.
+#[derive(Clone, Debug)]
+pub struct UrlRewriteConfig {
+    pub parent: ParentService,
+    pub prefix: String,
+    pub rewrite: http::filter::UrlRewrite,
+}
+
+/// Modifies the response metadata on a parent service's gRPC routes.
+#[derive(Clone, Debug)]
+pub struct GrpcResponseHeadersConfig {
+    pub parent: ParentService,
+    pub headers: grpc::filter::ModifyHeader,
+}
+
+/// Routes a parent service's connections that indicate a matching server name
+/// to `backend`, without terminating TLS.
+///
+/// Once a parent service has a TLS route, its connections are no longer
+/// inspected for HTTP. Connections that do not match any of the parent's TLS
+/// routes are routed by the parent's opaque routes that apply to all
+/// connections.
+#[derive(Clone, Debug)]
+pub struct TlsRouteConfig {
+    pub parent: ParentService,
+    pub sni: MatchHost,
+    pub backend: Backend,
+}
+
+/// Applies `filter` to a parent service's opaque connections that match
+/// `matches`.
+///
+/// The filter is applied on a route that otherwise behaves like the parent's
+/// opaque route that applies to all connections. Parents without such a route
+/// are not filtered.
+#[derive(Clone, Debug)]
+pub struct OpaqueFilterConfig {
+    pub parent: ParentService,
+    pub matches: opaq::MatchConnection,
+    pub filter: opaq::Filter,
+}
+
+// === impl LocalConfig ===
+
+impl LocalConfig {
+    pub(crate) fn apply(&self, policy: ClientPolicy) -> ClientPolicy {
+        let ClientPolicy {
+            parent,
+            protocol,
+            backends,
+        } = policy;
+
+        let tls_routes = self
+            .tls_routes
+            .iter()
+            .filter(|r| r.parent.matches(&parent))
+            .collect::<Vec<_>>();
+        let mut backends = backends.to_vec();
+
+        let protocol = match protocol {
+            Protocol::Detect { opaque, .. } | Protocol::Opaque(opaque)
+                if !tls_routes.is_empty() =>
+            {
+                for route in &tls_routes {
+                    if !backends.contains(&route.backend) {
+                        backends.push(route.backend.clone());
+                    }
+                }
+                Protocol::Tls(self.tls(route_tls(opaque, &tls_routes), &parent))
+            }
+            Protocol::Detect {
+                timeout,
+                http1,
+                http2,
+                opaque,
+            } => Protocol::Detect {
+                timeout,
+                http1: self.http1(http1, &parent),
+                http2: self.http2(http2, &parent),
+                opaque: self.opaque(opaque, &parent),
+            },
+            Protocol::Http1(http1) => Protocol::Http1(self.http1(http1, &parent)),
+            Protocol::Http2(http2) => Protocol::Http2(self.http2(http2, &parent)),
+            Protocol::Grpc(grpc) => Protocol::Grpc(self.grpc(grpc, &parent)),
+            Protocol::Opaque(opaque) => Protocol::Opaque(self.opaque(opaque, &parent)),
+            Protocol::Tls(tls) => Protocol::Tls(self.tls(tls, &parent)),
+        };
+
+        let backends = backends
+            .into_iter()
+            .map(|mut backend| {
+                self.backend(&mut backend);
+                backend
+            })
+            .collect();
+
+        ClientPolicy {
+            parent,
+            protocol,
+            backends,
+        }
+    }
+
+    fn http1(&self, http1: http::Http1, parent: &Meta) -> http::Http1 {
+        http::Http1 {
+            routes: self.http_routes(&http1.routes, parent),
+            failure_accrual: self.failure_accrual(http1.failure_accrual, parent),
+        }
+    }
+
+    fn http2(&self, http2: http::Http2, parent: &Meta) -> http::Http2 {
+        http::Http2 {
+            routes: self.http_routes(&http2.routes, parent),
+            failure_accrual: self.failure_accrual(http2.failure_accrual, parent),
+        }
+    }
+
+    fn http_routes(&self, routes: &[http::Route], parent: &Meta) -> Arc<[http::Route]> {
+        let mirrors = self
+            .mirrors
+            .iter()
+            .filter(|m| m.parent.matches(parent))
+            .map(|m| &m.mirror)
+            .collect::<Vec<_>>();
+        let rewrites = self
+            .url_rewrites
+            .iter()
+            .filter(|r| r.parent.matches(parent))
+            .collect::<Vec<_>>();
+
+        map_routes(routes, |route| {
+            for rule in route.rules.iter_mut() {
+                let retry = self.retry(parent, &rule.policy.meta).map(|config| {
+                    let conditions = config.http_conditions.as_ref();
+                    config.route_retry(conditions.unwrap_or(&rule.policy.failure_policy).clone())
+                });
+                self.route_policy(&mut rule.policy, parent, retry);
+                http_mirrors(&mut rule.policy.filters, &mirrors);
+                http_url_rewrite(rule, &rewrites);
+            }
+        })
+    }
+
+    fn grpc(&self, grpc: grpc::Grpc, parent: &Meta) -> grpc::Grpc {
+        let response_headers = self
+            .grpc_response_headers
+            .iter()
+            .filter(|h| h.parent.matches(parent))
+            .map(|h| &h.headers)
+            .collect::<Vec<_>>();
+
+        let routes = map_routes(&grpc.routes, |route| {
+            for rule in route.rules.iter_mut() {
+                let retry = self.retry(parent, &rule.policy.meta).map(|config| {
+                    let conditions = config.grpc_conditions.as_ref();
+                    config.route_retry(conditions.unwrap_or(&rule.policy.failure_policy).clone())
+                });
+                self.route_policy(&mut rule.policy, parent, retry);
+                grpc_response_headers(&mut rule.policy.filters, &response_headers);
+            }
+        });
+        grpc::Grpc {
+            routes,
+            failure_accrual: self.failure_accrual(grpc.failure_accrual, parent),
+        }
+    }
+
+    fn opaque(&self, opaque: opaq::Opaque, parent: &Meta) -> opaq::Opaque {
+        let filters = self
+            .opaque_filters
+            .iter()
+            .filter(|f| f.parent.matches(parent))
+            .collect::<Vec<_>>();
+
+        let routes = map_routes(&opaque_filters(&opaque.routes, &filters), |route| {
+            self.distribution(&mut route.policy.distribution);
+        });
+        opaq::Opaque {
+            routes,
+            failure_accrual: self.failure_accrual(opaque.failure_accrual, parent),
+        }
+    }
+
+    fn tls(&self, tls: tls::Tls, parent: &Meta) -> tls::Tls {
+        let routes = map_routes(&tls.routes, |route| {
+            self.distribution(&mut route.policy.distribution);
+        });
+        tls::Tls {
+            routes,
+            failure_accrual: self.failure_accrual(tls.failure_accrual, parent),
+        }
+    }
+
+    fn failure_accrual(&self, accrual: FailureAccrual, parent: &Meta) -> FailureAccrual {
+        if accrual != FailureAccrual::None {
+            return accrual;
+        }
+        self.failure_accrual
+            .iter()
+            .find(|config| config.parent.matches(parent))
+            .map_or(accrual, |config| config.accrual)
+    }
+
+    /// Returns the retry configuration for a parent's route, if any.
+    fn retry(&self, parent: &Meta, route: &Meta) -> Option<&RetryConfig> {
+        self.retries
+            .iter()
+            .find(|config| config.routes.matches(parent, route))
+    }
+
+    /// Configures an HTTP or gRPC route.
+    fn route_policy<T: Clone, F>(
+        &self,
+        policy: &mut RoutePolicy<T, F>,
+        parent: &Meta,
+        retry: Option<RouteRetry<F>>,
+    ) {
+        if policy.retry.is_none() {
+            policy.retry = retry;
+        }
+        if policy.hedge.is_none() {
+            policy.hedge = self
+                .hedges
+                .iter()
+                .find(|config| config.routes.matches(parent, &policy.meta))
+                .map(|config| config.hedge.clone());
+        }
+        let RouteTimeouts {
+            response,
+            request,
+            idle,
+        } = self
+            .timeouts
+            .iter()
+            .find(|config| config.routes.matches(parent, &policy.meta))
+            .map(|config| config.timeouts)
+            .unwrap_or_default();
+        policy.timeouts.response = policy.timeouts.response.or(response);
+        policy.timeouts.request = policy.timeouts.request.or(request);
+        policy.timeouts.idle = policy.timeouts.idle.or(idle);
+        self.distribution(&mut policy.distribution);
+    }
+
+    fn distribution<T: Clone>(&self, distribution: &mut RouteDistribution<T>) {
+        match distribution {
+            RouteDistribution::Empty => {}
+            RouteDistribution::FirstAvailable(backends) => {
+                *backends = backends
+                    .iter()
+                    .cloned()
+                    .map(|mut rb| {
+                        self.backend(&mut rb.backend);
+                        rb
+                    })
+                    .collect();
+            }
+            RouteDistribution::RandomAvailable(backends) => {
+                *backends = backends
+                    .iter()
+                    .cloned()
+                    .map(|(mut rb, weight)| {
+                        self.backend(&mut rb.backend);
+                        (rb, weight)
+                    })
+                    .collect();
+            }
+        }
+    }
+
+    /// Returns true if the endpoints discovered for the balancer of `addr`
+    /// expect connections to begin with a PROXY protocol header.
+    pub(crate) fn sends_proxy_header(&self, addr: &NameAddr) -> bool {
+        self.proxy_protocol_backends
+            .iter()
+            .any(|backend| backend.matches_addr(addr))
+    }
+
+    fn backend(&self, backend: &mut Backend) {
+        let load = self
+            .loads
+            .iter()
+            .find(|config| config.backend.matches(&backend.meta));
+        if let Some(LoadConfig { load, .. }) = load {
+            if let BackendDispatcher::BalanceP2c(ref mut l, _) = backend.dispatcher {
+                *l = load.clone();
+            }
+        }
+
+        // Balanced endpoints are marked as they are discovered.
+        if let BackendDispatcher::Forward(_, ref mut metadata) = backend.dispatcher {
+            if self
+                .proxy_protocol_backends
+                .iter()
+                .any(|config| config.matches(&backend.meta))
+            {
+                *metadata = metadata.clone().with_proxy_protocol(true);
+            }
+        }
+    }
+}
+
+/// Adds each mirror to a route's filters, unless the route already mirrors
+/// requests to its backend.
+fn http_mirrors(filters: &mut Arc<[http::Filter]>, mirrors: &[&http::RequestMirror]) {
+    let missing = mirrors
+        .iter()
+        .filter(|mirror| {
+            !filters
+                .iter()
+                .any(|f| matches!(f, http::Filter::RequestMirror(m) if m.backend == mirror.backend))
+        })
+        .map(|mirror| http::Filter::RequestMirror((*mirror).clone()))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        *filters = filters.iter().cloned().chain(missing).collect();
+    }
+}
+
+/// Adds the first matching rewrite to a rule, unless it already rewrites
+/// requests.
+fn http_url_rewrite(rule: &mut http::Rule, rewrites: &[&UrlRewriteConfig]) {
+    if rule
+        .policy
+        .filters
+        .iter()
+        .any(|f| matches!(f, http::Filter::UrlRewrite(_)))
+    {
+        return;
+    }
+    if let Some(config) = rewrites.iter().find(|r| r.matches(&rule.matches)) {
+        let rewrite = http::Filter::UrlRewrite(config.rewrite.clone());
+        rule.policy.filters = rule
+            .policy
+            .filters
+            .iter()
+            .cloned()
+            .chain(Some(rewrite))
+            .collect();
+    }
+}
+
+/// Adds each response metadata modification to a route's filters, unless the
+/// route already applies it.
+fn grpc_response_headers(
+    filters: &mut Arc<[grpc::Filter]>,
+    modifications: &[&grpc::filter::ModifyHeader],
+) {
+    let missing = modifications
+        .iter()
+        .filter(|modify| {
+            !filters
+                .iter()
+                .any(|f| matches!(f, grpc::Filter::ResponseHeaders(m) if m == **modify))
+        })
+        .map(|modify| grpc::Filter::ResponseHeaders((*modify).clone()))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        *filters = filters.iter().cloned().chain(missing).collect();
+    }
+}
+
+/// Adds a filtered copy of the opaque route that applies to all connections
+/// for each filter, unless the routes already apply the filter to its matches.
+///
+/// Filtered routes precede the existing routes, so that a filter without
+/// matches replaces the route that applies to all connections.
+fn opaque_filters(
+    routes: &Arc<[opaq::Route]>,
+    configs: &[&OpaqueFilterConfig],
+) -> Arc<[opaq::Route]> {
+    let fallback = match routes.iter().find(|route| route.matches.is_empty()) {
+        Some(route) => route,
+        None => return routes.clone(),
+    };
+
+    let filtered = configs
+        .iter()
+        .filter_map(|config| {
+            // A match that applies to all connections is expressed by a route
+            // without matches.
+            let matches = if config.matches == opaq::MatchConnection::default() {
+                vec![]
+            } else {
+                vec![config.matches.clone()]
+            };
+            if routes.iter().any(|route| {
+                route.matches == matches && route.policy.filters.contains(&config.filter)
+            }) {
+                return None;
+            }
+            let mut route = fallback.clone();
+            route.matches = matches;
+            route.policy.filters = Arc::new([config.filter.clone()]);
+            Some(route)
+        })
+        .collect::<Vec<_>>();
+    if filtered.is_empty() {
+        return routes.clone();
+    }
+    filtered.into_iter().chain(routes.iter().cloned()).collect()
+}
+
+/// Routes connections by SNI to each route's backend, falling back to the
+/// opaque routes that apply to all connections.
+fn route_tls(opaque: opaq::Opaque, configs: &[&TlsRouteConfig]) -> tls::Tls {
+    let routes = configs.iter().map(|config| tls::Route {
+        snis: vec![config.sni.clone()],
+        policy: tls::Policy {
+            meta: Meta::new_default("tls"),
+            filters: Arc::new([]),
+            distribution: RouteDistribution::FirstAvailable(Arc::new([RouteBackend {
+                filters: Arc::new([]),
+                backend: config.backend.clone(),
+                timeouts: RouteTimeouts::default(),
+            }])),
+            timeouts: RouteTimeouts::default(),
+            failure_policy: opaq::NonIoErrors,
+            retry: None,
+            hedge: None,
+        },
+    });
+    let fallbacks = opaque
+        .routes
+        .iter()
+        .filter(|route| route.matches.is_empty())
+        .map(|route| tls::Route {
+            snis: vec![],
+            policy: route.policy.clone(),
+        });
+    tls::Tls {
+        routes: routes.chain(fallbacks).collect(),
+        failure_accrual: opaque.failure_accrual,
+    }
+}
+
+fn map_routes<R: Clone>(routes: &[R], f: impl Fn(&mut R)) -> Arc<[R]> {
+    routes
+        .iter()
+        .cloned()
+        .map(|mut route| {
+            f(&mut route);
+            route
+        })
+        .collect()
+}
+
+// === impl ParentService ===
+
+impl ParentService {
+    fn matches(&self, parent: &Meta) -> bool {
+        match parent {
+            Meta::Resource {
+                namespace,
+                name,
+                port,
+                ..
+            } => *namespace == self.namespace && *name == self.name && *port == Some(self.port),
+            Meta::Default { .. } => false,
+        }
+    }
+
+    /// Returns true if `addr` is this service's cluster-local DNS name, which
+    /// balancers use to discover its endpoints.
+    fn matches_addr(&self, addr: &NameAddr) -> bool {
+        let mut labels = addr.name().split('.');
+        labels.next() == Some(self.name.as_str())
+            && labels.next() == Some(self.namespace.as_str())
+            && labels
+                .next()
+                .map_or(false, |label| label.eq_ignore_ascii_case("svc"))
+            && addr.port() == self.port.get()
+    }
+}
+
+// === impl RouteScope ===
+
+impl RouteScope {
+    fn matches(&self, parent: &Meta, route: &Meta) -> bool {
+        self.parent.matches(parent)
+            && self
+                .route
+                .as_ref()
+                .map_or(true, |name| route.name() == *name)
+    }
+}
+
+// === impl UrlRewriteConfig ===
+
+impl UrlRewriteConfig {
+    fn matches(&self, matches: &[http::r#match::MatchRequest]) -> bool {
+        !matches.is_empty()
+            && matches.iter().all(|m| match m.path {
+                Some(http::r#match::MatchPath::Prefix(ref prefix)) => *prefix == self.prefix,
+                _ => false,
+            })
+    }
+}
+
+// === impl RetryConfig ===
+
+impl RetryConfig {
+    fn route_retry<F>(&self, conditions: F) -> RouteRetry<F> {
+        RouteRetry {
+            max_retries: self.max_retries,
+            max_request_bytes: self.max_request_bytes,
+            conditions,
+            timeout: self.timeout,
+            backoff: self.backoff,
+            budget: self.budget,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_proxy_client_policy::{
+        EndpointDiscovery, HedgeDelay, PeakEwma, Queue, StdevFactor,
+    };
+
+    fn backend() -> Backend {
+        Backend {
+            meta: Arc::new(Meta::Resource {
+                group: "core".to_string(),
+                kind: "Service".to_string(),
+                namespace: "ns".to_string(),
+                name: "backend".to_string(),
+                section: None,
+                port: NonZeroU16::new(8080),
+            }),
+            queue: Queue {
+                capacity: 100,
+                failfast_timeout: time::Duration::from_secs(3),
+            },
+            dispatcher: BackendDispatcher::BalanceP2c(
+                Load::PeakEwma(PeakEwma {
+                    decay: time::Duration::from_secs(10),
+                    default_rtt: time::Duration::from_millis(30),
+                }),
+                EndpointDiscovery::DestinationGet {
+                    path: "backend.ns.svc.cluster.local:8080".to_string(),
+                },
+            ),
+        }
+    }
+
+    fn http_policy() -> ClientPolicy {
+        let backend = backend();
+        let routes = Arc::new([http::default(RouteDistribution::FirstAvailable(Arc::new(
+            [RouteBackend {
+                filters: Arc::new([]),
+                backend: backend.clone(),
+                timeouts: RouteTimeouts::default(),
+            }],
+        )))]);
+        ClientPolicy {
+            parent: Meta::new_default("parent"),
+            protocol: Protocol::Http1(http::Http1 {
+                routes,
+                failure_accrual: Default::default(),
+            }),
+            backends: Arc::new([backend]),
+        }
+    }
+
+    fn opaque_policy(parent: Arc<Meta>) -> ClientPolicy {
+        let backend = backend();
+        ClientPolicy {
+            parent,
+            protocol: Protocol::Opaque(opaq::Opaque {
+                routes: Arc::new([opaq::Route {
+                    matches: vec![],
+                    policy: opaq::Policy {
+                        meta: Meta::new_default("opaque"),
+                        filters: Arc::new([]),
+                        distribution: RouteDistribution::FirstAvailable(Arc::new([RouteBackend {
+                            filters: Arc::new([]),
+                            backend: backend.clone(),
+                            timeouts: RouteTimeouts::default(),
+                        }])),
+                        timeouts: RouteTimeouts::default(),
+                        failure_policy: opaq::NonIoErrors,
+                        retry: None,
+                        hedge: None,
+                    },
+                }]),
+                failure_accrual: Default::default(),
+            }),
+            backends: Arc::new([backend]),
+        }
+    }
+
+    fn parent_service() -> ParentService {
+        ParentService {
+            namespace: "ns".to_string(),
+            name: "parent".to_string(),
+            port: NonZeroU16::new(8080).unwrap(),
+        }
+    }
+
+    fn parent_meta() -> Arc<Meta> {
+        Arc::new(Meta::Resource {
+            group: "core".to_string(),
+            kind: "Service".to_string(),
+            namespace: "ns".to_string(),
+            name: "parent".to_string(),
+            section: None,
+            port: NonZeroU16::new(8080),
+        })
+    }
+
+    fn http_rule_policy(policy: &ClientPolicy) -> &http::Policy {
+        match policy.protocol {
+            Protocol::Http1(ref http1) => &http1.routes[0].rules[0].policy,
+            _ => panic!("unexpected protocol: {:?}", policy.protocol),
+        }
+    }
+
+    #[test]
+    fn retries_configured_routes() {
+        let retry = |route: Option<&str>, http_conditions| RetryConfig {
+            routes: RouteScope {
+                parent: parent_service(),
+                route: route.map(Into::into),
+            },
+            http_conditions,
+            grpc_conditions: None,
+            max_retries: 2,
+            max_request_bytes: 1024,
+            timeout: Some(time::Duration::from_secs(1)),
+            backoff: None,
+            budget: RetryBudget {
+                retry_percent: 20,
+                min_retries_per_second: 10,
+                ttl: time::Duration::from_secs(10),
+            },
+        };
+        let parent_policy = || ClientPolicy {
+            parent: parent_meta(),
+            ..http_policy()
+        };
+
+        // Routes of other parents are not retried.
+        let local = LocalConfig {
+            retries: vec![retry(None, None)],
+            ..Default::default()
+        };
+        let policy = local.apply(http_policy());
+        assert!(http_rule_policy(&policy).retry.is_none());
+
+        let policy = local.apply(parent_policy());
+        let rule = http_rule_policy(&policy);
+        let retry = rule.retry.as_ref().expect("route must be retried");
+        assert_eq!(retry.max_retries, 2);
+        assert_eq!(retry.max_request_bytes, 1024);
+        assert_eq!(retry.conditions, rule.failure_policy);
+        assert_eq!(retry.timeout, Some(time::Duration::from_secs(1)));
+        assert_eq!(retry.budget.retry_percent, 20);
+
+        // Only routes with the configured name are retried, on the
+        // configured conditions.
+        let conditions = http::StatusRanges(Arc::new([503..=503]));
+        let local = LocalConfig {
+            retries: vec![
+                retry(Some("other"), None),
+                retry(Some("default"), Some(conditions.clone())),
+            ],
+            ..Default::default()
+        };
+        let policy = local.apply(parent_policy());
+        let retry = http_rule_policy(&policy).retry.as_ref().unwrap();
+        assert_eq!(retry.conditions, conditions);
+    }
+
+    #[test]
+    fn hedges_configured_routes() {
+        let hedge = RouteHedge {
+            delay: HedgeDelay::Percentile(90),
+            max_percent: 10,
+            max_request_bytes: 1024,
+            idempotent: false,
+        };
+        let config = |route: Option<&str>| HedgeConfig {
+            routes: RouteScope {
+                parent: parent_service(),
+                route: route.map(Into::into),
+            },
+            hedge: hedge.clone(),
+        };
+        let parent_policy = || ClientPolicy {
+            parent: parent_meta(),
+            ..http_policy()
+        };
+
+        // Routes of other parents are not hedged.
+        let local = LocalConfig {
+            hedges: vec![config(None)],
+            ..Default::default()
+        };
+        let policy = local.apply(http_policy());
+        assert_eq!(http_rule_policy(&policy).hedge, None);
+
+        let policy = local.apply(parent_policy());
+        assert_eq!(http_rule_policy(&policy).hedge, Some(hedge.clone()));
+
+        // Only the named route is hedged.
+        let local = LocalConfig {
+            hedges: vec![config(Some("other"))],
+            ..Default::default()
+        };
+        let policy = local.apply(parent_policy());
+        assert_eq!(http_rule_policy(&policy).hedge, None);
+        let local = LocalConfig {
+            hedges: vec![config(Some("default"))],
+            ..Default::default()
+        };
+        let policy = local.apply(parent_policy());
+        assert_eq!(http_rule_policy(&policy).hedge, Some(hedge));
+    }
+
+    #[test]
+    fn configures_unset_timeouts() {
+        let local = LocalConfig {
+            timeouts: vec![TimeoutsConfig {
+                routes: RouteScope {
+                    parent: parent_service(),
+                    route: None,
+                },
+                timeouts: RouteTimeouts {
+                    response: Some(time::Duration::from_secs(1)),
+                    request: None,
+                    idle: Some(time::Duration::from_secs(30)),
+                },
+            }],
+            ..Default::default()
+        };
+
+        // Routes of other parents are not bounded.
+        let policy = local.apply(http_policy());
+        assert_eq!(http_rule_policy(&policy).timeouts, RouteTimeouts::default());
+
+        let mut policy = ClientPolicy {
+            parent: parent_meta(),
+            ..http_policy()
+        };
+        if let Protocol::Http1(ref mut http1) = policy.protocol {
+            let mut route = http1.routes[0].clone();
+            route.rules[0].policy.timeouts.response = Some(time::Duration::from_secs(5));
+            http1.routes = Arc::new([route]);
+        }
+        let policy = local.apply(policy);
+        assert_eq!(
+            http_rule_policy(&policy).timeouts,
+            RouteTimeouts {
+                // Timeouts configured by the policy API are not overridden.
+                response: Some(time::Duration::from_secs(5)),
+                request: None,
+                idle: Some(time::Duration::from_secs(30)),
+            }
+        );
+    }
+
+    #[test]
+    fn overrides_backend_load() {
+        let load = |name: &str| LoadConfig {
+            backend: ParentService {
+                namespace: "ns".to_string(),
+                name: name.to_string(),
+                port: NonZeroU16::new(8080).unwrap(),
+            },
+            load: Load::LeastRequest,
+        };
+
+        // Other backends' loads are not overridden.
+        let policy = LocalConfig {
+            loads: vec![load("other")],
+            ..Default::default()
+        }
+        .apply(http_policy());
+        assert_eq!(policy.backends[0], backend());
+
+        let local = LocalConfig {
+            loads: vec![load("backend")],
+            ..Default::default()
+        };
+
+        let policy = local.apply(http_policy());
+        let expected = BackendDispatcher::BalanceP2c(
+            Load::LeastRequest,
+            EndpointDiscovery::DestinationGet {
+                path: "backend.ns.svc.cluster.local:8080".to_string(),
+            },
+        );
+        assert_eq!(policy.backends[0].dispatcher, expected);
+        match http_rule_policy(&policy).distribution {
+            RouteDistribution::FirstAvailable(ref backends) => {
+                // Route backends must continue to match the policy's backends.
+                assert_eq!(backends[0].backend, policy.backends[0]);
+            }
+            ref distribution => panic!("unexpected distribution: {distribution:?}"),
+        }
+    }
+
+    #[test]
+    fn configures_failure_accrual() {
+        let accrual = FailureAccrual::SuccessRate {
+            interval: time::Duration::from_secs(10),
+            min_requests: 100,
+            min_endpoints: 5,
+            stdev_factor: StdevFactor::new(1.9).unwrap(),
+            max_ejection_percent: 10,
+            backoff: ExponentialBackoff::try_new(
+                time::Duration::from_secs(30),
+                time::Duration::from_secs(300),
+                0.1,
+            )
+            .unwrap(),
+        };
+        let local = LocalConfig {
+            failure_accrual: vec![FailureAccrualConfig {
+                parent: parent_service(),
+                accrual,
+            }],
+            ..Default::default()
+        };
+
+        // Policies for other parents do not accrue failures.
+        match local.apply(http_policy()).protocol {
+            Protocol::Http1(ref http1) => assert_eq!(http1.failure_accrual, FailureAccrual::None),
+            ref protocol => panic!("unexpected protocol: {protocol:?}"),
+        }
+
+        let policy = local.apply(ClientPolicy {
+            parent: parent_meta(),
+            ..http_policy()
+        });
+        match policy.protocol {
+            Protocol::Http1(ref http1) => assert_eq!(http1.failure_accrual, accrual),
+            ref protocol => panic!("unexpected protocol: {protocol:?}"),
+        }
+
+        // Failure accrual configured by the policy API is not overridden.
+        let consecutive = FailureAccrual::ConsecutiveFailures {
+            max_failures: 7,
+            backoff: ExponentialBackoff::default(),
+        };
+        let mut policy = ClientPolicy {
+            parent: parent_meta(),
+            ..http_policy()
+        };
+        if let Protocol::Http1(ref mut http1) = policy.protocol {
+            http1.failure_accrual = consecutive;
+        }
+        match local.apply(policy).protocol {
+            Protocol::Http1(ref http1) => assert_eq!(http1.failure_accrual, consecutive),
+            ref protocol => panic!("unexpected protocol: {protocol:?}"),
+        }
+    }
+
+    #[test]
+    fn mirrors_parent_routes() {
+        let mirror = http::RequestMirror {
+            backend: Backend {
+                meta: Meta::new_default("mirror"),
+                ..backend()
+            },
+            distribution: Default::default(),
+            max_request_bytes: 1024,
+            max_in_flight: 10,
+        };
+        let local = LocalConfig {
+            mirrors: vec![MirrorConfig {
+                parent: parent_service(),
+                mirror: mirror.clone(),
+            }],
+            ..Default::default()
+        };
+
+        // Policies for other parents are not mirrored.
+        let policy = local.apply(http_policy());
+        assert!(http_rule_policy(&policy).filters.is_empty());
+
+        let policy = local.apply(ClientPolicy {
+            parent: parent_meta(),
+            ..http_policy()
+        });
+        let expected = [http::Filter::RequestMirror(mirror)];
+        assert_eq!(*http_rule_policy(&policy).filters, expected);
+
+        // Applying the configuration again does not add another mirror.
+        let policy = local.apply(policy);
+        assert_eq!(*http_rule_policy(&policy).filters, expected);
+    }
+
+    #[test]
+    fn rewrites_parent_prefix_routes() {
+        use http::{
+            filter::{ModifyPath, UrlRewrite},
+            r#match::{MatchPath, MatchRequest},
+        };
+
+        let rewrite = UrlRewrite {
+            authority: Some("legacy.ns.svc.cluster.local:8080".parse().unwrap()),
+            path: Some(ModifyPath::ReplacePrefixMatch("/legacy".to_string())),
+        };
+        let local = LocalConfig {
+            url_rewrites: vec![UrlRewriteConfig {
+                parent: parent_service(),
+                prefix: "/new".to_string(),
+                rewrite: rewrite.clone(),
+            }],
+            ..Default::default()
+        };
+        let prefix_policy = |parent: Arc<Meta>| {
+            let mut policy = ClientPolicy {
+                parent,
+                ..http_policy()
+            };
+            if let Protocol::Http1(ref mut http1) = policy.protocol {
+                let mut route = http1.routes[0].clone();
+                route.rules[0].matches = vec![MatchRequest {
+                    path: Some(MatchPath::Prefix("/new".to_string())),
+                    ..Default::default()
+                }];
+                http1.routes = Arc::new([route]);
+            }
+            policy
+        };
+
+        // Routes of other parents and rules that do not match the prefix are
+        // not rewritten.
+        let policy = local.apply(prefix_policy(Meta::new_default("parent")));
+        assert!(http_rule_policy(&policy).filters.is_empty());
+        let policy = local.apply(ClientPolicy {
+            parent: parent_meta(),
+            ..http_policy()
+        });
+        assert!(http_rule_policy(&policy).filters.is_empty());
+
+        let policy = local.apply(prefix_policy(parent_meta()));
+        let expected = [http::Filter::UrlRewrite(rewrite)];
+        assert_eq!(*http_rule_policy(&policy).filters, expected);
+
+        // Applying the configuration again does not add another rewrite.
+        let policy = local.apply(policy);
+        assert_eq!(*http_rule_policy(&policy).filters, expected);
+    }
+
+    #[test]
+    fn modifies_parent_grpc_response_headers() {
+        let headers = grpc::filter::ModifyHeader {
+            set: vec![(
+                "x-tenant".parse().unwrap(),
+                ::http::HeaderValue::from_static("acme"),
+            )],
+            ..Default::default()
+        };
+        let local = LocalConfig {
+            grpc_response_headers: vec![GrpcResponseHeadersConfig {
+                parent: parent_service(),
+                headers: headers.clone(),
+            }],
+            ..Default::default()
+        };
+        let grpc_policy = |parent: Arc<Meta>| ClientPolicy {
+            parent,
+            protocol: Protocol::Grpc(grpc::Grpc {
+                routes: Arc::new([grpc::default(RouteDistribution::Empty)]),
+                failure_accrual: Default::default(),
+            }),
+            backends: Arc::new([]),
+        };
+        let grpc_filters = |policy: &ClientPolicy| match policy.protocol {
+            Protocol::Grpc(ref grpc) => grpc.routes[0].rules[0].policy.filters.clone(),
+            ref protocol => panic!("unexpected protocol: {protocol:?}"),
+        };
+
+        // Routes of other parents are not modified.
+        let policy = local.apply(grpc_policy(Meta::new_default("parent")));
+        assert!(grpc_filters(&policy).is_empty());
+
+        let policy = local.apply(grpc_policy(parent_meta()));
+        let expected = [grpc::Filter::ResponseHeaders(headers)];
+        assert_eq!(*grpc_filters(&policy), expected);
+
+        // Applying the configuration again does not modify the response twice.
+        let policy = local.apply(policy);
+        assert_eq!(*grpc_filters(&policy), expected);
+    }
+
+    #[test]
+    fn routes_parent_connections_by_sni() {
+        let tls_backend = Backend {
+            meta: Meta::new_default("tls"),
+            ..backend()
+        };
+        let local = LocalConfig {
+            tls_routes: vec![TlsRouteConfig {
+                parent: parent_service(),
+                sni: "*.example.com".parse().unwrap(),
+                backend: tls_backend.clone(),
+            }],
+            ..Default::default()
+        };
+
+        // Connections to other parents are not routed by SNI.
+        let policy = opaque_policy(Meta::new_default("parent"));
+        assert_eq!(local.apply(policy.clone()), policy);
+
+        let policy = local.apply(opaque_policy(parent_meta()));
+        let tls = match policy.protocol {
+            Protocol::Tls(ref tls) => tls.clone(),
+            ref protocol => panic!("unexpected protocol: {protocol:?}"),
+        };
+        assert_eq!(*policy.backends, [backend(), tls_backend.clone()]);
+        let route = tls::find(&tls.routes, Some("api.example.com")).expect("route must match");
+        match route.policy.distribution {
+            RouteDistribution::FirstAvailable(ref backends) => {
+                assert_eq!(backends[0].backend, tls_backend);
+            }
+            ref distribution => panic!("unexpected distribution: {distribution:?}"),
+        }
+        // Connections without a matching SNI use the opaque route.
+        let route = tls::find(&tls.routes, Some("example.org")).expect("route must match");
+        assert_eq!(route.policy.meta, Meta::new_default("opaque"));
+        let route = tls::find(&tls.routes, None).expect("route must match");
+        assert_eq!(route.policy.meta, Meta::new_default("opaque"));
+
+        // Applying the configuration again does not add another backend.
+        assert_eq!(local.apply(policy.clone()), policy);
+    }
+
+    #[test]
+    fn filters_parent_opaque_connections() {
+        let matches = opaq::MatchConnection {
+            ports: None,
+            client_networks: vec!["10.1.0.0/16".parse().unwrap()],
+        };
+        let local = LocalConfig {
+            opaque_filters: vec![OpaqueFilterConfig {
+                parent: parent_service(),
+                matches: matches.clone(),
+                filter: opaq::Filter::Forbidden,
+            }],
+            ..Default::default()
+        };
+        let opaque_routes = |policy: &ClientPolicy| match policy.protocol {
+            Protocol::Opaque(ref opaque) => opaque.routes.clone(),
+            ref protocol => panic!("unexpected protocol: {protocol:?}"),
+        };
+
+        // Connections to other parents are not filtered.
+        let policy = opaque_policy(Meta::new_default("parent"));
+        assert_eq!(local.apply(policy.clone()), policy);
+
+        let policy = local.apply(opaque_policy(parent_meta()));
+        let routes = opaque_routes(&policy);
+        assert_eq!(routes.len(), 2);
+        let dst = ([10, 2, 0, 1], 8080).into();
+        let route = opaq::find(&routes, dst, [10, 1, 0, 1].into()).unwrap();
+        assert_eq!(route.matches, vec![matches]);
+        assert_eq!(*route.policy.filters, [opaq::Filter::Forbidden]);
+        assert_eq!(route.policy.distribution, routes[1].policy.distribution);
+        let route = opaq::find(&routes, dst, [10, 2, 0, 2].into()).unwrap();
+        assert!(route.policy.filters.is_empty());
+
+        // Applying the configuration again does not add another route.
+        assert_eq!(local.apply(policy.clone()), policy);
+    }
+
+    #[test]
+    fn sends_proxy_header_to_backends() {
+        let local = LocalConfig {
+            proxy_protocol_backends: vec![ParentService {
+                namespace: "ns".to_string(),
+                name: "backend".to_string(),
+                port: NonZeroU16::new(8080).unwrap(),
+            }],
+            ..Default::default()
+        };
+
+        // Balanced backends are matched by their discovery name.
+        assert!(local.sends_proxy_header(&"backend.ns.svc.cluster.local:8080".parse().unwrap()));
+        assert!(!local.sends_proxy_header(&"backend.ns.svc.cluster.local:8081".parse().unwrap()));
+        assert!(!local.sends_proxy_header(&"backend.other.svc.cluster.local:8080".parse().unwrap()));
+        assert!(!local.sends_proxy_header(&"backend.ns.example.com:8080".parse().unwrap()));
+
+        // Forwarded backends' endpoints are marked.
+        let forward = |name: &str| {
+            let mut backend = backend();
+            backend.meta = Arc::new(Meta::Resource {
+                group: "core".to_string(),
+                kind: "Service".to_string(),
+                namespace: "ns".to_string(),
+                name: name.to_string(),
+                section: None,
+                port: NonZeroU16::new(8080),
+            });
+            backend.dispatcher =
+                BackendDispatcher::Forward(([192, 0, 2, 30], 3306).into(), Default::default());
+            backend
+        };
+        for (name, expected) in [("backend", true), ("other", false)] {
+            let mut backend = forward(name);
+            local.backend(&mut backend);
+            match backend.dispatcher {
+                BackendDispatcher::Forward(_, ref metadata) => {
+                    assert_eq!(metadata.proxy_protocol(), expected, "{name}")
+                }
+                ref dispatcher => panic!("unexpected dispatcher: {dispatcher:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn unconfigured_is_noop() {
+        let policy = http_policy();
+        assert_eq!(LocalConfig::default().apply(policy.clone()), policy);
+    }
+}
@@ -11,7 +11,7 @@ use linkerd_app_core::{
     },
     svc,
     transport::addrs::*,
-    Error,
+    Error, Infallible,
 };
 use std::fmt::Debug;
 use tokio::sync::watch;
@@ -51,7 +51,23 @@ impl Outbound<()> {
         // Endpoint resolver.
         R: Resolve<ConcreteAddr, Endpoint = Metadata, Error = Error>,
     {
-        let opaq = self.to_tcp_connect().push_opaq_cached(resolve.clone());
+        let tls = self.to_tcp_connect().push_tls_cached(resolve.clone());
+
+        // Opaque connections with a TLS client policy are routed by SNI.
+        let opaq = self
+            .to_tcp_connect()
+            .push_opaq_cached(resolve.clone())
+            .map_stack(|_, _, stk| {
+                stk.push_switch(
+                    |parent: Sidecar| -> Result<_, Infallible> {
+                        if let policy::Protocol::Tls(_) = parent.policy.borrow().protocol {
+                            return Ok(svc::Either::B(parent));
+                        }
+                        Ok(svc::Either::A(parent))
+                    },
+                    tls.into_inner(),
+                )
+            });
 
         let http = self
             .to_tcp_connect()
@@ -113,6 +129,12 @@ impl svc::Param<Option<profiles::LogicalAddr>> for Sidecar {
     }
 }
 
+impl svc::Param<policy::Receiver> for Sidecar {
+    fn param(&self) -> policy::Receiver {
+        self.policy.clone()
+    }
+}
+
 impl svc::Param<Option<profiles::Receiver>> for Sidecar {
     fn param(&self) -> Option<profiles::Receiver> {
         self.profile.clone()
@@ -138,6 +160,11 @@ impl svc::Param<Protocol> for Sidecar {
 
 impl svc::Param<opaq::Logical> for Sidecar {
     fn param(&self) -> opaq::Logical {
+        let OrigDstAddr(addr) = self.orig_dst;
+        if opaq::should_route_with_policy(&self.policy.borrow()) {
+            return opaq::Logical::Policy(Remote(ServerAddr(addr)), self.policy.clone());
+        }
+
         if let Some(profile) = self.profile.clone() {
             if let Some(profiles::LogicalAddr(addr)) = profile.logical_addr() {
                 return opaq::Logical::Route(addr, profile);
@@ -148,7 +175,6 @@ impl svc::Param<opaq::Logical> for Sidecar {
             }
         }
 
-        let OrigDstAddr(addr) = self.orig_dst;
         opaq::Logical::Forward(Remote(ServerAddr(addr)), Default::default())
     }
 }
@@ -7,6 +7,7 @@ use linkerd_app_core::{
 
 mod connect;
 mod endpoint;
+pub mod proxy_header;
 pub mod tagged_transport;
 
 pub use self::connect::Connect;
@@ -1,4 +1,4 @@
-use super::{tagged_transport::TaggedTransport, *};
+use super::{proxy_header::ProxyHeader, tagged_transport::TaggedTransport, *};
 use crate::{ConnectMeta, Outbound};
 use linkerd_app_core::{
     io,
@@ -28,6 +28,7 @@ impl<C> Outbound<C> {
         T: svc::Param<Option<tagged_transport::PortOverride>>,
         T: svc::Param<Option<http::AuthorityOverride>>,
         T: svc::Param<Option<SessionProtocol>>,
+        T: svc::Param<proxy_header::SendProxyHeader>,
         T: svc::Param<transport::labels::Key>,
         // Connector stack.
         C: svc::MakeConnection<Connect, Metadata = Local<ClientAddr>, Error = io::Error>,
@@ -46,6 +47,9 @@ impl<C> Outbound<C> {
                 // Encodes a transport header if the established connection is TLS'd and
                 // ALPN negotiation indicates support.
                 .push(TaggedTransport::layer())
+                // Encodes a PROXY protocol header if the endpoint is configured
+                // to receive one and the connection is not TLS'd.
+                .push(ProxyHeader::layer(rt.identity.name().clone()))
                 // Limits the time we wait for a connection to be established.
                 .push_connect_timeout(config.proxy.connect.timeout)
                 .push(svc::stack::BoxFuture::layer())
@@ -0,0 +1,213 @@
+use crate::ConnectMeta;
+use bytes::BytesMut;
+use futures::prelude::*;
+use linkerd_app_core::{
+    identity,
+    io::{self, AsyncWriteExt},
+    svc,
+    transport::{addrs::*, proxy_protocol},
+    Conditional, Error, Result,
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tracing::{debug, trace};
+
+/// Configures whether connections to an endpoint are prefaced with a PROXY
+/// protocol v2 header.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SendProxyHeader(pub bool);
+
+/// Prefaces connections to endpoints that are configured to receive a PROXY
+/// protocol v2 header with one.
+///
+/// The header describes the connection's local address and the endpoint's
+/// address. Since the proxy shares the application's network namespace, the
+/// local address is the application's address; HTTP connections are shared by
+/// many application connections, so the header never describes an individual
+/// application connection. The local workload's identity is included in a
+/// [`proxy_protocol::PP2_TYPE_CLIENT_IDENTITY`] TLV.
+///
+/// Headers are never written to connections secured by TLS, since a meshed
+/// endpoint's proxy would not expect one.
+#[derive(Clone, Debug)]
+pub struct ProxyHeader<S> {
+    inner: S,
+    identity: identity::Name,
+}
+
+// === impl ProxyHeader ===
+
+impl<S> ProxyHeader<S> {
+    pub fn layer(identity: identity::Name) -> impl svc::Layer<S, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            identity: identity.clone(),
+        })
+    }
+}
+
+impl<T, S> svc::Service<T> for ProxyHeader<S>
+where
+    T: svc::Param<SendProxyHeader> + svc::Param<Remote<ServerAddr>>,
+    S: svc::MakeConnection<T, Metadata = ConnectMeta> + Send + 'static,
+    S::Connection: Send + Unpin,
+    S::Future: Send + 'static,
+{
+    type Response = (S::Connection, S::Metadata);
+    type Error = Error;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<(S::Connection, S::Metadata)>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, ep: T) -> Self::Future {
+        let SendProxyHeader(send) = ep.param();
+        let Remote(ServerAddr(destination)) = ep.param();
+        let connect = self.inner.connect(ep);
+        if !send {
+            return Box::pin(connect.err_into::<Error>());
+        }
+
+        let identity = self.identity.clone();
+        Box::pin(async move {
+            let (mut io, meta) = connect.await.map_err(Into::into)?;
+            if let Conditional::Some(_) = meta.tls {
+                trace!("Connection is secured by TLS; not writing a PROXY protocol header");
+                return Ok((io, meta));
+            }
+
+            let Local(ClientAddr(client)) = meta.socket;
+            let header = proxy_protocol::Header::Proxied {
+                client: Remote(ClientAddr(client)),
+                destination,
+            };
+            let mut buf = BytesMut::new();
+            header.encode_v2(
+                &[(
+                    proxy_protocol::PP2_TYPE_CLIENT_IDENTITY,
+                    identity.as_bytes(),
+                )],
+                &mut buf,
+            )?;
+            debug!(?header, "Writing PROXY protocol header");
+            io.write_all(&buf).await?;
+
+            Ok((io, meta))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use linkerd_app_core::tls;
+    use std::str::FromStr;
+    use tower::util::{service_fn, ServiceExt};
+
+    #[derive(Clone, Debug)]
+    struct Endpoint(bool);
+
+    impl svc::Param<SendProxyHeader> for Endpoint {
+        fn param(&self) -> SendProxyHeader {
+            SendProxyHeader(self.0)
+        }
+    }
+
+    impl svc::Param<Remote<ServerAddr>> for Endpoint {
+        fn param(&self) -> Remote<ServerAddr> {
+            Remote(ServerAddr(([192, 0, 2, 30], 3306).into()))
+        }
+    }
+
+    fn identity() -> identity::Name {
+        identity::Name::from_str("foo.ns.serviceaccount.identity.linkerd.cluster.local").unwrap()
+    }
+
+    fn connect(
+        expect: Vec<u8>,
+        secured: bool,
+    ) -> impl Fn(Endpoint) -> future::Ready<Result<(tokio_test::io::Mock, ConnectMeta), io::Error>>
+    {
+        move |_| {
+            let mut io = tokio_test::io::Builder::new();
+            if !expect.is_empty() {
+                io.write(&expect);
+            }
+            io.write(b"hello");
+            let meta = tls::ConnectMeta {
+                socket: Local(ClientAddr(([192, 0, 2, 10], 45678).into())),
+                tls: if secured {
+                    Conditional::Some(None)
+                } else {
+                    Conditional::None(tls::NoClientTls::NotProvidedByServiceDiscovery)
+                },
+            };
+            future::ready(Ok::<_, io::Error>((io.build(), meta)))
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn writes_header() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let mut header = BytesMut::new();
+        proxy_protocol::Header::Proxied {
+            client: Remote(ClientAddr(([192, 0, 2, 10], 45678).into())),
+            destination: ([192, 0, 2, 30], 3306).into(),
+        }
+        .encode_v2(
+            &[(
+                proxy_protocol::PP2_TYPE_CLIENT_IDENTITY,
+                identity().as_bytes(),
+            )],
+            &mut header,
+        )
+        .unwrap();
+
+        let svc = ProxyHeader {
+            inner: service_fn(connect(header.to_vec(), false)),
+            identity: identity(),
+        };
+        let (mut io, _meta) = svc
+            .oneshot(Endpoint(true))
+            .await
+            .expect("Connect must not fail");
+        io.write_all(b"hello").await.expect("Write must succeed");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn unconfigured() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let svc = ProxyHeader {
+            inner: service_fn(connect(vec![], false)),
+            identity: identity(),
+        };
+        let (mut io, _meta) = svc
+            .oneshot(Endpoint(false))
+            .await
+            .expect("Connect must not fail");
+        io.write_all(b"hello").await.expect("Write must succeed");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn secured() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let svc = ProxyHeader {
+            inner: service_fn(connect(vec![], true)),
+            identity: identity(),
+        };
+        let (mut io, _meta) = svc
+            .oneshot(Endpoint(true))
+            .await
+            .expect("Connect must not fail");
+        io.write_all(b"hello").await.expect("Write must succeed");
+    }
+}
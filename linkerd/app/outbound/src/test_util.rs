@@ -20,6 +20,7 @@ pub(crate) fn default_config() -> Config {
     };
     Config {
         ingress_mode: false,
+        forward_proxy_mode: false,
         emit_headers: true,
         allow_discovery: IpMatch::new(Some(IpNet::from_str("0.0.0.0/0").unwrap())).into(),
         proxy: config::ProxyConfig {
@@ -44,12 +45,19 @@ pub(crate) fn default_config() -> Config {
                 h2_settings: h2::Settings::default(),
             },
             max_in_flight_requests: 10_000,
+            adaptive_concurrency_limit: None,
             detect_protocol_timeout: Duration::from_secs(3),
         },
         inbound_ips: Default::default(),
         discovery_idle_timeout: Duration::from_secs(60),
         tcp_connection_queue: buffer,
         http_request_queue: buffer,
+        proxy_protocol: Default::default(),
+        zone_affinity: None,
+        zone_label: "zone".into(),
+        slow_start: None,
+        deadline_header: None,
+        local_policy: Default::default(),
     }
 }
 
@@ -0,0 +1,161 @@
+use crate::{policy, tcp, Outbound};
+use bytes::BytesMut;
+use linkerd_app_core::{
+    detect,
+    io::{self, AsyncReadExt},
+    profiles,
+    proxy::{
+        api_resolve::{ConcreteAddr, Metadata},
+        core::Resolve,
+    },
+    svc, tls,
+    transport::addrs::*,
+    Error,
+};
+use std::{fmt::Debug, hash::Hash};
+use tracing::{debug, trace};
+
+mod logical;
+mod metrics;
+#[cfg(test)]
+mod tests;
+
+pub use self::metrics::TlsRouteMetrics;
+
+/// Detects the server name indicated in a TLS ClientHello without
+/// terminating TLS.
+#[derive(Clone, Debug, Default)]
+pub struct DetectSni(());
+
+/// A target that has been annotated with the SNI value read from the
+/// client's ClientHello, if any.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Sni<T> {
+    sni: Option<tls::ServerId>,
+    parent: T,
+}
+
+// === impl Outbound ===
+
+impl<C> Outbound<C> {
+    /// Builds a stack that proxies TLS connections without terminating them,
+    /// routing each connection by the server name in its ClientHello.
+    ///
+    /// This stack uses caching so that a router may be reused across multiple
+    /// connections with the same SNI, and so that a load balancer may be
+    /// reused across all routers for the same target.
+    pub fn push_tls_cached<T, I, R>(
+        self,
+        resolve: R,
+    ) -> Outbound<
+        svc::ArcNewService<
+            T,
+            impl svc::Service<I, Response = (), Error = Error, Future = impl Send> + Clone,
+        >,
+    >
+    where
+        // TLS target
+        T: svc::Param<policy::Receiver>,
+        T: svc::Param<Option<profiles::LogicalAddr>>,
+        T: Eq + Hash + Clone + Debug + Send + Sync + 'static,
+        // Server-side connection
+        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr,
+        I: Debug + Send + Sync + Unpin + 'static,
+        // Endpoint discovery
+        R: Resolve<ConcreteAddr, Endpoint = Metadata, Error = Error>,
+        // TCP endpoint stack.
+        C: svc::MakeConnection<tcp::Connect, Metadata = Local<ClientAddr>, Error = io::Error>,
+        C: Clone + Send + Sync + Unpin + 'static,
+        C::Connection: Send + Unpin,
+        C::Future: Send + Unpin,
+    {
+        self.push_tcp_endpoint()
+            .push_opaq_concrete(resolve)
+            .map_stack(|config, _rt, stk| stk.push_new_idle_cached(config.discovery_idle_timeout))
+            .push_tls_logical()
+            .map_stack(|config, _rt, stk| {
+                stk.push_new_idle_cached(config.discovery_idle_timeout)
+                    .push_map_target(
+                        |(detected, parent): (detect::Result<tls::ServerId>, T)| Sni {
+                            sni: detect::allow_timeout(detected),
+                            parent,
+                        },
+                    )
+                    .lift_new_with_target()
+                    .push(detect::NewDetectService::layer(svc::CloneParam::from(
+                        detect::Config {
+                            detect: DetectSni::default(),
+                            capacity: DetectSni::CAPACITY,
+                            timeout: config.proxy.detect_protocol_timeout,
+                        },
+                    )))
+                    .push(svc::ArcNewService::layer())
+            })
+    }
+}
+
+// === impl DetectSni ===
+
+impl DetectSni {
+    /// The maximum number of bytes buffered while reading a ClientHello.
+    const CAPACITY: usize = 8192;
+}
+
+#[async_trait::async_trait]
+impl<I: io::AsyncRead + Send + Unpin + 'static> detect::Detect<I> for DetectSni {
+    type Protocol = tls::ServerId;
+
+    async fn detect(&self, io: &mut I, buf: &mut BytesMut) -> Result<Option<tls::ServerId>, Error> {
+        loop {
+            if buf.len() >= Self::CAPACITY {
+                // If we can't buffer an entire TLS ClientHello, the
+                // connection is handled without an SNI.
+                debug!(read = buf.len(), "Buffer insufficient for TLS ClientHello");
+                return Ok(None);
+            }
+
+            trace!(capacity = buf.capacity(), "Reading");
+            let sz = io.read_buf(buf).await?;
+            trace!(sz, "Read");
+            if sz == 0 {
+                // No data was read because the socket closed.
+                debug!(read = buf.len(), "Could not read TLS ClientHello");
+                return Ok(None);
+            }
+
+            match tls::server::parse_sni(buf.as_ref()) {
+                Ok(sni) => {
+                    debug!(?sni, "Read TLS ClientHello");
+                    return Ok(sni);
+                }
+                Err(tls::server::Incomplete) => {}
+            }
+        }
+    }
+}
+
+// === impl Sni ===
+
+impl<T> svc::Param<Option<tls::ServerId>> for Sni<T> {
+    fn param(&self) -> Option<tls::ServerId> {
+        self.sni.clone()
+    }
+}
+
+impl<T> svc::Param<policy::Receiver> for Sni<T>
+where
+    T: svc::Param<policy::Receiver>,
+{
+    fn param(&self) -> policy::Receiver {
+        self.parent.param()
+    }
+}
+
+impl<T> svc::Param<Option<profiles::LogicalAddr>> for Sni<T>
+where
+    T: svc::Param<Option<profiles::LogicalAddr>>,
+{
+    fn param(&self) -> Option<profiles::LogicalAddr> {
+        self.parent.param()
+    }
+}
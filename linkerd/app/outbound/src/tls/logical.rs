@@ -0,0 +1,244 @@
+use super::{metrics::NewCountConnections, Sni};
+use crate::{
+    opaq::concrete,
+    policy::{self, FailureAccrual},
+    Outbound, ParentRef, RouteRef,
+};
+use linkerd_app_core::{io, profiles, svc, tls, Error};
+use linkerd_distribute as distribute;
+use std::{fmt::Debug, hash::Hash};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Concrete<T> {
+    target: concrete::Dispatch,
+    failure_accrual: FailureAccrual,
+    parent: T,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("no TLS route")]
+pub struct NoRoute;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Params<T: Eq + Hash + Clone + Debug> {
+    route: Option<RouteParams<T>>,
+    backends: distribute::Backends<Concrete<T>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct RouteParams<T> {
+    parent_ref: ParentRef,
+    route_ref: RouteRef,
+    sni: Option<tls::ServerId>,
+    distribution: Distribution<T>,
+}
+
+type NewBackendCache<T, N, S> = distribute::NewBackendCache<Concrete<T>, (), N, S>;
+type NewDistribute<T, N> = distribute::NewDistribute<Concrete<T>, (), N>;
+type Distribution<T> = distribute::Distribution<Concrete<T>>;
+
+// === impl Outbound ===
+
+impl<N> Outbound<N> {
+    /// Builds a `NewService` that produces a router service for each TLS
+    /// target.
+    ///
+    /// The router watches the target's client policy and selects a route for
+    /// each connection based on the server name indicated by the client.
+    /// Connections that do not match a route are failed with a [`NoRoute`]
+    /// error.
+    ///
+    /// Concrete targets are built from the parent target, without the server
+    /// name, so that connections with different server names may share a
+    /// backend.
+    pub fn push_tls_logical<T, I, NSvc>(
+        self,
+    ) -> Outbound<
+        svc::ArcNewService<
+            Sni<T>,
+            impl svc::Service<I, Response = (), Error = Error, Future = impl Send> + Clone,
+        >,
+    >
+    where
+        // TLS logical target.
+        T: svc::Param<policy::Receiver>,
+        T: Eq + Hash + Clone + Debug + Send + Sync + 'static,
+        // Server-side socket.
+        I: io::AsyncRead + io::AsyncWrite + Debug + Send + Unpin + 'static,
+        // Concrete stack.
+        N: svc::NewService<Concrete<T>, Service = NSvc> + Clone + Send + Sync + 'static,
+        NSvc: svc::Service<I, Response = ()> + Clone + Send + Sync + 'static,
+        NSvc::Future: Send,
+        NSvc::Error: Into<Error>,
+    {
+        self.map_stack(|_, rt, concrete| {
+            let route = svc::layers()
+                .lift_new()
+                .push(NewDistribute::layer())
+                // The router does not take the backend's availability into
+                // consideration, so we must eagerly fail requests to prevent
+                // leaking tasks onto the runtime.
+                .push_on_service(svc::LoadShed::layer())
+                .push(NewCountConnections::layer(rt.metrics.tls_routes.clone()));
+
+            // A `NewService`--instantiated once per TLS target--that caches a
+            // set of concrete services so that, as the watch provides new
+            // `Params`, we can reuse inner services.
+            let router = svc::layers()
+                // Each `RouteParams` provides a `Distribution` that is used to
+                // choose a concrete service for a given route.
+                .lift_new()
+                .push(NewBackendCache::layer())
+                // Lazily cache a service for each `RouteParams`
+                // returned from the `SelectRoute` impl.
+                .push_on_service(route)
+                .push(svc::NewOneshotRoute::<Params<T>, _, _>::layer_cached());
+
+            // For each TLS target, watch its client policy, maintaining a cache
+            // of all concrete services used by the router.
+            concrete
+                // Share the concrete stack with each router stack.
+                .lift_new()
+                // Rebuild this router stack every time the policy changes.
+                .push_on_service(router)
+                .push(svc::NewSpawnWatch::<policy::ClientPolicy, _>::layer_into::<
+                    Params<T>,
+                >())
+                .push(svc::ArcNewService::layer())
+        })
+    }
+}
+
+// === impl Params ===
+
+impl<T> From<(policy::ClientPolicy, Sni<T>)> for Params<T>
+where
+    T: Eq + Hash + Clone + Debug,
+{
+    fn from((policy, Sni { sni, parent }): (policy::ClientPolicy, Sni<T>)) -> Self {
+        let (routes, failure_accrual) = match policy.protocol {
+            policy::Protocol::Tls(policy::tls::Tls {
+                ref routes,
+                failure_accrual,
+            }) => (routes.clone(), failure_accrual),
+            _ => (Default::default(), Default::default()),
+        };
+
+        // Fail backends are omitted, since opaque connections cannot be
+        // failed with a message.
+        let mk_concrete = |bke: &policy::Backend| -> Option<Concrete<T>> {
+            Some(Concrete {
+                target: concrete::Dispatch::from_backend(bke)?,
+                failure_accrual,
+                parent: parent.clone(),
+            })
+        };
+
+        let backends = policy.backends.iter().filter_map(&mk_concrete).collect();
+
+        let route = policy::tls::find(&routes, sni.as_ref().map(|s| s.as_str())).map(|route| {
+            let distribution = match route.policy.distribution {
+                policy::RouteDistribution::Empty => Distribution::Empty,
+                policy::RouteDistribution::FirstAvailable(ref backends) => {
+                    Distribution::first_available(
+                        backends.iter().filter_map(|rb| mk_concrete(&rb.backend)),
+                    )
+                }
+                policy::RouteDistribution::RandomAvailable(ref backends) => {
+                    Distribution::random_available(
+                        backends
+                            .iter()
+                            .filter_map(|(rb, weight)| Some((mk_concrete(&rb.backend)?, *weight))),
+                    )
+                    .expect("distribution must be valid")
+                }
+            };
+            RouteParams {
+                parent_ref: ParentRef(policy.parent.clone()),
+                route_ref: RouteRef(route.policy.meta.clone()),
+                sni,
+                distribution,
+            }
+        });
+
+        Self { route, backends }
+    }
+}
+
+impl<T> svc::Param<distribute::Backends<Concrete<T>>> for Params<T>
+where
+    T: Clone + Eq + Hash + Debug,
+{
+    fn param(&self) -> distribute::Backends<Concrete<T>> {
+        self.backends.clone()
+    }
+}
+
+impl<T, I> svc::router::SelectRoute<I> for Params<T>
+where
+    T: Clone + Eq + Hash + Debug,
+{
+    type Key = RouteParams<T>;
+    type Error = NoRoute;
+
+    fn select(&self, _: &I) -> Result<Self::Key, Self::Error> {
+        self.route.clone().ok_or(NoRoute)
+    }
+}
+
+// === impl RouteParams ===
+
+impl<T: Clone> svc::Param<Distribution<T>> for RouteParams<T> {
+    fn param(&self) -> Distribution<T> {
+        self.distribution.clone()
+    }
+}
+
+impl<T> svc::Param<ParentRef> for RouteParams<T> {
+    fn param(&self) -> ParentRef {
+        self.parent_ref.clone()
+    }
+}
+
+impl<T> svc::Param<RouteRef> for RouteParams<T> {
+    fn param(&self) -> RouteRef {
+        self.route_ref.clone()
+    }
+}
+
+impl<T> svc::Param<Option<tls::ServerId>> for RouteParams<T> {
+    fn param(&self) -> Option<tls::ServerId> {
+        self.sni.clone()
+    }
+}
+
+// === impl Concrete ===
+
+impl<T> std::ops::Deref for Concrete<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.parent
+    }
+}
+
+impl<T> svc::Param<Option<profiles::LogicalAddr>> for Concrete<T>
+where
+    T: svc::Param<Option<profiles::LogicalAddr>>,
+{
+    fn param(&self) -> Option<profiles::LogicalAddr> {
+        self.parent.param()
+    }
+}
+
+impl<T> svc::Param<concrete::Dispatch> for Concrete<T> {
+    fn param(&self) -> concrete::Dispatch {
+        self.target.clone()
+    }
+}
+
+impl<T> svc::Param<FailureAccrual> for Concrete<T> {
+    fn param(&self) -> FailureAccrual {
+        self.failure_accrual
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{
+    metrics::{write_meta_labels, write_service_meta_labels},
+    ParentRef, RouteRef,
+};
+use ahash::AHashMap;
+use linkerd_app_core::{
+    metrics::{metrics, Counter, FmtLabels, FmtMetrics},
+    svc, tls,
+};
+use parking_lot::Mutex;
+use std::{
+    fmt::Write,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+metrics! {
+    outbound_tls_route_open_total: Counter {
+        "The total number of outbound TLS connections dispatched to a TLS route"
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TlsRouteMetrics(Arc<Mutex<AHashMap<Labels, Arc<Counter>>>>);
+
+#[derive(Clone, Debug)]
+pub(super) struct NewCountConnections<N> {
+    inner: N,
+    metrics: TlsRouteMetrics,
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct CountConnections<S> {
+    inner: S,
+    connections: Arc<Counter>,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct Labels(ParentRef, RouteRef, Option<tls::ServerId>);
+
+// === impl TlsRouteMetrics ===
+
+impl TlsRouteMetrics {
+    fn open_total(&self, pr: ParentRef, rr: RouteRef, sni: Option<tls::ServerId>) -> Arc<Counter> {
+        self.0
+            .lock()
+            .entry(Labels(pr, rr, sni))
+            .or_default()
+            .clone()
+    }
+}
+
+impl FmtMetrics for TlsRouteMetrics {
+    fn fmt_metrics(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let open = self.0.lock();
+        if !open.is_empty() {
+            outbound_tls_route_open_total.fmt_help(f)?;
+            outbound_tls_route_open_total.fmt_scopes(f, open.iter(), |c| c)?;
+        }
+        drop(open);
+
+        Ok(())
+    }
+}
+
+impl FmtLabels for Labels {
+    fn fmt_labels(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Labels(parent, route, sni) = self;
+
+        write_service_meta_labels("parent", parent, f)?;
+        f.write_char(',')?;
+        write_meta_labels("route", route, f)?;
+        match sni {
+            Some(sni) => write!(f, ",sni=\"{sni}\"")?,
+            None => write!(f, ",sni=\"\"")?,
+        }
+
+        Ok(())
+    }
+}
+
+// === impl NewCountConnections ===
+
+impl<N> NewCountConnections<N> {
+    pub(super) fn layer(metrics: TlsRouteMetrics) -> impl svc::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            metrics: metrics.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewCountConnections<N>
+where
+    T: svc::Param<ParentRef> + svc::Param<RouteRef> + svc::Param<Option<tls::ServerId>>,
+    N: svc::NewService<T>,
+{
+    type Service = CountConnections<N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let connections = self
+            .metrics
+            .open_total(target.param(), target.param(), target.param());
+        CountConnections {
+            inner: self.inner.new_service(target),
+            connections,
+        }
+    }
+}
+
+// === impl CountConnections ===
+
+impl<I, S> svc::Service<I> for CountConnections<S>
+where
+    S: svc::Service<I>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, io: I) -> Self::Future {
+        self.connections.incr();
+        self.inner.call(io)
+    }
+}
@@ -0,0 +1,52 @@
+use super::*;
+use linkerd_app_core::detect::Detect;
+use std::str::FromStr;
+use tokio_test::io;
+
+const CLIENT_HELLO: &[u8] =
+    include_bytes!("../../../../tls/src/server/testdata/example-com-client-hello.bin");
+
+#[tokio::test(flavor = "current_thread")]
+async fn detects_sni() {
+    let _trace = linkerd_tracing::test::trace_init();
+
+    let (head, tail) = CLIENT_HELLO.split_at(CLIENT_HELLO.len() / 2);
+    let mut buf = BytesMut::with_capacity(DetectSni::CAPACITY);
+    let mut io = io::Builder::new().read(head).read(tail).build();
+    let sni = DetectSni::default()
+        .detect(&mut io, &mut buf)
+        .await
+        .unwrap();
+    assert_eq!(sni, Some(tls::ServerId::from_str("example.com").unwrap()));
+    assert_eq!(&buf[..], CLIENT_HELLO);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn not_tls() {
+    let _trace = linkerd_tracing::test::trace_init();
+
+    const REQ: &[u8] = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+    let mut buf = BytesMut::with_capacity(DetectSni::CAPACITY);
+    let mut io = io::Builder::new().read(REQ).build();
+    let sni = DetectSni::default()
+        .detect(&mut io, &mut buf)
+        .await
+        .unwrap();
+    assert_eq!(sni, None);
+    assert_eq!(&buf[..], REQ);
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn incomplete_client_hello() {
+    let _trace = linkerd_tracing::test::trace_init();
+
+    let partial = &CLIENT_HELLO[..CLIENT_HELLO.len() / 2];
+    let mut buf = BytesMut::with_capacity(DetectSni::CAPACITY);
+    let mut io = io::Builder::new().read(partial).build();
+    let sni = DetectSni::default()
+        .detect(&mut io, &mut buf)
+        .await
+        .unwrap();
+    assert_eq!(sni, None);
+    assert_eq!(&buf[..], partial);
+}
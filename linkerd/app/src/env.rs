@@ -2,24 +2,32 @@ use crate::core::{
     addr,
     config::*,
     control::{Config as ControlConfig, ControlAddr},
-    proxy::http::{h1, h2},
+    proxy::http::{
+        self,
+        balance::{SlowStartConfig, SlowStartCurve, ZoneAffinityConfig},
+        h1, h2,
+    },
+    svc::AdaptiveLimitConfig,
     tls,
-    transport::{Keepalive, ListenAddr},
+    transport::{proxy_protocol, Keepalive, ListenAddr},
     Addr, AddrMatch, Conditional, IpNet,
 };
 use crate::{dns, gateway, identity, inbound, oc_collector, outbound, policy};
 use rangemap::RangeInclusiveSet;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fs,
     net::{IpAddr, SocketAddr},
     path::PathBuf,
     str::FromStr,
+    sync::Arc,
     time::Duration,
 };
 use thiserror::Error;
 use tracing::{debug, error, info, warn};
 
+mod outbound_policy;
+
 /// The strings used to build a configuration.
 pub trait Strings {
     /// Retrieves the value for the key `key`.
@@ -88,6 +96,40 @@ pub enum ParseError {
     InvalidTrustAnchors,
     #[error("not a valid port policy: {0}")]
     InvalidPortPolicy(String),
+    #[error("not a valid slow-start curve: {0}")]
+    InvalidSlowStartCurve(String),
+    #[error("not a valid balancer load: {0}")]
+    InvalidBalancerLoad(String),
+    #[error("not a valid hedge delay: {0}")]
+    InvalidHedgeDelay(String),
+    #[error("not a valid route hedge: {0}")]
+    InvalidRouteHedge(String),
+    #[error("not a valid header name")]
+    NotAHeaderName,
+    #[error("not a non-negative, finite number of standard deviations")]
+    NotAStdevFactor,
+    #[error("not a valid rate limit key: {0}")]
+    InvalidRateLimitKey(String),
+    #[error("not a valid parent service: {0}")]
+    InvalidParentService(String),
+    #[error("not a valid route retry: {0}")]
+    InvalidRouteRetry(String),
+    #[error("not valid route timeouts: {0}")]
+    InvalidRouteTimeouts(String),
+    #[error("not a valid route mirror: {0}")]
+    InvalidRouteMirror(String),
+    #[error("not a valid URL rewrite: {0}")]
+    InvalidUrlRewrite(String),
+    #[error("not a valid header modifier: {0}")]
+    InvalidHeaderModifier(String),
+    #[error("not a valid TLS route: {0}")]
+    InvalidTlsRoute(String),
+    #[error("not a valid opaque filter: {0}")]
+    InvalidOpaqueFilter(String),
+    #[error("not a valid JWT claim: {0}")]
+    InvalidJwtClaim(String),
+    #[error("not a valid external authorization failure mode: {0}")]
+    InvalidExtAuthzFailureMode(String),
 }
 
 // Environment variables to look at when loading the configuration
@@ -100,6 +142,10 @@ pub const ENV_METRICS_RETAIN_IDLE: &str = "LINKERD2_PROXY_METRICS_RETAIN_IDLE";
 
 const ENV_INGRESS_MODE: &str = "LINKERD2_PROXY_INGRESS_MODE";
 
+/// Configures the outbound proxy to serve applications that explicitly use it
+/// as an HTTP forward proxy (i.e. via CONNECT or absolute-form requests).
+const ENV_FORWARD_PROXY_MODE: &str = "LINKERD2_PROXY_FORWARD_PROXY_MODE";
+
 const ENV_INBOUND_HTTP_QUEUE_CAPACITY: &str = "LINKERD2_PROXY_INBOUND_HTTP_QUEUE_CAPACITY";
 const ENV_INBOUND_HTTP_FAILFAST_TIMEOUT: &str = "LINKERD2_PROXY_INBOUND_HTTP_FAILFAST_TIMEOUT";
 
@@ -127,6 +173,88 @@ const ENV_OUTBOUND_MAX_IDLE_CONNS_PER_ENDPOINT: &str =
 pub const ENV_INBOUND_MAX_IN_FLIGHT: &str = "LINKERD2_PROXY_INBOUND_MAX_IN_FLIGHT";
 pub const ENV_OUTBOUND_MAX_IN_FLIGHT: &str = "LINKERD2_PROXY_OUTBOUND_MAX_IN_FLIGHT";
 
+/// Enables adaptive concurrency limits, bounded by the `MAX_IN_FLIGHT` limits.
+/// Inbound limits apply per server and outbound limits apply per backend.
+const ENV_INBOUND_ADAPTIVE_CONCURRENCY: &str = "LINKERD2_PROXY_INBOUND_ADAPTIVE_CONCURRENCY";
+const ENV_OUTBOUND_ADAPTIVE_CONCURRENCY: &str = "LINKERD2_PROXY_OUTBOUND_ADAPTIVE_CONCURRENCY";
+const ENV_INBOUND_ADAPTIVE_CONCURRENCY_INITIAL_LIMIT: &str =
+    "LINKERD2_PROXY_INBOUND_ADAPTIVE_CONCURRENCY_INITIAL_LIMIT";
+const ENV_OUTBOUND_ADAPTIVE_CONCURRENCY_INITIAL_LIMIT: &str =
+    "LINKERD2_PROXY_OUTBOUND_ADAPTIVE_CONCURRENCY_INITIAL_LIMIT";
+const ENV_INBOUND_ADAPTIVE_CONCURRENCY_MIN_LIMIT: &str =
+    "LINKERD2_PROXY_INBOUND_ADAPTIVE_CONCURRENCY_MIN_LIMIT";
+const ENV_OUTBOUND_ADAPTIVE_CONCURRENCY_MIN_LIMIT: &str =
+    "LINKERD2_PROXY_OUTBOUND_ADAPTIVE_CONCURRENCY_MIN_LIMIT";
+
+/// The zone in which the proxy runs. When set, outbound load balancers prefer
+/// endpoints whose zone label matches, spilling over to other zones when the
+/// local zone has too few endpoints or too few of them are ready.
+const ENV_ZONE: &str = "LINKERD2_PROXY_ZONE";
+
+/// The destination endpoint label that describes each endpoint's zone.
+/// Defaults to `zone`.
+const ENV_OUTBOUND_ZONE_LABEL: &str = "LINKERD2_PROXY_OUTBOUND_ZONE_LABEL";
+const ENV_OUTBOUND_ZONE_MIN_LOCAL_PERCENT: &str = "LINKERD2_PROXY_OUTBOUND_ZONE_MIN_LOCAL_PERCENT";
+const ENV_OUTBOUND_ZONE_MIN_READY_PERCENT: &str = "LINKERD2_PROXY_OUTBOUND_ZONE_MIN_READY_PERCENT";
+
+/// Configures peak-EWMA balancers to ramp up the share of traffic sent to
+/// newly discovered endpoints over the given window. Each endpoint starts at
+/// the configured percentage of its full weight, which grows either
+/// `linear`ly or `exponential`ly. Slow-start is disabled unless a window is
+/// set.
+///
+/// This configuration applies to every outbound backend that uses a peak-EWMA
+/// balancer; backends configured with other load balancing algorithms are
+/// not slowly started.
+const ENV_OUTBOUND_SLOW_START_WINDOW: &str = "LINKERD2_PROXY_OUTBOUND_SLOW_START_WINDOW";
+const ENV_OUTBOUND_SLOW_START_MIN_WEIGHT_PERCENT: &str =
+    "LINKERD2_PROXY_OUTBOUND_SLOW_START_MIN_WEIGHT_PERCENT";
+const ENV_OUTBOUND_SLOW_START_CURVE: &str = "LINKERD2_PROXY_OUTBOUND_SLOW_START_CURVE";
+
+/// Configures the header, in addition to `grpc-timeout`, with which callers may
+/// bound the duration of requests on outbound HTTP and gRPC routes, like
+/// `l5d-timeout: 500ms`. The header is not forwarded. Defaults to
+/// `l5d-timeout`; an empty value disables it.
+const ENV_OUTBOUND_DEADLINE_HEADER: &str = "LINKERD2_PROXY_OUTBOUND_DEADLINE_HEADER";
+
+/// Rewrites requests on inbound HTTP routes, which the policy API cannot yet
+/// configure. `URL_REWRITES` is a comma-separated list of
+/// `<prefix>=[<authority>]<path>` entries, which rewrite requests on route
+/// rules that only match the path `<prefix>` as for
+/// `ENV_OUTBOUND_ROUTE_URL_REWRITES`.
+const ENV_INBOUND_ROUTE_URL_REWRITES: &str = "LINKERD2_PROXY_INBOUND_ROUTE_URL_REWRITES";
+
+/// Limits the rate of requests on inbound HTTP and gRPC routes that do not
+/// configure a rate limit, which the policy API cannot yet express. Each route
+/// permits `REQUESTS_PER_SECOND` requests on average and up to `BURST` requests
+/// at once (by default, `REQUESTS_PER_SECOND`). `KEY` determines whether
+/// requests share a limit per `route` (the default), per `client-identity`, or
+/// per `client-ip`. Rate limiting is disabled unless `REQUESTS_PER_SECOND` is
+/// set.
+const ENV_INBOUND_RATE_LIMIT_REQUESTS_PER_SECOND: &str =
+    "LINKERD2_PROXY_INBOUND_RATE_LIMIT_REQUESTS_PER_SECOND";
+const ENV_INBOUND_RATE_LIMIT_BURST: &str = "LINKERD2_PROXY_INBOUND_RATE_LIMIT_BURST";
+const ENV_INBOUND_RATE_LIMIT_KEY: &str = "LINKERD2_PROXY_INBOUND_RATE_LIMIT_KEY";
+
+/// Authorizes inbound HTTP and gRPC requests that present a valid JWT bearer
+/// token, which the policy API cannot yet configure. Token signatures are
+/// validated against the JWKS in the `JWKS_PATH` file. If set, tokens must be
+/// issued by `ISSUER`, include one of the comma-separated `AUDIENCES`, and
+/// include each of the comma-separated `<claim>=<value>` `CLAIMS`. JWT
+/// authentication is disabled unless `JWKS_PATH` is set.
+const ENV_INBOUND_JWT_JWKS_PATH: &str = "LINKERD2_PROXY_INBOUND_JWT_JWKS_PATH";
+const ENV_INBOUND_JWT_ISSUER: &str = "LINKERD2_PROXY_INBOUND_JWT_ISSUER";
+const ENV_INBOUND_JWT_AUDIENCES: &str = "LINKERD2_PROXY_INBOUND_JWT_AUDIENCES";
+const ENV_INBOUND_JWT_CLAIMS: &str = "LINKERD2_PROXY_INBOUND_JWT_CLAIMS";
+
+/// Audits rather than enforces the authorization policies of inbound servers
+/// and routes, which the policy API cannot yet configure. Connections and
+/// requests that would be denied are permitted, logged, and counted. `SERVERS`
+/// and `ROUTES` are comma-separated lists of server and route names, where `*`
+/// matches all names.
+const ENV_INBOUND_AUDIT_SERVERS: &str = "LINKERD2_PROXY_INBOUND_AUDIT_SERVERS";
+const ENV_INBOUND_AUDIT_ROUTES: &str = "LINKERD2_PROXY_INBOUND_AUDIT_ROUTES";
+
 const ENV_OUTBOUND_DISABLE_INFORMATIONAL_HEADERS: &str =
     "LINKERD2_PROXY_OUTBOUND_DISABLE_INFORMATIONAL_HEADERS";
 
@@ -168,6 +296,38 @@ pub const ENV_INBOUND_PORTS_REQUIRE_IDENTITY: &str =
 
 pub const ENV_INBOUND_PORTS_REQUIRE_TLS: &str = "LINKERD2_PROXY_INBOUND_PORTS_REQUIRE_TLS";
 
+/// Constrains which inbound ports expect connections to begin with a PROXY
+/// protocol (v1 or v2) header, as sent by L4 load balancers. When a header is
+/// read, the client address it describes replaces the connection's peer
+/// address. The connection's original destination address is always used for
+/// routing and policy; the header's destination is only logged.
+///
+/// Connections on `REQUIRE` ports that do not include a header are refused.
+/// Connections on `ALLOW` ports may omit the header. If a port is in both
+/// lists, the header is required.
+pub const ENV_INBOUND_PORTS_REQUIRE_PROXY_PROTOCOL: &str =
+    "LINKERD2_PROXY_INBOUND_PORTS_REQUIRE_PROXY_PROTOCOL";
+pub const ENV_INBOUND_PORTS_ALLOW_PROXY_PROTOCOL: &str =
+    "LINKERD2_PROXY_INBOUND_PORTS_ALLOW_PROXY_PROTOCOL";
+
+/// Constrains which clients may send PROXY protocol headers to the inbound
+/// proxy, as a comma-separated list of networks. Connections from other
+/// clients are refused on `REQUIRE` ports and handled with their socket
+/// addresses on `ALLOW` ports. This must be set when any PROXY protocol ports
+/// are configured.
+pub const ENV_INBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS: &str =
+    "LINKERD2_PROXY_INBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS";
+
+/// Like `ENV_INBOUND_PORTS_*_PROXY_PROTOCOL` and
+/// `ENV_INBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS`, but for the outbound proxy's
+/// original destination ports. These only apply in ingress mode.
+pub const ENV_OUTBOUND_PORTS_REQUIRE_PROXY_PROTOCOL: &str =
+    "LINKERD2_PROXY_OUTBOUND_PORTS_REQUIRE_PROXY_PROTOCOL";
+pub const ENV_OUTBOUND_PORTS_ALLOW_PROXY_PROTOCOL: &str =
+    "LINKERD2_PROXY_OUTBOUND_PORTS_ALLOW_PROXY_PROTOCOL";
+pub const ENV_OUTBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS: &str =
+    "LINKERD2_PROXY_OUTBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS";
+
 /// Configures the default port policy for inbound connections.
 ///
 /// This must parse to a valid port policy (one of: `deny`, `authenticated`,
@@ -181,6 +341,26 @@ pub const ENV_POLICY_SVC_BASE: &str = "LINKERD2_PROXY_POLICY_SVC";
 pub const ENV_POLICY_WORKLOAD: &str = "LINKERD2_PROXY_POLICY_WORKLOAD";
 pub const ENV_POLICY_CLUSTER_NETWORKS: &str = "LINKERD2_PROXY_POLICY_CLUSTER_NETWORKS";
 
+/// Configures the external authorization service that is used by inbound
+/// routes' external authorization filters.
+///
+/// If this is not set, external authorization checks fail.
+pub const ENV_EXT_AUTHZ_SVC_BASE: &str = "LINKERD2_PROXY_EXT_AUTHZ_SVC";
+
+/// Checks requests on every inbound HTTP and gRPC route with the external
+/// authorization service, which the policy API cannot yet configure. Checks
+/// are enabled when `ENABLED` is true, which requires that the service is
+/// configured by `ENV_EXT_AUTHZ_SVC_BASE`. The service must respond within
+/// `TIMEOUT`; otherwise, requests fail unless `FAILURE_MODE` is `allow` rather
+/// than `deny` (the default). If `CACHE_TTL` is set, decisions are cached for
+/// that long. `HEADERS` is a comma-separated list of the request headers that
+/// are sent to the service.
+const ENV_INBOUND_EXT_AUTHZ_ENABLED: &str = "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_ENABLED";
+const ENV_INBOUND_EXT_AUTHZ_TIMEOUT: &str = "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_TIMEOUT";
+const ENV_INBOUND_EXT_AUTHZ_FAILURE_MODE: &str = "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_FAILURE_MODE";
+const ENV_INBOUND_EXT_AUTHZ_CACHE_TTL: &str = "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_CACHE_TTL";
+const ENV_INBOUND_EXT_AUTHZ_HEADERS: &str = "LINKERD2_PROXY_INBOUND_EXT_AUTHZ_HEADERS";
+
 pub const ENV_INBOUND_IPS: &str = "LINKERD2_PROXY_INBOUND_IPS";
 
 pub const ENV_IDENTITY_DISABLED: &str = "LINKERD2_PROXY_IDENTITY_DISABLED";
@@ -222,6 +402,13 @@ const ENV_INITIAL_STREAM_WINDOW_SIZE: &str = "LINKERD2_PROXY_HTTP2_INITIAL_STREA
 const ENV_INITIAL_CONNECTION_WINDOW_SIZE: &str =
     "LINKERD2_PROXY_HTTP2_INITIAL_CONNECTION_WINDOW_SIZE";
 
+/// Enables tunneling HTTP/1.1 upgrades (i.e. WebSockets) over HTTP/2 extended
+/// CONNECT streams between proxies.
+///
+/// Upgrades are only tunneled to proxies that advertise support for extended
+/// CONNECT streams. Otherwise, they use dedicated HTTP/1.1 connections.
+pub const ENV_HTTP2_EXTENDED_CONNECT: &str = "LINKERD2_PROXY_HTTP2_EXTENDED_CONNECT";
+
 const ENV_INBOUND_HTTP1_CONNECTION_POOL_IDLE_TIMEOUT: &str =
     "LINKERD2_PROXY_INBOUND_HTTP1_CONNECTION_POOL_IDLE_TIMEOUT";
 const ENV_OUTBOUND_HTTP1_CONNECTION_POOL_IDLE_TIMEOUT: &str =
@@ -306,6 +493,20 @@ const DEFAULT_OUTBOUND_MAX_IDLE_CONNS_PER_ENDPOINT: usize = usize::MAX;
 const DEFAULT_INBOUND_MAX_IN_FLIGHT: usize = 100_000;
 const DEFAULT_OUTBOUND_MAX_IN_FLIGHT: usize = 100_000;
 
+// By default, inbound requests fail if the external authorization service does
+// not respond within a second.
+const DEFAULT_INBOUND_EXT_AUTHZ_TIMEOUT: Duration = Duration::from_secs(1);
+
+// By default, outbound traffic spills over to other zones when less than 20%
+// of a balancer's endpoints are in the local zone, or when less than 80% of
+// the local endpoints are ready.
+const DEFAULT_OUTBOUND_ZONE_MIN_LOCAL_PERCENT: u32 = 20;
+const DEFAULT_OUTBOUND_ZONE_MIN_READY_PERCENT: u32 = 80;
+const DEFAULT_OUTBOUND_ZONE_LABEL: &str = "zone";
+
+const DEFAULT_OUTBOUND_SLOW_START_MIN_WEIGHT_PERCENT: u32 = 10;
+const DEFAULT_OUTBOUND_SLOW_START_CURVE: SlowStartCurve = SlowStartCurve::Linear;
+const DEFAULT_OUTBOUND_DEADLINE_HEADER: &str = "l5d-timeout";
 const DEFAULT_DESTINATION_PROFILE_SUFFIXES: &str = "svc.cluster.local.";
 const DEFAULT_DESTINATION_PROFILE_SKIP_TIMEOUT: Duration = Duration::from_millis(500);
 
@@ -366,6 +567,46 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
     let inbound_max_in_flight = parse(strings, ENV_INBOUND_MAX_IN_FLIGHT, parse_number);
     let outbound_max_in_flight = parse(strings, ENV_OUTBOUND_MAX_IN_FLIGHT, parse_number);
 
+    let inbound_adaptive_concurrency = parse(strings, ENV_INBOUND_ADAPTIVE_CONCURRENCY, parse_bool);
+    let outbound_adaptive_concurrency =
+        parse(strings, ENV_OUTBOUND_ADAPTIVE_CONCURRENCY, parse_bool);
+    let inbound_adaptive_concurrency_initial = parse(
+        strings,
+        ENV_INBOUND_ADAPTIVE_CONCURRENCY_INITIAL_LIMIT,
+        parse_number,
+    );
+    let outbound_adaptive_concurrency_initial = parse(
+        strings,
+        ENV_OUTBOUND_ADAPTIVE_CONCURRENCY_INITIAL_LIMIT,
+        parse_number,
+    );
+    let inbound_adaptive_concurrency_min = parse(
+        strings,
+        ENV_INBOUND_ADAPTIVE_CONCURRENCY_MIN_LIMIT,
+        parse_number,
+    );
+    let outbound_adaptive_concurrency_min = parse(
+        strings,
+        ENV_OUTBOUND_ADAPTIVE_CONCURRENCY_MIN_LIMIT,
+        parse_number,
+    );
+
+    let zone = strings.get(ENV_ZONE);
+    let outbound_zone_min_local = parse(strings, ENV_OUTBOUND_ZONE_MIN_LOCAL_PERCENT, parse_number);
+    let outbound_zone_min_ready = parse(strings, ENV_OUTBOUND_ZONE_MIN_READY_PERCENT, parse_number);
+
+    let outbound_slow_start_window = parse(strings, ENV_OUTBOUND_SLOW_START_WINDOW, parse_duration);
+    let outbound_slow_start_min_weight = parse(
+        strings,
+        ENV_OUTBOUND_SLOW_START_MIN_WEIGHT_PERCENT,
+        parse_number,
+    );
+    let outbound_slow_start_curve = parse(
+        strings,
+        ENV_OUTBOUND_SLOW_START_CURVE,
+        parse_slow_start_curve,
+    );
+
     let metrics_retain_idle = parse(strings, ENV_METRICS_RETAIN_IDLE, parse_duration);
 
     // DNS
@@ -402,6 +643,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
     let initial_stream_window_size = parse(strings, ENV_INITIAL_STREAM_WINDOW_SIZE, parse_number);
     let initial_connection_window_size =
         parse(strings, ENV_INITIAL_CONNECTION_WINDOW_SIZE, parse_number);
+    let h2_extended_connect = parse(strings, ENV_HTTP2_EXTENDED_CONNECT, parse_bool);
 
     let tap = parse_tap_config(strings);
 
@@ -412,6 +654,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         initial_connection_window_size: Some(
             initial_connection_window_size?.unwrap_or(DEFAULT_INITIAL_CONNECTION_WINDOW_SIZE),
         ),
+        extended_connect: h2_extended_connect?.unwrap_or(false),
         ..Default::default()
     };
 
@@ -429,11 +672,13 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         } else {
             debug!(allowed = ?ips, "Only allowing connections targeting `{}`", ENV_INBOUND_IPS);
         }
-        std::sync::Arc::new(ips)
+        Arc::new(ips)
     };
 
     let outbound = {
         let ingress_mode = parse(strings, ENV_INGRESS_MODE, parse_bool)?.unwrap_or(false);
+        let forward_proxy_mode =
+            parse(strings, ENV_FORWARD_PROXY_MODE, parse_bool)?.unwrap_or(false);
 
         // Instances can opt out of receiving informational headers by setting this configuration.
         // These headers are also omitted by default if ingress-mode is enabled.
@@ -483,6 +728,13 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
 
         let detect_protocol_timeout =
             outbound_detect_timeout?.unwrap_or(DEFAULT_OUTBOUND_DETECT_TIMEOUT);
+        let proxy_protocol = parse_proxy_protocol(
+            strings,
+            ENV_OUTBOUND_PORTS_REQUIRE_PROXY_PROTOCOL,
+            ENV_OUTBOUND_PORTS_ALLOW_PROXY_PROTOCOL,
+            ENV_OUTBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS,
+            detect_protocol_timeout,
+        )?;
 
         let tcp_queue_capacity =
             outbound_tcp_queue_capacity?.unwrap_or(DEFAULT_OUTBOUND_TCP_QUEUE_CAPACITY);
@@ -492,16 +744,34 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             outbound_http_queue_capacity?.unwrap_or(DEFAULT_OUTBOUND_HTTP_QUEUE_CAPACITY);
         let http_failfast_timeout =
             outbound_http_failfast_timeout?.unwrap_or(DEFAULT_OUTBOUND_HTTP_FAILFAST_TIMEOUT);
+        let max_in_flight_requests =
+            outbound_max_in_flight?.unwrap_or(DEFAULT_OUTBOUND_MAX_IN_FLIGHT);
+        let zone_affinity =
+            zone_affinity(zone?, outbound_zone_min_local?, outbound_zone_min_ready?);
+        let slow_start = slow_start(
+            outbound_slow_start_window?,
+            outbound_slow_start_min_weight?,
+            outbound_slow_start_curve?,
+        );
+        let zone_label = parse_outbound_zone_label(strings)?;
+        let deadline_header = parse_outbound_deadline_header(strings)?;
+        let local_policy = outbound_policy::parse_outbound_local_policy(strings)?;
 
         outbound::Config {
             ingress_mode,
+            forward_proxy_mode,
             emit_headers: !disable_headers,
             allow_discovery: AddrMatch::new(dst_profile_suffixes.clone(), dst_profile_networks),
             proxy: ProxyConfig {
                 server,
                 connect,
-                max_in_flight_requests: outbound_max_in_flight?
-                    .unwrap_or(DEFAULT_OUTBOUND_MAX_IN_FLIGHT),
+                max_in_flight_requests,
+                adaptive_concurrency_limit: adaptive_concurrency_limit(
+                    outbound_adaptive_concurrency?,
+                    outbound_adaptive_concurrency_initial?,
+                    outbound_adaptive_concurrency_min?,
+                    max_in_flight_requests,
+                ),
                 detect_protocol_timeout,
             },
             inbound_ips: inbound_ips.clone(),
@@ -514,6 +784,12 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 capacity: http_queue_capacity,
                 failfast_timeout: http_failfast_timeout,
             },
+            proxy_protocol,
+            zone_affinity,
+            zone_label,
+            slow_start,
+            deadline_header,
+            local_policy,
         }
     };
 
@@ -563,6 +839,13 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
 
         let detect_protocol_timeout =
             inbound_detect_timeout?.unwrap_or(DEFAULT_INBOUND_DETECT_TIMEOUT);
+        let proxy_protocol = parse_proxy_protocol(
+            strings,
+            ENV_INBOUND_PORTS_REQUIRE_PROXY_PROTOCOL,
+            ENV_INBOUND_PORTS_ALLOW_PROXY_PROTOCOL,
+            ENV_INBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS,
+            detect_protocol_timeout,
+        )?;
 
         // Ensure that connections that directly target the inbound port are secured (unless
         // identity is disabled).
@@ -630,19 +913,30 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             }
         };
 
+        let max_in_flight_requests =
+            inbound_max_in_flight?.unwrap_or(DEFAULT_INBOUND_MAX_IN_FLIGHT);
+
+        let local_policy = parse_inbound_local_policy(strings)?;
+
         inbound::Config {
             allow_discovery: dst_profile_suffixes.into_iter().collect(),
             proxy: ProxyConfig {
                 server,
                 connect,
-                max_in_flight_requests: inbound_max_in_flight?
-                    .unwrap_or(DEFAULT_INBOUND_MAX_IN_FLIGHT),
+                max_in_flight_requests,
+                adaptive_concurrency_limit: adaptive_concurrency_limit(
+                    inbound_adaptive_concurrency?,
+                    inbound_adaptive_concurrency_initial?,
+                    inbound_adaptive_concurrency_min?,
+                    max_in_flight_requests,
+                ),
                 detect_protocol_timeout,
             },
             policy,
             profile_skip_timeout: dst_profile_skip_timeout?
                 .unwrap_or(DEFAULT_DESTINATION_PROFILE_SKIP_TIMEOUT),
             allowed_ips: inbound_ips.into(),
+            local_policy,
 
             discovery_idle_timeout,
             http_request_queue: QueueConfig {
@@ -651,6 +945,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 failfast_timeout: inbound_http_failfast_timeout?
                     .unwrap_or(DEFAULT_INBOUND_HTTP_FAILFAST_TIMEOUT),
             },
+            proxy_protocol,
         }
     };
 
@@ -692,7 +987,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             EnvError::InvalidEnvVar
         })?;
 
-        let control = {
+        let mk_control = |addr: ControlAddr| {
             let connect = if addr.addr.is_loopback() {
                 inbound.proxy.connect.clone()
             } else {
@@ -707,7 +1002,13 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 },
             }
         };
-        policy::Config { control, workload }
+        let control = mk_control(addr);
+        let ext_authz = parse_control_addr(strings, ENV_EXT_AUTHZ_SVC_BASE)?.map(mk_control);
+        policy::Config {
+            control,
+            workload,
+            ext_authz,
+        }
     };
 
     let admin = super::admin::Config {
@@ -884,12 +1185,271 @@ fn parse_tap_config(
     if let Some(id) = tap_identity {
         return Ok(Some((
             addr,
-            vec![id].into_iter().map(tls::ClientId).collect(),
+            vec![id].into_iter().map(tls::ClientId::from).collect(),
         )));
     }
     Ok(None)
 }
 
+fn adaptive_concurrency_limit(
+    enabled: Option<bool>,
+    initial: Option<usize>,
+    min: Option<usize>,
+    max: usize,
+) -> Option<AdaptiveLimitConfig> {
+    if !enabled.unwrap_or(false) {
+        return None;
+    }
+    let default = AdaptiveLimitConfig::default();
+    Some(AdaptiveLimitConfig {
+        initial: initial.unwrap_or(default.initial),
+        min: min.unwrap_or(default.min),
+        max,
+        ..default
+    })
+}
+
+fn zone_affinity(
+    zone: Option<String>,
+    min_local_percent: Option<u32>,
+    min_ready_percent: Option<u32>,
+) -> Option<ZoneAffinityConfig> {
+    let zone = zone.as_deref().map(str::trim).filter(|z| !z.is_empty())?;
+    Some(ZoneAffinityConfig {
+        zone: zone.into(),
+        min_local_percent: min_local_percent
+            .unwrap_or(DEFAULT_OUTBOUND_ZONE_MIN_LOCAL_PERCENT)
+            .min(100),
+        min_ready_percent: min_ready_percent
+            .unwrap_or(DEFAULT_OUTBOUND_ZONE_MIN_READY_PERCENT)
+            .min(100),
+    })
+}
+
+fn parse_outbound_zone_label<S: Strings>(strings: &S) -> Result<Arc<str>, EnvError> {
+    let label = strings.get(ENV_OUTBOUND_ZONE_LABEL)?;
+    let label = label.as_deref().map(str::trim).filter(|l| !l.is_empty());
+    Ok(label.unwrap_or(DEFAULT_OUTBOUND_ZONE_LABEL).into())
+}
+
+fn slow_start(
+    window: Option<Duration>,
+    min_weight_percent: Option<u32>,
+    curve: Option<SlowStartCurve>,
+) -> Option<SlowStartConfig> {
+    let window = window.filter(|w| !w.is_zero())?;
+    Some(SlowStartConfig {
+        window,
+        min_weight_percent: min_weight_percent
+            .unwrap_or(DEFAULT_OUTBOUND_SLOW_START_MIN_WEIGHT_PERCENT)
+            .min(100),
+        curve: curve.unwrap_or(DEFAULT_OUTBOUND_SLOW_START_CURVE),
+    })
+}
+
+fn parse_outbound_deadline_header<S: Strings>(
+    strings: &S,
+) -> Result<Option<http::HeaderName>, EnvError> {
+    match strings.get(ENV_OUTBOUND_DEADLINE_HEADER)? {
+        None => Ok(Some(http::HeaderName::from_static(
+            DEFAULT_OUTBOUND_DEADLINE_HEADER,
+        ))),
+        Some(header) if header.trim().is_empty() => Ok(None),
+        Some(_) => parse(strings, ENV_OUTBOUND_DEADLINE_HEADER, parse_header_name),
+    }
+}
+
+fn parse_inbound_url_rewrites(
+    s: &str,
+) -> Result<Vec<inbound::policy::UrlRewriteConfig>, ParseError> {
+    let mut rewrites = Vec::new();
+    for entry in s.split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let (prefix, rewrite) = parse_url_rewrite(entry).ok_or_else(|| {
+            error!("Expected <prefix>=[<authority>]<path>; found: {entry}");
+            ParseError::InvalidUrlRewrite(entry.to_string())
+        })?;
+        rewrites.push(inbound::policy::UrlRewriteConfig { prefix, rewrite });
+    }
+    Ok(rewrites)
+}
+
+/// Parses a `<prefix>=[<authority>]<path>` rewrite, which replaces a path
+/// prefix and, optionally, the request's authority.
+fn parse_url_rewrite(s: &str) -> Option<(String, outbound::policy::http::filter::UrlRewrite)> {
+    use outbound::policy::http::filter::{ModifyPath, UrlRewrite};
+
+    let (prefix, rewrite) = s.split_once('=')?;
+    if !prefix.starts_with('/') {
+        return None;
+    }
+    let (authority, path) = rewrite.split_at(rewrite.find('/')?);
+    let authority = if authority.is_empty() {
+        None
+    } else {
+        Some(authority.parse().ok()?)
+    };
+    let rewrite = UrlRewrite {
+        authority,
+        path: Some(ModifyPath::ReplacePrefixMatch(path.to_string())),
+    };
+    Some((prefix.to_string(), rewrite))
+}
+
+fn parse_inbound_local_policy<S: Strings>(
+    strings: &S,
+) -> Result<inbound::policy::LocalConfig, EnvError> {
+    let rate_limit = match parse(
+        strings,
+        ENV_INBOUND_RATE_LIMIT_REQUESTS_PER_SECOND,
+        parse_number,
+    )? {
+        Some(requests_per_second) if requests_per_second > 0 => {
+            Some(inbound::policy::LocalRateLimit {
+                requests_per_second,
+                burst: parse(strings, ENV_INBOUND_RATE_LIMIT_BURST, parse_number)?
+                    .unwrap_or(requests_per_second),
+                key: parse(strings, ENV_INBOUND_RATE_LIMIT_KEY, parse_rate_limit_key)?
+                    .unwrap_or(inbound::policy::RateLimitKey::Route),
+            })
+        }
+        _ => None,
+    };
+
+    let url_rewrites = parse(
+        strings,
+        ENV_INBOUND_ROUTE_URL_REWRITES,
+        parse_inbound_url_rewrites,
+    )?
+    .unwrap_or_default();
+
+    let jwt = match parse(strings, ENV_INBOUND_JWT_JWKS_PATH, |s| Ok(PathBuf::from(s)))? {
+        Some(jwks_path) if !jwks_path.as_os_str().is_empty() => {
+            Some(inbound::policy::JwtAuthentication {
+                jwks_path,
+                issuer: strings
+                    .get(ENV_INBOUND_JWT_ISSUER)?
+                    .filter(|s| !s.is_empty()),
+                audiences: parse(strings, ENV_INBOUND_JWT_AUDIENCES, parse_names)?
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect(),
+                claims: parse(strings, ENV_INBOUND_JWT_CLAIMS, parse_jwt_claims)?
+                    .unwrap_or_default(),
+            })
+        }
+        _ => None,
+    };
+
+    let ext_authz = if parse(strings, ENV_INBOUND_EXT_AUTHZ_ENABLED, parse_bool)?.unwrap_or(false) {
+        if strings
+            .get(&format!("{}_ADDR", ENV_EXT_AUTHZ_SVC_BASE))?
+            .is_none()
+        {
+            error!(
+                "{} requires {}_ADDR",
+                ENV_INBOUND_EXT_AUTHZ_ENABLED, ENV_EXT_AUTHZ_SVC_BASE
+            );
+            return Err(EnvError::InvalidEnvVar);
+        }
+        Some(inbound::policy::ExtAuthz {
+            timeout: parse(strings, ENV_INBOUND_EXT_AUTHZ_TIMEOUT, parse_duration)?
+                .unwrap_or(DEFAULT_INBOUND_EXT_AUTHZ_TIMEOUT),
+            failure_mode: parse(
+                strings,
+                ENV_INBOUND_EXT_AUTHZ_FAILURE_MODE,
+                parse_ext_authz_failure_mode,
+            )?
+            .unwrap_or(inbound::policy::ExtAuthzFailureMode::Deny),
+            cache_ttl: parse(strings, ENV_INBOUND_EXT_AUTHZ_CACHE_TTL, parse_duration)?,
+            headers: parse(strings, ENV_INBOUND_EXT_AUTHZ_HEADERS, parse_header_names)?
+                .unwrap_or_default(),
+        })
+    } else {
+        None
+    };
+
+    let audit = inbound::policy::AuditConfig {
+        servers: parse(strings, ENV_INBOUND_AUDIT_SERVERS, parse_names)?.unwrap_or_default(),
+        routes: parse(strings, ENV_INBOUND_AUDIT_ROUTES, parse_names)?.unwrap_or_default(),
+    };
+
+    Ok(inbound::policy::LocalConfig {
+        rate_limit,
+        url_rewrites,
+        jwt,
+        ext_authz,
+        audit,
+    })
+}
+
+fn parse_names(s: &str) -> Result<Vec<String>, ParseError> {
+    Ok(s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+fn parse_ext_authz_failure_mode(
+    s: &str,
+) -> Result<inbound::policy::ExtAuthzFailureMode, ParseError> {
+    match s.trim() {
+        "allow" => Ok(inbound::policy::ExtAuthzFailureMode::Allow),
+        "deny" => Ok(inbound::policy::ExtAuthzFailureMode::Deny),
+        mode => {
+            error!("Expected allow or deny; found: {mode}");
+            Err(ParseError::InvalidExtAuthzFailureMode(mode.to_string()))
+        }
+    }
+}
+
+fn parse_jwt_claims(s: &str) -> Result<BTreeMap<String, String>, ParseError> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|claim| match claim.split_once('=') {
+            Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+            _ => {
+                error!("Expected <claim>=<value>; found: {claim}");
+                Err(ParseError::InvalidJwtClaim(claim.to_string()))
+            }
+        })
+        .collect()
+}
+
+fn parse_rate_limit_key(s: &str) -> Result<inbound::policy::RateLimitKey, ParseError> {
+    match s.trim() {
+        "route" => Ok(inbound::policy::RateLimitKey::Route),
+        "client-identity" => Ok(inbound::policy::RateLimitKey::ClientIdentity),
+        "client-ip" => Ok(inbound::policy::RateLimitKey::ClientIp),
+        key => Err(ParseError::InvalidRateLimitKey(key.to_string())),
+    }
+}
+
+fn parse_header_name(s: &str) -> Result<http::HeaderName, ParseError> {
+    http::HeaderName::from_str(s.trim()).map_err(|_| ParseError::NotAHeaderName)
+}
+
+fn parse_header_names(s: &str) -> Result<Vec<http::HeaderName>, ParseError> {
+    s.split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(parse_header_name)
+        .collect()
+}
+
+fn parse_slow_start_curve(s: &str) -> Result<SlowStartCurve, ParseError> {
+    match s {
+        "linear" => Ok(SlowStartCurve::Linear),
+        "exponential" => Ok(SlowStartCurve::Exponential),
+        curve => Err(ParseError::InvalidSlowStartCurve(curve.to_string())),
+    }
+}
+
 fn parse_bool(s: &str) -> Result<bool, ParseError> {
     s.parse().map_err(Into::into)
 }
@@ -982,6 +1542,31 @@ fn parse_port_range_set(s: &str) -> Result<RangeInclusiveSet<u16>, ParseError> {
     Ok(set)
 }
 
+fn parse_proxy_protocol(
+    strings: &dyn Strings,
+    require: &str,
+    allow: &str,
+    trusted: &str,
+    timeout: Duration,
+) -> Result<proxy_protocol::Config, EnvError> {
+    let required = parse(strings, require, parse_port_range_set)?.unwrap_or_default();
+    let allowed = parse(strings, allow, parse_port_range_set)?.unwrap_or_default();
+    let trusted_networks = parse(strings, trusted, parse_networks)?.unwrap_or_default();
+    // Headers describe the client's address, so only clients from explicitly
+    // trusted networks may send them.
+    if (!required.is_empty() || !allowed.is_empty()) && trusted_networks.is_empty() {
+        error!("{} or {} requires {}", require, allow, trusted);
+        return Err(EnvError::InvalidEnvVar);
+    }
+
+    Ok(proxy_protocol::Config {
+        required_ports: required.into_iter().collect(),
+        allowed_ports: allowed.into_iter().collect(),
+        trusted_networks: trusted_networks.into_iter().collect(),
+        timeout,
+    })
+}
+
 pub(super) fn parse_identity(s: &str) -> Result<identity::Name, ParseError> {
     identity::Name::from_str(s).map_err(|identity::InvalidName| {
         error!("Not a valid identity name: {}", s);
@@ -1265,6 +1850,12 @@ pub fn parse_identity_config<S: Strings>(
 mod tests {
     use super::*;
 
+    impl Strings for HashMap<&'static str, &'static str> {
+        fn get(&self, key: &str) -> Result<Option<String>, EnvError> {
+            Ok(HashMap::get(self, key).map(|s| s.to_string()))
+        }
+    }
+
     fn test_unit<F: Fn(u64) -> Duration>(unit: &str, to_duration: F) {
         for v in &[0, 1, 23, 456_789] {
             let d = to_duration(*v);
@@ -1498,4 +2089,275 @@ mod tests {
         assert!(dbg!(parse_port_range_set("69420")).is_err());
         assert!(dbg!(parse_port_range_set("1-69420")).is_err());
     }
+
+    #[test]
+    fn slow_start_config() {
+        assert_eq!(slow_start(None, Some(50), None), None);
+        assert_eq!(slow_start(Some(Duration::ZERO), None, None), None);
+        assert_eq!(
+            slow_start(
+                Some(Duration::from_secs(30)),
+                Some(200),
+                Some(parse_slow_start_curve("exponential").unwrap()),
+            ),
+            Some(SlowStartConfig {
+                window: Duration::from_secs(30),
+                min_weight_percent: 100,
+                curve: SlowStartCurve::Exponential,
+            })
+        );
+        assert_eq!(
+            parse_slow_start_curve("quadratic"),
+            Err(ParseError::InvalidSlowStartCurve("quadratic".to_string()))
+        );
+    }
+
+    #[test]
+    fn outbound_zone_label() {
+        assert_eq!(
+            &*parse_outbound_zone_label(&HashMap::<&str, &str>::new()).unwrap(),
+            "zone"
+        );
+
+        let env = HashMap::from([(ENV_OUTBOUND_ZONE_LABEL, "topology.kubernetes.io/zone")]);
+        assert_eq!(
+            &*parse_outbound_zone_label(&env).unwrap(),
+            "topology.kubernetes.io/zone"
+        );
+
+        let env = HashMap::from([(ENV_OUTBOUND_ZONE_LABEL, " ")]);
+        assert_eq!(&*parse_outbound_zone_label(&env).unwrap(), "zone");
+    }
+
+    #[test]
+    fn outbound_deadline_header() {
+        assert_eq!(
+            parse_outbound_deadline_header(&HashMap::<&str, &str>::new()).unwrap(),
+            Some(http::HeaderName::from_static("l5d-timeout"))
+        );
+
+        let env = HashMap::from([(ENV_OUTBOUND_DEADLINE_HEADER, "x-request-timeout")]);
+        assert_eq!(
+            parse_outbound_deadline_header(&env).unwrap(),
+            Some(http::HeaderName::from_static("x-request-timeout"))
+        );
+
+        let env = HashMap::from([(ENV_OUTBOUND_DEADLINE_HEADER, "")]);
+        assert_eq!(parse_outbound_deadline_header(&env).unwrap(), None);
+
+        let env = HashMap::from([(ENV_OUTBOUND_DEADLINE_HEADER, "not a header")]);
+        assert!(parse_outbound_deadline_header(&env).is_err());
+    }
+
+    #[test]
+    fn inbound_url_rewrites() {
+        let rewrites = parse_inbound_url_rewrites("/new=/legacy").unwrap();
+        assert_eq!(rewrites.len(), 1);
+        assert_eq!(rewrites[0].prefix, "/new");
+        assert!(parse_inbound_url_rewrites("new=/legacy").is_err());
+    }
+
+    #[test]
+    fn inbound_rate_limit() {
+        use inbound::policy::{LocalRateLimit, RateLimitKey};
+
+        let rate_limit = |env: HashMap<&'static str, &'static str>| {
+            parse_inbound_local_policy(&env).map(|local| local.rate_limit)
+        };
+
+        assert_eq!(rate_limit(HashMap::new()).unwrap(), None);
+        assert_eq!(
+            rate_limit(HashMap::from([(
+                ENV_INBOUND_RATE_LIMIT_REQUESTS_PER_SECOND,
+                "100"
+            )]))
+            .unwrap(),
+            Some(LocalRateLimit {
+                requests_per_second: 100,
+                burst: 100,
+                key: RateLimitKey::Route,
+            })
+        );
+        assert_eq!(
+            rate_limit(HashMap::from([
+                (ENV_INBOUND_RATE_LIMIT_REQUESTS_PER_SECOND, "10"),
+                (ENV_INBOUND_RATE_LIMIT_BURST, "50"),
+                (ENV_INBOUND_RATE_LIMIT_KEY, "client-identity"),
+            ]))
+            .unwrap(),
+            Some(LocalRateLimit {
+                requests_per_second: 10,
+                burst: 50,
+                key: RateLimitKey::ClientIdentity,
+            })
+        );
+        assert!(rate_limit(HashMap::from([
+            (ENV_INBOUND_RATE_LIMIT_REQUESTS_PER_SECOND, "10"),
+            (ENV_INBOUND_RATE_LIMIT_KEY, "client-port"),
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn inbound_jwt() {
+        let jwt = |env: HashMap<&'static str, &'static str>| {
+            parse_inbound_local_policy(&env).map(|local| local.jwt)
+        };
+
+        assert_eq!(jwt(HashMap::new()).unwrap(), None);
+        assert_eq!(
+            jwt(HashMap::from([(ENV_INBOUND_JWT_ISSUER, "issuer")])).unwrap(),
+            None
+        );
+        assert_eq!(
+            jwt(HashMap::from([
+                (ENV_INBOUND_JWT_JWKS_PATH, "/var/run/linkerd/jwks.json"),
+                (ENV_INBOUND_JWT_ISSUER, "issuer"),
+                (ENV_INBOUND_JWT_AUDIENCES, "web, api"),
+                (ENV_INBOUND_JWT_CLAIMS, "role=admin,tier="),
+            ]))
+            .unwrap(),
+            Some(inbound::policy::JwtAuthentication {
+                jwks_path: "/var/run/linkerd/jwks.json".into(),
+                issuer: Some("issuer".to_string()),
+                audiences: ["web".to_string(), "api".to_string()].into(),
+                claims: [
+                    ("role".to_string(), "admin".to_string()),
+                    ("tier".to_string(), "".to_string()),
+                ]
+                .into(),
+            })
+        );
+        assert!(jwt(HashMap::from([
+            (ENV_INBOUND_JWT_JWKS_PATH, "/var/run/linkerd/jwks.json"),
+            (ENV_INBOUND_JWT_CLAIMS, "admin"),
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn inbound_ext_authz() {
+        use inbound::policy::{ExtAuthz, ExtAuthzFailureMode};
+
+        let ext_authz = |env: HashMap<&'static str, &'static str>| {
+            parse_inbound_local_policy(&env).map(|local| local.ext_authz)
+        };
+
+        assert_eq!(ext_authz(HashMap::new()).unwrap(), None);
+        assert_eq!(
+            ext_authz(HashMap::from([(ENV_INBOUND_EXT_AUTHZ_ENABLED, "false")])).unwrap(),
+            None
+        );
+        assert_eq!(
+            ext_authz(HashMap::from([
+                (ENV_INBOUND_EXT_AUTHZ_ENABLED, "true"),
+                ("LINKERD2_PROXY_EXT_AUTHZ_SVC_ADDR", "127.0.0.1:9191"),
+            ]))
+            .unwrap(),
+            Some(ExtAuthz {
+                timeout: DEFAULT_INBOUND_EXT_AUTHZ_TIMEOUT,
+                failure_mode: ExtAuthzFailureMode::Deny,
+                cache_ttl: None,
+                headers: vec![],
+            })
+        );
+        assert_eq!(
+            ext_authz(HashMap::from([
+                (ENV_INBOUND_EXT_AUTHZ_ENABLED, "true"),
+                ("LINKERD2_PROXY_EXT_AUTHZ_SVC_ADDR", "127.0.0.1:9191"),
+                (ENV_INBOUND_EXT_AUTHZ_TIMEOUT, "250ms"),
+                (ENV_INBOUND_EXT_AUTHZ_FAILURE_MODE, "allow"),
+                (ENV_INBOUND_EXT_AUTHZ_CACHE_TTL, "10s"),
+                (ENV_INBOUND_EXT_AUTHZ_HEADERS, "x-user, authorization"),
+            ]))
+            .unwrap(),
+            Some(ExtAuthz {
+                timeout: Duration::from_millis(250),
+                failure_mode: ExtAuthzFailureMode::Allow,
+                cache_ttl: Some(Duration::from_secs(10)),
+                headers: vec![
+                    http::HeaderName::from_static("x-user"),
+                    http::header::AUTHORIZATION,
+                ],
+            })
+        );
+
+        // The service must be configured.
+        assert!(ext_authz(HashMap::from([(ENV_INBOUND_EXT_AUTHZ_ENABLED, "true")])).is_err());
+        assert!(ext_authz(HashMap::from([
+            (ENV_INBOUND_EXT_AUTHZ_ENABLED, "true"),
+            ("LINKERD2_PROXY_EXT_AUTHZ_SVC_ADDR", "127.0.0.1:9191"),
+            (ENV_INBOUND_EXT_AUTHZ_FAILURE_MODE, "ignore"),
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn proxy_protocol_config() {
+        let env = HashMap::from([
+            (ENV_INBOUND_PORTS_REQUIRE_PROXY_PROTOCOL, "8000-8999"),
+            (ENV_INBOUND_PORTS_ALLOW_PROXY_PROTOCOL, "80,8080"),
+            (ENV_INBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS, "10.0.0.0/8"),
+        ]);
+        let config = parse_proxy_protocol(
+            &env,
+            ENV_INBOUND_PORTS_REQUIRE_PROXY_PROTOCOL,
+            ENV_INBOUND_PORTS_ALLOW_PROXY_PROTOCOL,
+            ENV_INBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS,
+            DEFAULT_INBOUND_DETECT_TIMEOUT,
+        )
+        .unwrap();
+        // Port ranges are not expanded.
+        assert_eq!(config.required_ports, vec![8000..=8999]);
+        assert_eq!(config.mode(8443), Some(proxy_protocol::Mode::Strict));
+        assert_eq!(config.mode(8080), Some(proxy_protocol::Mode::Strict));
+        assert_eq!(config.mode(80), Some(proxy_protocol::Mode::Optional));
+        assert_eq!(config.mode(443), None);
+        assert!(config.is_trusted([10, 1, 1, 1].into()));
+        assert!(!config.is_trusted([192, 168, 1, 1].into()));
+
+        let config = parse_proxy_protocol(
+            &HashMap::<&str, &str>::new(),
+            ENV_INBOUND_PORTS_REQUIRE_PROXY_PROTOCOL,
+            ENV_INBOUND_PORTS_ALLOW_PROXY_PROTOCOL,
+            ENV_INBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS,
+            DEFAULT_INBOUND_DETECT_TIMEOUT,
+        )
+        .unwrap();
+        assert_eq!(config.mode(80), None);
+        assert!(!config.is_trusted([192, 168, 1, 1].into()));
+
+        // Trusted networks must be configured with PROXY protocol ports.
+        for (ports, value) in [
+            (ENV_INBOUND_PORTS_REQUIRE_PROXY_PROTOCOL, "4143"),
+            (ENV_INBOUND_PORTS_ALLOW_PROXY_PROTOCOL, "80"),
+        ] {
+            assert!(parse_proxy_protocol(
+                &HashMap::from([(ports, value)]),
+                ENV_INBOUND_PORTS_REQUIRE_PROXY_PROTOCOL,
+                ENV_INBOUND_PORTS_ALLOW_PROXY_PROTOCOL,
+                ENV_INBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS,
+                DEFAULT_INBOUND_DETECT_TIMEOUT,
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn inbound_audit() {
+        let audit = |env: HashMap<&'static str, &'static str>| {
+            parse_inbound_local_policy(&env).unwrap().audit
+        };
+
+        let config = audit(HashMap::new());
+        assert!(config.servers.is_empty());
+        assert!(config.routes.is_empty());
+
+        let config = audit(HashMap::from([
+            (ENV_INBOUND_AUDIT_SERVERS, "*"),
+            (ENV_INBOUND_AUDIT_ROUTES, "web, admin,"),
+        ]));
+        assert_eq!(config.servers, vec!["*".to_string()]);
+        assert_eq!(config.routes, vec!["web".to_string(), "admin".to_string()]);
+    }
 }
@@ -0,0 +1,1466 @@
+//! Configures client policy features that the policy API cannot yet express.
+//!
+//! The policy API remains the source of truth for outbound policy: these
+//! settings are layered onto each policy it returns, and never replace a value
+//! that the policy API sets. Each setting is disabled unless configured and
+//! applies only to the parent services, routes, or backends that it names:
+//!
+//! - Retries, hedging, timeouts, and failure accrual are only set where the
+//!   policy leaves them unset.
+//! - Mirrors, URL rewrites, gRPC response headers, TLS routes, and opaque
+//!   filters are added to the policy's routes; the policy's own routes and
+//!   filters are preserved.
+//! - Balancer loads replace the peak-EWMA load of the named backends, since the
+//!   policy API cannot configure any other load.
+//!
+//! These settings should be removed as the policy API learns to express them.
+
+use super::{
+    parse, parse_addr, parse_backoff, parse_duration, parse_header_name, parse_number,
+    parse_url_rewrite, EnvError, ParseError, Strings, DEFAULT_OUTBOUND_HTTP_FAILFAST_TIMEOUT,
+    DEFAULT_OUTBOUND_TCP_FAILFAST_TIMEOUT, DEFAULT_OUTBOUND_TCP_QUEUE_CAPACITY,
+};
+use crate::{
+    core::{exp_backoff::ExponentialBackoff, proxy::http, Addr, IpNet},
+    outbound,
+};
+use std::{collections::BTreeSet, str::FromStr, sync::Arc, time::Duration};
+use tracing::{error, info};
+
+/// Configures the proxy to retry failed requests on outbound HTTP and gRPC
+/// routes, which the policy API cannot yet configure. `RETRIES` is a
+/// comma-separated list of `<name>.<namespace>:<port>[/<route>][=<conditions>]`
+/// entries, each of which retries requests on the parent service's routes or,
+/// if `<route>` is set, only on its routes with that name. `<conditions>` is a
+/// `;`-separated list of the HTTP statuses (like `503`, `500-504`, or `5xx`)
+/// and gRPC codes (like `unavailable`) that are retried; when a route's
+/// protocol has no conditions, the responses that its failure policy
+/// classifies as failures are retried.
+///
+/// A request is retried up to `MAX_RETRIES` times (by default, once), unless
+/// its body is larger than `MAX_REQUEST_BYTES`. Each attempt may be bounded by
+/// a `TIMEOUT`, and attempts are separated by the
+/// `LINKERD2_PROXY_OUTBOUND_ROUTE_RETRY_EXP_BACKOFF_*` backoff. Each route's
+/// retries are limited to `BUDGET_PERCENT` (by default, 20) percent of its
+/// requests over the last `BUDGET_TTL` (by default, 10s), in addition to
+/// `BUDGET_MIN_RETRIES_PER_SECOND` (by default, 10) retries per second.
+const ENV_OUTBOUND_ROUTE_RETRIES: &str = "LINKERD2_PROXY_OUTBOUND_ROUTE_RETRIES";
+const ENV_OUTBOUND_ROUTE_RETRY_MAX_RETRIES: &str =
+    "LINKERD2_PROXY_OUTBOUND_ROUTE_RETRY_MAX_RETRIES";
+const ENV_OUTBOUND_ROUTE_RETRY_MAX_REQUEST_BYTES: &str =
+    "LINKERD2_PROXY_OUTBOUND_ROUTE_RETRY_MAX_REQUEST_BYTES";
+const ENV_OUTBOUND_ROUTE_RETRY_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_ROUTE_RETRY_TIMEOUT";
+const ENV_OUTBOUND_ROUTE_RETRY_BUDGET_PERCENT: &str =
+    "LINKERD2_PROXY_OUTBOUND_ROUTE_RETRY_BUDGET_PERCENT";
+const ENV_OUTBOUND_ROUTE_RETRY_BUDGET_MIN_RETRIES_PER_SECOND: &str =
+    "LINKERD2_PROXY_OUTBOUND_ROUTE_RETRY_BUDGET_MIN_RETRIES_PER_SECOND";
+const ENV_OUTBOUND_ROUTE_RETRY_BUDGET_TTL: &str = "LINKERD2_PROXY_OUTBOUND_ROUTE_RETRY_BUDGET_TTL";
+
+/// Configures the proxy to hedge slow requests on outbound HTTP and gRPC routes,
+/// which the policy API cannot yet configure. `HEDGES` is a comma-separated
+/// list of `<name>.<namespace>:<port>[/<route>][=idempotent]` entries, each of
+/// which hedges requests on the parent service's routes or, if `<route>` is
+/// set, only on its routes with that name. Only requests with idempotent
+/// methods are hedged unless the entry marks all of the routes' requests as
+/// `idempotent`; since gRPC requests are always `POST`s, gRPC routes are only
+/// hedged when so marked.
+///
+/// A second copy of a request is sent to a different endpoint once the request
+/// has been outstanding for `DELAY`, which is either a duration or a percentile
+/// of the route's recent response latencies, like `p90`, and must be set with
+/// `HEDGES`. At most `MAX_PERCENT` of a route's requests are hedged, and
+/// requests with bodies larger than `MAX_REQUEST_BYTES` are never hedged.
+const ENV_OUTBOUND_ROUTE_HEDGES: &str = "LINKERD2_PROXY_OUTBOUND_ROUTE_HEDGES";
+const ENV_OUTBOUND_ROUTE_HEDGE_DELAY: &str = "LINKERD2_PROXY_OUTBOUND_ROUTE_HEDGE_DELAY";
+const ENV_OUTBOUND_ROUTE_HEDGE_MAX_PERCENT: &str =
+    "LINKERD2_PROXY_OUTBOUND_ROUTE_HEDGE_MAX_PERCENT";
+const ENV_OUTBOUND_ROUTE_HEDGE_MAX_REQUEST_BYTES: &str =
+    "LINKERD2_PROXY_OUTBOUND_ROUTE_HEDGE_MAX_REQUEST_BYTES";
+
+/// Bounds requests on outbound HTTP and gRPC routes that do not configure each
+/// timeout. `TIMEOUTS` is a comma-separated list of
+/// `<name>.<namespace>:<port>[/<route>]=<timeouts>` entries, each of which
+/// bounds requests on the parent service's routes or, if `<route>` is set, only
+/// on its routes with that name. `<timeouts>` is a `;`-separated list of
+/// `response:<duration>`, which bounds the time until response headers are
+/// received, `request:<duration>`, which bounds the total duration of each
+/// request including its response body, and `idle:<duration>`, which bounds
+/// the time between response body frames. The policy API can only configure
+/// response timeouts.
+const ENV_OUTBOUND_ROUTE_TIMEOUTS: &str = "LINKERD2_PROXY_OUTBOUND_ROUTE_TIMEOUTS";
+
+/// Overrides the load balancing strategy of outbound HTTP and gRPC backends,
+/// which the policy API always configures as `peak-ewma`. This is a
+/// comma-separated list of `<name>.<namespace>:<port>=<load>` entries, each of
+/// which balances the named backend service by `least-request`, by
+/// `weighted-round-robin` over the weights set by endpoint discovery, or by
+/// `consistent-hash(<key>)`. Consistent hashing pins requests to endpoints by a
+/// key of `header:<name>`, `cookie:<name>`, or `client-ip`; hashing by client
+/// IP is only useful where the proxy serves remote clients, as in ingress mode.
+/// Backends that are not listed are balanced as the policy API configures.
+const ENV_OUTBOUND_BALANCER_LOADS: &str = "LINKERD2_PROXY_OUTBOUND_BALANCER_LOADS";
+
+/// Configures success-rate failure accrual for outbound policies that do not
+/// configure failure accrual, which the policy API cannot yet express.
+/// `PARENTS` is a comma-separated list of `<name>.<namespace>:<port>` parent
+/// services whose backends accrue failures. Each `INTERVAL` (by default, 10s),
+/// endpoints that served at least `MIN_REQUESTS` requests are
+/// compared when there are at least `MIN_ENDPOINTS` of them. Endpoints whose
+/// success rate is more than `STDEV_FACTOR` standard deviations below the mean
+/// are made unavailable for the
+/// `LINKERD2_PROXY_OUTBOUND_SUCCESS_RATE_ACCRUAL_EXP_BACKOFF_*` backoff, unless
+/// more than `MAX_EJECTION_PERCENT` of the backend's endpoints would be
+/// unavailable. Success-rate accrual is disabled unless `PARENTS` is set.
+const ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_PARENTS: &str =
+    "LINKERD2_PROXY_OUTBOUND_SUCCESS_RATE_ACCRUAL_PARENTS";
+const ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_INTERVAL: &str =
+    "LINKERD2_PROXY_OUTBOUND_SUCCESS_RATE_ACCRUAL_INTERVAL";
+const ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_MIN_REQUESTS: &str =
+    "LINKERD2_PROXY_OUTBOUND_SUCCESS_RATE_ACCRUAL_MIN_REQUESTS";
+const ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_MIN_ENDPOINTS: &str =
+    "LINKERD2_PROXY_OUTBOUND_SUCCESS_RATE_ACCRUAL_MIN_ENDPOINTS";
+const ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_STDEV_FACTOR: &str =
+    "LINKERD2_PROXY_OUTBOUND_SUCCESS_RATE_ACCRUAL_STDEV_FACTOR";
+const ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_MAX_EJECTION_PERCENT: &str =
+    "LINKERD2_PROXY_OUTBOUND_SUCCESS_RATE_ACCRUAL_MAX_EJECTION_PERCENT";
+
+/// Mirrors requests on the HTTP routes of outbound services, which the policy
+/// API cannot yet configure. `MIRRORS` is a comma-separated list of
+/// `<name>.<namespace>:<port>=<authority>` entries, each of which copies
+/// `PERCENT` (by default, 100) of the requests on the parent service's routes
+/// to the service discovered at `<authority>`. Requests with bodies larger than
+/// `MAX_REQUEST_BYTES` are not mirrored, and no more than `MAX_IN_FLIGHT`
+/// mirrored requests are sent to each mirror at once. Mirroring is disabled
+/// unless `MIRRORS` is set.
+const ENV_OUTBOUND_ROUTE_MIRRORS: &str = "LINKERD2_PROXY_OUTBOUND_ROUTE_MIRRORS";
+const ENV_OUTBOUND_ROUTE_MIRROR_PERCENT: &str = "LINKERD2_PROXY_OUTBOUND_ROUTE_MIRROR_PERCENT";
+const ENV_OUTBOUND_ROUTE_MIRROR_MAX_REQUEST_BYTES: &str =
+    "LINKERD2_PROXY_OUTBOUND_ROUTE_MIRROR_MAX_REQUEST_BYTES";
+const ENV_OUTBOUND_ROUTE_MIRROR_MAX_IN_FLIGHT: &str =
+    "LINKERD2_PROXY_OUTBOUND_ROUTE_MIRROR_MAX_IN_FLIGHT";
+
+/// Rewrites requests on the HTTP routes of outbound services, which the
+/// policy API cannot yet configure. `URL_REWRITES` is a comma-separated list of
+/// `<name>.<namespace>:<port><prefix>=[<authority>]<path>` entries. Requests on
+/// the parent service's route rules that only match the path `<prefix>` have
+/// that prefix replaced by `<path>` and, if one is set, their authority
+/// replaced by `<authority>`.
+const ENV_OUTBOUND_ROUTE_URL_REWRITES: &str = "LINKERD2_PROXY_OUTBOUND_ROUTE_URL_REWRITES";
+
+/// Sets response metadata on the gRPC routes of outbound services, which the
+/// policy API cannot yet configure. `GRPC_RESPONSE_HEADERS` is a
+/// comma-separated list of `<name>.<namespace>:<port>=<header>:<value>`
+/// entries, each of which sets `<header>` to `<value>` on the responses of the
+/// parent service's gRPC routes.
+const ENV_OUTBOUND_GRPC_RESPONSE_HEADERS: &str = "LINKERD2_PROXY_OUTBOUND_GRPC_RESPONSE_HEADERS";
+
+/// Routes the connections of outbound services by the server name in their TLS
+/// ClientHello, which the policy API cannot yet configure. `TLS_ROUTES` is a
+/// comma-separated list of `<name>.<namespace>:<port>/<sni>=<authority>`
+/// entries, each of which proxies the parent service's connections that
+/// indicate a server name matching `<sni>` (e.g. `api.example.com` or
+/// `*.example.com`) to the service discovered at `<authority>`, without
+/// terminating TLS. Other connections to the parent service are proxied as
+/// opaque connections.
+const ENV_OUTBOUND_TLS_ROUTES: &str = "LINKERD2_PROXY_OUTBOUND_TLS_ROUTES";
+
+/// Filters the opaque connections of outbound services, which the policy API
+/// cannot yet configure. `OPAQUE_FILTERS` is a comma-separated list of
+/// `<name>.<namespace>:<port>[@<network>]=<filter>` entries, each of which
+/// applies `<filter>` to the parent service's connections from clients in
+/// `<network>` or, if no network is set, from all clients. `<filter>` is
+/// either `forbidden`, which refuses connections, or `fail:<percent>`, which
+/// fails that percentage of connections.
+const ENV_OUTBOUND_OPAQUE_FILTERS: &str = "LINKERD2_PROXY_OUTBOUND_OPAQUE_FILTERS";
+
+/// Prefaces outbound connections to the non-meshed endpoints of backend
+/// services with a PROXY protocol v2 header, which the policy API cannot yet
+/// configure. This is a comma-separated list of `<name>.<namespace>:<port>`
+/// backend services. The header describes the proxy's local address, which is
+/// the application's address, and the local workload's identity. Meshed
+/// endpoints never receive a header.
+const ENV_OUTBOUND_PROXY_PROTOCOL_BACKENDS: &str =
+    "LINKERD2_PROXY_OUTBOUND_PROXY_PROTOCOL_BACKENDS";
+
+const DEFAULT_OUTBOUND_ROUTE_RETRY_MAX_RETRIES: usize = 1;
+const DEFAULT_OUTBOUND_ROUTE_RETRY_MAX_REQUEST_BYTES: usize = 64 * 1024;
+// Like ServiceProfile retry budgets, route retry budgets permit 20% of requests
+// to be retried, in addition to 10 retries per second.
+const DEFAULT_OUTBOUND_ROUTE_RETRY_BUDGET: outbound::policy::RetryBudget =
+    outbound::policy::RetryBudget {
+        retry_percent: 20,
+        min_retries_per_second: 10,
+        ttl: Duration::from_secs(10),
+    };
+const DEFAULT_OUTBOUND_ROUTE_RETRY_BACKOFF: ExponentialBackoff =
+    ExponentialBackoff::new_unchecked(Duration::from_millis(25), Duration::from_millis(250), 0.1);
+const DEFAULT_OUTBOUND_ROUTE_HEDGE_MAX_PERCENT: u32 = 10;
+const DEFAULT_OUTBOUND_ROUTE_HEDGE_MAX_REQUEST_BYTES: usize = 64 * 1024;
+const DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_MIN_REQUESTS: usize = 100;
+const DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_MIN_ENDPOINTS: usize = 5;
+const DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_STDEV_FACTOR: f64 = 1.9;
+const DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_MAX_EJECTION_PERCENT: u32 = 10;
+const DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_BACKOFF: ExponentialBackoff =
+    ExponentialBackoff::new_unchecked(Duration::from_secs(30), Duration::from_secs(300), 0.1);
+const DEFAULT_OUTBOUND_ROUTE_MIRROR_PERCENT: u32 = 100;
+const DEFAULT_OUTBOUND_ROUTE_MIRROR_MAX_REQUEST_BYTES: usize = 64 * 1024;
+const DEFAULT_OUTBOUND_ROUTE_MIRROR_MAX_IN_FLIGHT: usize = 100;
+// Mirror and TLS route backends are balanced like the backends the policy API
+// configures.
+const DEFAULT_OUTBOUND_LOCAL_BACKEND_EWMA: outbound::policy::PeakEwma =
+    outbound::policy::PeakEwma {
+        decay: Duration::from_secs(10),
+        default_rtt: Duration::from_millis(30),
+    };
+
+const OUTBOUND_ROUTE_RETRY_BASE: &str = "OUTBOUND_ROUTE_RETRY";
+const OUTBOUND_SUCCESS_RATE_ACCRUAL_BASE: &str = "OUTBOUND_SUCCESS_RATE_ACCRUAL";
+
+pub(super) fn parse_outbound_local_policy<S: Strings>(
+    strings: &S,
+) -> Result<outbound::policy::LocalConfig, EnvError> {
+    let retries = match parse(strings, ENV_OUTBOUND_ROUTE_RETRIES, parse_route_retries)? {
+        Some(retries) if !retries.is_empty() => {
+            let max_retries = parse(strings, ENV_OUTBOUND_ROUTE_RETRY_MAX_RETRIES, parse_number)?
+                .unwrap_or(DEFAULT_OUTBOUND_ROUTE_RETRY_MAX_RETRIES);
+            let max_request_bytes = parse(
+                strings,
+                ENV_OUTBOUND_ROUTE_RETRY_MAX_REQUEST_BYTES,
+                parse_number,
+            )?
+            .unwrap_or(DEFAULT_OUTBOUND_ROUTE_RETRY_MAX_REQUEST_BYTES);
+            let timeout = parse(strings, ENV_OUTBOUND_ROUTE_RETRY_TIMEOUT, parse_duration)?;
+            let backoff = parse_backoff(
+                strings,
+                OUTBOUND_ROUTE_RETRY_BASE,
+                DEFAULT_OUTBOUND_ROUTE_RETRY_BACKOFF,
+            )?;
+            let budget = outbound::policy::RetryBudget {
+                retry_percent: parse(
+                    strings,
+                    ENV_OUTBOUND_ROUTE_RETRY_BUDGET_PERCENT,
+                    parse_number,
+                )?
+                .unwrap_or(DEFAULT_OUTBOUND_ROUTE_RETRY_BUDGET.retry_percent),
+                min_retries_per_second: parse(
+                    strings,
+                    ENV_OUTBOUND_ROUTE_RETRY_BUDGET_MIN_RETRIES_PER_SECOND,
+                    parse_number,
+                )?
+                .unwrap_or(DEFAULT_OUTBOUND_ROUTE_RETRY_BUDGET.min_retries_per_second),
+                ttl: parse(strings, ENV_OUTBOUND_ROUTE_RETRY_BUDGET_TTL, parse_duration)?
+                    .unwrap_or(DEFAULT_OUTBOUND_ROUTE_RETRY_BUDGET.ttl),
+            };
+            retries
+                .into_iter()
+                .map(|retry| outbound::policy::RetryConfig {
+                    routes: retry.routes,
+                    http_conditions: retry.http_conditions,
+                    grpc_conditions: retry.grpc_conditions,
+                    max_retries,
+                    max_request_bytes,
+                    timeout,
+                    backoff: Some(backoff),
+                    budget,
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    let hedges = match parse(strings, ENV_OUTBOUND_ROUTE_HEDGES, parse_route_hedges)? {
+        Some(hedges) if !hedges.is_empty() => {
+            let delay = parse(strings, ENV_OUTBOUND_ROUTE_HEDGE_DELAY, parse_hedge_delay)?
+                .ok_or_else(|| {
+                    error!(
+                        "{} requires {}",
+                        ENV_OUTBOUND_ROUTE_HEDGES, ENV_OUTBOUND_ROUTE_HEDGE_DELAY
+                    );
+                    EnvError::InvalidEnvVar
+                })?;
+            let max_percent = parse(strings, ENV_OUTBOUND_ROUTE_HEDGE_MAX_PERCENT, parse_number)?
+                .unwrap_or(DEFAULT_OUTBOUND_ROUTE_HEDGE_MAX_PERCENT)
+                .min(100);
+            let max_request_bytes = parse(
+                strings,
+                ENV_OUTBOUND_ROUTE_HEDGE_MAX_REQUEST_BYTES,
+                parse_number,
+            )?
+            .unwrap_or(DEFAULT_OUTBOUND_ROUTE_HEDGE_MAX_REQUEST_BYTES);
+            hedges
+                .into_iter()
+                .map(|(routes, idempotent)| outbound::policy::HedgeConfig {
+                    routes,
+                    hedge: outbound::policy::RouteHedge {
+                        delay,
+                        max_percent,
+                        max_request_bytes,
+                        idempotent,
+                    },
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    let timeouts =
+        parse(strings, ENV_OUTBOUND_ROUTE_TIMEOUTS, parse_route_timeouts)?.unwrap_or_default();
+
+    let loads =
+        parse(strings, ENV_OUTBOUND_BALANCER_LOADS, parse_balancer_loads)?.unwrap_or_default();
+
+    let failure_accrual = match parse(
+        strings,
+        ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_PARENTS,
+        parse_parent_services,
+    )? {
+        Some(parents) if !parents.is_empty() => {
+            let accrual = outbound::policy::FailureAccrual::SuccessRate {
+                interval: parse(
+                    strings,
+                    ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_INTERVAL,
+                    parse_duration,
+                )?
+                .filter(|interval| !interval.is_zero())
+                .unwrap_or(DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_INTERVAL),
+                min_requests: parse(
+                    strings,
+                    ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_MIN_REQUESTS,
+                    parse_number,
+                )?
+                .unwrap_or(DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_MIN_REQUESTS),
+                min_endpoints: parse(
+                    strings,
+                    ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_MIN_ENDPOINTS,
+                    parse_number,
+                )?
+                .unwrap_or(DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_MIN_ENDPOINTS),
+                stdev_factor: parse(
+                    strings,
+                    ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_STDEV_FACTOR,
+                    parse_stdev_factor,
+                )?
+                .unwrap_or_else(|| {
+                    outbound::policy::StdevFactor::new(
+                        DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_STDEV_FACTOR,
+                    )
+                    .expect("default stdev factor must be valid")
+                }),
+                max_ejection_percent: parse(
+                    strings,
+                    ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_MAX_EJECTION_PERCENT,
+                    parse_number,
+                )?
+                .unwrap_or(DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_MAX_EJECTION_PERCENT)
+                .min(100),
+                backoff: parse_backoff(
+                    strings,
+                    OUTBOUND_SUCCESS_RATE_ACCRUAL_BASE,
+                    DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_BACKOFF,
+                )?,
+            };
+            parents
+                .into_iter()
+                .map(|parent| outbound::policy::FailureAccrualConfig { parent, accrual })
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    let mirrors = match parse(strings, ENV_OUTBOUND_ROUTE_MIRRORS, parse_route_mirrors)? {
+        Some(mirrors) if !mirrors.is_empty() => {
+            let percent = parse(strings, ENV_OUTBOUND_ROUTE_MIRROR_PERCENT, parse_number)?
+                .unwrap_or(DEFAULT_OUTBOUND_ROUTE_MIRROR_PERCENT)
+                .min(100);
+            let distribution =
+                outbound::policy::http::filter::Distribution::from_ratio(percent, 100)
+                    .expect("percentage must be a valid ratio");
+            let max_request_bytes = parse(
+                strings,
+                ENV_OUTBOUND_ROUTE_MIRROR_MAX_REQUEST_BYTES,
+                parse_number,
+            )?
+            .unwrap_or(DEFAULT_OUTBOUND_ROUTE_MIRROR_MAX_REQUEST_BYTES);
+            let max_in_flight = parse(
+                strings,
+                ENV_OUTBOUND_ROUTE_MIRROR_MAX_IN_FLIGHT,
+                parse_number,
+            )?
+            .unwrap_or(DEFAULT_OUTBOUND_ROUTE_MIRROR_MAX_IN_FLIGHT)
+            .max(1);
+            mirrors
+                .into_iter()
+                .map(|mirror| mirror.into_config(&distribution, max_request_bytes, max_in_flight))
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    let url_rewrites = parse(
+        strings,
+        ENV_OUTBOUND_ROUTE_URL_REWRITES,
+        parse_outbound_url_rewrites,
+    )?
+    .unwrap_or_default();
+
+    let grpc_response_headers = parse(
+        strings,
+        ENV_OUTBOUND_GRPC_RESPONSE_HEADERS,
+        parse_grpc_response_headers,
+    )?
+    .unwrap_or_default();
+
+    let tls_routes = parse(strings, ENV_OUTBOUND_TLS_ROUTES, parse_tls_routes)?.unwrap_or_default();
+
+    let opaque_filters =
+        parse(strings, ENV_OUTBOUND_OPAQUE_FILTERS, parse_opaque_filters)?.unwrap_or_default();
+
+    let proxy_protocol_backends = parse(
+        strings,
+        ENV_OUTBOUND_PROXY_PROTOCOL_BACKENDS,
+        parse_parent_services,
+    )?
+    .unwrap_or_default();
+
+    // Local configuration is layered onto the policy API's policies, so log
+    // which settings are in effect.
+    let configured = [
+        (ENV_OUTBOUND_ROUTE_RETRIES, !retries.is_empty()),
+        (ENV_OUTBOUND_ROUTE_HEDGES, !hedges.is_empty()),
+        (ENV_OUTBOUND_ROUTE_TIMEOUTS, !timeouts.is_empty()),
+        (ENV_OUTBOUND_BALANCER_LOADS, !loads.is_empty()),
+        (
+            ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_PARENTS,
+            !failure_accrual.is_empty(),
+        ),
+        (ENV_OUTBOUND_ROUTE_MIRRORS, !mirrors.is_empty()),
+        (ENV_OUTBOUND_ROUTE_URL_REWRITES, !url_rewrites.is_empty()),
+        (
+            ENV_OUTBOUND_GRPC_RESPONSE_HEADERS,
+            !grpc_response_headers.is_empty(),
+        ),
+        (ENV_OUTBOUND_TLS_ROUTES, !tls_routes.is_empty()),
+        (ENV_OUTBOUND_OPAQUE_FILTERS, !opaque_filters.is_empty()),
+        (
+            ENV_OUTBOUND_PROXY_PROTOCOL_BACKENDS,
+            !proxy_protocol_backends.is_empty(),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(env, configured)| configured.then_some(env))
+    .collect::<Vec<_>>();
+    if !configured.is_empty() {
+        info!(
+            ?configured,
+            "Outbound policies are supplemented by local configuration"
+        );
+    }
+
+    Ok(outbound::policy::LocalConfig {
+        retries,
+        hedges,
+        timeouts,
+        loads,
+        failure_accrual,
+        mirrors,
+        url_rewrites,
+        grpc_response_headers,
+        tls_routes,
+        opaque_filters,
+        proxy_protocol_backends,
+    })
+}
+
+/// A route's retry conditions, as configured by `ENV_OUTBOUND_ROUTE_RETRIES`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RouteRetries {
+    routes: outbound::policy::RouteScope,
+    http_conditions: Option<outbound::policy::http::StatusRanges>,
+    grpc_conditions: Option<outbound::policy::grpc::Codes>,
+}
+
+fn parse_route_retries(s: &str) -> Result<Vec<RouteRetries>, ParseError> {
+    let mut retries = Vec::new();
+    for entry in s.split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let invalid = || {
+            error!("Expected <name>.<namespace>:<port>[/<route>][=<conditions>]; found: {entry}");
+            ParseError::InvalidRouteRetry(entry.to_string())
+        };
+        let (routes, conditions) = match entry.split_once('=') {
+            Some((routes, conditions)) => (routes, Some(conditions)),
+            None => (entry, None),
+        };
+        let routes = parse_route_scope(routes.trim()).ok_or_else(invalid)?;
+
+        let mut statuses = Vec::new();
+        let mut codes = BTreeSet::new();
+        for condition in conditions.into_iter().flat_map(|c| c.split(';')) {
+            let condition = condition.trim();
+            if let Some(code) = parse_grpc_code(condition) {
+                codes.insert(code);
+            } else {
+                statuses.push(parse_status_range(condition).ok_or_else(invalid)?);
+            }
+        }
+        retries.push(RouteRetries {
+            routes,
+            http_conditions: if statuses.is_empty() {
+                None
+            } else {
+                Some(outbound::policy::http::StatusRanges(statuses.into()))
+            },
+            grpc_conditions: if codes.is_empty() {
+                None
+            } else {
+                Some(outbound::policy::grpc::Codes(Arc::new(codes)))
+            },
+        });
+    }
+    Ok(retries)
+}
+
+/// Parses an HTTP status (like `503`), range of statuses (like `500-504`), or
+/// class of statuses (like `5xx`).
+fn parse_status_range(s: &str) -> Option<std::ops::RangeInclusive<u16>> {
+    let status = |s: &str| s.parse::<u16>().ok().filter(|s| (100..=599).contains(s));
+    if let Some(class) = s.strip_suffix("xx") {
+        let class = class.parse::<u16>().ok().filter(|c| (1..=5).contains(c))?;
+        return Some(class * 100..=class * 100 + 99);
+    }
+    match s.split_once('-') {
+        Some((min, max)) => {
+            let (min, max) = (status(min.trim())?, status(max.trim())?);
+            if min > max {
+                return None;
+            }
+            Some(min..=max)
+        }
+        None => status(s).map(|s| s..=s),
+    }
+}
+
+/// Parses the name of a gRPC status code, like `unavailable` or
+/// `deadline-exceeded`.
+fn parse_grpc_code(s: &str) -> Option<u16> {
+    const CODES: [&str; 16] = [
+        "cancelled",
+        "unknown",
+        "invalid-argument",
+        "deadline-exceeded",
+        "not-found",
+        "already-exists",
+        "permission-denied",
+        "resource-exhausted",
+        "failed-precondition",
+        "aborted",
+        "out-of-range",
+        "unimplemented",
+        "internal",
+        "unavailable",
+        "data-loss",
+        "unauthenticated",
+    ];
+    let s = s.to_ascii_lowercase().replace('_', "-");
+    CODES
+        .iter()
+        .position(|code| *code == s)
+        .map(|i| i as u16 + 1)
+}
+
+/// A route mirror, as configured by `ENV_OUTBOUND_ROUTE_MIRRORS`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RouteMirror {
+    parent: outbound::policy::ParentService,
+    authority: String,
+}
+
+impl RouteMirror {
+    fn into_config(
+        self,
+        distribution: &outbound::policy::http::filter::Distribution,
+        max_request_bytes: usize,
+        max_in_flight: usize,
+    ) -> outbound::policy::MirrorConfig {
+        use outbound::policy::{
+            http::RequestMirror, Backend, BackendDispatcher, EndpointDiscovery, Load, Meta,
+            MirrorConfig, Queue,
+        };
+
+        let backend = Backend {
+            meta: Meta::new_default(self.authority.clone()),
+            // No more than `max_in_flight` mirrored requests are ever pending.
+            queue: Queue {
+                capacity: max_in_flight,
+                failfast_timeout: DEFAULT_OUTBOUND_HTTP_FAILFAST_TIMEOUT,
+            },
+            dispatcher: BackendDispatcher::BalanceP2c(
+                Load::PeakEwma(DEFAULT_OUTBOUND_LOCAL_BACKEND_EWMA),
+                EndpointDiscovery::DestinationGet {
+                    path: self.authority,
+                },
+            ),
+        };
+        MirrorConfig {
+            parent: self.parent,
+            mirror: RequestMirror {
+                backend,
+                distribution: distribution.clone(),
+                max_request_bytes,
+                max_in_flight,
+            },
+        }
+    }
+}
+
+fn parse_route_mirrors(s: &str) -> Result<Vec<RouteMirror>, ParseError> {
+    let mut mirrors = Vec::new();
+    for entry in s.split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let invalid = || {
+            error!("Expected <name>.<namespace>:<port>=<authority>; found: {entry}");
+            ParseError::InvalidRouteMirror(entry.to_string())
+        };
+        let (parent, authority) = entry.split_once('=').ok_or_else(invalid)?;
+        let parent = parse_parent_service(parent.trim()).ok_or_else(invalid)?;
+        let authority = match parse_addr(authority.trim())? {
+            Addr::Name(addr) => addr.to_string(),
+            Addr::Socket(_) => return Err(invalid()),
+        };
+        mirrors.push(RouteMirror { parent, authority });
+    }
+    Ok(mirrors)
+}
+
+fn parse_outbound_url_rewrites(
+    s: &str,
+) -> Result<Vec<outbound::policy::UrlRewriteConfig>, ParseError> {
+    let mut rewrites = Vec::new();
+    for entry in s.split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let invalid = || {
+            error!(
+                "Expected <name>.<namespace>:<port><prefix>=[<authority>]<path>; found: {entry}"
+            );
+            ParseError::InvalidUrlRewrite(entry.to_string())
+        };
+        let (parent, rewrite) = entry
+            .find('/')
+            .map(|i| entry.split_at(i))
+            .ok_or_else(invalid)?;
+        let parent = parse_parent_service(parent).ok_or_else(invalid)?;
+        let (prefix, rewrite) = parse_url_rewrite(rewrite).ok_or_else(invalid)?;
+        rewrites.push(outbound::policy::UrlRewriteConfig {
+            parent,
+            prefix,
+            rewrite,
+        });
+    }
+    Ok(rewrites)
+}
+
+fn parse_grpc_response_headers(
+    s: &str,
+) -> Result<Vec<outbound::policy::GrpcResponseHeadersConfig>, ParseError> {
+    let mut configs = Vec::new();
+    for entry in s.split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let invalid = || {
+            error!("Expected <name>.<namespace>:<port>=<header>:<value>; found: {entry}");
+            ParseError::InvalidHeaderModifier(entry.to_string())
+        };
+        let (parent, header) = entry.split_once('=').ok_or_else(invalid)?;
+        let parent = parse_parent_service(parent.trim()).ok_or_else(invalid)?;
+        let (name, value) = header.split_once(':').ok_or_else(invalid)?;
+        let name = parse_header_name(name)?;
+        let value = http::HeaderValue::from_str(value.trim()).map_err(|_| invalid())?;
+        configs.push(outbound::policy::GrpcResponseHeadersConfig {
+            parent,
+            headers: outbound::policy::grpc::filter::ModifyHeader {
+                set: vec![(name, value)],
+                ..Default::default()
+            },
+        });
+    }
+    Ok(configs)
+}
+
+fn parse_tls_routes(s: &str) -> Result<Vec<outbound::policy::TlsRouteConfig>, ParseError> {
+    use outbound::policy::{Backend, BackendDispatcher, EndpointDiscovery, Load, Meta, Queue};
+
+    let mut routes = Vec::new();
+    for entry in s.split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let invalid = || {
+            error!("Expected <name>.<namespace>:<port>/<sni>=<authority>; found: {entry}");
+            ParseError::InvalidTlsRoute(entry.to_string())
+        };
+        let (route, authority) = entry.split_once('=').ok_or_else(invalid)?;
+        let (parent, sni) = route.split_once('/').ok_or_else(invalid)?;
+        let parent = parse_parent_service(parent.trim()).ok_or_else(invalid)?;
+        let sni = sni.trim().parse().map_err(|_| invalid())?;
+        let authority = match parse_addr(authority.trim())? {
+            Addr::Name(addr) => addr.to_string(),
+            Addr::Socket(_) => return Err(invalid()),
+        };
+        let backend = Backend {
+            meta: Meta::new_default(authority.clone()),
+            queue: Queue {
+                capacity: DEFAULT_OUTBOUND_TCP_QUEUE_CAPACITY,
+                failfast_timeout: DEFAULT_OUTBOUND_TCP_FAILFAST_TIMEOUT,
+            },
+            dispatcher: BackendDispatcher::BalanceP2c(
+                Load::PeakEwma(DEFAULT_OUTBOUND_LOCAL_BACKEND_EWMA),
+                EndpointDiscovery::DestinationGet { path: authority },
+            ),
+        };
+        routes.push(outbound::policy::TlsRouteConfig {
+            parent,
+            sni,
+            backend,
+        });
+    }
+    Ok(routes)
+}
+
+fn parse_opaque_filters(s: &str) -> Result<Vec<outbound::policy::OpaqueFilterConfig>, ParseError> {
+    use outbound::policy::{http::filter, opaq};
+
+    let mut configs = Vec::new();
+    for entry in s.split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let invalid = || {
+            error!("Expected <name>.<namespace>:<port>[@<network>]=<filter>; found: {entry}");
+            ParseError::InvalidOpaqueFilter(entry.to_string())
+        };
+        let (target, filter) = entry.split_once('=').ok_or_else(invalid)?;
+        let (parent, network) = match target.split_once('@') {
+            Some((parent, network)) => (parent, Some(network)),
+            None => (target, None),
+        };
+        let parent = parse_parent_service(parent.trim()).ok_or_else(invalid)?;
+        let client_networks = match network {
+            Some(network) => vec![IpNet::from_str(network.trim()).map_err(|_| invalid())?],
+            None => vec![],
+        };
+        let filter = match filter.trim() {
+            "forbidden" => opaq::Filter::Forbidden,
+            other => {
+                let percent = other
+                    .strip_prefix("fail:")
+                    .and_then(|pct| pct.parse::<u32>().ok())
+                    .filter(|pct| *pct <= 100)
+                    .ok_or_else(invalid)?;
+                opaq::Filter::InjectFailure(filter::InjectFailure {
+                    response: "injected by local configuration".into(),
+                    distribution: filter::Distribution::from_ratio(percent, 100)
+                        .expect("percentage must be a valid ratio"),
+                })
+            }
+        };
+        configs.push(outbound::policy::OpaqueFilterConfig {
+            parent,
+            matches: opaq::MatchConnection {
+                ports: None,
+                client_networks,
+            },
+            filter,
+        });
+    }
+    Ok(configs)
+}
+
+/// Parses a `<name>.<namespace>:<port>` service reference.
+fn parse_parent_service(s: &str) -> Option<outbound::policy::ParentService> {
+    let (service, port) = s.rsplit_once(':')?;
+    let (name, namespace) = service.split_once('.')?;
+    if name.is_empty() || namespace.is_empty() {
+        return None;
+    }
+    Some(outbound::policy::ParentService {
+        namespace: namespace.to_string(),
+        name: name.to_string(),
+        port: port.parse().ok()?,
+    })
+}
+
+fn parse_parent_services(s: &str) -> Result<Vec<outbound::policy::ParentService>, ParseError> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            parse_parent_service(s).ok_or_else(|| {
+                error!("Expected <name>.<namespace>:<port>; found: {s}");
+                ParseError::InvalidParentService(s.to_string())
+            })
+        })
+        .collect()
+}
+
+fn parse_route_timeouts(s: &str) -> Result<Vec<outbound::policy::TimeoutsConfig>, ParseError> {
+    let mut configs = Vec::new();
+    for entry in s.split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let invalid = || {
+            error!("Expected <name>.<namespace>:<port>[/<route>]=<timeouts>; found: {entry}");
+            ParseError::InvalidRouteTimeouts(entry.to_string())
+        };
+        let (routes, timeouts) = entry.split_once('=').ok_or_else(invalid)?;
+        let routes = parse_route_scope(routes.trim()).ok_or_else(invalid)?;
+        let mut config = outbound::policy::TimeoutsConfig {
+            routes,
+            timeouts: Default::default(),
+        };
+        for timeout in timeouts.split(';') {
+            let (kind, duration) = timeout.split_once(':').ok_or_else(invalid)?;
+            let duration = Some(parse_duration(duration.trim()).map_err(|_| invalid())?);
+            match kind.trim() {
+                "response" => config.timeouts.response = duration,
+                "request" => config.timeouts.request = duration,
+                "idle" => config.timeouts.idle = duration,
+                _ => return Err(invalid()),
+            }
+        }
+        configs.push(config);
+    }
+    Ok(configs)
+}
+
+/// Parses a `<name>.<namespace>:<port>[/<route>]` reference to a parent
+/// service's routes.
+fn parse_route_scope(s: &str) -> Option<outbound::policy::RouteScope> {
+    let (parent, route) = match s.split_once('/') {
+        Some((parent, route)) if !route.is_empty() => (parent, Some(route.to_string())),
+        Some(_) => return None,
+        None => (s, None),
+    };
+    Some(outbound::policy::RouteScope {
+        parent: parse_parent_service(parent)?,
+        route,
+    })
+}
+
+fn parse_balancer_loads(s: &str) -> Result<Vec<outbound::policy::LoadConfig>, ParseError> {
+    let mut loads = Vec::new();
+    for entry in s.split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let load = entry
+            .split_once('=')
+            .and_then(|(backend, load)| Some((parse_parent_service(backend.trim())?, load.trim())));
+        match load {
+            // Peak EWMA balancers are configured by the policy API.
+            Some((_, "peak-ewma")) => {}
+            Some((backend, load)) => loads.push(outbound::policy::LoadConfig {
+                backend,
+                load: parse_balancer_load(load)
+                    .ok_or_else(|| ParseError::InvalidBalancerLoad(entry.to_string()))?,
+            }),
+            None => {
+                error!("Expected <name>.<namespace>:<port>=<load>; found: {entry}");
+                return Err(ParseError::InvalidBalancerLoad(entry.to_string()));
+            }
+        }
+    }
+    Ok(loads)
+}
+
+/// Parses a balancer load strategy, like `least-request` or
+/// `consistent-hash(header:x-user)`.
+fn parse_balancer_load(s: &str) -> Option<outbound::policy::Load> {
+    use outbound::policy::{ConsistentHash, HashKey, Load};
+
+    match s {
+        "least-request" => return Some(Load::LeastRequest),
+        "weighted-round-robin" => return Some(Load::WeightedRoundRobin),
+        _ => {}
+    }
+    let key = s
+        .strip_prefix("consistent-hash(")?
+        .strip_suffix(')')?
+        .trim();
+    let key = match key.split_once(':') {
+        Some(("header", name)) => HashKey::Header(parse_header_name(name).ok()?),
+        Some(("cookie", name)) if !name.trim().is_empty() => {
+            HashKey::Cookie(name.trim().to_string())
+        }
+        None if key == "client-ip" => HashKey::ClientIp,
+        _ => return None,
+    };
+    Some(Load::ConsistentHash(ConsistentHash { key }))
+}
+
+/// Parses `<name>.<namespace>:<port>[/<route>][=idempotent]` entries into the
+/// routes that are hedged and whether all of their requests are idempotent.
+fn parse_route_hedges(s: &str) -> Result<Vec<(outbound::policy::RouteScope, bool)>, ParseError> {
+    let mut hedges = Vec::new();
+    for entry in s.split(',') {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let invalid = || {
+            error!("Expected <name>.<namespace>:<port>[/<route>][=idempotent]; found: {entry}");
+            ParseError::InvalidRouteHedge(entry.to_string())
+        };
+        let (routes, idempotent) = match entry.split_once('=') {
+            Some((routes, marker)) if marker.trim() == "idempotent" => (routes, true),
+            Some(_) => return Err(invalid()),
+            None => (entry, false),
+        };
+        let routes = parse_route_scope(routes.trim()).ok_or_else(invalid)?;
+        hedges.push((routes, idempotent));
+    }
+    Ok(hedges)
+}
+
+/// Parses a hedge delay as either a percentile of observed latencies, like
+/// `p90`, or a fixed duration.
+fn parse_hedge_delay(s: &str) -> Result<outbound::policy::HedgeDelay, ParseError> {
+    let s = s.trim();
+    match s.strip_prefix('p') {
+        Some(p) => match p.parse::<u8>() {
+            Ok(percentile @ 1..=99) => Ok(outbound::policy::HedgeDelay::Percentile(percentile)),
+            _ => Err(ParseError::InvalidHedgeDelay(s.to_string())),
+        },
+        None => parse_duration(s)
+            .map(outbound::policy::HedgeDelay::Fixed)
+            .map_err(|_| ParseError::InvalidHedgeDelay(s.to_string())),
+    }
+}
+
+fn parse_stdev_factor(s: &str) -> Result<outbound::policy::StdevFactor, ParseError> {
+    let factor = parse_number::<f64>(s.trim())?;
+    outbound::policy::StdevFactor::new(factor).ok_or(ParseError::NotAStdevFactor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn outbound_local_policy() {
+        let local = parse_outbound_local_policy(&HashMap::<&str, &str>::new()).unwrap();
+        assert!(local.retries.is_empty());
+
+        // Retry parameters alone do not enable retries.
+        let env = HashMap::from([(ENV_OUTBOUND_ROUTE_RETRY_MAX_RETRIES, "3")]);
+        assert!(parse_outbound_local_policy(&env)
+            .unwrap()
+            .retries
+            .is_empty());
+
+        let env = HashMap::from([
+            (
+                ENV_OUTBOUND_ROUTE_RETRIES,
+                "web.ns:8080/api=5xx;429;unavailable",
+            ),
+            (ENV_OUTBOUND_ROUTE_RETRY_MAX_RETRIES, "3"),
+            (ENV_OUTBOUND_ROUTE_RETRY_TIMEOUT, "1s"),
+            (ENV_OUTBOUND_ROUTE_RETRY_BUDGET_PERCENT, "10"),
+        ]);
+        let retries = parse_outbound_local_policy(&env).unwrap().retries;
+        assert_eq!(retries.len(), 1);
+        let retry = &retries[0];
+        assert_eq!(retry.routes.route.as_deref(), Some("api"));
+        assert_eq!(
+            retry.http_conditions,
+            Some(outbound::policy::http::StatusRanges(Arc::new([
+                500..=599,
+                429..=429
+            ])))
+        );
+        assert_eq!(
+            retry.grpc_conditions,
+            Some(outbound::policy::grpc::Codes(Arc::new([14].into())))
+        );
+        assert_eq!(retry.max_retries, 3);
+        assert_eq!(
+            retry.max_request_bytes,
+            DEFAULT_OUTBOUND_ROUTE_RETRY_MAX_REQUEST_BYTES
+        );
+        assert_eq!(retry.timeout, Some(Duration::from_secs(1)));
+        assert_eq!(retry.backoff, Some(DEFAULT_OUTBOUND_ROUTE_RETRY_BACKOFF));
+        assert_eq!(
+            retry.budget,
+            outbound::policy::RetryBudget {
+                retry_percent: 10,
+                ..DEFAULT_OUTBOUND_ROUTE_RETRY_BUDGET
+            }
+        );
+    }
+
+    #[test]
+    fn outbound_route_retries() {
+        let retries = parse_route_retries("web.ns:8080, api.ns:80/get=500-504").unwrap();
+        assert_eq!(retries.len(), 2);
+        assert_eq!(retries[0].routes.route, None);
+        assert_eq!(retries[0].http_conditions, None);
+        assert_eq!(retries[0].grpc_conditions, None);
+        assert_eq!(retries[1].routes.route.as_deref(), Some("get"));
+
+        for invalid in [
+            "web.ns:8080/",
+            "web.ns:8080=6xx",
+            "web.ns:8080=504-500",
+            "web",
+        ] {
+            assert_eq!(
+                parse_route_retries(invalid),
+                Err(ParseError::InvalidRouteRetry(invalid.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn outbound_route_hedges() {
+        use outbound::policy::HedgeDelay;
+
+        let local = parse_outbound_local_policy(&HashMap::<&str, &str>::new()).unwrap();
+        assert!(local.hedges.is_empty());
+
+        // Hedging applies only to the routes that opt into it.
+        let env = HashMap::from([(ENV_OUTBOUND_ROUTE_HEDGE_DELAY, "p95")]);
+        assert!(parse_outbound_local_policy(&env).unwrap().hedges.is_empty());
+
+        let env = HashMap::from([
+            (
+                ENV_OUTBOUND_ROUTE_HEDGES,
+                "web.ns:8080, api.ns:80/get=idempotent",
+            ),
+            (ENV_OUTBOUND_ROUTE_HEDGE_DELAY, "p95"),
+        ]);
+        let hedges = parse_outbound_local_policy(&env).unwrap().hedges;
+        assert_eq!(hedges.len(), 2);
+        assert_eq!(hedges[0].routes.route, None);
+        assert!(!hedges[0].hedge.idempotent);
+        assert_eq!(hedges[1].routes.route.as_deref(), Some("get"));
+        assert!(hedges[1].hedge.idempotent);
+        let hedge = &hedges[0].hedge;
+        assert_eq!(hedge.delay, HedgeDelay::Percentile(95));
+        assert_eq!(hedge.max_percent, DEFAULT_OUTBOUND_ROUTE_HEDGE_MAX_PERCENT);
+        assert_eq!(
+            hedge.max_request_bytes,
+            DEFAULT_OUTBOUND_ROUTE_HEDGE_MAX_REQUEST_BYTES
+        );
+
+        let env = HashMap::from([
+            (ENV_OUTBOUND_ROUTE_HEDGES, "web.ns:8080"),
+            (ENV_OUTBOUND_ROUTE_HEDGE_DELAY, "50ms"),
+            (ENV_OUTBOUND_ROUTE_HEDGE_MAX_PERCENT, "150"),
+        ]);
+        let hedges = parse_outbound_local_policy(&env).unwrap().hedges;
+        assert_eq!(
+            hedges[0].hedge.delay,
+            HedgeDelay::Fixed(Duration::from_millis(50))
+        );
+        assert_eq!(hedges[0].hedge.max_percent, 100);
+
+        // Hedged routes require a delay.
+        let env = HashMap::from([(ENV_OUTBOUND_ROUTE_HEDGES, "web.ns:8080")]);
+        assert!(parse_outbound_local_policy(&env).is_err());
+
+        for invalid in ["web.ns:8080/", "web.ns:8080=post", "web"] {
+            assert_eq!(
+                parse_route_hedges(invalid),
+                Err(ParseError::InvalidRouteHedge(invalid.to_string()))
+            );
+        }
+
+        for invalid in ["p0", "p100", "pfast", "soon"] {
+            assert_eq!(
+                parse_hedge_delay(invalid),
+                Err(ParseError::InvalidHedgeDelay(invalid.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn outbound_balancer_loads() {
+        use outbound::policy::{ConsistentHash, HashKey, Load};
+
+        let loads = |s: &'static str| {
+            parse_outbound_local_policy(&HashMap::from([(ENV_OUTBOUND_BALANCER_LOADS, s)])).map(
+                |local| {
+                    local
+                        .loads
+                        .into_iter()
+                        .map(|config| (config.backend.name, config.load))
+                        .collect::<Vec<_>>()
+                },
+            )
+        };
+
+        assert!(parse_outbound_local_policy(&HashMap::<&str, &str>::new())
+            .unwrap()
+            .loads
+            .is_empty());
+        assert!(loads("web.ns:8080=peak-ewma").unwrap().is_empty());
+        assert_eq!(
+            loads(
+                "web.ns:8080=least-request, api.ns:80=weighted-round-robin, \
+                 cart.ns:80=consistent-hash(cookie:session), \
+                 user.ns:80=consistent-hash(header:x-user), \
+                 ingress.ns:80=consistent-hash(client-ip)"
+            )
+            .unwrap(),
+            vec![
+                ("web".to_string(), Load::LeastRequest),
+                ("api".to_string(), Load::WeightedRoundRobin),
+                (
+                    "cart".to_string(),
+                    Load::ConsistentHash(ConsistentHash {
+                        key: HashKey::Cookie("session".to_string()),
+                    })
+                ),
+                (
+                    "user".to_string(),
+                    Load::ConsistentHash(ConsistentHash {
+                        key: HashKey::Header(http::HeaderName::from_static("x-user")),
+                    })
+                ),
+                (
+                    "ingress".to_string(),
+                    Load::ConsistentHash(ConsistentHash {
+                        key: HashKey::ClientIp,
+                    })
+                ),
+            ]
+        );
+
+        for invalid in [
+            "least-request",
+            "web.ns:8080=random",
+            "web.ns:8080=consistent-hash",
+            "web.ns:8080=consistent-hash(query:user)",
+        ] {
+            assert!(loads(invalid).is_err(), "{invalid} must not parse");
+        }
+    }
+
+    #[test]
+    fn outbound_route_timeouts() {
+        let env = HashMap::from([(
+            ENV_OUTBOUND_ROUTE_TIMEOUTS,
+            "web.ns:8080=request:1h;idle:30s, api.ns:80/get=response:500ms",
+        )]);
+        let timeouts = parse_outbound_local_policy(&env).unwrap().timeouts;
+        assert_eq!(timeouts.len(), 2);
+        assert_eq!(timeouts[0].routes.parent.name, "web");
+        assert_eq!(timeouts[0].routes.route, None);
+        assert_eq!(
+            timeouts[0].timeouts,
+            outbound::policy::RouteTimeouts {
+                response: None,
+                request: Some(Duration::from_secs(60 * 60)),
+                idle: Some(Duration::from_secs(30)),
+            }
+        );
+        assert_eq!(timeouts[1].routes.route.as_deref(), Some("get"));
+        assert_eq!(
+            timeouts[1].timeouts.response,
+            Some(Duration::from_millis(500))
+        );
+
+        for invalid in ["web.ns:8080", "web.ns:8080=idle", "web.ns:8080=total:1s"] {
+            assert_eq!(
+                parse_route_timeouts(invalid).unwrap_err(),
+                ParseError::InvalidRouteTimeouts(invalid.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn outbound_route_mirrors() {
+        assert_eq!(
+            parse_route_mirrors("web.emojivoto:80=web-mirror.emojivoto.svc.cluster.local:8080, ")
+                .unwrap(),
+            vec![RouteMirror {
+                parent: outbound::policy::ParentService {
+                    namespace: "emojivoto".to_string(),
+                    name: "web".to_string(),
+                    port: std::num::NonZeroU16::new(80).unwrap(),
+                },
+                authority: "web-mirror.emojivoto.svc.cluster.local:8080".to_string(),
+            }]
+        );
+        assert!(parse_route_mirrors("web:80=web-mirror.emojivoto:8080").is_err());
+        assert!(parse_route_mirrors("web.emojivoto=web-mirror.emojivoto:8080").is_err());
+        assert!(parse_route_mirrors("web.emojivoto:0=web-mirror.emojivoto:8080").is_err());
+        assert!(parse_route_mirrors("web.emojivoto:80=10.0.0.1:8080").is_err());
+
+        let env = HashMap::from([
+            (
+                ENV_OUTBOUND_ROUTE_MIRRORS,
+                "web.emojivoto:80=web-mirror.emojivoto.svc.cluster.local:8080",
+            ),
+            (ENV_OUTBOUND_ROUTE_MIRROR_PERCENT, "10"),
+            (ENV_OUTBOUND_ROUTE_MIRROR_MAX_IN_FLIGHT, "5"),
+        ]);
+        let mirrors = parse_outbound_local_policy(&env).unwrap().mirrors;
+        assert_eq!(mirrors.len(), 1);
+        let mirror = &mirrors[0].mirror;
+        assert_eq!(
+            mirror.distribution,
+            outbound::policy::http::filter::Distribution::from_ratio(10, 100).unwrap()
+        );
+        assert_eq!(
+            mirror.max_request_bytes,
+            DEFAULT_OUTBOUND_ROUTE_MIRROR_MAX_REQUEST_BYTES
+        );
+        assert_eq!(mirror.max_in_flight, 5);
+        assert_eq!(mirror.backend.queue.capacity, 5);
+
+        assert!(parse_outbound_local_policy(&HashMap::<&str, &str>::new())
+            .unwrap()
+            .mirrors
+            .is_empty());
+    }
+
+    #[test]
+    fn outbound_grpc_response_headers() {
+        let configs =
+            parse_grpc_response_headers("web.emojivoto:80=x-tenant: acme, web.emojivoto:80=a:b")
+                .unwrap();
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs[0].parent.name, "web");
+        assert_eq!(
+            configs[0].headers.set,
+            vec![(
+                http::HeaderName::from_static("x-tenant"),
+                http::HeaderValue::from_static("acme")
+            )]
+        );
+        assert!(parse_grpc_response_headers("web.emojivoto:80=x-tenant").is_err());
+        assert!(parse_grpc_response_headers("web.emojivoto=x-tenant:acme").is_err());
+        assert!(parse_grpc_response_headers("web.emojivoto:80=x tenant:acme").is_err());
+    }
+
+    #[test]
+    fn outbound_tls_routes() {
+        use outbound::policy::{route::MatchHost, BackendDispatcher, EndpointDiscovery};
+
+        let routes = parse_tls_routes(
+            "web.emojivoto:443/*.example.com=egress.emojivoto.svc.cluster.local:443, ",
+        )
+        .unwrap();
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].parent.name, "web");
+        assert_eq!(routes[0].parent.port.get(), 443);
+        assert_eq!(routes[0].sni, "*.example.com".parse::<MatchHost>().unwrap());
+        match routes[0].backend.dispatcher {
+            BackendDispatcher::BalanceP2c(_, EndpointDiscovery::DestinationGet { ref path }) => {
+                assert_eq!(path, "egress.emojivoto.svc.cluster.local:443")
+            }
+            ref dispatcher => panic!("unexpected dispatcher: {dispatcher:?}"),
+        }
+        assert!(parse_tls_routes("web.emojivoto:443=egress.emojivoto:443").is_err());
+        assert!(parse_tls_routes("web.emojivoto/example.com=egress.emojivoto:443").is_err());
+        assert!(parse_tls_routes("web.emojivoto:443/10.0.0.1=egress.emojivoto:443").is_err());
+        assert!(parse_tls_routes("web.emojivoto:443/example.com=10.0.0.1:443").is_err());
+
+        let env = HashMap::from([(
+            ENV_OUTBOUND_TLS_ROUTES,
+            "web.emojivoto:443/example.com=egress.emojivoto.svc.cluster.local:443",
+        )]);
+        assert_eq!(
+            parse_outbound_local_policy(&env).unwrap().tls_routes.len(),
+            1
+        );
+        assert!(parse_outbound_local_policy(&HashMap::<&str, &str>::new())
+            .unwrap()
+            .tls_routes
+            .is_empty());
+    }
+
+    #[test]
+    fn outbound_opaque_filters() {
+        use outbound::policy::opaq;
+
+        let configs =
+            parse_opaque_filters("db.prod:5432@10.1.0.0/16=forbidden, db.prod:5432=fail:10")
+                .unwrap();
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs[0].parent.name, "db");
+        assert_eq!(
+            configs[0].matches.client_networks,
+            vec!["10.1.0.0/16".parse::<IpNet>().unwrap()]
+        );
+        assert_eq!(configs[0].filter, opaq::Filter::Forbidden);
+        assert_eq!(configs[1].matches, opaq::MatchConnection::default());
+        match configs[1].filter {
+            opaq::Filter::InjectFailure(ref fail) => assert_eq!(
+                fail.distribution,
+                outbound::policy::http::filter::Distribution::from_ratio(10, 100).unwrap()
+            ),
+            ref filter => panic!("unexpected filter: {filter:?}"),
+        }
+        assert!(parse_opaque_filters("db.prod:5432=deny").is_err());
+        assert!(parse_opaque_filters("db.prod:5432=fail:101").is_err());
+        assert!(parse_opaque_filters("db.prod:5432@db.prod=forbidden").is_err());
+        assert!(parse_opaque_filters("db.prod=forbidden").is_err());
+
+        let env = HashMap::from([(ENV_OUTBOUND_OPAQUE_FILTERS, "db.prod:5432=forbidden")]);
+        assert_eq!(
+            parse_outbound_local_policy(&env)
+                .unwrap()
+                .opaque_filters
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn outbound_success_rate_accrual() {
+        use outbound::policy::{FailureAccrual, StdevFactor};
+
+        let accrual = |env: HashMap<&'static str, &'static str>| {
+            parse_outbound_local_policy(&env).map(|local| local.failure_accrual)
+        };
+
+        assert!(accrual(HashMap::new()).unwrap().is_empty());
+        // Accrual parameters alone do not enable failure accrual.
+        assert!(accrual(HashMap::from([(
+            ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_INTERVAL,
+            "10s"
+        )]))
+        .unwrap()
+        .is_empty());
+
+        let configs = accrual(HashMap::from([
+            (
+                ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_PARENTS,
+                "web.ns:8080,api.ns:80",
+            ),
+            (ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_INTERVAL, "5s"),
+            (ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_STDEV_FACTOR, "2.5"),
+        ]))
+        .unwrap();
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs[1].parent.name, "api");
+        assert_eq!(
+            configs[0].accrual,
+            FailureAccrual::SuccessRate {
+                interval: Duration::from_secs(5),
+                min_requests: DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_MIN_REQUESTS,
+                min_endpoints: DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_MIN_ENDPOINTS,
+                stdev_factor: StdevFactor::new(2.5).unwrap(),
+                max_ejection_percent: DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_MAX_EJECTION_PERCENT,
+                backoff: DEFAULT_OUTBOUND_SUCCESS_RATE_ACCRUAL_BACKOFF,
+            }
+        );
+        assert!(accrual(HashMap::from([(
+            ENV_OUTBOUND_SUCCESS_RATE_ACCRUAL_PARENTS,
+            "web"
+        )]))
+        .is_err());
+        assert_eq!(parse_stdev_factor("-1"), Err(ParseError::NotAStdevFactor));
+        assert_eq!(parse_stdev_factor("inf"), Err(ParseError::NotAStdevFactor));
+        assert!(parse_stdev_factor("many").is_err());
+    }
+
+    #[test]
+    fn outbound_url_rewrites() {
+        use outbound::policy::http::filter::{ModifyPath, UrlRewrite};
+
+        let rewrites = parse_outbound_url_rewrites(
+            "web.emojivoto:80/new=/legacy,web.emojivoto:80/v2=legacy.emojivoto:8080/",
+        )
+        .unwrap();
+        assert_eq!(rewrites.len(), 2);
+        assert_eq!(
+            rewrites[0].parent,
+            outbound::policy::ParentService {
+                namespace: "emojivoto".to_string(),
+                name: "web".to_string(),
+                port: std::num::NonZeroU16::new(80).unwrap(),
+            }
+        );
+        assert_eq!(rewrites[0].prefix, "/new");
+        assert_eq!(
+            rewrites[0].rewrite,
+            UrlRewrite {
+                authority: None,
+                path: Some(ModifyPath::ReplacePrefixMatch("/legacy".to_string())),
+            }
+        );
+        assert_eq!(rewrites[1].prefix, "/v2");
+        assert_eq!(
+            rewrites[1].rewrite,
+            UrlRewrite {
+                authority: Some("legacy.emojivoto:8080".parse().unwrap()),
+                path: Some(ModifyPath::ReplacePrefixMatch("/".to_string())),
+            }
+        );
+        assert!(parse_outbound_url_rewrites("web.emojivoto/new=/legacy").is_err());
+        assert!(parse_outbound_url_rewrites("web.emojivoto:80/new").is_err());
+        assert!(parse_outbound_url_rewrites("web.emojivoto:80/new=legacy").is_err());
+    }
+
+    #[test]
+    fn outbound_proxy_protocol_backends() {
+        let backends = |env: HashMap<&'static str, &'static str>| {
+            parse_outbound_local_policy(&env).map(|local| local.proxy_protocol_backends)
+        };
+
+        assert!(backends(HashMap::new()).unwrap().is_empty());
+        assert_eq!(
+            backends(HashMap::from([(
+                ENV_OUTBOUND_PROXY_PROTOCOL_BACKENDS,
+                "mysql.db:3306, web.ns:8080"
+            )]))
+            .unwrap(),
+            vec![
+                outbound::policy::ParentService {
+                    namespace: "db".to_string(),
+                    name: "mysql".to_string(),
+                    port: std::num::NonZeroU16::new(3306).unwrap(),
+                },
+                outbound::policy::ParentService {
+                    namespace: "ns".to_string(),
+                    name: "web".to_string(),
+                    port: std::num::NonZeroU16::new(8080).unwrap(),
+                },
+            ]
+        );
+        assert!(backends(HashMap::from([(
+            ENV_OUTBOUND_PROXY_PROTOCOL_BACKENDS,
+            "10.0.0.0/8"
+        )]))
+        .is_err());
+    }
+}
@@ -169,7 +169,11 @@ impl Config {
             span_sink: oc_collector.span_sink(),
             drain: drain_rx.clone(),
         };
-        let inbound = Inbound::new(inbound, runtime.clone());
+        let inbound = match policies.ext_authz.clone() {
+            Some(client) => Inbound::new(inbound, runtime.clone())
+                .with_ext_authz(inbound::policy::ExtAuthzClient::new(client)),
+            None => Inbound::new(inbound, runtime.clone()),
+        };
         let outbound = Outbound::new(outbound, runtime);
 
         let inbound_policies = inbound.build_policies(
@@ -13,6 +13,9 @@ use std::sync::Arc;
 pub struct Config {
     pub control: control::Config,
     pub workload: String,
+
+    /// Configures the external authorization service, if one is used.
+    pub ext_authz: Option<control::Config>,
 }
 
 /// Handles to policy service clients.
@@ -27,6 +30,9 @@ pub struct Policy<S> {
     pub workload: Arc<str>,
 
     pub backoff: ExponentialBackoff,
+
+    /// External authorization service gRPC client, if one is configured.
+    pub ext_authz: Option<S>,
 }
 
 // === impl Config ===
@@ -51,6 +57,12 @@ impl Config {
         let addr = self.control.addr.clone();
         let workload = self.workload.into();
         let backoff = self.control.connect.backoff;
+        let ext_authz = self.ext_authz.map(|control| {
+            control
+                .build(dns.clone(), metrics.clone(), identity.clone())
+                .new_service(())
+                .map_err(Error::from)
+        });
         let client = self
             .control
             .build(dns, metrics, identity)
@@ -62,6 +74,7 @@ impl Config {
             client,
             workload,
             backoff,
+            ext_authz,
         })
     }
 }
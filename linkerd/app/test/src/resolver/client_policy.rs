@@ -73,11 +73,13 @@ impl ClientPolicies {
                     meta: Meta::new_default("default"),
                     filters: Arc::new([]),
                     failure_policy: Default::default(),
-                    request_timeout: None,
+                    timeouts: Default::default(),
+                    retry: None,
+                    hedge: None,
                     distribution: RouteDistribution::FirstAvailable(Arc::new([RouteBackend {
                         filters: Arc::new([]),
                         backend: backend.clone(),
-                        request_timeout: None,
+                        timeouts: Default::default(),
                     }])),
                 },
             }],
@@ -94,17 +96,23 @@ impl ClientPolicies {
                 failure_accrual: Default::default(),
             },
             opaque: opaq::Opaque {
-                policy: Some(opaq::Policy {
-                    meta: Meta::new_default("default"),
-                    filters: Arc::new([]),
-                    failure_policy: Default::default(),
-                    request_timeout: None,
-                    distribution: RouteDistribution::FirstAvailable(Arc::new([RouteBackend {
+                routes: Arc::new([opaq::Route {
+                    matches: vec![],
+                    policy: opaq::Policy {
+                        meta: Meta::new_default("default"),
                         filters: Arc::new([]),
-                        backend: backend.clone(),
-                        request_timeout: None,
-                    }])),
-                }),
+                        failure_policy: Default::default(),
+                        timeouts: Default::default(),
+                        retry: None,
+                        hedge: None,
+                        distribution: RouteDistribution::FirstAvailable(Arc::new([RouteBackend {
+                            filters: Arc::new([]),
+                            backend: backend.clone(),
+                            timeouts: Default::default(),
+                        }])),
+                    },
+                }]),
+                failure_accrual: Default::default(),
             },
         };
         let policy = ClientPolicy {
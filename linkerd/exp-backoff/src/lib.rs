@@ -82,6 +82,16 @@ impl ExponentialBackoff {
         }
     }
 
+    /// Returns a jittered backoff duration after `iterations` prior attempts.
+    ///
+    /// This is useful when the caller cannot hold a stateful
+    /// [`ExponentialBackoffStream`], e.g. when the backoff state must be
+    /// cloned.
+    pub fn delay(&self, iterations: u32) -> time::Duration {
+        let base = self.base(iterations);
+        base + self.jitter(base, &mut thread_rng())
+    }
+
     fn base(&self, iterations: u32) -> time::Duration {
         debug_assert!(
             self.min <= self.max,
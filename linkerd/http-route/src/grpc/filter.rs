@@ -1,3 +1,6 @@
 pub mod inject_failure;
 
 pub use self::inject_failure::{Distribution, FailureResponse, InjectFailure};
+
+/// gRPC request and response metadata is modified like HTTP headers.
+pub use crate::http::filter::ModifyHeader;
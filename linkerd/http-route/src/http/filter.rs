@@ -1,15 +1,65 @@
 pub mod inject_failure;
 pub mod modify_header;
 pub mod redirect;
+pub mod url_rewrite;
 
 pub use self::{
     inject_failure::{Distribution, FailureResponse, InjectFailure},
     modify_header::ModifyHeader,
     redirect::{InvalidRedirect, RedirectRequest, Redirection},
+    url_rewrite::{InvalidUrlRewrite, UrlRewrite},
 };
+use crate::http::RouteMatch;
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum ModifyPath {
     ReplaceFullPath(String),
     ReplacePrefixMatch(String),
 }
+
+// === impl ModifyPath ===
+
+impl ModifyPath {
+    /// Returns the path (and query) produced by applying this modification to
+    /// the original URI.
+    ///
+    /// Returns `None` if the path prefix is to be replaced but the route was
+    /// not matched by a path prefix.
+    //
+    // XXX This function probably does more allocation that is strictly needed.
+    // We may want to optimize it as it settles.
+    pub(crate) fn modify(&self, orig_uri: &http::Uri, rm: &RouteMatch) -> Option<String> {
+        use crate::http::r#match::PathMatch;
+
+        match self {
+            // If a full path (potentially including a query) is specified, use
+            // it.
+            ModifyPath::ReplaceFullPath(p) => Some(p.clone()),
+
+            // If a prefix rewrite is specified, use the original query
+            // parameters.
+            //
+            // XXX #fragments are not included in the rewritten path; but
+            // fragments are generally not transmitted to servers.
+            ModifyPath::ReplacePrefixMatch(new_pfx) => match rm.route.path() {
+                PathMatch::Prefix(pfx_len) if *pfx_len <= orig_uri.path().len() => {
+                    let mut new_path = new_pfx.to_string();
+                    let (_, rest) = orig_uri.path().split_at(*pfx_len);
+                    if !rest.is_empty() && !rest.starts_with('/') {
+                        new_path.push('/');
+                    }
+                    new_path.push_str(rest);
+                    if let Some(q) = orig_uri.query() {
+                        new_path.push('?');
+                        new_path.push_str(q);
+                    }
+                    Some(new_path)
+                }
+
+                // If the matched rule was not a prefix match, the modification
+                // cannot be applied.
+                _ => None,
+            },
+        }
+    }
+}
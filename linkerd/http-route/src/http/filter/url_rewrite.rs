@@ -0,0 +1,186 @@
+use super::ModifyPath;
+use crate::http::RouteMatch;
+use http::{
+    header,
+    uri::{Authority, InvalidUri, InvalidUriParts, Uri},
+};
+
+/// Rewrites a request's path and/or authority before it is forwarded.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct UrlRewrite {
+    pub authority: Option<Authority>,
+    pub path: Option<ModifyPath>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidUrlRewrite {
+    #[error("rewrites may only replace the path prefix when a path prefix match applied")]
+    ReplacePrefix,
+
+    #[error("rewrite produced an invalid path: {0}")]
+    Path(#[from] InvalidUri),
+
+    #[error("rewrite produced an invalid URI: {0}")]
+    Uri(#[from] InvalidUriParts),
+}
+
+// === impl UrlRewrite ===
+
+impl UrlRewrite {
+    /// Rewrites the request's URI in-place.
+    ///
+    /// The authority is rewritten in the URI (if it is in absolute-form) and in
+    /// the `host` header (if one is set).
+    pub fn apply<B>(
+        &self,
+        req: &mut http::Request<B>,
+        rm: &RouteMatch,
+    ) -> Result<(), InvalidUrlRewrite> {
+        let path_and_query = match &self.path {
+            None => None,
+            Some(path) => Some(
+                path.modify(req.uri(), rm)
+                    .ok_or(InvalidUrlRewrite::ReplacePrefix)?
+                    .try_into()?,
+            ),
+        };
+
+        let mut parts = req.uri().clone().into_parts();
+        if let Some(pq) = path_and_query {
+            parts.path_and_query = Some(pq);
+        }
+        if let Some(authority) = &self.authority {
+            if parts.authority.is_some() {
+                parts.authority = Some(authority.clone());
+            }
+            if req.headers().contains_key(header::HOST) {
+                let host = header::HeaderValue::from_str(authority.as_str())
+                    .expect("authority must be a valid header value");
+                req.headers_mut().insert(header::HOST, host);
+            }
+        }
+        *req.uri_mut() = Uri::from_parts(parts)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{find, r#match::MatchPath, MatchRequest, Route, Rule};
+
+    fn apply(req: &mut http::Request<()>, rule: Rule<UrlRewrite>) -> Result<(), InvalidUrlRewrite> {
+        let routes = vec![Route {
+            hosts: vec![],
+            rules: vec![rule],
+        }];
+        let (rm, rewrite) = find(&*routes, req).expect("request must match");
+        rewrite.apply(req, &rm)
+    }
+
+    #[test]
+    fn default_noop() {
+        let rule = Rule {
+            matches: vec![MatchRequest::default()],
+            policy: UrlRewrite::default(),
+        };
+        let mut req = http::Request::builder()
+            .uri("http://example.com/foo?a=b")
+            .header(header::HOST, "example.com")
+            .body(())
+            .unwrap();
+        apply(&mut req, rule).expect("must apply");
+        assert_eq!(req.uri(), "http://example.com/foo?a=b");
+        assert_eq!(req.headers()[header::HOST], "example.com");
+    }
+
+    #[test]
+    fn authority() {
+        let rule = Rule {
+            matches: vec![MatchRequest::default()],
+            policy: UrlRewrite {
+                authority: Some("example.org:8080".parse().unwrap()),
+                ..UrlRewrite::default()
+            },
+        };
+        let mut req = http::Request::builder()
+            .uri("http://example.com/foo?a=b")
+            .header(header::HOST, "example.com")
+            .body(())
+            .unwrap();
+        apply(&mut req, rule.clone()).expect("must apply");
+        assert_eq!(req.uri(), "http://example.org:8080/foo?a=b");
+        assert_eq!(req.headers()[header::HOST], "example.org:8080");
+
+        // Origin-form URIs are not converted to absolute-form.
+        let mut req = http::Request::builder()
+            .uri("/foo?a=b")
+            .header(header::HOST, "example.com")
+            .body(())
+            .unwrap();
+        apply(&mut req, rule).expect("must apply");
+        assert_eq!(req.uri(), "/foo?a=b");
+        assert_eq!(req.headers()[header::HOST], "example.org:8080");
+    }
+
+    #[test]
+    fn replace_path_full() {
+        let rule = Rule {
+            matches: vec![MatchRequest::default()],
+            policy: UrlRewrite {
+                path: Some(ModifyPath::ReplaceFullPath("/bar".to_string())),
+                ..UrlRewrite::default()
+            },
+        };
+        let mut req = http::Request::builder()
+            .uri("http://example.com/foo?a=b")
+            .body(())
+            .unwrap();
+        apply(&mut req, rule).expect("must apply");
+        assert_eq!(req.uri(), "http://example.com/bar");
+    }
+
+    #[test]
+    fn replace_path_prefix() {
+        let rule = Rule {
+            matches: vec![MatchRequest {
+                path: Some(MatchPath::Prefix("/foo".to_string())),
+                ..MatchRequest::default()
+            }],
+            policy: UrlRewrite {
+                path: Some(ModifyPath::ReplacePrefixMatch("/qux".to_string())),
+                ..UrlRewrite::default()
+            },
+        };
+        let mut req = http::Request::builder()
+            .uri("/foo/bar?a=b&c")
+            .body(())
+            .unwrap();
+        apply(&mut req, rule).expect("must apply");
+        assert_eq!(req.uri(), "/qux/bar?a=b&c");
+    }
+
+    #[test]
+    fn replace_path_prefix_exact_match() {
+        let rule = Rule {
+            matches: vec![MatchRequest {
+                path: Some(MatchPath::Exact("/foo/bar".to_string())),
+                ..MatchRequest::default()
+            }],
+            policy: UrlRewrite {
+                path: Some(ModifyPath::ReplacePrefixMatch("/qux".to_string())),
+                ..UrlRewrite::default()
+            },
+        };
+        let mut req = http::Request::builder()
+            .uri("http://example.com/foo/bar")
+            .body(())
+            .unwrap();
+        assert!(matches!(
+            apply(&mut req, rule).expect_err("must not apply"),
+            InvalidUrlRewrite::ReplacePrefix
+        ));
+        assert_eq!(req.uri(), "http://example.com/foo/bar");
+    }
+}
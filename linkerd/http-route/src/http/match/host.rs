@@ -49,8 +49,12 @@ impl std::str::FromStr for MatchHost {
 
 impl MatchHost {
     pub fn summarize_match(&self, uri: &Uri) -> Option<HostMatch> {
-        let mut host = uri.authority()?.host();
+        self.summarize_host(uri.authority()?.host())
+    }
 
+    /// Matches a hostname (e.g. from a TLS server name indication), rather
+    /// than a request URI.
+    pub fn summarize_host(&self, mut host: &str) -> Option<HostMatch> {
         match self {
             Self::Exact(h) => {
                 if !h.ends_with('.') {
@@ -209,6 +213,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn host() {
+        let m = "*.example.com"
+            .parse::<MatchHost>()
+            .expect("*.example.com parses");
+        assert_eq!(
+            m.summarize_host("foo.example.com"),
+            Some(HostMatch::Suffix(".example.com".len()))
+        );
+        assert_eq!(m.summarize_host("example.com"), None);
+
+        let m = "example.com"
+            .parse::<MatchHost>()
+            .expect("example.com parses");
+        assert_eq!(
+            m.summarize_host("example.com."),
+            Some(HostMatch::Exact("example.com".len()))
+        );
+        assert_eq!(m.summarize_host("foo.example.com"), None);
+    }
+
     #[test]
     fn cmp() {
         assert!(HostMatch::Exact("example.com".len()) > HostMatch::Suffix(".example.com".len()));
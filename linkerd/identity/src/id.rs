@@ -0,0 +1,75 @@
+use crate::{Name, SpiffeId};
+use std::{fmt, str::FromStr};
+
+/// A peer's identity, as presented in its certificate.
+///
+/// Identities are usually DNS-like names, though peers may instead present a
+/// SPIFFE ID as a URI SAN.
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub enum Id {
+    Dns(Name),
+    Spiffe(SpiffeId),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, thiserror::Error)]
+#[error("invalid identity")]
+pub struct InvalidId;
+
+// === impl Id ===
+
+impl Id {
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Dns(name) => name.as_str(),
+            Self::Spiffe(id) => id.as_str(),
+        }
+    }
+
+    /// Returns the identity's DNS-like name, if it has one.
+    #[inline]
+    pub fn dns_name(&self) -> Option<&Name> {
+        match self {
+            Self::Dns(name) => Some(name),
+            Self::Spiffe(_) => None,
+        }
+    }
+}
+
+impl From<Name> for Id {
+    fn from(name: Name) -> Self {
+        Self::Dns(name)
+    }
+}
+
+impl From<SpiffeId> for Id {
+    fn from(id: SpiffeId) -> Self {
+        Self::Spiffe(id)
+    }
+}
+
+impl FromStr for Id {
+    type Err = InvalidId;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("spiffe://") {
+            return s.parse().map(Self::Spiffe).map_err(|_| InvalidId);
+        }
+        s.parse().map(Self::Dns).map_err(|_| InvalidId)
+    }
+}
+
+impl fmt::Debug for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dns(name) => fmt::Debug::fmt(name, f),
+            Self::Spiffe(id) => fmt::Debug::fmt(id, f),
+        }
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
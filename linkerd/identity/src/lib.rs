@@ -2,12 +2,16 @@
 #![forbid(unsafe_code)]
 
 mod credentials;
+mod id;
 mod local;
 mod name;
+mod spiffe;
 
 pub use self::{
     credentials::{Credentials, DerX509},
+    id::{Id, InvalidId},
     local::LocalId,
     name::Name,
+    spiffe::{InvalidSpiffeId, SpiffeId},
 };
 pub use linkerd_dns_name::InvalidName;
@@ -0,0 +1,146 @@
+use std::{fmt, str::FromStr, sync::Arc};
+
+/// A SPIFFE ID, e.g. `spiffe://example.org/ns/default/sa/web`.
+///
+/// SPIFFE IDs are presented as URI SANs in X.509 SVIDs. They are validated as
+/// described by the [SPIFFE ID specification][spec].
+///
+/// [spec]: https://github.com/spiffe/spiffe/blob/main/standards/SPIFFE-ID.md
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct SpiffeId {
+    uri: Arc<str>,
+    /// The offset of the path within the URI.
+    path: usize,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, thiserror::Error)]
+#[error("invalid SPIFFE ID")]
+pub struct InvalidSpiffeId;
+
+// === impl SpiffeId ===
+
+impl SpiffeId {
+    const SCHEME: &'static str = "spiffe://";
+
+    const MAX_LEN: usize = 2048;
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.uri
+    }
+
+    /// Returns the ID's trust domain, e.g. `example.org`.
+    #[inline]
+    pub fn trust_domain(&self) -> &str {
+        &self.uri[Self::SCHEME.len()..self.path]
+    }
+
+    /// Returns the ID's path, e.g. `/ns/default/sa/web`. The path may be
+    /// empty.
+    #[inline]
+    pub fn path(&self) -> &str {
+        &self.uri[self.path..]
+    }
+}
+
+impl FromStr for SpiffeId {
+    type Err = InvalidSpiffeId;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() > Self::MAX_LEN {
+            return Err(InvalidSpiffeId);
+        }
+
+        let rest = s.strip_prefix(Self::SCHEME).ok_or(InvalidSpiffeId)?;
+        let (trust_domain, path) = match rest.find('/') {
+            Some(idx) => rest.split_at(idx),
+            None => (rest, ""),
+        };
+
+        // Trust domains may only include lowercase letters, numbers, dots,
+        // dashes, and underscores.
+        if trust_domain.is_empty()
+            || !trust_domain
+                .bytes()
+                .all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_'))
+        {
+            return Err(InvalidSpiffeId);
+        }
+
+        // Paths are made of non-empty segments that may only include letters,
+        // numbers, dots, dashes, and underscores. Relative segments are not
+        // permitted.
+        if !path.is_empty() {
+            for segment in path[1..].split('/') {
+                if segment.is_empty()
+                    || segment == "."
+                    || segment == ".."
+                    || !segment
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_'))
+                {
+                    return Err(InvalidSpiffeId);
+                }
+            }
+        }
+
+        Ok(Self {
+            uri: s.into(),
+            path: Self::SCHEME.len() + trust_domain.len(),
+        })
+    }
+}
+
+impl fmt::Debug for SpiffeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.uri, f)
+    }
+}
+
+impl fmt::Display for SpiffeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.uri, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses() {
+        let id = "spiffe://example.org/ns/default/sa/web"
+            .parse::<SpiffeId>()
+            .expect("must parse");
+        assert_eq!(id.trust_domain(), "example.org");
+        assert_eq!(id.path(), "/ns/default/sa/web");
+
+        let id = "spiffe://example.org"
+            .parse::<SpiffeId>()
+            .expect("must parse");
+        assert_eq!(id.trust_domain(), "example.org");
+        assert_eq!(id.path(), "");
+
+        for invalid in [
+            "",
+            "example.org",
+            "http://example.org/web",
+            "SPIFFE://example.org/web",
+            "spiffe://",
+            "spiffe:///web",
+            "spiffe://Example.org/web",
+            "spiffe://example.org:8080/web",
+            "spiffe://user@example.org/web",
+            "spiffe://example.org/",
+            "spiffe://example.org/web/",
+            "spiffe://example.org//web",
+            "spiffe://example.org/./web",
+            "spiffe://example.org/../web",
+            "spiffe://example.org/web?query",
+            "spiffe://example.org/web#fragment",
+            "spiffe://example.org/web%20app",
+        ] {
+            assert!(invalid.parse::<SpiffeId>().is_err(), "{invalid:?}");
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::creds::CredsRx;
-use linkerd_identity::Name;
+use linkerd_identity::{Name, SpiffeId};
 use linkerd_io as io;
 use linkerd_stack::{Param, Service};
 use linkerd_tls::{ClientId, LocalId, NegotiatedProtocol, ServerTls};
@@ -118,13 +118,22 @@ impl<I> ServerIo<I> {
             debug!("Peer certificate missing SANs");
             None
         })?;
-        sans.into_iter()
-            .filter_map(|san| san.dnsname()?.parse().ok())
-            .next()
-            .or_else(|| {
-                debug!("Peer certificate missing DNS SANs");
+        if let Some(name) = sans
+            .iter()
+            .find_map(|san| san.dnsname()?.parse::<Name>().ok())
+        {
+            return Some(ClientId::from(name));
+        }
+
+        // X.509 SVIDs must contain exactly one URI SAN.
+        let mut uris = sans.iter().filter_map(|san| san.uri());
+        match (uris.next(), uris.next()) {
+            (Some(uri), None) => uri.parse::<SpiffeId>().ok().map(ClientId::from),
+            _ => {
+                debug!("Peer certificate missing DNS SANs or a SPIFFE ID");
                 None
-            })
+            }
+        }
     }
 }
 
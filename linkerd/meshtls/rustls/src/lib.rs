@@ -3,6 +3,7 @@
 
 mod client;
 pub mod creds;
+mod san;
 mod server;
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,165 @@
+//! Minimal DER parsing to extract URI SANs from X.509 certificates.
+//!
+//! webpki only exposes a certificate's DNS SANs, so SPIFFE IDs are read
+//! directly from the certificate's subjectAltName extension. The certificate
+//! has already been validated by the time this is used, so this only needs to
+//! be robust to (not validate) malformed input.
+
+const SEQUENCE: u8 = 0x30;
+const OID: u8 = 0x06;
+const BOOLEAN: u8 = 0x01;
+const OCTET_STRING: u8 = 0x04;
+const EXTENSIONS: u8 = 0xa3;
+const URI: u8 = 0x86;
+
+/// The subjectAltName OID, 2.5.29.17.
+const SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// Returns the URI SANs in a DER-encoded certificate.
+pub(crate) fn uris(cert: &[u8]) -> Vec<&str> {
+    subject_alt_names(cert)
+        .map(|names| {
+            tlvs(names)
+                .filter(|(tag, _)| *tag == URI)
+                .filter_map(|(_, uri)| std::str::from_utf8(uri).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn subject_alt_names(cert: &[u8]) -> Option<&[u8]> {
+    let (cert, _) = expect(SEQUENCE, cert)?;
+    let (tbs, _) = expect(SEQUENCE, cert)?;
+    let (_, exts) = tlvs(tbs).find(|(tag, _)| *tag == EXTENSIONS)?;
+    let (exts, _) = expect(SEQUENCE, exts)?;
+    tlvs(exts).find_map(|(tag, ext)| {
+        if tag != SEQUENCE {
+            return None;
+        }
+        let (oid, ext) = expect(OID, ext)?;
+        if oid != SUBJECT_ALT_NAME {
+            return None;
+        }
+        let ext = match expect(BOOLEAN, ext) {
+            Some((_critical, ext)) => ext,
+            None => ext,
+        };
+        let (value, _) = expect(OCTET_STRING, ext)?;
+        let (names, _) = expect(SEQUENCE, value)?;
+        Some(names)
+    })
+}
+
+/// Iterates over the tag-length-value items in `input`.
+fn tlvs(mut input: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    std::iter::from_fn(move || {
+        let (tag, value, rest) = read(input)?;
+        input = rest;
+        Some((tag, value))
+    })
+}
+
+fn expect(tag: u8, input: &[u8]) -> Option<(&[u8], &[u8])> {
+    match read(input)? {
+        (t, value, rest) if t == tag => Some((value, rest)),
+        _ => None,
+    }
+}
+
+/// Reads a single tag-length-value item, returning its tag, its value, and the
+/// remaining input. Multi-byte tags are not supported.
+fn read(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&len, mut input) = input.split_first()?;
+    let len = if len < 0x80 {
+        len as usize
+    } else {
+        let n = (len & 0x7f) as usize;
+        if n == 0 || n > 4 || input.len() < n {
+            return None;
+        }
+        let (bytes, rest) = input.split_at(n);
+        input = rest;
+        bytes.iter().fold(0, |len, &b| (len << 8) | b as usize)
+    };
+    if input.len() < len {
+        return None;
+    }
+    let (value, rest) = input.split_at(len);
+    Some((tag, value, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        if value.len() < 0x80 {
+            out.push(value.len() as u8);
+        } else {
+            out.push(0x82);
+            out.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        }
+        out.extend_from_slice(value);
+        out
+    }
+
+    fn cert(names: &[Vec<u8>]) -> Vec<u8> {
+        let san = tlv(
+            SEQUENCE,
+            &[
+                tlv(OID, SUBJECT_ALT_NAME),
+                tlv(BOOLEAN, &[0xff]),
+                tlv(OCTET_STRING, &tlv(SEQUENCE, &names.concat())),
+            ]
+            .concat(),
+        );
+        let other = tlv(
+            SEQUENCE,
+            &[
+                tlv(OID, &[0x55, 0x1d, 0x13]),
+                tlv(OCTET_STRING, &tlv(SEQUENCE, &[])),
+            ]
+            .concat(),
+        );
+        let tbs = tlv(
+            SEQUENCE,
+            &[
+                tlv(0xa0, &tlv(0x02, &[2])),
+                tlv(0x02, &[1]),
+                tlv(SEQUENCE, &[]),
+                tlv(SEQUENCE, &[]),
+                tlv(SEQUENCE, &[]),
+                tlv(SEQUENCE, &[]),
+                tlv(SEQUENCE, &[]),
+                tlv(EXTENSIONS, &tlv(SEQUENCE, &[other, san].concat())),
+            ]
+            .concat(),
+        );
+        tlv(
+            SEQUENCE,
+            &[tbs, tlv(SEQUENCE, &[]), tlv(0x03, &[0; 200])].concat(),
+        )
+    }
+
+    #[test]
+    fn reads_uris() {
+        let uri = "spiffe://example.org/ns/default/sa/web";
+        let cert = cert(&[tlv(0x82, b"web.example.org"), tlv(URI, uri.as_bytes())]);
+        assert_eq!(uris(&cert), vec![uri]);
+
+        let cert = cert(&[tlv(0x82, b"web.example.org")]);
+        assert!(uris(&cert).is_empty());
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(uris(&[]).is_empty());
+        assert!(uris(&[SEQUENCE, 0x84, 0xff, 0xff, 0xff, 0xff]).is_empty());
+
+        let mut cert = cert(&[tlv(URI, b"spiffe://example.org/web")]);
+        cert.truncate(cert.len() / 2);
+        assert!(uris(&cert).is_empty());
+    }
+}
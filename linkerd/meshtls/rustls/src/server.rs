@@ -1,5 +1,5 @@
 use futures::prelude::*;
-use linkerd_identity::{LocalId, Name};
+use linkerd_identity::{LocalId, Name, SpiffeId};
 use linkerd_io as io;
 use linkerd_stack::{Param, Service};
 use linkerd_tls::{ClientId, NegotiatedProtocol, NegotiatedProtocolRef, ServerTls};
@@ -132,15 +132,22 @@ fn client_identity<I>(tls: &tokio_rustls::server::TlsStream<I>) -> Option<Client
     let end_cert = webpki::EndEntityCert::try_from(c).ok()?;
     let dns_names = end_cert.dns_names().ok()?;
 
-    match dns_names.first()? {
-        webpki::GeneralDnsNameRef::DnsName(n) => {
+    match dns_names.first() {
+        Some(webpki::GeneralDnsNameRef::DnsName(n)) => {
             let s: &str = (*n).into();
-            s.parse().ok().map(ClientId)
+            s.parse::<Name>().ok().map(ClientId::from)
         }
-        webpki::GeneralDnsNameRef::Wildcard(_) => {
+        Some(webpki::GeneralDnsNameRef::Wildcard(_)) => {
             // Wildcards can perhaps be handled in a future path...
             None
         }
+        None => {
+            // X.509 SVIDs must contain exactly one URI SAN.
+            match crate::san::uris(c)[..] {
+                [uri] => uri.parse::<SpiffeId>().ok().map(ClientId::from),
+                _ => None,
+            }
+        }
     }
 }
 
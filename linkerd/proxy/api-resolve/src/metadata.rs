@@ -22,6 +22,14 @@ pub struct Metadata {
 
     /// Used to override the the authority if needed
     authority_override: Option<Authority>,
+
+    /// The endpoint's relative weight, as set by the destination service.
+    weight: u32,
+
+    /// Whether connections to the endpoint are prefaced with a PROXY protocol
+    /// header describing the application's client address. This only applies
+    /// to endpoints that are not meshed.
+    proxy_protocol: bool,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
@@ -46,17 +54,23 @@ impl Default for Metadata {
             authority_override: None,
             tagged_transport_port: None,
             protocol_hint: ProtocolHint::Unknown,
+            weight: Self::DEFAULT_WEIGHT,
+            proxy_protocol: false,
         }
     }
 }
 
 impl Metadata {
+    /// The weight the destination service assigns to endpoints by default.
+    pub const DEFAULT_WEIGHT: u32 = 10_000;
+
     pub fn new(
         labels: impl IntoIterator<Item = (String, String)>,
         protocol_hint: ProtocolHint,
         tagged_transport_port: Option<u16>,
         identity: Option<ServerId>,
         authority_override: Option<Authority>,
+        weight: u32,
     ) -> Self {
         Self {
             labels: labels.into_iter().collect::<BTreeMap<_, _>>().into(),
@@ -64,6 +78,17 @@ impl Metadata {
             tagged_transport_port,
             identity,
             authority_override,
+            weight,
+            proxy_protocol: false,
+        }
+    }
+
+    /// Configures whether connections to the endpoint are prefaced with a
+    /// PROXY protocol header.
+    pub fn with_proxy_protocol(self, proxy_protocol: bool) -> Self {
+        Self {
+            proxy_protocol,
+            ..self
         }
     }
 
@@ -87,4 +112,12 @@ impl Metadata {
     pub fn authority_override(&self) -> Option<&Authority> {
         self.authority_override.as_ref()
     }
+
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    pub fn proxy_protocol(&self) -> bool {
+        self.proxy_protocol
+    }
 }
@@ -0,0 +1,332 @@
+use crate::discover;
+use futures::{future, TryFutureExt};
+use linkerd_error::Error;
+use linkerd_proxy_core::Resolve;
+use linkerd_stack::{layer, NewService, Param, Service};
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{
+    discover::{Change, Discover},
+    ready_cache::{error::Failed, ReadyCache},
+};
+use tracing::{debug, trace};
+
+/// Obtains a hash from a request so that requests with the same hash are
+/// dispatched to the same endpoint.
+pub trait HashRequest<Req> {
+    /// Returns `None` if the request has no hashable key, in which case the
+    /// request is dispatched to an arbitrary endpoint.
+    fn hash_request(&self, req: &Req) -> Option<u64>;
+}
+
+/// Configures a stack to resolve targets to consistently hash requests over
+/// `N`-typed endpoint stacks.
+///
+/// The target must provide a `K`-typed [`HashRequest`] implementation.
+#[derive(Debug)]
+pub struct NewBalanceConsistentHash<K, Req, R, N> {
+    update_queue_capacity: usize,
+    resolve: R,
+    inner: N,
+    _marker: PhantomData<fn(Req) -> K>,
+}
+
+/// Dispatches requests to endpoints on a hash ring.
+///
+/// Each endpoint is placed on the ring at several points, so that adding or
+/// removing an endpoint only moves the keys that hash near those points. When
+/// the endpoint that owns a key is not ready (e.g. because it is at capacity or
+/// its connection is being reestablished), the request is dispatched to the
+/// next ready endpoint on the ring, so affinity is lost for as long as the
+/// owner remains pending. Each such request is logged at the `debug` level.
+pub struct ConsistentHash<D, K, Req>
+where
+    D: Discover,
+    D::Key: Hash + Eq,
+{
+    discover: D,
+    key: K,
+    ring: Ring<D::Key>,
+    services: ReadyCache<D::Key, D::Service, Req>,
+}
+
+#[derive(Debug)]
+struct Ring<K> {
+    points: BTreeMap<u64, K>,
+}
+
+// === impl NewBalanceConsistentHash ===
+
+impl<K, Req, R, N> NewBalanceConsistentHash<K, Req, R, N> {
+    /// See [`crate::NewBalancePeakEwma`].
+    const UPDATE_QUEUE_CAPACITY: usize = 1_000;
+
+    pub fn new(inner: N, resolve: R) -> Self {
+        Self {
+            update_queue_capacity: Self::UPDATE_QUEUE_CAPACITY,
+            resolve,
+            inner,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn layer(resolve: R) -> impl layer::Layer<N, Service = Self> + Clone
+    where
+        R: Clone,
+    {
+        layer::mk(move |inner| Self::new(inner, resolve.clone()))
+    }
+}
+
+impl<K, T, Req, R, M, N, S> NewService<T> for NewBalanceConsistentHash<K, Req, R, M>
+where
+    T: Param<K> + Clone + Send,
+    K: HashRequest<Req>,
+    R: Resolve<T>,
+    M: NewService<T, Service = N> + Clone,
+    N: NewService<(SocketAddr, R::Endpoint), Service = S> + Send + 'static,
+    S: Service<Req> + Send,
+    S::Error: Into<Error>,
+{
+    type Service = ConsistentHash<discover::Buffer<S>, K, Req>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let key = target.param();
+        let disco = discover::spawn_new_from_resolve(
+            self.update_queue_capacity,
+            self.resolve.clone(),
+            self.inner.clone(),
+            target,
+        );
+        ConsistentHash::new(disco, key)
+    }
+}
+
+impl<K, Req, R: Clone, N: Clone> Clone for NewBalanceConsistentHash<K, Req, R, N> {
+    fn clone(&self) -> Self {
+        Self {
+            update_queue_capacity: self.update_queue_capacity,
+            resolve: self.resolve.clone(),
+            inner: self.inner.clone(),
+            _marker: self._marker,
+        }
+    }
+}
+
+// === impl ConsistentHash ===
+
+impl<D, K, Req> ConsistentHash<D, K, Req>
+where
+    D: Discover + Unpin,
+    D::Key: Hash + Eq + Clone,
+    D::Error: Into<Error>,
+    D::Service: Service<Req>,
+    <D::Service as Service<Req>>::Error: Into<Error>,
+{
+    pub fn new(discover: D, key: K) -> Self {
+        Self {
+            discover,
+            key,
+            ring: Ring::default(),
+            services: ReadyCache::default(),
+        }
+    }
+
+    /// Polls `discover` for updates, adding new endpoints to the ring.
+    fn update_from_discover(&mut self, cx: &mut Context<'_>) -> Result<(), Error> {
+        loop {
+            let change = match Pin::new(&mut self.discover).poll_discover(cx) {
+                Poll::Ready(Some(change)) => change.map_err(Into::into)?,
+                Poll::Ready(None) | Poll::Pending => return Ok(()),
+            };
+            match change {
+                Change::Insert(key, svc) => {
+                    trace!("insert");
+                    self.ring.insert(key.clone());
+                    self.services.push(key, svc);
+                }
+                Change::Remove(key) => {
+                    trace!("remove");
+                    self.ring.remove(&key);
+                    self.services.evict(&key);
+                }
+            }
+        }
+    }
+
+    fn promote_pending_to_ready(&mut self, cx: &mut Context<'_>) {
+        loop {
+            match self.services.poll_pending(cx) {
+                Poll::Ready(Ok(())) | Poll::Pending => break,
+                Poll::Ready(Err(Failed(_, error))) => {
+                    debug!(%error, "dropping failed endpoint");
+                }
+            }
+        }
+    }
+
+    /// Ensures that all ready endpoints remain ready, so that a request may
+    /// be dispatched to whichever endpoint owns its key.
+    fn check_ready(&mut self, cx: &mut Context<'_>) {
+        // Iterate in reverse so that services that become unready (and are
+        // swapped out of the ready set) do not disturb unchecked indices.
+        for index in (0..self.services.ready_len()).rev() {
+            if let Err(Failed(_, error)) = self.services.check_ready_index(cx, index) {
+                debug!(%error, "endpoint failed");
+            }
+        }
+    }
+}
+
+impl<D, K, Req> Service<Req> for ConsistentHash<D, K, Req>
+where
+    D: Discover + Unpin,
+    D::Key: Hash + Eq + Clone,
+    D::Error: Into<Error>,
+    D::Service: Service<Req>,
+    <D::Service as Service<Req>>::Error: Into<Error>,
+    K: HashRequest<Req>,
+{
+    type Response = <D::Service as Service<Req>>::Response;
+    type Error = Error;
+    type Future = future::ErrInto<<D::Service as Service<Req>>::Future, Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.update_from_discover(cx)?;
+        self.promote_pending_to_ready(cx);
+        self.check_ready(cx);
+        trace!(
+            ready = %self.services.ready_len(),
+            pending = %self.services.pending_len(),
+            "poll_ready"
+        );
+        if self.services.ready_len() == 0 {
+            // We have previously registered interest in updates from discover
+            // and pending services.
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let hash = self.key.hash_request(&req);
+        let pinned = hash.is_some();
+        let hash = hash.unwrap_or_else(rand::random::<u64>);
+        let services = &self.services;
+        let mut owner_pending = false;
+        let key = self
+            .ring
+            .iter_from(hash)
+            .find(|key| {
+                let ready = services.get_ready(*key).is_some();
+                owner_pending |= !ready;
+                ready
+            })
+            .cloned();
+        if pinned && owner_pending {
+            // The request loses its affinity until the endpoint that owns its
+            // key becomes ready again (or is removed from the ring).
+            debug!("Endpoint is not ready; dispatching request to the next endpoint on the ring");
+        }
+        match key {
+            Some(key) => self.services.call_ready(&key, req).err_into(),
+            // Every ready endpoint is on the ring, so this can only happen if
+            // the balancer was not ready.
+            None => self.services.call_ready_index(0, req).err_into(),
+        }
+    }
+}
+
+// === impl Ring ===
+
+impl<K> Default for Ring<K> {
+    fn default() -> Self {
+        Self {
+            points: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> Ring<K> {
+    /// The number of points on the ring for each endpoint.
+    const POINTS_PER_KEY: u32 = 100;
+
+    fn insert(&mut self, key: K) {
+        self.remove(&key);
+        for n in 0..Self::POINTS_PER_KEY {
+            let mut hasher = DefaultHasher::new();
+            (&key, n).hash(&mut hasher);
+            self.points.insert(hasher.finish(), key.clone());
+        }
+    }
+
+    fn remove(&mut self, key: &K) {
+        self.points.retain(|_, k| k != key);
+    }
+
+    /// Iterates over endpoints clockwise from the given hash.
+    fn iter_from(&self, hash: u64) -> impl Iterator<Item = &K> {
+        self.points
+            .range(hash..)
+            .chain(self.points.range(..hash))
+            .map(|(_, key)| key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owner(ring: &Ring<u16>, hash: u64) -> u16 {
+        *ring.iter_from(hash).next().expect("ring must not be empty")
+    }
+
+    #[test]
+    fn removal_only_moves_removed_keys() {
+        let mut ring = Ring::default();
+        for ep in 0..10 {
+            ring.insert(ep);
+        }
+        let hashes = (0..1_000)
+            .map(|_| rand::random::<u64>())
+            .collect::<Vec<_>>();
+        let before = hashes.iter().map(|h| owner(&ring, *h)).collect::<Vec<_>>();
+
+        ring.remove(&3);
+        for (h, prior) in hashes.iter().zip(before) {
+            let now = owner(&ring, *h);
+            if prior == 3 {
+                assert_ne!(now, 3);
+            } else {
+                assert_eq!(
+                    now, prior,
+                    "only keys owned by the removed endpoint may move"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn addition_only_moves_keys_to_added_endpoint() {
+        let mut ring = Ring::default();
+        for ep in 0..10 {
+            ring.insert(ep);
+        }
+        let hashes = (0..1_000)
+            .map(|_| rand::random::<u64>())
+            .collect::<Vec<_>>();
+        let before = hashes.iter().map(|h| owner(&ring, *h)).collect::<Vec<_>>();
+
+        ring.insert(10);
+        for (h, prior) in hashes.iter().zip(before) {
+            let now = owner(&ring, *h);
+            assert!(now == prior || now == 10);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::discover;
+use linkerd_error::Error;
+use linkerd_proxy_core::Resolve;
+use linkerd_stack::{layer, NewService, Service};
+use rand::thread_rng;
+use std::{marker::PhantomData, net::SocketAddr};
+use tower::{
+    balance::p2c,
+    load::{self, PendingRequests},
+};
+
+/// Configures a stack to resolve targets to balance requests over `N`-typed
+/// endpoint stacks, preferring the endpoint with the fewest outstanding
+/// requests.
+///
+/// Unlike [`crate::NewBalancePeakEwma`], endpoint latency is not considered,
+/// so this is best suited to workloads where request latency is uniform.
+#[derive(Debug)]
+pub struct NewBalanceLeastRequest<C, Req, R, N> {
+    update_queue_capacity: usize,
+    resolve: R,
+    inner: N,
+    _marker: PhantomData<fn(Req) -> C>,
+}
+
+type Buffer<C, S> = discover::Buffer<PendingRequests<S, C>>;
+pub type LeastRequest<C, Req, S> = p2c::Balance<Buffer<C, S>, Req>;
+
+/// Wraps the inner stack in [`NewPendingRequests`] to produce
+/// [`PendingRequests`] services.
+#[derive(Debug)]
+pub struct NewNewPendingRequests<C, Req, N> {
+    inner: N,
+    _marker: PhantomData<fn(Req) -> C>,
+}
+
+/// Wraps the inner services in [`PendingRequests`] services so their load is
+/// tracked for the p2c balancer.
+#[derive(Debug)]
+pub struct NewPendingRequests<C, Req, N> {
+    inner: N,
+    _marker: PhantomData<fn(Req) -> C>,
+}
+
+// === impl NewBalanceLeastRequest ===
+
+impl<C, Req, R, N> NewBalanceLeastRequest<C, Req, R, N> {
+    /// See [`crate::NewBalancePeakEwma`].
+    const UPDATE_QUEUE_CAPACITY: usize = 1_000;
+
+    pub fn new(inner: N, resolve: R) -> Self {
+        Self {
+            update_queue_capacity: Self::UPDATE_QUEUE_CAPACITY,
+            resolve,
+            inner,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn layer(resolve: R) -> impl layer::Layer<N, Service = Self> + Clone
+    where
+        R: Clone,
+    {
+        layer::mk(move |inner| Self::new(inner, resolve.clone()))
+    }
+}
+
+impl<C, T, Req, R, M, N, S> NewService<T> for NewBalanceLeastRequest<C, Req, R, M>
+where
+    T: Clone + Send,
+    R: Resolve<T>,
+    M: NewService<T, Service = N> + Clone,
+    N: NewService<(SocketAddr, R::Endpoint), Service = S> + Send + 'static,
+    S: Service<Req> + Send,
+    S::Error: Into<Error>,
+    C: load::TrackCompletion<load::pending_requests::Handle, S::Response>
+        + Default
+        + Send
+        + 'static,
+    Req: 'static,
+    LeastRequest<C, Req, S>: Service<Req>,
+{
+    type Service = LeastRequest<C, Req, S>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let new = NewNewPendingRequests {
+            inner: self.inner.clone(),
+            _marker: PhantomData,
+        };
+        let disco = discover::spawn_new_from_resolve(
+            self.update_queue_capacity,
+            self.resolve.clone(),
+            new,
+            target,
+        );
+        LeastRequest::from_rng(disco, &mut thread_rng()).expect("RNG must be valid")
+    }
+}
+
+impl<C, Req, R: Clone, N: Clone> Clone for NewBalanceLeastRequest<C, Req, R, N> {
+    fn clone(&self) -> Self {
+        Self {
+            update_queue_capacity: self.update_queue_capacity,
+            resolve: self.resolve.clone(),
+            inner: self.inner.clone(),
+            _marker: self._marker,
+        }
+    }
+}
+
+// === impl NewNewPendingRequests ===
+
+impl<C, T, N, Req> NewService<T> for NewNewPendingRequests<C, Req, N>
+where
+    N: NewService<T>,
+{
+    type Service = NewPendingRequests<C, Req, N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        NewPendingRequests {
+            inner: self.inner.new_service(target),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<C, Req, N: Clone> Clone for NewNewPendingRequests<C, Req, N> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _marker: self._marker,
+        }
+    }
+}
+
+// === impl NewPendingRequests ===
+
+impl<C, T, N, Req, S> NewService<T> for NewPendingRequests<C, Req, N>
+where
+    C: load::TrackCompletion<load::pending_requests::Handle, S::Response> + Default,
+    N: NewService<T, Service = S>,
+    S: Service<Req>,
+{
+    type Service = PendingRequests<S, C>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        PendingRequests::new(self.inner.new_service(target), C::default())
+    }
+}
@@ -10,8 +10,23 @@ use tower::{
 
 mod discover;
 mod gauge_endpoints;
-
-pub use self::gauge_endpoints::{EndpointsGauges, NewGaugeEndpoints};
+mod hash;
+mod least_request;
+mod round_robin;
+mod slow_start;
+mod zone;
+
+pub use self::{
+    gauge_endpoints::{EndpointsGauges, NewGaugeEndpoints},
+    hash::{ConsistentHash, HashRequest, NewBalanceConsistentHash},
+    least_request::{LeastRequest, NewBalanceLeastRequest},
+    round_robin::{NewBalanceWeightedRoundRobin, Weight, Weighted, WeightedRoundRobin},
+    slow_start::{SlowStart, SlowStartConfig, SlowStartCost, SlowStartCurve},
+    zone::{
+        EndpointZone, NewZoneAffinity, NewZoneAffinityEndpoint, ZoneAffinityConfig,
+        ZoneAffinityEndpoint,
+    },
+};
 pub use tower::load::peak_ewma::Handle;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -30,7 +45,7 @@ pub struct NewBalancePeakEwma<C, Req, R, N> {
     _marker: PhantomData<fn(Req) -> C>,
 }
 
-type Buffer<C, S> = discover::Buffer<PeakEwma<S, C>>;
+type Buffer<C, S> = discover::Buffer<SlowStart<PeakEwma<S, C>>>;
 pub type Balance<C, Req, S> = p2c::Balance<Buffer<C, S>, Req>;
 
 /// Wraps the inner stack in [`NewPeakEwma`] to produce [`PeakEwma`] services.
@@ -42,9 +57,14 @@ pub struct NewNewPeakEwma<C, Req, N> {
 
 /// Wraps the inner services in [`PeakEwma`] services so their load is tracked
 /// for the p2c balancer.
+///
+/// When a [`SlowStartConfig`] is set, each new endpoint's load is wrapped in a
+/// [`SlowStart`] so that it receives a growing share of requests over the
+/// slow-start window.
 #[derive(Debug)]
 pub struct NewPeakEwma<C, Req, N> {
     config: EwmaConfig,
+    slow_start: Option<SlowStartConfig>,
     inner: N,
     _marker: PhantomData<fn(Req) -> C>,
 }
@@ -84,7 +104,7 @@ impl<C, Req, R, N> NewBalancePeakEwma<C, Req, R, N> {
 
 impl<C, T, Req, R, M, N, S> NewService<T> for NewBalancePeakEwma<C, Req, R, M>
 where
-    T: Param<EwmaConfig> + Clone + Send,
+    T: Param<EwmaConfig> + Param<Option<SlowStartConfig>> + Clone + Send,
     R: Resolve<T>,
     M: NewService<T, Service = N> + Clone,
     N: NewService<(SocketAddr, R::Endpoint), Service = S> + Send + 'static,
@@ -129,16 +149,18 @@ impl<C, Req, R: Clone, N: Clone> Clone for NewBalancePeakEwma<C, Req, R, N> {
 
 impl<C, T, N, Req> NewService<T> for NewNewPeakEwma<C, Req, N>
 where
-    T: Param<EwmaConfig>,
+    T: Param<EwmaConfig> + Param<Option<SlowStartConfig>>,
     N: NewService<T>,
 {
     type Service = NewPeakEwma<C, Req, N::Service>;
 
     fn new_service(&self, target: T) -> Self::Service {
         let config = target.param();
+        let slow_start = target.param();
         let inner = self.inner.new_service(target);
         NewPeakEwma {
             config,
+            slow_start,
             inner,
             _marker: PhantomData,
         }
@@ -162,7 +184,7 @@ where
     N: NewService<T, Service = S>,
     S: Service<Req>,
 {
-    type Service = PeakEwma<S, C>;
+    type Service = SlowStart<PeakEwma<S, C>>;
 
     fn new_service(&self, target: T) -> Self::Service {
         // Converts durations to nanos in f64.
@@ -177,11 +199,12 @@ where
             n + s
         }
 
-        PeakEwma::new(
+        let ewma = PeakEwma::new(
             self.inner.new_service(target),
             self.config.default_rtt,
             nanos(self.config.decay),
             C::default(),
-        )
+        );
+        SlowStart::new(self.slow_start, ewma)
     }
 }
@@ -0,0 +1,361 @@
+use crate::discover;
+use futures::{future, TryFutureExt};
+use linkerd_error::Error;
+use linkerd_proxy_core::Resolve;
+use linkerd_stack::{layer, ExtractParam, NewService, Service};
+use std::{
+    hash::Hash,
+    marker::PhantomData,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{
+    discover::{Change, Discover},
+    ready_cache::{error::Failed, ReadyCache},
+};
+use tracing::{debug, trace};
+
+/// An endpoint's relative share of requests.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Weight(pub u32);
+
+/// Configures a stack to resolve targets to distribute requests over `N`-typed
+/// endpoint stacks in proportion to each endpoint's [`Weight`].
+///
+/// Endpoint weights are obtained from resolved endpoints via the `X`-typed
+/// [`ExtractParam`].
+#[derive(Debug)]
+pub struct NewBalanceWeightedRoundRobin<X, Req, R, N> {
+    update_queue_capacity: usize,
+    extract: X,
+    resolve: R,
+    inner: N,
+    _marker: PhantomData<fn(Req)>,
+}
+
+/// Dispatches requests to ready endpoints using smooth weighted round-robin,
+/// so that endpoints with larger weights receive proportionally more requests
+/// without receiving them in bursts.
+pub struct WeightedRoundRobin<D, Req>
+where
+    D: Discover,
+    D::Key: Hash + Eq,
+{
+    discover: D,
+    services: ReadyCache<D::Key, D::Service, Req>,
+}
+
+/// An endpoint service annotated with its weight.
+#[derive(Debug)]
+pub struct Weighted<S> {
+    inner: S,
+    weight: i64,
+    current: i64,
+}
+
+/// Wraps the inner stack in [`NewWeighted`] to produce [`Weighted`] services.
+#[derive(Clone, Debug)]
+pub struct NewNewWeighted<X, N> {
+    extract: X,
+    inner: N,
+}
+
+/// Wraps each endpoint service with its extracted [`Weight`].
+#[derive(Clone, Debug)]
+pub struct NewWeighted<X, N> {
+    extract: X,
+    inner: N,
+}
+
+// === impl NewBalanceWeightedRoundRobin ===
+
+impl<X, Req, R, N> NewBalanceWeightedRoundRobin<X, Req, R, N> {
+    /// See [`crate::NewBalancePeakEwma`].
+    const UPDATE_QUEUE_CAPACITY: usize = 1_000;
+
+    pub fn new(extract: X, inner: N, resolve: R) -> Self {
+        Self {
+            update_queue_capacity: Self::UPDATE_QUEUE_CAPACITY,
+            extract,
+            resolve,
+            inner,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn layer_via(extract: X, resolve: R) -> impl layer::Layer<N, Service = Self> + Clone
+    where
+        X: Clone,
+        R: Clone,
+    {
+        layer::mk(move |inner| Self::new(extract.clone(), inner, resolve.clone()))
+    }
+}
+
+impl<X, T, Req, R, M, N, S> NewService<T> for NewBalanceWeightedRoundRobin<X, Req, R, M>
+where
+    T: Clone + Send,
+    X: ExtractParam<Weight, R::Endpoint> + Clone + Send + 'static,
+    R: Resolve<T>,
+    M: NewService<T, Service = N> + Clone,
+    N: NewService<(SocketAddr, R::Endpoint), Service = S> + Send + 'static,
+    S: Service<Req> + Send,
+    S::Error: Into<Error>,
+{
+    type Service = WeightedRoundRobin<discover::Buffer<Weighted<S>>, Req>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let new = NewNewWeighted {
+            extract: self.extract.clone(),
+            inner: self.inner.clone(),
+        };
+        let disco = discover::spawn_new_from_resolve(
+            self.update_queue_capacity,
+            self.resolve.clone(),
+            new,
+            target,
+        );
+        WeightedRoundRobin::new(disco)
+    }
+}
+
+impl<X: Clone, Req, R: Clone, N: Clone> Clone for NewBalanceWeightedRoundRobin<X, Req, R, N> {
+    fn clone(&self) -> Self {
+        Self {
+            update_queue_capacity: self.update_queue_capacity,
+            extract: self.extract.clone(),
+            resolve: self.resolve.clone(),
+            inner: self.inner.clone(),
+            _marker: self._marker,
+        }
+    }
+}
+
+// === impl NewNewWeighted ===
+
+impl<T, X, N> NewService<T> for NewNewWeighted<X, N>
+where
+    X: Clone,
+    N: NewService<T>,
+{
+    type Service = NewWeighted<X, N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        NewWeighted {
+            extract: self.extract.clone(),
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl NewWeighted ===
+
+impl<E, X, N> NewService<(SocketAddr, E)> for NewWeighted<X, N>
+where
+    X: ExtractParam<Weight, E>,
+    N: NewService<(SocketAddr, E)>,
+{
+    type Service = Weighted<N::Service>;
+
+    fn new_service(&self, (addr, endpoint): (SocketAddr, E)) -> Self::Service {
+        let Weight(weight) = self.extract.extract_param(&endpoint);
+        // Endpoints without a weight are treated as having the smallest weight
+        // so that they are not starved of requests.
+        let weight = i64::from(weight.max(1));
+        trace!(%addr, weight, "Weighted endpoint");
+        Weighted {
+            inner: self.inner.new_service((addr, endpoint)),
+            weight,
+            current: 0,
+        }
+    }
+}
+
+// === impl Weighted ===
+
+impl<Req, S: Service<Req>> Service<Req> for Weighted<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+// === impl WeightedRoundRobin ===
+
+impl<D, S, Req> WeightedRoundRobin<D, Req>
+where
+    D: Discover<Service = Weighted<S>> + Unpin,
+    D::Key: Hash + Eq + Clone,
+    D::Error: Into<Error>,
+    S: Service<Req>,
+    S::Error: Into<Error>,
+{
+    pub fn new(discover: D) -> Self {
+        Self {
+            discover,
+            services: ReadyCache::default(),
+        }
+    }
+
+    fn update_from_discover(&mut self, cx: &mut Context<'_>) -> Result<(), Error> {
+        loop {
+            let change = match Pin::new(&mut self.discover).poll_discover(cx) {
+                Poll::Ready(Some(change)) => change.map_err(Into::into)?,
+                Poll::Ready(None) | Poll::Pending => return Ok(()),
+            };
+            match change {
+                Change::Insert(key, svc) => {
+                    trace!("insert");
+                    self.services.push(key, svc);
+                }
+                Change::Remove(key) => {
+                    trace!("remove");
+                    self.services.evict(&key);
+                }
+            }
+        }
+    }
+
+    fn promote_pending_to_ready(&mut self, cx: &mut Context<'_>) {
+        loop {
+            match self.services.poll_pending(cx) {
+                Poll::Ready(Ok(())) | Poll::Pending => break,
+                Poll::Ready(Err(Failed(_, error))) => {
+                    debug!(%error, "dropping failed endpoint");
+                }
+            }
+        }
+    }
+
+    /// Selects the ready endpoint with the largest current weight, after
+    /// increasing each endpoint's current weight by its configured weight.
+    fn select(&mut self) -> usize {
+        let mut total = 0;
+        let mut selected = 0;
+        let mut max = i64::MIN;
+        for index in 0..self.services.ready_len() {
+            let (_, svc) = self
+                .services
+                .get_ready_index_mut(index)
+                .expect("index must be ready");
+            svc.current += svc.weight;
+            total += svc.weight;
+            if svc.current > max {
+                max = svc.current;
+                selected = index;
+            }
+        }
+        if let Some((_, svc)) = self.services.get_ready_index_mut(selected) {
+            svc.current -= total;
+        }
+        selected
+    }
+}
+
+impl<D, S, Req> Service<Req> for WeightedRoundRobin<D, Req>
+where
+    D: Discover<Service = Weighted<S>> + Unpin,
+    D::Key: Hash + Eq + Clone,
+    D::Error: Into<Error>,
+    S: Service<Req>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::ErrInto<S::Future, Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.update_from_discover(cx)?;
+        self.promote_pending_to_ready(cx);
+
+        // Ensure that all ready endpoints remain ready so that the selected
+        // endpoint may be called. Iterate in reverse so that services that
+        // become unready do not disturb unchecked indices.
+        for index in (0..self.services.ready_len()).rev() {
+            if let Err(Failed(_, error)) = self.services.check_ready_index(cx, index) {
+                debug!(%error, "endpoint failed");
+            }
+        }
+
+        trace!(
+            ready = %self.services.ready_len(),
+            pending = %self.services.pending_len(),
+            "poll_ready"
+        );
+        if self.services.ready_len() == 0 {
+            // We have previously registered interest in updates from discover
+            // and pending services.
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let index = self.select();
+        self.services.call_ready_index(index, req).err_into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    #[tokio::test]
+    async fn distributes_by_weight() {
+        let (svc_a, mut handle_a) = tower_test::mock::pair::<(), ()>();
+        let (svc_b, mut handle_b) = tower_test::mock::pair::<(), ()>();
+        handle_a.allow(100);
+        handle_b.allow(100);
+
+        let disco = stream::iter(vec![
+            Ok::<_, Error>(Change::Insert(
+                "a",
+                Weighted {
+                    inner: svc_a,
+                    weight: 3,
+                    current: 0,
+                },
+            )),
+            Ok(Change::Insert(
+                "b",
+                Weighted {
+                    inner: svc_b,
+                    weight: 1,
+                    current: 0,
+                },
+            )),
+        ]);
+        let mut balance = WeightedRoundRobin::new(disco);
+
+        let mut a = 0;
+        let mut b = 0;
+        for _ in 0..8 {
+            future::poll_fn(|cx| balance.poll_ready(cx)).await.unwrap();
+            let rsp = balance.call(());
+            tokio::select! {
+                biased;
+                Some((_, tx)) = handle_a.next_request() => {
+                    a += 1;
+                    tx.send_response(());
+                }
+                Some((_, tx)) = handle_b.next_request() => {
+                    b += 1;
+                    tx.send_response(());
+                }
+            }
+            rsp.await.unwrap();
+        }
+        assert_eq!((a, b), (6, 2));
+    }
+}
@@ -0,0 +1,202 @@
+//! Slow-start for newly discovered endpoints.
+//!
+//! A new endpoint is initially reported as unloaded (i.e., at the balancer's
+//! default RTT), so it would otherwise immediately receive a full share of
+//! requests. While an endpoint warms up, its load is instead reported as
+//! [`SlowStartCost::Warming`], which loses every p2c comparison, with a
+//! probability that decreases over the slow-start window. The endpoint's
+//! effective weight therefore grows from a small fraction to its full weight
+//! over the course of the window.
+
+use linkerd_stack::Service;
+use rand::Rng;
+use std::{
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time;
+use tower::load::Load;
+
+/// Configures how newly discovered endpoints are ramped up.
+///
+/// Slow-start only applies to peak-EWMA balancers, as it relies on the
+/// balancer comparing endpoints' loads.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SlowStartConfig {
+    /// The duration over which an endpoint's weight grows to its full value.
+    pub window: Duration,
+
+    /// The percentage of its full weight that an endpoint is given when it is
+    /// first discovered.
+    pub min_weight_percent: u32,
+
+    /// How an endpoint's weight grows over the window.
+    pub curve: SlowStartCurve,
+}
+
+/// Describes how an endpoint's weight grows over the slow-start window.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SlowStartCurve {
+    /// The weight grows by a constant amount over time.
+    Linear,
+
+    /// The weight grows by a constant factor over time, so that an endpoint
+    /// receives relatively little traffic until late in the window.
+    Exponential,
+}
+
+/// Wraps an endpoint's [`Load`] so that it is selected less often until its
+/// slow-start window elapses.
+#[derive(Debug)]
+pub struct SlowStart<S> {
+    inner: S,
+    warmup: Option<Warmup>,
+}
+
+/// The load metric of a [`SlowStart`] service.
+///
+/// `Warming` endpoints are considered more loaded than any `Ready` endpoint.
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub enum SlowStartCost<M> {
+    Ready(M),
+    Warming,
+}
+
+#[derive(Debug)]
+struct Warmup {
+    config: SlowStartConfig,
+    started: time::Instant,
+}
+
+// === impl SlowStartConfig ===
+
+impl SlowStartConfig {
+    /// Returns the fraction of its full weight that an endpoint is given after
+    /// `elapsed` time.
+    fn weight(&self, elapsed: Duration) -> f64 {
+        if elapsed >= self.window {
+            return 1.0;
+        }
+
+        // The minimum weight must be positive for the weight to grow
+        // exponentially.
+        let min = f64::from(self.min_weight_percent.clamp(1, 100)) / 100.0;
+        let progress = elapsed.as_secs_f64() / self.window.as_secs_f64();
+        match self.curve {
+            SlowStartCurve::Linear => min + (1.0 - min) * progress,
+            SlowStartCurve::Exponential => min.powf(1.0 - progress),
+        }
+    }
+}
+
+// === impl SlowStart ===
+
+impl<S> SlowStart<S> {
+    pub fn new(config: Option<SlowStartConfig>, inner: S) -> Self {
+        let warmup = config.filter(|c| !c.window.is_zero()).map(|config| Warmup {
+            config,
+            started: time::Instant::now(),
+        });
+        Self { inner, warmup }
+    }
+}
+
+impl<S: Load> Load for SlowStart<S> {
+    type Metric = SlowStartCost<S::Metric>;
+
+    fn load(&self) -> Self::Metric {
+        if let Some(Warmup { config, started }) = self.warmup {
+            let weight = config.weight(time::Instant::now().saturating_duration_since(started));
+            if weight < 1.0 && !rand::thread_rng().gen_bool(weight) {
+                return SlowStartCost::Warming;
+            }
+        }
+
+        SlowStartCost::Ready(self.inner.load())
+    }
+}
+
+impl<Req, S: Service<Req>> Service<Req> for SlowStart<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Constant;
+
+    impl Load for Constant {
+        type Metric = u32;
+
+        fn load(&self) -> u32 {
+            1
+        }
+    }
+
+    fn config(curve: SlowStartCurve) -> SlowStartConfig {
+        SlowStartConfig {
+            window: Duration::from_secs(10),
+            min_weight_percent: 10,
+            curve,
+        }
+    }
+
+    #[test]
+    fn weight_grows_over_window() {
+        let linear = config(SlowStartCurve::Linear);
+        assert!((linear.weight(Duration::ZERO) - 0.1).abs() < 1e-9);
+        assert!((linear.weight(Duration::from_secs(5)) - 0.55).abs() < 1e-9);
+        assert_eq!(linear.weight(Duration::from_secs(10)), 1.0);
+
+        let exponential = config(SlowStartCurve::Exponential);
+        assert!((exponential.weight(Duration::ZERO) - 0.1).abs() < 1e-9);
+        let half = exponential.weight(Duration::from_secs(5));
+        assert!((half - 0.1f64.sqrt()).abs() < 1e-9);
+        assert!(half < linear.weight(Duration::from_secs(5)));
+        assert_eq!(exponential.weight(Duration::from_secs(11)), 1.0);
+    }
+
+    #[test]
+    fn warming_endpoints_lose_comparisons() {
+        assert!(SlowStartCost::Ready(u32::MAX) < SlowStartCost::Warming);
+        assert!(SlowStartCost::Ready(1) < SlowStartCost::Ready(2));
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn ramps_up() {
+        let svc = SlowStart::new(
+            Some(SlowStartConfig {
+                min_weight_percent: 0,
+                ..config(SlowStartCurve::Linear)
+            }),
+            Constant,
+        );
+        let ready = |svc: &SlowStart<Constant>| {
+            (0..1_000)
+                .filter(|_| svc.load() == SlowStartCost::Ready(1))
+                .count()
+        };
+
+        let initial = ready(&svc);
+        assert!(initial < 100, "{initial} of 1000 selected");
+
+        time::advance(Duration::from_secs(10)).await;
+        assert_eq!(ready(&svc), 1_000);
+
+        let disabled = SlowStart::new(None, Constant);
+        assert_eq!(ready(&disabled), 1_000);
+    }
+}
@@ -0,0 +1,480 @@
+//! Zone-aware load balancing.
+//!
+//! Balancers prefer endpoints in the proxy's own zone: endpoints in other
+//! zones are held unready, so that they are not selected, as long as the local
+//! zone has enough endpoints and enough of them are ready. Otherwise, requests
+//! spill over to endpoints in all zones until the local zone recovers.
+//!
+//! An endpoint is counted once its balancer first polls it, so that endpoints
+//! that are still buffered by discovery do not count. Its readiness is
+//! observed whenever the balancer polls it: the balancer polls pending
+//! endpoints as they are woken and polls each endpoint again after dispatching
+//! a request to it. An endpoint stops counting when it fails, since the
+//! balancer evicts failed endpoints, or when it is dropped.
+
+use linkerd_stack::{layer, ExtractParam, NewService, Service};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+use tracing::{debug, trace};
+
+/// Configures zone-aware load balancing.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ZoneAffinityConfig {
+    /// The zone in which the proxy runs.
+    pub zone: Arc<str>,
+
+    /// Requests spill over to other zones when less than this percentage of a
+    /// balancer's endpoints are in the local zone.
+    pub min_local_percent: u32,
+
+    /// Requests spill over to other zones when less than this percentage of
+    /// the local zone's endpoints are ready.
+    pub min_ready_percent: u32,
+}
+
+/// The zone of a resolved endpoint, if it is known.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct EndpointZone(pub Option<String>);
+
+/// Wraps each of a balancer's endpoint stacks so that endpoints in the local
+/// zone are preferred.
+///
+/// The balancer's [`ZoneAffinityConfig`] is obtained from the balancer target
+/// via the `X`-typed [`ExtractParam`]; zone-aware balancing is disabled when
+/// it is `None`. Each endpoint's zone is obtained from resolved endpoints via
+/// the `Z`-typed [`ExtractParam`].
+#[derive(Clone, Debug)]
+pub struct NewZoneAffinity<X, Z, N> {
+    extract: X,
+    zone: Z,
+    inner: N,
+}
+
+/// Builds [`ZoneAffinityEndpoint`]s that share a balancer's zone state.
+#[derive(Clone, Debug)]
+pub struct NewZoneAffinityEndpoint<Z, N> {
+    zones: Option<Zones>,
+    zone: Z,
+    inner: N,
+}
+
+/// An endpoint service that is held unready when it is not in the local zone
+/// and requests are not spilling over to other zones.
+#[derive(Debug)]
+pub struct ZoneAffinityEndpoint<S> {
+    inner: S,
+    endpoint: Option<Endpoint>,
+}
+
+/// The zone state shared by all of a balancer's endpoints.
+#[derive(Clone, Debug)]
+struct Zones(Arc<Mutex<State>>);
+
+#[derive(Debug)]
+struct State {
+    zone: Arc<str>,
+    min_local_percent: usize,
+    min_ready_percent: usize,
+    local: usize,
+    local_ready: usize,
+    remote: usize,
+    spill: bool,
+
+    /// Remote endpoints waiting for spillover to begin, by endpoint ID.
+    wakers: HashMap<usize, Waker>,
+    next_id: usize,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    zones: Zones,
+    id: usize,
+    local: bool,
+
+    /// Whether the endpoint is counted by the zone state and, if so, whether
+    /// it was ready when last polled.
+    counted: Option<bool>,
+}
+
+// === impl NewZoneAffinity ===
+
+impl<X: Clone, Z: Clone, N> NewZoneAffinity<X, Z, N> {
+    pub fn new(extract: X, zone: Z, inner: N) -> Self {
+        Self {
+            extract,
+            zone,
+            inner,
+        }
+    }
+
+    pub fn layer_via(extract: X, zone: Z) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self::new(extract.clone(), zone.clone(), inner))
+    }
+}
+
+impl<T, X, Z, N> NewService<T> for NewZoneAffinity<X, Z, N>
+where
+    X: ExtractParam<Option<ZoneAffinityConfig>, T>,
+    Z: Clone,
+    N: NewService<T>,
+{
+    type Service = NewZoneAffinityEndpoint<Z, N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let zones = self.extract.extract_param(&target).map(Zones::new);
+        NewZoneAffinityEndpoint {
+            zones,
+            zone: self.zone.clone(),
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl NewZoneAffinityEndpoint ===
+
+impl<E, Z, N> NewService<(SocketAddr, E)> for NewZoneAffinityEndpoint<Z, N>
+where
+    Z: ExtractParam<EndpointZone, E>,
+    N: NewService<(SocketAddr, E)>,
+{
+    type Service = ZoneAffinityEndpoint<N::Service>;
+
+    fn new_service(&self, (addr, endpoint): (SocketAddr, E)) -> Self::Service {
+        let endpoint_state = self.zones.as_ref().map(|zones| {
+            let EndpointZone(zone) = self.zone.extract_param(&endpoint);
+            let local = zones.is_local(zone.as_deref());
+            trace!(%addr, ?zone, local, "Zoned endpoint");
+            zones.endpoint(local)
+        });
+        ZoneAffinityEndpoint {
+            inner: self.inner.new_service((addr, endpoint)),
+            endpoint: endpoint_state,
+        }
+    }
+}
+
+// === impl ZoneAffinityEndpoint ===
+
+impl<Req, S: Service<Req>> Service<Req> for ZoneAffinityEndpoint<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let endpoint = match self.endpoint.as_mut() {
+            Some(endpoint) => endpoint,
+            None => return self.inner.poll_ready(cx),
+        };
+
+        let poll = if endpoint.local {
+            self.inner.poll_ready(cx)
+        } else {
+            // Remote endpoints are only polled while requests spill over to
+            // other zones. Otherwise, they are woken when spillover begins.
+            endpoint.set_counted(Some(false));
+            if !endpoint.poll_spill(cx) {
+                return Poll::Pending;
+            }
+            self.inner.poll_ready(cx)
+        };
+
+        match poll {
+            Poll::Ready(Ok(())) => endpoint.set_counted(Some(true)),
+            Poll::Pending => endpoint.set_counted(Some(false)),
+            // The balancer evicts endpoints that fail.
+            Poll::Ready(Err(_)) => endpoint.set_counted(None),
+        }
+        poll
+    }
+
+    #[inline]
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+// === impl Zones ===
+
+impl Zones {
+    fn new(config: ZoneAffinityConfig) -> Self {
+        Self(Arc::new(Mutex::new(State {
+            zone: config.zone,
+            min_local_percent: config.min_local_percent as usize,
+            min_ready_percent: config.min_ready_percent as usize,
+            local: 0,
+            local_ready: 0,
+            remote: 0,
+            spill: true,
+            wakers: HashMap::new(),
+            next_id: 0,
+        })))
+    }
+
+    fn is_local(&self, zone: Option<&str>) -> bool {
+        zone == Some(&*self.0.lock().zone)
+    }
+
+    /// Returns a new endpoint, which is not counted until it is polled.
+    fn endpoint(&self, local: bool) -> Endpoint {
+        let mut state = self.0.lock();
+        let id = state.next_id;
+        state.next_id = state.next_id.wrapping_add(1);
+        Endpoint {
+            zones: self.clone(),
+            id,
+            local,
+            counted: None,
+        }
+    }
+}
+
+// === impl State ===
+
+impl State {
+    fn should_spill(&self) -> bool {
+        let total = self.local + self.remote;
+        self.local == 0
+            || self.local * 100 < self.min_local_percent * total
+            || self.local_ready * 100 < self.min_ready_percent * self.local
+    }
+
+    fn count(&mut self, local: bool, counted: Option<bool>) {
+        match counted {
+            None => {}
+            Some(_) if !local => self.remote += 1,
+            Some(ready) => {
+                self.local += 1;
+                if ready {
+                    self.local_ready += 1;
+                }
+            }
+        }
+    }
+
+    fn uncount(&mut self, local: bool, counted: Option<bool>) {
+        match counted {
+            None => {}
+            Some(_) if !local => self.remote -= 1,
+            Some(ready) => {
+                self.local -= 1;
+                if ready {
+                    self.local_ready -= 1;
+                }
+            }
+        }
+    }
+
+    fn update(&mut self) {
+        let spill = self.should_spill();
+        if spill == self.spill {
+            return;
+        }
+
+        debug!(
+            spill,
+            local = self.local,
+            local.ready = self.local_ready,
+            remote = self.remote,
+            "Zone spillover changed"
+        );
+        self.spill = spill;
+        if spill {
+            for (_, waker) in self.wakers.drain() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+// === impl Endpoint ===
+
+impl Endpoint {
+    fn set_counted(&mut self, counted: Option<bool>) {
+        // Readiness is only tracked for local endpoints.
+        let counted = counted.map(|ready| ready && self.local);
+        if counted == self.counted {
+            return;
+        }
+
+        let mut state = self.zones.0.lock();
+        state.uncount(self.local, self.counted);
+        state.count(self.local, counted);
+        self.counted = counted;
+        if counted.is_none() {
+            state.wakers.remove(&self.id);
+        }
+        state.update();
+    }
+
+    /// Returns true if requests spill over to other zones. Otherwise, the task
+    /// is notified when spillover begins.
+    ///
+    /// Each endpoint holds at most one waker, which is released when it stops
+    /// counting, so that wakers do not accumulate as endpoints come and go.
+    fn poll_spill(&self, cx: &mut Context<'_>) -> bool {
+        let mut state = self.zones.0.lock();
+        if !state.spill {
+            match state.wakers.get_mut(&self.id) {
+                Some(waker) if waker.will_wake(cx.waker()) => {}
+                Some(waker) => *waker = cx.waker().clone(),
+                None => {
+                    state.wakers.insert(self.id, cx.waker().clone());
+                }
+            }
+        }
+        state.spill
+    }
+}
+
+impl Drop for Endpoint {
+    fn drop(&mut self) {
+        self.set_counted(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_stack::ServiceExt;
+    use tower::{balance::p2c, discover::ServiceList, load::Constant};
+    use tower_test::mock::{self, Spawn};
+
+    fn zones(min_local_percent: u32, min_ready_percent: u32) -> Zones {
+        Zones::new(ZoneAffinityConfig {
+            zone: "a".into(),
+            min_local_percent,
+            min_ready_percent,
+        })
+    }
+
+    fn endpoint(
+        zones: &Zones,
+        local: bool,
+    ) -> (
+        ZoneAffinityEndpoint<mock::Mock<(), ()>>,
+        mock::Handle<(), ()>,
+    ) {
+        let (inner, handle) = mock::pair();
+        let svc = ZoneAffinityEndpoint {
+            inner,
+            endpoint: Some(zones.endpoint(local)),
+        };
+        (svc, handle)
+    }
+
+    fn counted(zones: &Zones, local: bool) -> Endpoint {
+        let mut endpoint = zones.endpoint(local);
+        endpoint.set_counted(Some(false));
+        endpoint
+    }
+
+    #[test]
+    fn spills_without_local_capacity() {
+        let zones = zones(25, 0);
+        assert!(zones.0.lock().spill);
+
+        // Endpoints are not counted until they are polled.
+        let _new = zones.endpoint(true);
+        assert!(zones.0.lock().spill);
+
+        let mut endpoints = vec![counted(&zones, true)];
+        assert!(!zones.0.lock().spill);
+
+        // 1 of 5 endpoints is less than 25% of the balancer's endpoints.
+        for _ in 0..4 {
+            endpoints.push(counted(&zones, false));
+        }
+        assert!(zones.0.lock().spill);
+
+        endpoints.pop();
+        assert!(!zones.0.lock().spill);
+    }
+
+    #[test]
+    fn prefers_local_endpoints() {
+        let zones = zones(0, 50);
+        assert!(zones.is_local(Some("a")));
+        assert!(!zones.is_local(Some("b")));
+        assert!(!zones.is_local(None));
+
+        let (local, mut local_handle) = endpoint(&zones, true);
+        let (remote, mut remote_handle) = endpoint(&zones, false);
+        let (mut local, mut remote) = (Spawn::new(local), Spawn::new(remote));
+        local_handle.allow(1);
+        remote_handle.allow(1);
+
+        // Until a local endpoint is ready, requests spill over to the remote
+        // endpoint.
+        assert!(remote.poll_ready().is_ready());
+        assert!(local.poll_ready().is_ready());
+        assert!(remote.poll_ready().is_pending());
+
+        // When the local endpoint fails, it is no longer counted and the
+        // remote endpoint is woken.
+        local_handle.send_error("failed");
+        assert!(matches!(local.poll_ready(), Poll::Ready(Err(_))));
+        assert_eq!(zones.0.lock().local, 0);
+        assert!(remote.is_woken());
+        assert!(remote.poll_ready().is_ready());
+    }
+
+    #[test]
+    fn releases_wakers() {
+        let zones = zones(0, 50);
+        let (local, mut local_handle) = endpoint(&zones, true);
+        let mut local = Spawn::new(local);
+        local_handle.allow(1);
+        assert!(local.poll_ready().is_ready());
+
+        // Each remote endpoint holds a single waker, however often it is
+        // polled, until it is dropped.
+        let remotes = (0..3)
+            .map(|_| Spawn::new(endpoint(&zones, false).0))
+            .collect::<Vec<_>>();
+        for mut remote in remotes {
+            assert!(remote.poll_ready().is_pending());
+            assert!(remote.poll_ready().is_pending());
+            assert_eq!(zones.0.lock().wakers.len(), 1);
+        }
+        assert!(zones.0.lock().wakers.is_empty());
+        assert_eq!(zones.0.lock().remote, 0);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn balances_over_local_endpoints() {
+        let zones = zones(0, 50);
+        let (local, mut local_handle) = endpoint(&zones, true);
+        let (remote, mut remote_handle) = endpoint(&zones, false);
+        let mut balance = p2c::Balance::new(ServiceList::new(vec![
+            Constant::new(local, 0),
+            Constant::new(remote, 0),
+        ]));
+
+        // While the local endpoint is ready, requests are not sent to the
+        // remote endpoint.
+        local_handle.allow(1);
+        remote_handle.allow(1);
+        balance.ready().await.expect("ready");
+        let _rsp = balance.call(());
+        let (_, _tx) = local_handle.next_request().await.expect("request");
+        assert!(remote_handle.poll_request().is_pending());
+
+        // Once the local endpoint is not ready, requests spill over to the
+        // remote endpoint.
+        balance.ready().await.expect("ready");
+        let _rsp = balance.call(());
+        let (_, _tx) = remote_handle.next_request().await.expect("request");
+        assert_eq!(zones.0.lock().local_ready, 0);
+
+        // Endpoints are no longer counted once the balancer drops them.
+        drop(balance);
+        let state = zones.0.lock();
+        assert_eq!((state.local, state.remote), (0, 0));
+        assert!(state.wakers.is_empty());
+    }
+}
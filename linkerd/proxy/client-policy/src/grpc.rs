@@ -1,5 +1,5 @@
 use crate::FailureAccrual;
-use linkerd_http_route::{grpc, http};
+use linkerd_http_route::grpc;
 use std::sync::Arc;
 
 pub use linkerd_http_route::grpc::{filter, find, r#match, RouteMatch};
@@ -20,7 +20,10 @@ pub struct Grpc {
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Filter {
     InjectFailure(filter::InjectFailure),
-    RequestHeaders(http::filter::ModifyHeader),
+    RequestHeaders(filter::ModifyHeader),
+
+    /// Modifies response metadata.
+    ResponseHeaders(filter::ModifyHeader),
     InternalError(&'static str),
 }
 
@@ -37,7 +40,9 @@ pub fn default(distribution: crate::RouteDistribution<Filter>) -> Route {
                 filters: Arc::new([]),
                 distribution,
                 failure_policy: Codes::default(),
-                request_timeout: None,
+                timeouts: Default::default(),
+                retry: None,
+                hedge: None,
             },
         }],
     }
@@ -87,7 +92,7 @@ pub mod proto {
         proto::{
             BackendSet, InvalidBackend, InvalidDistribution, InvalidFailureAccrual, InvalidMeta,
         },
-        Meta, RouteBackend, RouteDistribution,
+        Meta, RouteBackend, RouteDistribution, RouteTimeouts,
     };
     use linkerd2_proxy_api::outbound::{self, grpc_route};
     use linkerd_http_route::{
@@ -229,7 +234,13 @@ pub mod proto {
                 filters,
                 distribution,
                 failure_policy: Codes::default(),
-                request_timeout,
+                timeouts: RouteTimeouts {
+                    response: request_timeout,
+                    ..Default::default()
+                },
+                // The policy API does not yet configure retries.
+                retry: None,
+                hedge: None,
             },
         })
     }
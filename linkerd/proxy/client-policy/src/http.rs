@@ -32,9 +32,30 @@ pub enum Filter {
     Redirect(filter::RedirectRequest),
     RequestHeaders(filter::ModifyHeader),
     ResponseHeaders(filter::ModifyHeader),
+    UrlRewrite(filter::UrlRewrite),
+    RequestMirror(RequestMirror),
     InternalError(&'static str),
 }
 
+/// Sends a copy of a sample of requests to an additional backend. Responses
+/// from the mirror backend are discarded.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RequestMirror {
+    pub backend: crate::Backend,
+
+    /// Determines which requests are mirrored.
+    pub distribution: filter::Distribution,
+
+    /// Requests with bodies larger than this are not mirrored, as their bodies
+    /// cannot be buffered for replay.
+    pub max_request_bytes: usize,
+
+    /// The maximum number of mirrored requests that may be in flight at once.
+    /// Requests are not mirrored while this many mirrored requests are
+    /// pending.
+    pub max_in_flight: usize,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct StatusRanges(pub Arc<[RangeInclusive<u16>]>);
 
@@ -48,7 +69,9 @@ pub fn default(distribution: crate::RouteDistribution<Filter>) -> Route {
                 filters: Arc::new([]),
                 distribution,
                 failure_policy: StatusRanges::default(),
-                request_timeout: None,
+                timeouts: Default::default(),
+                retry: None,
+                hedge: None,
             },
         }],
     }
@@ -99,7 +122,7 @@ pub mod proto {
         proto::{
             BackendSet, InvalidBackend, InvalidDistribution, InvalidFailureAccrual, InvalidMeta,
         },
-        Meta, RouteBackend, RouteDistribution,
+        Meta, RouteBackend, RouteDistribution, RouteTimeouts,
     };
     use linkerd2_proxy_api::outbound::{self, http_route};
     use linkerd_http_route::http::{
@@ -250,7 +273,13 @@ pub mod proto {
                 filters,
                 distribution,
                 failure_policy: StatusRanges::default(),
-                request_timeout,
+                timeouts: RouteTimeouts {
+                    response: request_timeout,
+                    ..Default::default()
+                },
+                // The policy API does not yet configure retries.
+                retry: None,
+                hedge: None,
             },
         })
     }
@@ -7,6 +7,7 @@ use std::{borrow::Cow, fmt, hash::Hash, net::SocketAddr, num::NonZeroU16, sync::
 pub mod grpc;
 pub mod http;
 pub mod opaq;
+pub mod tls;
 
 pub use linkerd_http_route as route;
 pub use linkerd_proxy_api_resolve::Metadata as EndpointMetadata;
@@ -34,8 +35,7 @@ pub enum Protocol {
 
     Opaque(opaq::Opaque),
 
-    // TODO(ver) TLS-aware type
-    Tls(opaq::Opaque),
+    Tls(tls::Tls),
 }
 
 #[derive(Clone, Debug, Eq)]
@@ -58,7 +58,7 @@ pub struct RoutePolicy<T, F> {
     pub meta: Arc<Meta>,
     pub filters: Arc<[T]>,
     pub distribution: RouteDistribution<T>,
-    /// Request timeout applied to HTTP and gRPC routes.
+    /// Request timeouts applied to HTTP and gRPC routes.
     ///
     /// Opaque routes are proxied as opaque TCP, and therefore, we have no
     /// concept of a "request", so this field is ignored by opaque routes.
@@ -69,10 +69,109 @@ pub struct RoutePolicy<T, F> {
     /// design for filters, as filters synchronously modify a request or return
     /// an error --- a filter cannot wrap the response future in order to add a
     /// timeout.
-    pub request_timeout: Option<time::Duration>,
+    pub timeouts: RouteTimeouts,
 
     /// Configures what responses are classified as failures.
     pub failure_policy: F,
+
+    /// Configures how failed requests are retried.
+    ///
+    /// As with `timeouts`, this is only honored by HTTP and gRPC
+    /// routes. The retry conditions are expressed with the same type as the
+    /// route's failure policy.
+    pub retry: Option<RouteRetry<F>>,
+
+    /// Configures how slow requests are hedged.
+    ///
+    /// As with `retry`, this is only honored by HTTP and gRPC routes.
+    pub hedge: Option<RouteHedge>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RouteRetry<F> {
+    /// The maximum number of times a single request may be retried.
+    pub max_retries: usize,
+
+    /// Requests with bodies larger than this are never retried, as their
+    /// bodies cannot be buffered for replay.
+    pub max_request_bytes: usize,
+
+    /// Configures which responses may be retried.
+    pub conditions: F,
+
+    /// An optional timeout applied to each attempt, including the first.
+    pub timeout: Option<time::Duration>,
+
+    /// An optional backoff applied between attempts.
+    pub backoff: Option<linkerd_exp_backoff::ExponentialBackoff>,
+
+    /// Limits the route's retries to a proportion of its requests.
+    pub budget: RetryBudget,
+}
+
+/// Limits a route's retries to a proportion of its requests, so that a
+/// failing backend does not receive several times its usual load.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RetryBudget {
+    /// The maximum number of retries, as a percentage of the route's requests.
+    pub retry_percent: u32,
+
+    /// The number of retries permitted each second regardless of the route's
+    /// request volume.
+    pub min_retries_per_second: u32,
+
+    /// The duration over which requests are counted toward the budget.
+    pub ttl: time::Duration,
+}
+
+/// Sends a second copy of a slow request, using whichever response succeeds
+/// first.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RouteHedge {
+    /// Determines how long a request is outstanding before it is hedged.
+    pub delay: HedgeDelay,
+
+    /// The maximum number of hedged requests, as a percentage of the route's
+    /// requests.
+    pub max_percent: u32,
+
+    /// Requests with bodies larger than this are never hedged, as their
+    /// bodies cannot be buffered for replay.
+    pub max_request_bytes: usize,
+
+    /// Indicates that all of the route's requests are idempotent, so that
+    /// they may be hedged regardless of their methods. Otherwise, only requests
+    /// with idempotent methods are hedged, so gRPC routes, whose requests are
+    /// always `POST`s, are never hedged.
+    pub idempotent: bool,
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum HedgeDelay {
+    /// Requests are hedged after a fixed delay.
+    Fixed(time::Duration),
+
+    /// Requests are hedged after the given percentile (between 1 and 99) of
+    /// the route's recently observed response latencies.
+    Percentile(u8),
+}
+
+/// Timeouts applied to HTTP and gRPC requests on a route or route backend.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct RouteTimeouts {
+    /// Bounds the time until response headers are received.
+    ///
+    /// This is the timeout configured by the policy API's `request_timeout`.
+    pub response: Option<time::Duration>,
+
+    /// Bounds the total duration of a request, including the time spent
+    /// streaming the response body.
+    pub request: Option<time::Duration>,
+
+    /// Bounds the time that may elapse between response body frames, so that
+    /// long-lived streams may run indefinitely as long as data continues to
+    /// flow.
+    pub idle: Option<time::Duration>,
 }
 
 // TODO(ver) Weighted random WITHOUT availability awareness, as required by
@@ -90,7 +189,7 @@ pub enum RouteDistribution<T> {
 pub struct RouteBackend<T> {
     pub filters: Arc<[T]>,
     pub backend: Backend,
-    pub request_timeout: Option<time::Duration>,
+    pub timeouts: RouteTimeouts,
 }
 
 // TODO(ver) how does configuration like failure accrual fit in here? What about
@@ -120,9 +219,23 @@ pub enum EndpointDiscovery {
 }
 
 /// Configures the load balancing strategy for a backend.
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+///
+/// The policy API only configures `PeakEwma` balancers. The outbound proxy's
+/// configuration may override this with another strategy for all backends.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Load {
+    /// Uses P2C to pick the endpoint with the lowest peak EWMA latency.
     PeakEwma(PeakEwma),
+
+    /// Uses P2C to pick the endpoint with the fewest outstanding requests.
+    LeastRequest,
+
+    /// Distributes requests over endpoints in proportion to the weights set by
+    /// endpoint discovery.
+    WeightedRoundRobin,
+
+    /// Pins requests to endpoints by hashing a property of each request.
+    ConsistentHash(ConsistentHash),
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -131,6 +244,27 @@ pub struct PeakEwma {
     pub default_rtt: time::Duration,
 }
 
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ConsistentHash {
+    pub key: HashKey,
+}
+
+/// The request property used to select an endpoint for consistent hashing.
+///
+/// Requests that do not have the configured property are dispatched to an
+/// arbitrary endpoint.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum HashKey {
+    Header(::http::header::HeaderName),
+    Cookie(String),
+    /// Hashes the address of the client that sent the request to the proxy.
+    ///
+    /// Outbound proxies usually serve a single local client, so this is only
+    /// useful where the proxy serves remote clients, as in ingress mode or on a
+    /// gateway.
+    ClientIp,
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum FailureAccrual {
     /// Endpoints do not become unavailable due to observed failures.
@@ -144,8 +278,33 @@ pub enum FailureAccrual {
         /// Backoff for probing the endpoint when it is in a failed state.
         backoff: linkerd_exp_backoff::ExponentialBackoff,
     },
+    /// Endpoints are marked as unavailable when their success rate is a
+    /// statistical outlier among all of the backend's endpoints.
+    SuccessRate {
+        /// The interval over which success rates are computed.
+        interval: time::Duration,
+        /// The minimum number of requests an endpoint must serve in an
+        /// interval for its success rate to be considered.
+        min_requests: usize,
+        /// The minimum number of endpoints that must serve `min_requests` in
+        /// an interval for outliers to be detected.
+        min_endpoints: usize,
+        /// Endpoints with a success rate more than `stdev_factor` standard
+        /// deviations below the mean are considered outliers.
+        stdev_factor: StdevFactor,
+        /// The maximum percentage of the backend's endpoints that may be
+        /// unavailable at once.
+        max_ejection_percent: u32,
+        /// Backoff for the duration that an outlier is unavailable. The backoff
+        /// grows when an endpoint is repeatedly found to be an outlier.
+        backoff: linkerd_exp_backoff::ExponentialBackoff,
+    },
 }
 
+/// A non-negative, finite number of standard deviations.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StdevFactor(f64);
+
 // === impl ClientPolicy ===
 
 impl ClientPolicy {
@@ -168,7 +327,9 @@ impl ClientPolicy {
                         .collect(),
                         distribution: RouteDistribution::Empty,
                         failure_policy: http::StatusRanges::default(),
-                        request_timeout: None,
+                        timeouts: Default::default(),
+                        retry: None,
+                        hedge: None,
                     },
                 }],
             }])
@@ -190,7 +351,8 @@ impl ClientPolicy {
                 opaque: opaq::Opaque {
                     // TODO(eliza): eventually, can we configure the opaque
                     // policy to fail conns?
-                    policy: None,
+                    routes: Arc::new([]),
+                    failure_accrual: Default::default(),
                 },
             },
             backends: BACKENDS.clone(),
@@ -221,7 +383,8 @@ impl ClientPolicy {
                 opaque: opaq::Opaque {
                     // TODO(eliza): eventually, can we configure the opaque
                     // policy to fail conns?
-                    policy: None,
+                    routes: Arc::new([]),
+                    failure_accrual: Default::default(),
                 },
             },
             backends: NO_BACKENDS.clone(),
@@ -324,6 +487,33 @@ impl Default for FailureAccrual {
     }
 }
 
+// === impl StdevFactor ===
+
+impl StdevFactor {
+    /// Returns a factor if `factor` is finite and not negative.
+    pub fn new(factor: f64) -> Option<Self> {
+        if factor.is_finite() && factor >= 0.0 {
+            Some(Self(factor))
+        } else {
+            None
+        }
+    }
+
+    pub fn as_f64(self) -> f64 {
+        self.0
+    }
+}
+
+// It's okay for `StdevFactor` to be `Eq` because its constructor ensures that
+// the factor is finite.
+impl Eq for StdevFactor {}
+
+impl Hash for StdevFactor {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
 #[cfg(feature = "proto")]
 pub mod proto {
     use super::*;
@@ -508,7 +698,10 @@ pub mod proto {
                 | Protocol::Http2(http::Http2 { ref routes, .. }) => {
                     http::proto::fill_route_backends(routes, &mut backends);
                 }
-                Protocol::Opaque(ref p) | Protocol::Tls(ref p) => {
+                Protocol::Opaque(ref p) => {
+                    p.fill_backends(&mut backends);
+                }
+                Protocol::Tls(ref p) => {
                     p.fill_backends(&mut backends);
                 }
                 Protocol::Grpc(ref p) => {
@@ -622,7 +815,10 @@ pub mod proto {
             Ok(RouteBackend {
                 filters,
                 backend,
-                request_timeout,
+                timeouts: RouteTimeouts {
+                    response: request_timeout,
+                    ..Default::default()
+                },
             })
         }
     }
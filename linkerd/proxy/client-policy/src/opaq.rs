@@ -1,8 +1,48 @@
-use crate::RoutePolicy;
+use crate::{FailureAccrual, RoutePolicy};
+use ipnet::IpNet;
+use linkerd_http_route::http::filter::inject_failure;
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
 
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct Opaque {
-    pub policy: Option<Policy>,
+    pub routes: Arc<[Route]>,
+    /// Configures how connection failures affect the availability of the
+    /// backends' endpoints.
+    pub failure_accrual: FailureAccrual,
+}
+
+/// A TCPRoute-style route that applies to connections matching any of its
+/// matches.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Route {
+    /// A list of conditions that determine whether this route applies to a
+    /// connection.
+    ///
+    /// If at least one match is specified, any match may apply to the
+    /// connection. When no matches are present, all connections match.
+    pub matches: Vec<MatchConnection>,
+
+    pub policy: Policy,
+}
+
+/// Matches connections by their destination port and client address.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MatchConnection {
+    /// An inclusive range of destination ports. All ports match when unset.
+    pub ports: Option<PortRange>,
+
+    /// Client networks. All clients match when empty.
+    pub client_networks: Vec<IpNet>,
+}
+
+/// An inclusive range of ports.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PortRange {
+    pub min: u16,
+    pub max: u16,
 }
 
 pub type Policy = RoutePolicy<Filter, NonIoErrors>;
@@ -10,8 +50,66 @@ pub type Policy = RoutePolicy<Filter, NonIoErrors>;
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 pub struct NonIoErrors;
 
+/// Filters applied to opaque connections before they are dispatched to a
+/// backend.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub enum Filter {}
+pub enum Filter {
+    /// Refuses all connections.
+    Forbidden,
+
+    /// Fails connections at a configured rate. The error message is reported
+    /// when a connection is failed.
+    InjectFailure(inject_failure::InjectFailure<Arc<str>>),
+}
+
+/// Finds the route that applies to a connection.
+///
+/// Routes with matches are preferred over routes without matches. When
+/// multiple routes apply equally, the first one wins.
+pub fn find(routes: &[Route], dst: SocketAddr, client: IpAddr) -> Option<&Route> {
+    let mut fallback = None;
+    for rt in routes.iter() {
+        if rt.matches.is_empty() {
+            fallback = fallback.or(Some(rt));
+        } else if rt.matches.iter().any(|m| m.matches(dst, client)) {
+            return Some(rt);
+        }
+    }
+    fallback
+}
+
+// === impl MatchConnection ===
+
+impl MatchConnection {
+    pub fn matches(&self, dst: SocketAddr, client: IpAddr) -> bool {
+        if let Some(ports) = self.ports {
+            if !ports.contains(dst.port()) {
+                return false;
+            }
+        }
+
+        self.client_networks.is_empty() || self.client_networks.iter().any(|n| n.contains(&client))
+    }
+}
+
+// === impl PortRange ===
+
+impl PortRange {
+    pub fn contains(&self, port: u16) -> bool {
+        self.min <= port && port <= self.max
+    }
+}
+
+impl From<u16> for PortRange {
+    fn from(port: u16) -> Self {
+        Self {
+            min: port,
+            max: port,
+        }
+    }
+}
+
+// === impl NonIoErrors ===
 
 impl NonIoErrors {
     pub fn contains(&self, e: &(dyn std::error::Error + 'static)) -> bool {
@@ -30,7 +128,6 @@ pub(crate) mod proto {
     use linkerd2_proxy_api::outbound::{self, opaque_route};
 
     use once_cell::sync::Lazy;
-    use std::sync::Arc;
 
     pub(crate) static NO_FILTERS: Lazy<Arc<[Filter]>> = Lazy::new(|| Arc::new([]));
 
@@ -66,50 +163,54 @@ pub(crate) mod proto {
                 return Err(InvalidOpaqueRoute::OnlyOneRoute(proto.routes.len()));
             }
 
-            proto
+            let route = proto
                 .routes
                 .into_iter()
                 .next()
-                .ok_or(InvalidOpaqueRoute::OnlyOneRoute(0))?
-                .try_into()
+                .ok_or(InvalidOpaqueRoute::OnlyOneRoute(0))?;
+
+            Ok(Self {
+                routes: Arc::new([try_route(route)?]),
+                // The policy API does not yet configure failure accrual for
+                // opaque routes.
+                failure_accrual: Default::default(),
+            })
         }
     }
 
-    impl TryFrom<outbound::OpaqueRoute> for Opaque {
-        type Error = InvalidOpaqueRoute;
+    /// Converts an `OpaqueRoute` into a `Route` that matches all connections.
+    fn try_route(
+        outbound::OpaqueRoute { metadata, rules }: outbound::OpaqueRoute,
+    ) -> Result<Route, InvalidOpaqueRoute> {
+        let meta = Arc::new(
+            metadata
+                .ok_or(InvalidMeta("missing metadata"))?
+                .try_into()?,
+        );
 
-        fn try_from(
-            outbound::OpaqueRoute { metadata, rules }: outbound::OpaqueRoute,
-        ) -> Result<Self, Self::Error> {
-            let meta = Arc::new(
-                metadata
-                    .ok_or(InvalidMeta("missing metadata"))?
-                    .try_into()?,
-            );
-
-            // Currently, opaque rules have no match expressions, so if there's
-            // more than one rule, we have no way of determining which one to
-            // use. Therefore, require that there's exactly one rule.
-            if rules.len() != 1 {
-                return Err(InvalidOpaqueRoute::OnlyOneRule(rules.len()));
-            }
+        // Currently, opaque rules have no match expressions, so if there's
+        // more than one rule, we have no way of determining which one to
+        // use. Therefore, require that there's exactly one rule.
+        if rules.len() != 1 {
+            return Err(InvalidOpaqueRoute::OnlyOneRule(rules.len()));
+        }
 
-            let policy = rules
-                .into_iter()
-                .map(|rule| try_rule(&meta, rule))
-                .next()
-                .ok_or(InvalidOpaqueRoute::OnlyOneRule(0))??;
+        let policy = rules
+            .into_iter()
+            .map(|rule| try_rule(&meta, rule))
+            .next()
+            .ok_or(InvalidOpaqueRoute::OnlyOneRule(0))??;
 
-            Ok(Self {
-                policy: Some(policy),
-            })
-        }
+        Ok(Route {
+            matches: vec![],
+            policy,
+        })
     }
 
     impl Opaque {
         pub(crate) fn fill_backends(&self, set: &mut BackendSet) {
-            for p in &self.policy {
-                p.distribution.fill_backends(set);
+            for Route { ref policy, .. } in &*self.routes {
+                policy.distribution.fill_backends(set);
             }
         }
     }
@@ -127,8 +228,10 @@ pub(crate) mod proto {
             filters: NO_FILTERS.clone(),
             failure_policy: NonIoErrors::default(),
             distribution,
-            // Request timeouts are ignored on opaque routes.
-            request_timeout: None,
+            // Request timeouts and retries are ignored on opaque routes.
+            timeouts: Default::default(),
+            retry: None,
+            hedge: None,
         })
     }
 
@@ -185,11 +288,11 @@ pub(crate) mod proto {
     }
 
     // Necessary to satisfy `RouteBackend::try_from_proto` type constraints.
-    // TODO(eliza): if filters are added to opaque routes, change this to a
-    // proper `TryFrom` impl...
+    // TODO(eliza): if the policy API adds filters to opaque routes, change
+    // this to a proper `TryFrom` impl...
     impl From<()> for Filter {
         fn from(_: ()) -> Self {
-            unreachable!("no filters can be configured on opaque routes yet")
+            unreachable!("the policy API does not configure filters on opaque routes")
         }
     }
 }
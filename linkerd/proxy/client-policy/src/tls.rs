@@ -0,0 +1,74 @@
+use crate::{opaq, FailureAccrual};
+use linkerd_http_route::{HostMatch, MatchHost};
+use std::sync::Arc;
+
+pub type Policy = opaq::Policy;
+
+/// Configures routing for TLS connections that are proxied without being
+/// terminated.
+///
+/// Connections are routed by the server name indicated in the client's TLS
+/// ClientHello.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Tls {
+    pub routes: Arc<[Route]>,
+
+    /// Configures how connection failures affect the availability of the
+    /// backends' endpoints.
+    pub failure_accrual: FailureAccrual,
+}
+
+/// A TLSRoute-style route that applies to connections with a matching SNI.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Route {
+    /// A list of server names that this route applies to.
+    ///
+    /// If at least one match is specified, any match may apply to the
+    /// connection. When no matches are present, all connections match,
+    /// including those that do not indicate a server name.
+    pub snis: Vec<MatchHost>,
+
+    pub policy: Policy,
+}
+
+/// Finds the route that best matches a connection's server name.
+///
+/// Exact matches are preferred over suffix matches, and longer matches are
+/// preferred over shorter ones. Routes without SNI matches apply only when no
+/// other route matches. When multiple routes match equally, the first one
+/// wins.
+pub fn find<'r>(routes: &'r [Route], sni: Option<&str>) -> Option<&'r Route> {
+    let mut best: Option<(Option<HostMatch>, &'r Route)> = None;
+    for rt in routes.iter() {
+        let hm = if rt.snis.is_empty() {
+            None
+        } else {
+            let sni = match sni {
+                Some(sni) => sni,
+                None => continue,
+            };
+            match rt.snis.iter().filter_map(|m| m.summarize_host(sni)).max() {
+                Some(hm) => Some(hm),
+                None => continue,
+            }
+        };
+        if best.as_ref().map_or(true, |(m, _)| hm > *m) {
+            best = Some((hm, rt));
+        }
+    }
+    best.map(|(_, rt)| rt)
+}
+
+#[cfg(feature = "proto")]
+pub(crate) mod proto {
+    use super::*;
+    use crate::proto::BackendSet;
+
+    impl Tls {
+        pub(crate) fn fill_backends(&self, set: &mut BackendSet) {
+            for Route { ref policy, .. } in &*self.routes {
+                policy.distribution.fill_backends(set);
+            }
+        }
+    }
+}
@@ -1,7 +1,132 @@
+use crate::ClientHandle;
 pub use hyper_balance::{PendingUntilFirstData, PendingUntilFirstDataBody};
 pub use linkerd_proxy_balance::*;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
 
 pub type Body<B> = PendingUntilFirstDataBody<Handle, B>;
 
 pub type NewBalancePeakEwma<B, R, N> =
     linkerd_proxy_balance::NewBalancePeakEwma<PendingUntilFirstData, http::Request<B>, R, N>;
+
+pub type NewBalanceLeastRequest<B, R, N> =
+    linkerd_proxy_balance::NewBalanceLeastRequest<PendingUntilFirstData, http::Request<B>, R, N>;
+
+pub type NewBalanceWeightedRoundRobin<X, B, R, N> =
+    linkerd_proxy_balance::NewBalanceWeightedRoundRobin<X, http::Request<B>, R, N>;
+
+pub type NewBalanceConsistentHash<B, R, N> =
+    linkerd_proxy_balance::NewBalanceConsistentHash<HashKey, http::Request<B>, R, N>;
+
+/// Configures which part of a request is used to pin it to an endpoint.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    /// Hashes the value of the named request header.
+    Header(http::header::HeaderName),
+    /// Hashes the value of the named cookie.
+    Cookie(String),
+    /// Hashes the IP address of the client, as set by the server's
+    /// [`ClientHandle`].
+    ClientIp,
+}
+
+// === impl HashKey ===
+
+impl<B> HashRequest<http::Request<B>> for HashKey {
+    fn hash_request(&self, req: &http::Request<B>) -> Option<u64> {
+        let mut hasher = DefaultHasher::new();
+        match self {
+            Self::Header(name) => {
+                req.headers().get(name)?.as_bytes().hash(&mut hasher);
+            }
+            Self::Cookie(name) => {
+                let value = req
+                    .headers()
+                    .get_all(http::header::COOKIE)
+                    .iter()
+                    .filter_map(|v| v.to_str().ok())
+                    .flat_map(|v| v.split(';'))
+                    .filter_map(|c| c.trim().split_once('='))
+                    .find_map(|(n, v)| if n == name { Some(v) } else { None })?;
+                value.hash(&mut hasher);
+            }
+            Self::ClientIp => {
+                req.extensions()
+                    .get::<ClientHandle>()?
+                    .addr
+                    .ip()
+                    .hash(&mut hasher);
+            }
+        }
+        Some(hasher.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(key: &HashKey, req: &http::Request<()>) -> Option<u64> {
+        key.hash_request(req)
+    }
+
+    #[test]
+    fn header() {
+        let key = HashKey::Header(http::header::HeaderName::from_static("x-user"));
+        let alice = http::Request::builder()
+            .header("x-user", "alice")
+            .body(())
+            .unwrap();
+        let bob = http::Request::builder()
+            .header("x-user", "bob")
+            .body(())
+            .unwrap();
+        assert!(hash(&key, &alice).is_some());
+        assert_eq!(hash(&key, &alice), hash(&key, &alice));
+        assert_ne!(hash(&key, &alice), hash(&key, &bob));
+        assert_eq!(hash(&key, &http::Request::new(())), None);
+    }
+
+    #[test]
+    fn cookie() {
+        let key = HashKey::Cookie("session".to_string());
+        let req = |cookie: &str| {
+            http::Request::builder()
+                .header(http::header::COOKIE, cookie)
+                .body(())
+                .unwrap()
+        };
+        assert_eq!(
+            hash(&key, &req("a=b; session=abc")),
+            hash(&key, &req("session=abc")),
+        );
+        assert_ne!(
+            hash(&key, &req("session=abc")),
+            hash(&key, &req("session=def")),
+        );
+        assert_eq!(hash(&key, &req("a=b; sessions=abc")), None);
+    }
+
+    #[test]
+    fn client_ip() {
+        let key = HashKey::ClientIp;
+        let req = |addr: &str| {
+            let (handle, _closed) = ClientHandle::new(addr.parse().unwrap());
+            let mut req = http::Request::new(());
+            req.extensions_mut().insert(handle);
+            req
+        };
+        assert!(hash(&key, &req("10.0.0.1:1234")).is_some());
+        assert_eq!(
+            hash(&key, &req("10.0.0.1:1234")),
+            hash(&key, &req("10.0.0.1:5678")),
+        );
+        assert_ne!(
+            hash(&key, &req("10.0.0.1:1234")),
+            hash(&key, &req("10.0.0.2:1234")),
+        );
+        assert_eq!(hash(&key, &http::Request::new(())), None);
+    }
+}
@@ -0,0 +1,421 @@
+//! Propagates the deadlines of requests whose callers specify a timeout.
+//!
+//! A caller may bound a request with a `grpc-timeout` header or with an
+//! additional, configurable header. [`NewEnforceDeadline`] determines the
+//! request's deadline from the lesser of these timeouts and the target's
+//! configured timeout, fails the request once this deadline elapses, and
+//! records it on the request as a [`RequestDeadline`] extension.
+//!
+//! [`PropagateDeadline`] is intended to be used immediately before a request is
+//! dispatched to an endpoint: it informs gRPC servers of the time remaining
+//! until the deadline with a `grpc-timeout` header, and fails the request
+//! rather than forwarding it if its deadline has already elapsed. Other
+//! servers have no standard means to learn of the deadline, so the remaining
+//! time is not forwarded to them.
+
+use crate::timeout::{Deadline, StreamTimeoutsBody, StreamTimeoutsFuture};
+use futures::{future, TryFutureExt};
+use http::header::{HeaderName, HeaderValue};
+use linkerd_error::Error;
+use linkerd_stack::{layer, ExtractParam, NewService, Service};
+use std::{
+    task::{Context, Poll},
+    time::Duration,
+};
+use thiserror::Error;
+use tokio::time;
+use tracing::debug;
+
+const GRPC_TIMEOUT: &str = "grpc-timeout";
+
+/// Param type configuring how request deadlines are determined.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DeadlineParams {
+    /// Bounds the timeout read from a request's headers.
+    pub timeout: Option<Duration>,
+
+    /// A header, in addition to `grpc-timeout`, from which a request's timeout
+    /// may be read (e.g. `500ms`, `10s`). This header is not forwarded.
+    pub header: Option<HeaderName>,
+}
+
+/// A request extension recording the time after which the request's caller
+/// no longer expects a response.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RequestDeadline {
+    deadline: time::Instant,
+    timeout: Duration,
+}
+
+#[derive(Clone, Debug, Error)]
+#[error("request deadline of {0:?} exceeded")]
+pub struct DeadlineExceededError(Duration);
+
+#[derive(Clone, Debug)]
+pub struct NewEnforceDeadline<X, N> {
+    inner: N,
+    extract: X,
+}
+
+#[derive(Clone, Debug)]
+pub struct EnforceDeadline<S> {
+    inner: S,
+    params: DeadlineParams,
+}
+
+#[derive(Clone, Debug)]
+pub struct PropagateDeadline<S> {
+    inner: S,
+}
+
+// === impl RequestDeadline ===
+
+impl RequestDeadline {
+    /// Returns the time remaining until the deadline elapses.
+    pub fn remaining(&self) -> Duration {
+        self.deadline
+            .saturating_duration_since(time::Instant::now())
+    }
+}
+
+// === impl NewEnforceDeadline ===
+
+impl<X: Clone, N> NewEnforceDeadline<X, N> {
+    pub fn layer_via(extract: X) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            extract: extract.clone(),
+        })
+    }
+}
+
+impl<N> NewEnforceDeadline<(), N> {
+    pub fn layer() -> impl layer::Layer<N, Service = Self> + Clone {
+        Self::layer_via(())
+    }
+}
+
+impl<T, X, N> NewService<T> for NewEnforceDeadline<X, N>
+where
+    X: ExtractParam<DeadlineParams, T>,
+    N: NewService<T>,
+{
+    type Service = EnforceDeadline<N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let params = self.extract.extract_param(&target);
+        EnforceDeadline {
+            inner: self.inner.new_service(target),
+            params,
+        }
+    }
+}
+
+// === impl EnforceDeadline ===
+
+impl<B, RspB, S> Service<http::Request<B>> for EnforceDeadline<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<RspB>>,
+    S::Error: Into<Error>,
+{
+    type Response = http::Response<StreamTimeoutsBody<RspB>>;
+    type Error = Error;
+    type Future = future::Either<
+        StreamTimeoutsFuture<S::Future>,
+        future::Ready<Result<Self::Response, Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        let header = self
+            .params
+            .header
+            .as_ref()
+            .and_then(|h| req.headers_mut().remove(h))
+            .and_then(|v| parse_timeout(&v));
+        let grpc = req.headers().get(GRPC_TIMEOUT).and_then(parse_grpc_timeout);
+
+        // Requests that don't specify a timeout are only bounded by the
+        // target's other timeouts.
+        let timeout = match header.into_iter().chain(grpc).min() {
+            Some(timeout) => self.params.timeout.map_or(timeout, |t| t.min(timeout)),
+            None => {
+                return future::Either::Left(StreamTimeoutsFuture::new(self.inner.call(req), None))
+            }
+        };
+        if timeout == Duration::ZERO {
+            debug!("Request deadline already exceeded");
+            return future::Either::Right(future::err(DeadlineExceededError(timeout).into()));
+        }
+
+        let deadline = time::Instant::now() + timeout;
+        req.extensions_mut()
+            .insert(RequestDeadline { deadline, timeout });
+        let deadline = Deadline::until(deadline, timeout, |t| DeadlineExceededError(t).into());
+        future::Either::Left(StreamTimeoutsFuture::new(
+            self.inner.call(req),
+            Some(deadline),
+        ))
+    }
+}
+
+// === impl PropagateDeadline ===
+
+impl<S> PropagateDeadline<S> {
+    pub fn layer() -> impl layer::Layer<S, Service = Self> + Clone {
+        layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<B, S> Service<http::Request<B>> for PropagateDeadline<S>
+where
+    S: Service<http::Request<B>>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::ErrInto<S::Future, Error>,
+        future::Ready<Result<S::Response, Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        if let Some(deadline) = req.extensions().get::<RequestDeadline>().copied() {
+            let remaining = deadline.remaining();
+            if remaining == Duration::ZERO {
+                debug!("Request deadline exceeded before dispatch");
+                let error = DeadlineExceededError(deadline.timeout);
+                return future::Either::Right(future::err(error.into()));
+            }
+
+            // Inform gRPC servers of the time remaining for their response,
+            // excluding the time already spent in the proxy. The deadline may
+            // have been set by another header, so the `grpc-timeout` header
+            // is added if the request does not already have one.
+            if req.headers().contains_key(GRPC_TIMEOUT) || is_grpc(&req) {
+                req.headers_mut()
+                    .insert(GRPC_TIMEOUT, encode_grpc_timeout(remaining));
+            }
+        }
+
+        future::Either::Left(self.inner.call(req).err_into())
+    }
+}
+
+fn is_grpc<B>(req: &http::Request<B>) -> bool {
+    req.headers()
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|ct| ct.to_str().ok())
+        .map_or(false, |ct| ct.starts_with("application/grpc"))
+}
+
+/// Parses a `grpc-timeout` header value, as described in the [gRPC over HTTP/2
+/// specification][spec].
+///
+/// [spec]: https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-HTTP2.md
+fn parse_grpc_timeout(value: &HeaderValue) -> Option<Duration> {
+    let value = value.to_str().ok()?;
+    if value.len() < 2 {
+        return None;
+    }
+    let (digits, unit) = value.split_at(value.len() - 1);
+    if digits.len() > 8 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n = digits.parse::<u64>().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(n * 60 * 60)),
+        "M" => Some(Duration::from_secs(n * 60)),
+        "S" => Some(Duration::from_secs(n)),
+        "m" => Some(Duration::from_millis(n)),
+        "u" => Some(Duration::from_micros(n)),
+        "n" => Some(Duration::from_nanos(n)),
+        _ => None,
+    }
+}
+
+/// Encodes a `grpc-timeout` header value, using the most precise unit that can
+/// represent the timeout in at most 8 digits. The timeout is rounded down.
+fn encode_grpc_timeout(timeout: Duration) -> HeaderValue {
+    const MAX: u128 = 99_999_999;
+    const UNITS: [(u128, char); 6] = [
+        (1, 'n'),
+        (1_000, 'u'),
+        (1_000_000, 'm'),
+        (1_000_000_000, 'S'),
+        (60 * 1_000_000_000, 'M'),
+        (60 * 60 * 1_000_000_000, 'H'),
+    ];
+
+    let nanos = timeout.as_nanos();
+    let (value, unit) = UNITS
+        .iter()
+        .map(|&(per, unit)| (nanos / per, unit))
+        .find(|&(value, _)| value <= MAX)
+        .unwrap_or((MAX, 'H'));
+    HeaderValue::try_from(format!("{value}{unit}")).expect("timeout must be a valid header value")
+}
+
+/// Parses a timeout like `500ms`, `10s`, `1m`, or `1h`.
+fn parse_timeout(value: &HeaderValue) -> Option<Duration> {
+    let value = value.to_str().ok()?.trim();
+    let (digits, unit) = value.split_at(value.find(|c: char| !c.is_ascii_digit())?);
+    let n = digits.parse::<u64>().ok()?;
+    match unit {
+        "ms" => Some(Duration::from_millis(n)),
+        "s" => Some(Duration::from_secs(n)),
+        "m" => Some(Duration::from_secs(n.checked_mul(60)?)),
+        "h" => Some(Duration::from_secs(n.checked_mul(60 * 60)?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoxBody;
+    use linkerd_stack::ServiceExt;
+
+    #[test]
+    fn grpc_timeout_roundtrip() {
+        for (value, timeout) in [
+            ("10n", Duration::from_nanos(10)),
+            ("250m", Duration::from_millis(250)),
+            ("99999999u", Duration::from_micros(99_999_999)),
+            ("100000m", Duration::from_secs(100)),
+            ("2H", Duration::from_secs(2 * 60 * 60)),
+        ] {
+            let value = HeaderValue::from_static(value);
+            assert_eq!(parse_grpc_timeout(&value), Some(timeout), "{value:?}");
+        }
+
+        assert_eq!(
+            encode_grpc_timeout(Duration::from_millis(250)),
+            HeaderValue::from_static("250000u")
+        );
+        assert_eq!(
+            encode_grpc_timeout(Duration::from_secs(100)),
+            HeaderValue::from_static("100000m")
+        );
+        assert_eq!(
+            encode_grpc_timeout(Duration::from_nanos(1)),
+            HeaderValue::from_static("1n")
+        );
+
+        for value in ["", "1", "10x", "123456789S", "-1S"] {
+            let value = HeaderValue::from_static(value);
+            assert_eq!(parse_grpc_timeout(&value), None, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn custom_timeout() {
+        for (value, timeout) in [
+            ("500ms", Some(Duration::from_millis(500))),
+            ("10s", Some(Duration::from_secs(10))),
+            ("2m", Some(Duration::from_secs(120))),
+            ("1h", Some(Duration::from_secs(3600))),
+            ("10", None),
+            ("s", None),
+            ("1.5s", None),
+        ] {
+            let value = HeaderValue::from_static(value);
+            assert_eq!(parse_timeout(&value), timeout, "{value:?}");
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn propagates_deadline() {
+        let (inner, mut handle) =
+            tower_test::mock::pair::<http::Request<BoxBody>, http::Response<BoxBody>>();
+        let svc = EnforceDeadline {
+            inner: PropagateDeadline { inner },
+            params: DeadlineParams {
+                timeout: Some(Duration::from_secs(10)),
+                header: Some(HeaderName::from_static("l5d-timeout")),
+            },
+        };
+        handle.allow(1);
+
+        let req = http::Request::builder()
+            .header(GRPC_TIMEOUT, "20S")
+            .header("l5d-timeout", "5s")
+            .body(BoxBody::default())
+            .unwrap();
+        let rsp = tokio::spawn(svc.oneshot(req));
+        let (req, _tx) = handle.next_request().await.expect("request");
+
+        // The lesser timeout is forwarded and the custom header is stripped.
+        assert_eq!(req.headers()[GRPC_TIMEOUT], "5000000u");
+        assert!(!req.headers().contains_key("l5d-timeout"));
+
+        let error = rsp.await.unwrap().expect_err("response must time out");
+        assert!(error.is::<DeadlineExceededError>(), "{error}");
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn propagates_deadline_to_grpc() {
+        let (inner, mut handle) =
+            tower_test::mock::pair::<http::Request<BoxBody>, http::Response<BoxBody>>();
+        let svc = EnforceDeadline {
+            inner: PropagateDeadline { inner },
+            params: DeadlineParams {
+                timeout: None,
+                header: Some(HeaderName::from_static("l5d-timeout")),
+            },
+        };
+        handle.allow(2);
+
+        // gRPC servers are informed of a deadline set by another header.
+        let req = http::Request::builder()
+            .header(http::header::CONTENT_TYPE, "application/grpc+proto")
+            .header("l5d-timeout", "5s")
+            .body(BoxBody::default())
+            .unwrap();
+        let _rsp = tokio::spawn(svc.clone().oneshot(req));
+        let (req, _tx) = handle.next_request().await.expect("request");
+        assert_eq!(req.headers()[GRPC_TIMEOUT], "5000000u");
+
+        // Other servers are not.
+        let req = http::Request::builder()
+            .header("l5d-timeout", "5s")
+            .body(BoxBody::default())
+            .unwrap();
+        let _rsp = tokio::spawn(svc.oneshot(req));
+        let (req, _tx) = handle.next_request().await.expect("request");
+        assert!(!req.headers().contains_key(GRPC_TIMEOUT));
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn fails_expired_deadline() {
+        let (inner, mut handle) =
+            tower_test::mock::pair::<http::Request<BoxBody>, http::Response<BoxBody>>();
+        let mut svc = PropagateDeadline { inner };
+        handle.allow(1);
+
+        let mut req = http::Request::new(BoxBody::default());
+        req.extensions_mut().insert(RequestDeadline {
+            deadline: time::Instant::now() + Duration::from_secs(1),
+            timeout: Duration::from_secs(1),
+        });
+        time::sleep(Duration::from_secs(2)).await;
+
+        let error = svc
+            .ready()
+            .await
+            .unwrap()
+            .call(req)
+            .await
+            .expect_err("request must fail");
+        assert!(error.is::<DeadlineExceededError>(), "{error}");
+    }
+}
@@ -1,4 +1,4 @@
-use crate::trace;
+use crate::{trace, upgrade::Http11Upgrade};
 use futures::prelude::*;
 pub use h2::{Error as H2Error, Reason};
 use hyper::{
@@ -11,6 +11,10 @@ use std::{
     future::Future,
     marker::PhantomData,
     pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     task::{Context, Poll},
     time::Duration,
 };
@@ -22,6 +26,15 @@ pub struct Settings {
     pub initial_stream_window_size: Option<u32>,
     pub initial_connection_window_size: Option<u32>,
     pub keepalive_timeout: Option<Duration>,
+
+    /// Enables extended CONNECT (RFC 8441) streams.
+    ///
+    /// Servers advertise support for extended CONNECT so that clients may
+    /// tunnel HTTP/1.1 upgrades (i.e. WebSockets) over a multiplexed
+    /// connection. Clients use extended CONNECT to carry HTTP/1.1 upgrades
+    /// when upgrading requests to HTTP/2, but only on connections whose peer
+    /// has advertised support for it.
+    pub extended_connect: bool,
 }
 
 #[derive(Debug)]
@@ -34,6 +47,8 @@ pub struct Connect<C, B> {
 #[derive(Debug)]
 pub struct Connection<B> {
     tx: SendRequest<B>,
+    extended_connect: bool,
+    peer_extended_connect: Arc<AtomicBool>,
 }
 
 // === impl Connect ===
@@ -84,6 +99,7 @@ where
             initial_connection_window_size,
             initial_stream_window_size,
             keepalive_timeout,
+            extended_connect,
         } = self.h2_settings;
 
         let connect = self
@@ -117,12 +133,34 @@ where
                     .instrument(trace_span!("handshake"))
                     .await?;
 
+                // The peer's SETTINGS are processed as the connection is
+                // driven, so whether it accepts extended CONNECT streams is
+                // recorded each time the connection is polled.
+                let peer_extended_connect = Arc::new(AtomicBool::new(false));
+                let conn = {
+                    let peer_extended_connect = peer_extended_connect.clone();
+                    let mut conn = Box::pin(conn);
+                    future::poll_fn(move |cx| {
+                        let poll = conn.as_mut().poll(cx);
+                        if poll.is_pending() {
+                            peer_extended_connect.store(
+                                conn.http2_is_extended_connect_protocol_enabled(),
+                                Ordering::Release,
+                            );
+                        }
+                        poll
+                    })
+                };
                 tokio::spawn(
                     conn.map_err(|error| debug!(%error, "failed"))
                         .instrument(trace_span!("conn").or_current()),
                 );
 
-                Ok(Connection { tx })
+                Ok(Connection {
+                    tx,
+                    extended_connect,
+                    peer_extended_connect,
+                })
             }
             .instrument(debug_span!("h2")),
         )
@@ -131,6 +169,20 @@ where
 
 // === impl Connection ===
 
+type RspFuture = Pin<
+    Box<dyn Future<Output = Result<http::Response<hyper::Body>, hyper::Error>> + Send + 'static>,
+>;
+
+impl<B> Connection<B> {
+    /// Indicates whether HTTP/1.1 upgrades may be carried over this
+    /// connection as extended CONNECT streams, i.e. whether extended CONNECT
+    /// is enabled locally and the peer has advertised
+    /// `SETTINGS_ENABLE_CONNECT_PROTOCOL`.
+    pub(crate) fn extended_connect(&self) -> bool {
+        self.extended_connect && self.peer_extended_connect.load(Ordering::Acquire)
+    }
+}
+
 impl<B> tower::Service<http::Request<B>> for Connection<B>
 where
     B: HttpBody + Send + 'static,
@@ -139,7 +191,7 @@ where
 {
     type Response = http::Response<hyper::Body>;
     type Error = hyper::Error;
-    type Future = conn::ResponseFuture;
+    type Future = RspFuture;
 
     #[inline]
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
@@ -161,6 +213,18 @@ where
             *req.version_mut() = http::Version::HTTP_11;
         }
 
-        self.tx.send_request(req)
+        // If the request is an extended CONNECT stream carrying an upgrade,
+        // the upgraded stream is bridged to the other half of the upgrade
+        // once the server accepts the tunnel.
+        let upgrade = req.extensions_mut().remove::<Http11Upgrade>();
+        Box::pin(self.tx.send_request(req).map_ok(move |mut rsp| {
+            if let Some(upgrade) = upgrade {
+                if rsp.status().is_success() {
+                    debug!("HTTP/2 CONNECT stream established");
+                    upgrade.insert_half(hyper::upgrade::on(&mut rsp));
+                }
+            }
+            rsp
+        }))
     }
 }
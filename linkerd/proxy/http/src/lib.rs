@@ -8,6 +8,7 @@ pub mod balance;
 pub mod classify;
 pub mod client;
 pub mod client_handle;
+pub mod deadline;
 pub mod detect;
 mod glue;
 pub mod h1;
@@ -26,12 +27,19 @@ pub mod upgrade;
 pub mod version;
 
 pub use self::{
-    balance::NewBalancePeakEwma,
+    balance::{
+        NewBalanceConsistentHash, NewBalanceLeastRequest, NewBalancePeakEwma,
+        NewBalanceWeightedRoundRobin,
+    },
     classify::{
         Classify, ClassifyEos, ClassifyResponse, NewClassifyGate, NewClassifyGateSet,
         NewInsertClassifyResponse,
     },
     client_handle::{ClientHandle, SetClientHandle},
+    deadline::{
+        DeadlineExceededError, DeadlineParams, NewEnforceDeadline, PropagateDeadline,
+        RequestDeadline,
+    },
     detect::DetectHttp,
     glue::{HyperServerSvc, UpgradeBody},
     header_from_target::NewHeaderFromTarget,
@@ -40,7 +48,10 @@ pub use self::{
     retain::Retain,
     server::{NewServeHttp, ServeHttp},
     strip_header::StripHeader,
-    timeout::{NewTimeout, ResponseTimeout, ResponseTimeoutError},
+    timeout::{
+        NewStreamTimeouts, NewTimeout, ResponseStreamTimeoutError, ResponseTimeout,
+        ResponseTimeoutError, StreamIdleTimeoutError, StreamTimeouts,
+    },
     version::Version,
 };
 pub use http::{
@@ -1,7 +1,7 @@
 use super::{h1, h2, upgrade};
 use futures::{future, prelude::*};
-use http::header::{HeaderValue, TRANSFER_ENCODING};
-use hyper::body::HttpBody;
+use http::header::{HeaderValue, CONNECTION, TRANSFER_ENCODING, UPGRADE};
+use hyper::{body::HttpBody, ext::Protocol};
 use linkerd_error::{Error, Result};
 use linkerd_http_box::BoxBody;
 use linkerd_stack::{layer, MakeConnection, Service};
@@ -72,9 +72,21 @@ where
 
     fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
         debug_assert!(req.version() != http::Version::HTTP_2);
+        // HTTP/1.1 upgrades may be tunneled over the HTTP/2 connection as
+        // extended CONNECT streams if the peer has advertised support for
+        // them. Otherwise, upgrades (including HTTP/1.1 CONNECT requests)
+        // require a dedicated HTTP/1.1 connection.
+        let mut tunnel = None;
         if req.extensions().get::<upgrade::Http11Upgrade>().is_some() {
-            debug!("Skipping orig-proto upgrade due to HTTP/1.1 upgrade");
-            return Box::pin(self.http1.request(req).map_ok(|rsp| rsp.map(BoxBody::new)));
+            tunnel = if self.h2.extended_connect() {
+                extended_connect_protocol(&req)
+            } else {
+                None
+            };
+            if tunnel.is_none() {
+                debug!("Skipping orig-proto upgrade due to HTTP/1.1 upgrade");
+                return Box::pin(self.http1.request(req).map_ok(|rsp| rsp.map(BoxBody::new)));
+            }
         }
 
         let orig_version = req.version();
@@ -100,6 +112,17 @@ where
         // transfer-encoding is illegal in HTTP2
         req.headers_mut().remove(TRANSFER_ENCODING);
 
+        // The upgrade is carried by the `:protocol` pseudo-header, since
+        // connection-level headers are illegal in HTTP2. The upgrade header
+        // is restored on the response.
+        let tunnel = tunnel.map(|(protocol, upgrade)| {
+            debug!(protocol = %protocol.as_str(), "Tunneling upgrade over extended CONNECT");
+            h1::strip_connection_headers(req.headers_mut());
+            *req.method_mut() = http::Method::CONNECT;
+            req.extensions_mut().insert(protocol);
+            upgrade
+        });
+
         *req.version_mut() = http::Version::HTTP_2;
 
         Box::pin(
@@ -122,12 +145,35 @@ where
                         .unwrap_or(orig_version);
                     trace!(?version, "Downgrading response");
                     *rsp.version_mut() = version;
+                    if let Some(upgrade) = tunnel {
+                        if rsp.status().is_success() {
+                            *rsp.status_mut() = http::StatusCode::SWITCHING_PROTOCOLS;
+                            rsp.headers_mut()
+                                .insert(CONNECTION, HeaderValue::from_static("upgrade"));
+                            rsp.headers_mut().insert(UPGRADE, upgrade);
+                        }
+                    }
                     rsp.map(|inner| BoxBody::new(UpgradeResponseBody { inner }))
                 }),
         )
     }
 }
 
+/// Returns the protocol of an HTTP/1.1 upgrade request that may be tunneled
+/// over an extended CONNECT stream, along with its original upgrade header.
+///
+/// HTTP/1.1 CONNECT requests and upgrades that offer multiple protocols are
+/// not tunneled.
+fn extended_connect_protocol<B>(req: &http::Request<B>) -> Option<(Protocol, HeaderValue)> {
+    if req.method() == http::Method::CONNECT {
+        return None;
+    }
+
+    let upgrade = req.headers().get(UPGRADE)?;
+    let protocol = upgrade.to_str().ok().filter(|p| !p.contains(','))?;
+    Some((Protocol::from(protocol.trim()), upgrade.clone()))
+}
+
 /// Handles HTTP/2 client errors for HTTP/1.1 requests by wrapping the error type. This
 /// simplifies error handling elsewhere so that HTTP/2 errors can only be encountered when the
 /// original request was HTTP/2.
@@ -252,6 +298,7 @@ where
 
     fn call(&mut self, mut req: http::Request<A>) -> Self::Future {
         let mut upgrade_response = false;
+        let mut tunnel_response = false;
 
         if req.version() == http::Version::HTTP_2 {
             if let Some(orig_proto) = req.headers_mut().remove(L5D_ORIG_PROTO) {
@@ -270,6 +317,23 @@ where
                 if was_absolute_form(val) {
                     req.extensions_mut().insert(h1::WasAbsoluteForm(()));
                 }
+
+                // Extended CONNECT streams are translated to HTTP/1.1
+                // upgrade requests.
+                if upgrade::is_extended_connect(&req) {
+                    let protocol = req.extensions_mut().remove::<Protocol>();
+                    match protocol.map(|p| HeaderValue::from_str(p.as_str())) {
+                        Some(Ok(protocol)) => {
+                            debug!(?protocol, "Downgrading extended CONNECT to upgrade");
+                            *req.method_mut() = http::Method::GET;
+                            req.headers_mut().insert(UPGRADE, protocol);
+                            req.headers_mut()
+                                .insert(CONNECTION, HeaderValue::from_static("upgrade"));
+                            tunnel_response = true;
+                        }
+                        _ => warn!("Invalid extended CONNECT protocol"),
+                    }
+                }
                 req.extensions_mut().insert(WasUpgrade(()));
                 upgrade_response = true;
             }
@@ -277,29 +341,86 @@ where
 
         let fut = self.inner.call(req);
 
-        if upgrade_response {
-            fut.map_ok(|mut res| {
-                let orig_proto = match res.version() {
-                    http::Version::HTTP_11 => "HTTP/1.1",
-                    http::Version::HTTP_10 => "HTTP/1.0",
-                    _ => return res,
-                };
-
-                res.headers_mut()
-                    .insert(L5D_ORIG_PROTO, HeaderValue::from_static(orig_proto));
-
-                // transfer-encoding is illegal in HTTP2
-                res.headers_mut().remove(TRANSFER_ENCODING);
-
-                *res.version_mut() = http::Version::HTTP_2;
-                res
-            })
+        if tunnel_response {
+            fut.map_ok(|res| orig_proto_response(extended_connect_response(res)))
+        } else if upgrade_response {
+            fut.map_ok(orig_proto_response)
         } else {
             fut.map_ok(|res| res)
         }
     }
 }
 
+/// Translates an HTTP/1.1 upgrade response to an HTTP/2 extended CONNECT
+/// response.
+fn extended_connect_response<B>(mut res: http::Response<B>) -> http::Response<B> {
+    if res.status() == http::StatusCode::SWITCHING_PROTOCOLS {
+        *res.status_mut() = http::StatusCode::OK;
+        h1::strip_connection_headers(res.headers_mut());
+    }
+    res
+}
+
+/// Marks a response to a downgraded request with its original protocol.
+fn orig_proto_response<B>(mut res: http::Response<B>) -> http::Response<B> {
+    let orig_proto = match res.version() {
+        http::Version::HTTP_11 => "HTTP/1.1",
+        http::Version::HTTP_10 => "HTTP/1.0",
+        _ => return res,
+    };
+
+    res.headers_mut()
+        .insert(L5D_ORIG_PROTO, HeaderValue::from_static(orig_proto));
+
+    // transfer-encoding is illegal in HTTP2
+    res.headers_mut().remove(TRANSFER_ENCODING);
+
+    *res.version_mut() = http::Version::HTTP_2;
+    res
+}
+
 fn was_absolute_form(val: &[u8]) -> bool {
     val.len() >= "HTTP/1.1; absolute-form".len() && &val[10..23] == b"absolute-form"
 }
+
+#[cfg(test)]
+#[tokio::test]
+async fn downgrades_extended_connect() {
+    use linkerd_stack::{layer::Layer, service_fn, ServiceExt};
+
+    let svc = Downgrade::layer().layer(service_fn(|req: http::Request<()>| async move {
+        assert_eq!(req.method(), http::Method::GET);
+        assert_eq!(req.version(), http::Version::HTTP_11);
+        assert_eq!(req.headers()[UPGRADE], "websocket");
+        assert_eq!(req.headers()[CONNECTION], "upgrade");
+        assert!(req.extensions().get::<WasUpgrade>().is_some());
+        let rsp = http::Response::builder()
+            .status(http::StatusCode::SWITCHING_PROTOCOLS)
+            .version(http::Version::HTTP_11)
+            .header(CONNECTION, "upgrade")
+            .header(UPGRADE, "websocket")
+            .header("sec-websocket-accept", "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=")
+            .body(())
+            .unwrap();
+        Ok::<_, Error>(rsp)
+    }));
+
+    let mut req = http::Request::builder()
+        .method(http::Method::CONNECT)
+        .version(http::Version::HTTP_2)
+        .uri("http://example.com/chat")
+        .header(L5D_ORIG_PROTO, "HTTP/1.1")
+        .header("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ==")
+        .body(())
+        .unwrap();
+    req.extensions_mut()
+        .insert(Protocol::from_static("websocket"));
+
+    let rsp = svc.oneshot(req).await.expect("request must succeed");
+    assert_eq!(rsp.status(), http::StatusCode::OK);
+    assert_eq!(rsp.version(), http::Version::HTTP_2);
+    assert_eq!(rsp.headers()[L5D_ORIG_PROTO], "HTTP/1.1");
+    assert!(rsp.headers().get(UPGRADE).is_none());
+    assert!(rsp.headers().get(CONNECTION).is_none());
+    assert!(rsp.headers().get("sec-websocket-accept").is_some());
+}
@@ -1,8 +1,5 @@
 use crate::{
-    self as http,
-    client_handle::SetClientHandle,
-    glue::{HyperServerSvc, UpgradeBody},
-    h2::Settings as H2Settings,
+    self as http, client_handle::SetClientHandle, glue::UpgradeBody, h2::Settings as H2Settings,
     trace, upgrade, ClientHandle, Version,
 };
 use linkerd_error::Error;
@@ -52,6 +49,11 @@ impl<N> NewServeHttp<N> {
             .http2_initial_stream_window_size(h2.initial_stream_window_size)
             .http2_initial_connection_window_size(h2.initial_connection_window_size);
 
+        // Allow clients to tunnel upgrades over extended CONNECT streams.
+        if h2.extended_connect {
+            server.http2_enable_connect_protocol();
+        }
+
         // Configure HTTP/2 PING frames
         // 判断是否实则了 http keep alive , 如果设置了则设置 http2 相关 keep alive 选项
         if let Some(timeout) = h2.keepalive_timeout {
@@ -157,9 +159,10 @@ where
                     }
 
                     Version::H2 => {
-                        let mut conn = server
-                            .http2_only(true)
-                            .serve_connection(io, HyperServerSvc::new(svc));
+                        let mut conn = server.http2_only(true).serve_connection(
+                            io,
+                            upgrade::ExtendedConnect::new(svc, drain.clone()),
+                        );
                         tokio::select! {
                             res = &mut conn => {
                                 debug!(?res, "The client is shutting down the connection");
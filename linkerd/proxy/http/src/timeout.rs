@@ -1,7 +1,14 @@
 use linkerd_error::Error;
-use linkerd_stack::{layer, ExtractParam, MapErr, NewService, Timeout, TimeoutError};
-use std::time::Duration;
+use linkerd_stack::{layer, ExtractParam, MapErr, NewService, Service, Timeout, TimeoutError};
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
 use thiserror::Error;
+use tokio::time;
 
 /// An HTTP-specific optional timeout layer.
 ///
@@ -24,6 +31,69 @@ pub struct ResponseTimeout(pub Option<Duration>);
 #[error("HTTP response timeout after {0:?}")]
 pub struct ResponseTimeoutError(Duration);
 
+/// An HTTP timeout layer that bounds streaming response bodies.
+///
+/// Unlike [`NewTimeout`], which only bounds the time until response headers
+/// are received, these timeouts continue to apply while the response body is
+/// streamed, so that long-lived streams may be permitted to run indefinitely
+/// as long as data continues to flow.
+#[derive(Clone, Debug)]
+pub struct NewStreamTimeouts<X, N> {
+    inner: N,
+    extract: X,
+}
+
+/// Param type configuring timeouts for streaming HTTP responses.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct StreamTimeouts {
+    /// Bounds the total duration of a request, from the time it is dispatched
+    /// until its response body completes.
+    pub total: Option<Duration>,
+
+    /// Bounds the amount of time that may elapse between response body frames.
+    pub idle: Option<Duration>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EnforceStreamTimeouts<S> {
+    inner: S,
+    timeouts: StreamTimeouts,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct StreamTimeoutsFuture<F> {
+    #[pin]
+    inner: F,
+    deadline: Option<Deadline>,
+    idle: Option<Duration>,
+}
+
+/// A response body that enforces [`StreamTimeouts`].
+#[pin_project]
+#[derive(Debug)]
+pub struct StreamTimeoutsBody<B> {
+    #[pin]
+    inner: B,
+    deadline: Option<Deadline>,
+    idle: Option<Deadline>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Deadline {
+    sleep: Pin<Box<time::Sleep>>,
+    timeout: Duration,
+    error: fn(Duration) -> Error,
+}
+
+#[derive(Clone, Debug, Error)]
+#[error("HTTP response stream timeout after {0:?}")]
+pub struct ResponseStreamTimeoutError(Duration);
+
+#[derive(Clone, Debug, Error)]
+#[error("HTTP response stream idle for {0:?}")]
+pub struct StreamIdleTimeoutError(Duration);
+
 // === impl NewTimeout ===
 
 impl<X: Clone, N> NewTimeout<X, N> {
@@ -65,3 +135,313 @@ where
         })
     }
 }
+
+// === impl NewStreamTimeouts ===
+
+impl<X: Clone, N> NewStreamTimeouts<X, N> {
+    pub fn layer_via(extract: X) -> impl tower::layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            extract: extract.clone(),
+        })
+    }
+}
+
+impl<N> NewStreamTimeouts<(), N> {
+    pub fn layer() -> impl tower::layer::Layer<N, Service = Self> + Clone {
+        Self::layer_via(())
+    }
+}
+
+impl<T, X, N> NewService<T> for NewStreamTimeouts<X, N>
+where
+    X: ExtractParam<StreamTimeouts, T>,
+    N: NewService<T>,
+{
+    type Service = EnforceStreamTimeouts<N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let timeouts = self.extract.extract_param(&target);
+        EnforceStreamTimeouts {
+            inner: self.inner.new_service(target),
+            timeouts,
+        }
+    }
+}
+
+// === impl EnforceStreamTimeouts ===
+
+impl<Req, B, S> Service<Req> for EnforceStreamTimeouts<S>
+where
+    S: Service<Req, Response = http::Response<B>>,
+    S::Error: Into<Error>,
+{
+    type Response = http::Response<StreamTimeoutsBody<B>>;
+    type Error = Error;
+    type Future = StreamTimeoutsFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        StreamTimeoutsFuture {
+            inner: self.inner.call(req),
+            deadline: self.timeouts.total.map(Deadline::total),
+            idle: self.timeouts.idle,
+        }
+    }
+}
+
+// === impl StreamTimeoutsFuture ===
+
+impl<F> StreamTimeoutsFuture<F> {
+    pub(crate) fn new(inner: F, deadline: Option<Deadline>) -> Self {
+        Self {
+            inner,
+            deadline,
+            idle: None,
+        }
+    }
+}
+
+impl<B, E, F> Future for StreamTimeoutsFuture<F>
+where
+    F: Future<Output = Result<http::Response<B>, E>>,
+    E: Into<Error>,
+{
+    type Output = Result<http::Response<StreamTimeoutsBody<B>>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let rsp = match this.inner.poll(cx) {
+            Poll::Ready(res) => res.map_err(Into::into)?,
+            Poll::Pending => {
+                if let Some(deadline) = this.deadline.as_mut() {
+                    deadline.poll_expired(cx)?;
+                }
+                return Poll::Pending;
+            }
+        };
+
+        // The idle timeout only applies once the response body is being
+        // streamed. The time to receive response headers is bounded by the
+        // total timeout.
+        let deadline = this.deadline.take();
+        let idle = this.idle.map(Deadline::idle);
+        Poll::Ready(Ok(rsp.map(|inner| StreamTimeoutsBody {
+            inner,
+            deadline,
+            idle,
+        })))
+    }
+}
+
+// === impl StreamTimeoutsBody ===
+
+impl<B> StreamTimeoutsBody<B> {
+    fn poll_timeouts(
+        deadline: &mut Option<Deadline>,
+        idle: &mut Option<Deadline>,
+        cx: &mut Context<'_>,
+    ) -> Result<(), Error> {
+        if let Some(deadline) = deadline.as_mut() {
+            deadline.poll_expired(cx)?;
+        }
+        if let Some(idle) = idle.as_mut() {
+            idle.poll_expired(cx)?;
+        }
+        Ok(())
+    }
+}
+
+impl<B> http_body::Body for StreamTimeoutsBody<B>
+where
+    B: http_body::Body,
+    B::Error: Into<Error>,
+{
+    type Data = B::Data;
+    type Error = Error;
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        match this.inner.poll_data(cx) {
+            Poll::Ready(Some(Ok(data))) => {
+                // Each data frame resets the idle timeout.
+                if let Some(idle) = this.idle.as_mut() {
+                    idle.reset();
+                }
+                Poll::Ready(Some(Ok(data)))
+            }
+            Poll::Ready(res) => Poll::Ready(res.map(|r| r.map_err(Into::into))),
+            Poll::Pending => {
+                if let Err(e) = Self::poll_timeouts(this.deadline, this.idle, cx) {
+                    return Poll::Ready(Some(Err(e)));
+                }
+                Poll::Pending
+            }
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        let this = self.project();
+        match this.inner.poll_trailers(cx) {
+            Poll::Ready(res) => Poll::Ready(res.map_err(Into::into)),
+            Poll::Pending => {
+                Self::poll_timeouts(this.deadline, this.idle, cx)?;
+                Poll::Pending
+            }
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+// === impl Deadline ===
+
+impl Deadline {
+    /// Returns a deadline that elapses at `deadline`, failing with an error
+    /// built from `timeout`.
+    pub(crate) fn until(
+        deadline: time::Instant,
+        timeout: Duration,
+        error: fn(Duration) -> Error,
+    ) -> Self {
+        Self {
+            sleep: Box::pin(time::sleep_until(deadline)),
+            timeout,
+            error,
+        }
+    }
+
+    fn total(timeout: Duration) -> Self {
+        Self::until(time::Instant::now() + timeout, timeout, |t| {
+            ResponseStreamTimeoutError(t).into()
+        })
+    }
+
+    fn idle(timeout: Duration) -> Self {
+        Self::until(time::Instant::now() + timeout, timeout, |t| {
+            StreamIdleTimeoutError(t).into()
+        })
+    }
+
+    fn reset(&mut self) {
+        self.sleep
+            .as_mut()
+            .reset(time::Instant::now() + self.timeout);
+    }
+
+    /// Returns an error if the deadline has elapsed.
+    fn poll_expired(&mut self, cx: &mut Context<'_>) -> Result<(), Error> {
+        match self.sleep.as_mut().poll(cx) {
+            Poll::Ready(()) => Err((self.error)(self.timeout)),
+            Poll::Pending => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoxBody;
+    use http_body::Body;
+    use linkerd_stack::ServiceExt;
+
+    fn timeouts(total: Option<u64>, idle: Option<u64>) -> StreamTimeouts {
+        StreamTimeouts {
+            total: total.map(Duration::from_secs),
+            idle: idle.map(Duration::from_secs),
+        }
+    }
+
+    fn enforce(
+        timeouts: StreamTimeouts,
+    ) -> (
+        EnforceStreamTimeouts<
+            tower_test::mock::Mock<http::Request<BoxBody>, http::Response<hyper::Body>>,
+        >,
+        tower_test::mock::Handle<http::Request<BoxBody>, http::Response<hyper::Body>>,
+    ) {
+        let (inner, handle) = tower_test::mock::pair();
+        (EnforceStreamTimeouts { inner, timeouts }, handle)
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn total_before_headers() {
+        let (svc, mut handle) = enforce(timeouts(Some(10), None));
+        handle.allow(1);
+        let rsp = tokio::spawn(svc.oneshot(http::Request::new(BoxBody::default())));
+        let (_req, _tx) = handle.next_request().await.expect("request");
+
+        let error = rsp.await.unwrap().expect_err("response must time out");
+        assert!(error.is::<ResponseStreamTimeoutError>(), "{error}");
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn total_while_streaming() {
+        let (svc, mut handle) = enforce(timeouts(Some(10), Some(3)));
+        handle.allow(1);
+        let rsp = tokio::spawn(svc.oneshot(http::Request::new(BoxBody::default())));
+        let (_req, tx) = handle.next_request().await.expect("request");
+        let (mut body_tx, body) = hyper::Body::channel();
+        tx.send_response(http::Response::new(body));
+        let mut body = rsp.await.unwrap().expect("response").into_body();
+
+        // Data continues to flow, so the idle timeout never elapses...
+        for _ in 0..4 {
+            time::sleep(Duration::from_secs(2)).await;
+            body_tx.send_data("hi".into()).await.unwrap();
+            body.data().await.expect("data").expect("data");
+        }
+
+        // ...but the stream is still bounded by the total timeout.
+        let error = body
+            .data()
+            .await
+            .expect("error")
+            .expect_err("must time out");
+        assert!(error.is::<ResponseStreamTimeoutError>(), "{error}");
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn idle_while_streaming() {
+        let (svc, mut handle) = enforce(timeouts(None, Some(3)));
+        handle.allow(1);
+        let rsp = tokio::spawn(svc.oneshot(http::Request::new(BoxBody::default())));
+        let (_req, tx) = handle.next_request().await.expect("request");
+        let (mut body_tx, body) = hyper::Body::channel();
+        tx.send_response(http::Response::new(body));
+        let mut body = rsp.await.unwrap().expect("response").into_body();
+
+        // Without a total timeout, the stream may run indefinitely while data
+        // flows.
+        for _ in 0..100 {
+            time::sleep(Duration::from_secs(2)).await;
+            body_tx.send_data("hi".into()).await.unwrap();
+            body.data().await.expect("data").expect("data");
+        }
+
+        let error = body
+            .data()
+            .await
+            .expect("error")
+            .expect_err("must time out");
+        assert!(error.is::<StreamIdleTimeoutError>(), "{error}");
+    }
+}
@@ -1,11 +1,11 @@
-//! HTTP/1.1 Upgrades
+//! HTTP/1.1 Upgrades and HTTP/2 extended CONNECT tunnels
 
 use crate::{glue::UpgradeBody, h1};
 use futures::{
     future::{self, Either},
     TryFutureExt,
 };
-use hyper::upgrade::OnUpgrade;
+use hyper::{ext::Protocol, upgrade::OnUpgrade};
 use linkerd_duplex::Duplex;
 use std::fmt;
 use std::mem;
@@ -62,6 +62,16 @@ pub struct Service<S> {
     upgrade_drain_signal: drain::Watch,
 }
 
+/// Bridges HTTP/2 extended CONNECT streams (RFC 8441) into the HTTP/1.1
+/// upgrade machinery, so that a tunneled stream may be joined with an
+/// upgraded HTTP/1.1 connection or another extended CONNECT stream.
+#[derive(Debug)]
+pub struct ExtendedConnect<S> {
+    service: S,
+    /// Watch any spawned upgrade tasks.
+    upgrade_drain_signal: drain::Watch,
+}
+
 // === impl Http11Upgrade ===
 
 impl Http11Upgrade {
@@ -219,3 +229,47 @@ where
         Either::Left(self.service.call(req))
     }
 }
+
+// === impl ExtendedConnect ===
+
+impl<S> ExtendedConnect<S> {
+    pub fn new(service: S, upgrade_drain_signal: drain::Watch) -> Self {
+        Self {
+            service,
+            upgrade_drain_signal,
+        }
+    }
+}
+
+impl<S> tower::Service<http::Request<hyper::Body>> for ExtendedConnect<S>
+where
+    S: tower::Service<http::Request<UpgradeBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<hyper::Body>) -> Self::Future {
+        let upgrade = if is_extended_connect(&req) {
+            trace!("server request is an extended CONNECT stream");
+            let halves = Http11Upgrade::halves(self.upgrade_drain_signal.clone());
+            req.extensions_mut().insert(halves.client);
+            let on_upgrade = hyper::upgrade::on(&mut req);
+            Some((halves.server, on_upgrade))
+        } else {
+            None
+        };
+
+        let req = req.map(|body| UpgradeBody::new(body, upgrade));
+        self.service.call(req)
+    }
+}
+
+/// Checks whether an HTTP/2 request is an extended CONNECT (RFC 8441) request.
+pub(crate) fn is_extended_connect<B>(req: &http::Request<B>) -> bool {
+    req.method() == http::Method::CONNECT && req.extensions().get::<Protocol>().is_some()
+}
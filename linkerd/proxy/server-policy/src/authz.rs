@@ -1,9 +1,11 @@
 use super::Meta;
 use std::{collections::BTreeSet, sync::Arc};
 
+mod jwt;
 mod network;
+mod spiffe;
 
-pub use self::network::Network;
+pub use self::{jwt::JwtAuthentication, network::Network, spiffe::SpiffeMatch};
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Authorization {
@@ -19,7 +21,9 @@ pub enum Authentication {
     TlsAuthenticated {
         identities: BTreeSet<String>,
         suffixes: Vec<Suffix>,
+        spiffe: Vec<SpiffeMatch>,
     },
+    Jwt(JwtAuthentication),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -45,6 +49,12 @@ impl Suffix {
     pub fn contains(&self, name: &str) -> bool {
         name.ends_with(&self.ends_with)
     }
+
+    /// Returns true if this suffix matches all identities.
+    #[inline]
+    pub fn is_wildcard(&self) -> bool {
+        self.ends_with.is_empty()
+    }
 }
 
 #[cfg(feature = "proto")]
@@ -121,11 +131,18 @@ pub mod proto {
                                 Authentication::TlsUnauthenticated
                             }
                             api::authn::permit_mesh_tls::Clients::Identities(ids) => {
-                                let identities = ids
-                                    .identities
-                                    .into_iter()
-                                    .map(|api::Identity { name }| name)
-                                    .collect();
+                                // Identities with the `spiffe://` scheme match
+                                // all SPIFFE IDs under their path.
+                                let mut identities = BTreeSet::new();
+                                let mut spiffe = Vec::new();
+                                for api::Identity { name } in ids.identities {
+                                    match SpiffeMatch::from_identity(&name) {
+                                        Some(m) => spiffe.push(m),
+                                        None => {
+                                            identities.insert(name);
+                                        }
+                                    }
+                                }
                                 let suffixes = ids
                                     .suffixes
                                     .into_iter()
@@ -134,6 +151,7 @@ pub mod proto {
                                 Authentication::TlsAuthenticated {
                                     identities,
                                     suffixes,
+                                    spiffe,
                                 }
                             }
                         }
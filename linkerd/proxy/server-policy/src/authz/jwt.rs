@@ -0,0 +1,32 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+/// Authenticates HTTP requests with a JSON Web Token (JWT) bearer token.
+///
+/// Tokens are read from the request's `authorization` header and their
+/// signatures are validated against a JSON Web Key Set (JWKS). A token is
+/// only accepted if it has not expired and its issuer, audience, and claims
+/// match this configuration.
+///
+/// JWTs may only authenticate HTTP requests, so this never authorizes opaque
+/// connections.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct JwtAuthentication {
+    /// A local file containing the JWKS used to validate token signatures.
+    ///
+    /// The file is reloaded periodically so that keys may be rotated.
+    pub jwks_path: PathBuf,
+
+    /// If set, tokens must have a matching `iss` claim.
+    pub issuer: Option<String>,
+
+    /// If not empty, tokens must have an `aud` claim that includes at least
+    /// one of these audiences.
+    pub audiences: BTreeSet<String>,
+
+    /// Claims that tokens must include. A claim matches if its value is the
+    /// given string or an array that includes the given string.
+    pub claims: BTreeMap<String, String>,
+}
@@ -0,0 +1,92 @@
+/// Matches SPIFFE IDs in a trust domain, optionally limited to IDs whose path
+/// is (or descends from) a path prefix.
+///
+/// The policy API configures these as identity names with the `spiffe://`
+/// scheme, e.g. `spiffe://example.org/ns/web` or `spiffe://example.org`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SpiffeMatch {
+    pub trust_domain: String,
+
+    /// Only whole path segments match, so that `/ns/web` matches
+    /// `/ns/web/sa/api` but not `/ns/webhook`. An empty prefix matches all IDs
+    /// in the trust domain.
+    pub path_prefix: String,
+}
+
+// === impl SpiffeMatch ===
+
+impl SpiffeMatch {
+    const SCHEME: &'static str = "spiffe://";
+
+    /// Parses a `spiffe://<trust-domain>[<path-prefix>]` identity name,
+    /// returning `None` if it is not a SPIFFE ID.
+    pub fn from_identity(name: &str) -> Option<Self> {
+        let id = name.strip_prefix(Self::SCHEME)?;
+        let (trust_domain, path_prefix) = match id.find('/') {
+            Some(i) => id.split_at(i),
+            None => (id, ""),
+        };
+        if trust_domain.is_empty() {
+            return None;
+        }
+        Some(Self {
+            trust_domain: trust_domain.to_string(),
+            path_prefix: path_prefix.to_string(),
+        })
+    }
+
+    pub fn contains(&self, trust_domain: &str, path: &str) -> bool {
+        if self.trust_domain != trust_domain {
+            return false;
+        }
+        match path.strip_prefix(self.path_prefix.trim_end_matches('/')) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mk(trust_domain: &str, path_prefix: &str) -> SpiffeMatch {
+        SpiffeMatch {
+            trust_domain: trust_domain.to_string(),
+            path_prefix: path_prefix.to_string(),
+        }
+    }
+
+    #[test]
+    fn contains() {
+        let path = "/ns/web/sa/api";
+        assert!(mk("example.org", "").contains("example.org", path));
+        assert!(mk("example.org", "/").contains("example.org", path));
+        assert!(mk("example.org", "/ns/web").contains("example.org", path));
+        assert!(mk("example.org", "/ns/web/").contains("example.org", path));
+        assert!(mk("example.org", "/ns/web/sa/api").contains("example.org", path));
+        assert!(mk("example.org", "").contains("example.org", ""));
+
+        assert!(!mk("example.com", "/ns/web").contains("example.org", path));
+        assert!(!mk("example.org", "/ns/we").contains("example.org", path));
+        assert!(!mk("example.org", "/ns/web/sa/api/v1").contains("example.org", path));
+        assert!(!mk("example.org", "/ns/web").contains("example.org", ""));
+    }
+
+    #[test]
+    fn from_identity() {
+        assert_eq!(
+            SpiffeMatch::from_identity("spiffe://example.org/ns/web"),
+            Some(mk("example.org", "/ns/web"))
+        );
+        assert_eq!(
+            SpiffeMatch::from_identity("spiffe://example.org"),
+            Some(mk("example.org", ""))
+        );
+        assert_eq!(SpiffeMatch::from_identity("spiffe:///ns/web"), None);
+        assert_eq!(
+            SpiffeMatch::from_identity("web.ns.serviceaccount.identity.linkerd.cluster.local"),
+            None
+        );
+    }
+}
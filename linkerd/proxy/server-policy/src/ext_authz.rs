@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+/// Checks each request on a route with an external authorization service.
+///
+/// The proxy sends the request's attributes---its method, path, authority,
+/// selected headers, and the client's identity and address---to the
+/// authorization service, which may allow the request (optionally adding or
+/// removing request headers) or deny it.
+///
+/// The check is performed after the route's authorizations and other filters
+/// have been applied.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ExtAuthz {
+    /// The maximum time to wait for the authorization service to respond.
+    pub timeout: Duration,
+
+    /// Determines whether requests are permitted when the authorization
+    /// service cannot be reached or fails to respond in time.
+    pub failure_mode: FailureMode,
+
+    /// If set, decisions are cached for requests with the same attributes.
+    pub cache_ttl: Option<Duration>,
+
+    /// Request headers that are sent to the authorization service. Other
+    /// headers are not sent.
+    pub headers: Vec<http::HeaderName>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FailureMode {
+    /// Requests are permitted when the authorization service fails.
+    Allow,
+    /// Requests are denied when the authorization service fails.
+    Deny,
+}
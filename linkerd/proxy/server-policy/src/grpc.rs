@@ -9,6 +9,8 @@ pub type Rule = grpc::Rule<Policy>;
 pub enum Filter {
     InjectFailure(filter::InjectFailure),
     RequestHeaders(http::filter::ModifyHeader),
+    RateLimit(crate::LocalRateLimit),
+    ExtAuthz(crate::ExtAuthz),
     InternalError(&'static str),
 }
 
@@ -29,6 +31,7 @@ pub fn default(authorizations: std::sync::Arc<[crate::Authorization]>) -> Route
                 meta: crate::Meta::new_default("default"),
                 authorizations,
                 filters: vec![],
+                authz_mode: crate::AuthzMode::Enforce,
             },
         }],
     }
@@ -136,6 +139,7 @@ pub mod proto {
                 authorizations,
                 filters,
                 meta,
+                authz_mode: crate::AuthzMode::Enforce,
             }
         };
 
@@ -10,6 +10,9 @@ pub enum Filter {
     InjectFailure(filter::InjectFailure),
     Redirect(filter::RedirectRequest),
     RequestHeaders(filter::ModifyHeader),
+    UrlRewrite(filter::UrlRewrite),
+    RateLimit(crate::LocalRateLimit),
+    ExtAuthz(crate::ExtAuthz),
     InternalError(&'static str),
 }
 
@@ -30,6 +33,7 @@ pub fn default(authorizations: std::sync::Arc<[crate::Authorization]>) -> Route
                 meta: crate::Meta::new_default("default"),
                 authorizations,
                 filters: vec![],
+                authz_mode: crate::AuthzMode::Enforce,
             },
         }],
     }
@@ -138,6 +142,7 @@ pub mod proto {
                 authorizations,
                 filters,
                 meta,
+                authz_mode: crate::AuthzMode::Enforce,
             }
         };
 
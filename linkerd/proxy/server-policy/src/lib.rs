@@ -4,13 +4,17 @@
 use std::{hash::Hash, sync::Arc, time};
 
 pub mod authz;
+pub mod ext_authz;
 pub mod grpc;
 pub mod http;
 pub mod meta;
+pub mod rate_limit;
 
 pub use self::{
     authz::{Authentication, Authorization},
+    ext_authz::ExtAuthz,
     meta::Meta,
+    rate_limit::{LocalRateLimit, RateLimitKey},
 };
 pub use linkerd_http_route as route;
 
@@ -18,6 +22,10 @@ pub use linkerd_http_route as route;
 pub struct ServerPolicy {
     pub protocol: Protocol,
     pub meta: Arc<Meta>,
+
+    /// Determines whether authorization denials are enforced for all of the
+    /// server's connections and routes.
+    pub authz_mode: AuthzMode,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -39,6 +47,23 @@ pub struct RoutePolicy<T> {
     pub meta: Arc<Meta>,
     pub authorizations: Arc<[Authorization]>,
     pub filters: Vec<T>,
+
+    /// Determines whether authorization denials are enforced for the route.
+    pub authz_mode: AuthzMode,
+}
+
+/// Describes how a policy handles requests and connections that are not
+/// authorized.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AuthzMode {
+    /// Unauthorized requests and connections are refused.
+    #[default]
+    Enforce,
+
+    /// Unauthorized requests and connections are permitted, but they are
+    /// logged and counted so that a policy's impact may be observed before it
+    /// is enforced.
+    Audit,
 }
 
 impl ServerPolicy {
@@ -60,15 +85,26 @@ impl ServerPolicy {
                             filters: vec![http::Filter::InternalError(
                                 "invalid server configuration",
                             )],
+                            authz_mode: AuthzMode::Enforce,
                         },
                     }],
                 }]),
                 tcp_authorizations: Arc::new([]),
             },
+            authz_mode: AuthzMode::Enforce,
         }
     }
 }
 
+// === impl AuthzMode ===
+
+impl AuthzMode {
+    #[inline]
+    pub fn is_audit(&self) -> bool {
+        matches!(self, Self::Audit)
+    }
+}
+
 #[cfg(feature = "proto")]
 pub mod proto {
     use super::*;
@@ -182,7 +218,11 @@ pub mod proto {
             // avoid label inference.
             let meta = Meta::try_new_with_default(labels, "policy.linkerd.io", "server")?;
 
-            Ok(ServerPolicy { protocol, meta })
+            Ok(ServerPolicy {
+                protocol,
+                meta,
+                authz_mode: AuthzMode::Enforce,
+            })
         }
     }
 }
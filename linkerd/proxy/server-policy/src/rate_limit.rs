@@ -0,0 +1,27 @@
+/// Limits the rate of requests on a route.
+///
+/// Each proxy enforces the limit independently with a token bucket that holds
+/// up to `burst` tokens and is refilled at `requests_per_second`. Requests
+/// that arrive when the bucket is empty are refused.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LocalRateLimit {
+    /// The number of requests permitted each second, on average.
+    pub requests_per_second: u32,
+    /// The maximum number of requests that may be permitted at once.
+    pub burst: u32,
+    /// Determines which requests share a token bucket.
+    pub key: RateLimitKey,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    /// All requests on the route share a single token bucket.
+    Route,
+    /// Requests share a token bucket with other requests from the same
+    /// client identity. Requests from unauthenticated clients share a single
+    /// token bucket.
+    ClientIdentity,
+    /// Requests share a token bucket with other requests from the same client
+    /// IP address.
+    ClientIp,
+}
@@ -3,4 +3,6 @@ use tower::load::CompleteOnResponse;
 
 pub type EwmaConfig = balance::EwmaConfig;
 
+pub use balance::{EndpointZone, NewZoneAffinity, SlowStartConfig, ZoneAffinityConfig};
+
 pub type NewBalancePeakEwma<Req, R, N> = balance::NewBalancePeakEwma<CompleteOnResponse, Req, R, N>;
@@ -13,12 +13,14 @@ pub mod addrs;
 mod connect;
 pub mod listen;
 pub mod orig_dst;
+pub mod proxy_protocol;
 
 pub use self::{
     addrs::{ClientAddr, ListenAddr, Local, OrigDstAddr, Remote, ServerAddr},
     connect::ConnectTcp,
     listen::{Bind, BindTcp},
     orig_dst::BindWithOrigDst,
+    proxy_protocol::NewProxyProtocol,
 };
 use linkerd_io as io;
 use socket2::TcpKeepalive;
@@ -0,0 +1,826 @@
+//! Reads [PROXY protocol][spec] headers from accepted connections.
+//!
+//! Load balancers that proxy TCP connections may prepend a PROXY protocol
+//! header to each connection, describing the original client's address.
+//! When a header is read, the client address it describes replaces the
+//! socket's peer address in the connection's target.
+//!
+//! The header's destination address is not used to route connections: a
+//! client could otherwise direct the proxy to an arbitrary address, and load
+//! balancers commonly describe their own frontend address rather than this
+//! proxy's. The socket's original destination address is always used, and
+//! the header's destination is only logged.
+//!
+//! [spec]: https://www.haproxy.org/download/2.8/doc/proxy-protocol.txt
+
+use crate::addrs::*;
+use bytes::{Buf, BufMut, BytesMut};
+use ipnet::IpNet;
+use linkerd_error::{Error, Result};
+use linkerd_io::{self as io, AsyncReadExt};
+use linkerd_stack::{layer, NewService, Param, Service, ServiceExt};
+use std::{
+    future::Future,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    ops::RangeInclusive,
+    pin::Pin,
+    str::FromStr,
+    task::{Context, Poll},
+};
+use tokio::time;
+use tracing::{debug, trace};
+
+/// Configures the ports on which PROXY protocol headers are read.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// Ports on which connections must begin with a header.
+    pub required_ports: Vec<RangeInclusive<u16>>,
+
+    /// Ports on which connections may begin with a header. Ports that are
+    /// also required require a header.
+    pub allowed_ports: Vec<RangeInclusive<u16>>,
+
+    /// Networks of the load balancers that are trusted to send headers.
+    ///
+    /// Headers are not read from clients outside of these networks: their
+    /// connections are refused on required ports and handled with the
+    /// socket's addresses on allowed ports. No clients are trusted when this
+    /// is empty.
+    pub trusted_networks: Vec<IpNet>,
+
+    /// The amount of time to wait for a header before failing the connection
+    /// (in strict mode) or proceeding without a header (in optional mode).
+    pub timeout: time::Duration,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Connections that do not begin with a PROXY protocol header are
+    /// refused.
+    Strict,
+
+    /// Connections may begin with a PROXY protocol header. Connections without
+    /// a header are handled with the socket's addresses.
+    Optional,
+}
+
+/// A decoded PROXY protocol header.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Header {
+    /// The connection was established by the proxying peer on its own behalf
+    /// (e.g. for a health check) or the proxied addresses are unknown, so the
+    /// socket's addresses apply.
+    Local,
+
+    /// The connection was proxied on behalf of a client.
+    Proxied {
+        client: Remote<ClientAddr>,
+
+        /// The address to which the client connected, as described by the
+        /// proxying peer. This is informational only.
+        destination: SocketAddr,
+    },
+}
+
+/// A connection target annotated with the PROXY protocol header read from the
+/// connection, if any.
+#[derive(Clone, Debug)]
+pub struct Addrs<A> {
+    pub inner: A,
+    pub header: Option<Header>,
+}
+
+/// The I/O type produced after a connection's PROXY protocol header is read.
+pub type ProxyProtocolIo<I> = io::EitherIo<I, io::PrefixedIo<I>>;
+
+#[derive(Clone, Debug)]
+pub struct NewProxyProtocol<N> {
+    inner: N,
+    config: Config,
+}
+
+#[derive(Clone, Debug)]
+pub struct ProxyProtocol<T, N> {
+    target: T,
+    mode: Option<Mode>,
+    trusted: bool,
+    timeout: time::Duration,
+    inner: N,
+}
+
+enum Decoded {
+    Header(Header),
+    Absent,
+    Incomplete,
+}
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+const V1_MAX_LEN: usize = 107;
+
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+const V2_HEADER_LEN: usize = 16;
+
+const BUFFER_CAPACITY: usize = 512;
+
+/// The PROXY protocol v2 TLV type used to describe the client's identity.
+///
+/// This is in the range of types reserved for application-specific data.
+pub const PP2_TYPE_CLIENT_IDENTITY: u8 = 0xE0;
+
+// === impl Config ===
+
+impl Config {
+    pub fn mode(&self, port: u16) -> Option<Mode> {
+        if self.required_ports.iter().any(|r| r.contains(&port)) {
+            return Some(Mode::Strict);
+        }
+        if self.allowed_ports.iter().any(|r| r.contains(&port)) {
+            return Some(Mode::Optional);
+        }
+        None
+    }
+
+    /// Returns true if headers may be read from the given client.
+    pub fn is_trusted(&self, client: IpAddr) -> bool {
+        self.trusted_networks.iter().any(|n| n.contains(&client))
+    }
+}
+
+// === impl Header ===
+
+impl Header {
+    /// Reads a PROXY protocol header from the provided stream.
+    ///
+    /// Returns `None` if the stream does not begin with a PROXY protocol
+    /// header. Any bytes read beyond the header remain in `buf`.
+    pub async fn read(
+        io: &mut (impl io::AsyncRead + Unpin),
+        buf: &mut BytesMut,
+    ) -> io::Result<Option<Self>> {
+        loop {
+            match decode(buf)? {
+                Decoded::Header(hdr) => return Ok(Some(hdr)),
+                Decoded::Absent => return Ok(None),
+                Decoded::Incomplete => {}
+            }
+
+            trace!(buf.len = buf.len(), "Reading PROXY protocol header");
+            if io.read_buf(buf).await? == 0 {
+                if buf.starts_with(V2_SIGNATURE) || buf.starts_with(V1_PREFIX) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Full PROXY protocol header not provided",
+                    ));
+                }
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Encodes a PROXY protocol v2 header with the provided TLVs.
+    pub fn encode_v2(&self, tlvs: &[(u8, &[u8])], buf: &mut BytesMut) -> io::Result<()> {
+        let (command, family, mut addrs) = match *self {
+            Header::Local => (0x0, 0x00, BytesMut::new()),
+            Header::Proxied {
+                client: Remote(ClientAddr(client)),
+                destination,
+            } => {
+                let mut addrs = BytesMut::with_capacity(36);
+                let family = match (client.ip(), destination.ip()) {
+                    (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                        addrs.put_slice(&src.octets());
+                        addrs.put_slice(&dst.octets());
+                        0x11
+                    }
+                    // Addresses must be of the same family, so IPv4 addresses
+                    // are mapped to IPv6 when the other address is IPv6.
+                    (src, dst) => {
+                        addrs.put_slice(&to_ipv6(src).octets());
+                        addrs.put_slice(&to_ipv6(dst).octets());
+                        0x21
+                    }
+                };
+                addrs.put_u16(client.port());
+                addrs.put_u16(destination.port());
+                (0x1, family, addrs)
+            }
+        };
+
+        for (typ, value) in tlvs {
+            let len = u16::try_from(value.len())
+                .map_err(|_| invalid_input("PROXY protocol TLV exceeds maximum length"))?;
+            addrs.put_u8(*typ);
+            addrs.put_u16(len);
+            addrs.put_slice(value);
+        }
+        let len = u16::try_from(addrs.len())
+            .map_err(|_| invalid_input("PROXY protocol header exceeds maximum length"))?;
+
+        buf.reserve(V2_HEADER_LEN + addrs.len());
+        buf.put_slice(V2_SIGNATURE);
+        buf.put_u8(0x20 | command);
+        buf.put_u8(family);
+        buf.put_u16(len);
+        buf.put_slice(&addrs);
+        Ok(())
+    }
+}
+
+fn to_ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+fn decode(buf: &mut BytesMut) -> io::Result<Decoded> {
+    if is_prefix(buf, V2_SIGNATURE) {
+        if buf.len() < V2_HEADER_LEN {
+            return Ok(Decoded::Incomplete);
+        }
+        let len = V2_HEADER_LEN + u16::from_be_bytes([buf[14], buf[15]]) as usize;
+        if buf.len() < len {
+            buf.reserve(len - buf.len());
+            return Ok(Decoded::Incomplete);
+        }
+        let hdr = buf.split_to(len);
+        return decode_v2(&hdr).map(Decoded::Header);
+    }
+
+    if is_prefix(buf, V1_PREFIX) {
+        return match buf.windows(2).position(|w| w == b"\r\n") {
+            Some(len) => {
+                let line = buf.split_to(len + 2);
+                decode_v1(&line[..len]).map(Decoded::Header)
+            }
+            None if buf.len() < V1_MAX_LEN => Ok(Decoded::Incomplete),
+            None => Err(invalid("PROXY protocol v1 header exceeds maximum length")),
+        };
+    }
+
+    Ok(Decoded::Absent)
+}
+
+/// Returns true if `buf` is a prefix of `sig` or begins with `sig`.
+fn is_prefix(buf: &[u8], sig: &[u8]) -> bool {
+    let len = buf.len().min(sig.len());
+    buf[..len] == sig[..len]
+}
+
+fn decode_v1(line: &[u8]) -> io::Result<Header> {
+    let line =
+        std::str::from_utf8(line).map_err(|_| invalid("invalid PROXY protocol v1 header"))?;
+    let mut parts = line.split(' ').skip(1);
+    match parts.next() {
+        // The remainder of the line must be ignored.
+        Some("UNKNOWN") => return Ok(Header::Local),
+        Some("TCP4") | Some("TCP6") => {}
+        _ => return Err(invalid("unsupported PROXY protocol v1 address family")),
+    }
+
+    let src_ip: IpAddr = parse_v1_part(parts.next())?;
+    let dst_ip: IpAddr = parse_v1_part(parts.next())?;
+    let src_port: u16 = parse_v1_part(parts.next())?;
+    let dst_port: u16 = parse_v1_part(parts.next())?;
+    if parts.next().is_some() {
+        return Err(invalid("invalid PROXY protocol v1 header"));
+    }
+
+    Ok(Header::Proxied {
+        client: Remote(ClientAddr(SocketAddr::new(src_ip, src_port))),
+        destination: SocketAddr::new(dst_ip, dst_port),
+    })
+}
+
+fn parse_v1_part<T: FromStr>(part: Option<&str>) -> io::Result<T> {
+    part.and_then(|p| p.parse().ok())
+        .ok_or_else(|| invalid("invalid PROXY protocol v1 address"))
+}
+
+fn decode_v2(hdr: &[u8]) -> io::Result<Header> {
+    let version = hdr[12] >> 4;
+    if version != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+
+    match hdr[12] & 0x0f {
+        // LOCAL
+        0x0 => return Ok(Header::Local),
+        // PROXY
+        0x1 => {}
+        _ => return Err(invalid("unsupported PROXY protocol v2 command")),
+    }
+
+    let mut addrs = &hdr[V2_HEADER_LEN..];
+    let (src_ip, dst_ip) = match hdr[13] {
+        // TCP over IPv4
+        0x11 if addrs.len() >= 12 => {
+            let src = Ipv4Addr::from(addrs.get_u32());
+            let dst = Ipv4Addr::from(addrs.get_u32());
+            (IpAddr::from(src), IpAddr::from(dst))
+        }
+        // TCP over IPv6
+        0x21 if addrs.len() >= 36 => {
+            let src = Ipv6Addr::from(addrs.get_u128());
+            let dst = Ipv6Addr::from(addrs.get_u128());
+            (IpAddr::from(src), IpAddr::from(dst))
+        }
+        0x11 | 0x21 => return Err(invalid("invalid PROXY protocol v2 address")),
+        // Other address families and transports are not proxied by this
+        // proxy, so the socket's addresses apply.
+        _ => return Ok(Header::Local),
+    };
+    let src_port = addrs.get_u16();
+    let dst_port = addrs.get_u16();
+    // Any remaining bytes describe TLVs, which are ignored.
+
+    Ok(Header::Proxied {
+        client: Remote(ClientAddr(SocketAddr::new(src_ip, src_port))),
+        destination: SocketAddr::new(dst_ip, dst_port),
+    })
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn invalid_input(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+// === impl Addrs ===
+
+impl<A> Param<Option<Header>> for Addrs<A> {
+    fn param(&self) -> Option<Header> {
+        self.header
+    }
+}
+
+impl<A> Param<Remote<ClientAddr>> for Addrs<A>
+where
+    A: Param<Remote<ClientAddr>>,
+{
+    fn param(&self) -> Remote<ClientAddr> {
+        match self.header {
+            Some(Header::Proxied { client, .. }) => client,
+            _ => self.inner.param(),
+        }
+    }
+}
+
+/// The socket's original destination address is used regardless of the
+/// header's destination.
+impl<A> Param<OrigDstAddr> for Addrs<A>
+where
+    A: Param<OrigDstAddr>,
+{
+    fn param(&self) -> OrigDstAddr {
+        self.inner.param()
+    }
+}
+
+impl<A> Param<Local<ServerAddr>> for Addrs<A>
+where
+    A: Param<Local<ServerAddr>>,
+{
+    fn param(&self) -> Local<ServerAddr> {
+        self.inner.param()
+    }
+}
+
+// === impl NewProxyProtocol ===
+
+impl<N> NewProxyProtocol<N> {
+    pub fn layer(config: Config) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            config: config.clone(),
+        })
+    }
+}
+
+impl<T, N> NewService<T> for NewProxyProtocol<N>
+where
+    T: Param<OrigDstAddr> + Param<Remote<ClientAddr>>,
+    N: Clone,
+{
+    type Service = ProxyProtocol<T, N>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let OrigDstAddr(addr) = target.param();
+        let Remote(ClientAddr(client)) = target.param();
+        ProxyProtocol {
+            mode: self.config.mode(addr.port()),
+            trusted: self.config.is_trusted(client.ip()),
+            timeout: self.config.timeout,
+            target,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+// === impl ProxyProtocol ===
+
+impl<T, I, N, S> Service<I> for ProxyProtocol<T, N>
+where
+    T: Clone + Send + 'static,
+    I: io::AsyncRead + Send + Unpin + 'static,
+    N: NewService<Addrs<T>, Service = S> + Clone + Send + 'static,
+    S: Service<ProxyProtocolIo<I>> + Send,
+    S::Error: Into<Error>,
+    S::Future: Send,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut io: I) -> Self::Future {
+        let mode = self.mode;
+        let trusted = self.trusted;
+        let timeout = self.timeout;
+        let target = self.target.clone();
+        let inner = self.inner.clone();
+        Box::pin(async move {
+            let mode = match mode {
+                Some(mode) if trusted => mode,
+                Some(Mode::Strict) => {
+                    debug!("Client is not trusted to send a PROXY protocol header");
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        "Client is not trusted to send a PROXY protocol header",
+                    )
+                    .into());
+                }
+                // Headers are not read from untrusted clients on ports that
+                // allow them, since a client could otherwise misrepresent its
+                // address.
+                Some(Mode::Optional) | None => {
+                    let svc = inner.new_service(Addrs {
+                        inner: target,
+                        header: None,
+                    });
+                    return svc
+                        .oneshot(io::EitherIo::Left(io))
+                        .await
+                        .map_err(Into::into);
+                }
+            };
+
+            let mut buf = BytesMut::with_capacity(BUFFER_CAPACITY);
+            let header = match time::timeout(timeout, Header::read(&mut io, &mut buf)).await {
+                Ok(res) => res?,
+                Err(_) if mode == Mode::Optional => {
+                    debug!("PROXY protocol header timed out");
+                    None
+                }
+                Err(_) => {
+                    debug!("PROXY protocol header timed out");
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "Reading a PROXY protocol header timed out",
+                    )
+                    .into());
+                }
+            };
+            match header {
+                Some(ref header) => debug!(?header, "Read PROXY protocol header"),
+                None if mode == Mode::Optional => debug!("No PROXY protocol header read"),
+                None => {
+                    debug!("No PROXY protocol header read");
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Connection did not include a PROXY protocol header",
+                    )
+                    .into());
+                }
+            }
+
+            let svc = inner.new_service(Addrs {
+                inner: target,
+                header,
+            });
+            svc.oneshot(io::EitherIo::Right(io::PrefixedIo::new(buf.freeze(), io)))
+                .await
+                .map_err(Into::into)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug)]
+    struct Target {
+        client: SocketAddr,
+        orig_dst: SocketAddr,
+    }
+
+    impl Target {
+        fn new(client: impl Into<SocketAddr>, orig_dst: impl Into<SocketAddr>) -> Self {
+            Self {
+                client: client.into(),
+                orig_dst: orig_dst.into(),
+            }
+        }
+    }
+
+    impl Param<OrigDstAddr> for Target {
+        fn param(&self) -> OrigDstAddr {
+            OrigDstAddr(self.orig_dst)
+        }
+    }
+
+    impl Param<Remote<ClientAddr>> for Target {
+        fn param(&self) -> Remote<ClientAddr> {
+            Remote(ClientAddr(self.client))
+        }
+    }
+
+    /// Accepts a connection that sends `bytes`, returning the addresses with
+    /// which it is handled and the bytes read after any header.
+    async fn accept(
+        config: &Config,
+        target: Target,
+        bytes: &[u8],
+    ) -> Result<(Remote<ClientAddr>, OrigDstAddr, Vec<u8>)> {
+        let new = NewProxyProtocol {
+            config: config.clone(),
+            inner: |addrs: Addrs<Target>| {
+                linkerd_stack::service_fn(move |mut io: ProxyProtocolIo<tokio_test::io::Mock>| {
+                    let client: Remote<ClientAddr> = addrs.param();
+                    let orig_dst: OrigDstAddr = addrs.param();
+                    async move {
+                        let mut buf = Vec::new();
+                        io.read_to_end(&mut buf).await?;
+                        Ok::<_, io::Error>((client, orig_dst, buf))
+                    }
+                })
+            },
+        };
+        let mut io = tokio_test::io::Builder::new();
+        if !bytes.is_empty() {
+            io.read(bytes);
+        }
+        new.new_service(target).oneshot(io.build()).await
+    }
+
+    fn decode_all(bytes: &[u8]) -> io::Result<(Option<Header>, BytesMut)> {
+        let mut buf = BytesMut::from(bytes);
+        match decode(&mut buf)? {
+            Decoded::Header(hdr) => Ok((Some(hdr), buf)),
+            Decoded::Absent => Ok((None, buf)),
+            Decoded::Incomplete => panic!("header must not be incomplete"),
+        }
+    }
+
+    fn proxied(client: &str, destination: &str) -> Header {
+        Header::Proxied {
+            client: Remote(ClientAddr(client.parse().unwrap())),
+            destination: destination.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn v1_tcp4() {
+        let (hdr, rest) =
+            decode_all(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nGET / HTTP/1.1\r\n")
+                .expect("header must decode");
+        assert_eq!(hdr, Some(proxied("192.0.2.1:56324", "198.51.100.1:443")));
+        assert_eq!(&rest[..], b"GET / HTTP/1.1\r\n");
+    }
+
+    #[test]
+    fn v1_tcp6() {
+        let (hdr, rest) = decode_all(b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 443\r\n")
+            .expect("header must decode");
+        assert_eq!(
+            hdr,
+            Some(proxied("[2001:db8::1]:56324", "[2001:db8::2]:443"))
+        );
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn v1_unknown() {
+        let (hdr, _) = decode_all(b"PROXY UNKNOWN ffff::1 ffff::2 1 2\r\n").unwrap();
+        assert_eq!(hdr, Some(Header::Local));
+    }
+
+    #[test]
+    fn v1_invalid() {
+        assert!(decode_all(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324\r\n").is_err());
+        assert!(decode_all(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 99999\r\n").is_err());
+        assert!(decode_all(b"PROXY UDP4 192.0.2.1 198.51.100.1 56324 443\r\n").is_err());
+        assert!(decode_all(&V1_PREFIX.repeat(20)).is_err());
+    }
+
+    #[test]
+    fn v2_tcp4() {
+        let mut bytes = V2_SIGNATURE.to_vec();
+        bytes.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        bytes.extend_from_slice(&[192, 0, 2, 1, 198, 51, 100, 1]);
+        bytes.extend_from_slice(&56324u16.to_be_bytes());
+        bytes.extend_from_slice(&443u16.to_be_bytes());
+        bytes.extend_from_slice(b"hello");
+
+        let (hdr, rest) = decode_all(&bytes).expect("header must decode");
+        assert_eq!(hdr, Some(proxied("192.0.2.1:56324", "198.51.100.1:443")));
+        assert_eq!(&rest[..], b"hello");
+    }
+
+    #[test]
+    fn v2_tcp6_with_tlvs() {
+        let src: Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let dst: Ipv6Addr = "2001:db8::2".parse().unwrap();
+        let mut bytes = V2_SIGNATURE.to_vec();
+        bytes.extend_from_slice(&[0x21, 0x21, 0, 36 + 4]);
+        bytes.extend_from_slice(&src.octets());
+        bytes.extend_from_slice(&dst.octets());
+        bytes.extend_from_slice(&56324u16.to_be_bytes());
+        bytes.extend_from_slice(&443u16.to_be_bytes());
+        // A NOOP TLV.
+        bytes.extend_from_slice(&[0x04, 0, 1, 0]);
+
+        let (hdr, rest) = decode_all(&bytes).expect("header must decode");
+        assert_eq!(
+            hdr,
+            Some(proxied("[2001:db8::1]:56324", "[2001:db8::2]:443"))
+        );
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn v2_local() {
+        let mut bytes = V2_SIGNATURE.to_vec();
+        bytes.extend_from_slice(&[0x20, 0x00, 0, 0]);
+        let (hdr, _) = decode_all(&bytes).expect("header must decode");
+        assert_eq!(hdr, Some(Header::Local));
+    }
+
+    #[test]
+    fn v2_incomplete() {
+        let mut bytes = V2_SIGNATURE.to_vec();
+        bytes.extend_from_slice(&[0x21, 0x11, 0, 12, 192, 0, 2]);
+        assert!(matches!(
+            decode(&mut BytesMut::from(&bytes[..])).unwrap(),
+            Decoded::Incomplete
+        ));
+        assert!(matches!(
+            decode(&mut BytesMut::from(&V2_SIGNATURE[..4])).unwrap(),
+            Decoded::Incomplete
+        ));
+    }
+
+    #[test]
+    fn v2_roundtrip() {
+        for hdr in [
+            Header::Local,
+            proxied("192.0.2.1:56324", "198.51.100.1:443"),
+            proxied("[2001:db8::1]:56324", "[2001:db8::2]:443"),
+        ] {
+            let mut buf = BytesMut::new();
+            hdr.encode_v2(
+                &[(
+                    PP2_TYPE_CLIENT_IDENTITY,
+                    b"foo.ns.serviceaccount.identity.linkerd.cluster.local",
+                )],
+                &mut buf,
+            )
+            .expect("header must encode");
+            buf.extend_from_slice(b"hello");
+
+            let (decoded, rest) = decode_all(&buf).expect("header must decode");
+            assert_eq!(decoded, Some(hdr));
+            assert_eq!(&rest[..], b"hello");
+        }
+
+        // Mixed address families are encoded as IPv6.
+        let mut buf = BytesMut::new();
+        proxied("192.0.2.1:56324", "[2001:db8::2]:443")
+            .encode_v2(&[], &mut buf)
+            .expect("header must encode");
+        let (decoded, _) = decode_all(&buf).expect("header must decode");
+        assert_eq!(
+            decoded,
+            Some(proxied("[::ffff:192.0.2.1]:56324", "[2001:db8::2]:443"))
+        );
+    }
+
+    #[test]
+    fn config() {
+        let config = Config {
+            required_ports: vec![4143..=4143, 8000..=8999],
+            allowed_ports: vec![80..=80, 8080..=8080],
+            trusted_networks: vec!["10.0.0.0/8".parse().unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(config.mode(4143), Some(Mode::Strict));
+        assert_eq!(config.mode(8500), Some(Mode::Strict));
+        // Required ports take precedence over allowed ports.
+        assert_eq!(config.mode(8080), Some(Mode::Strict));
+        assert_eq!(config.mode(80), Some(Mode::Optional));
+        assert_eq!(config.mode(443), None);
+
+        assert!(config.is_trusted([10, 1, 2, 3].into()));
+        assert!(!config.is_trusted([192, 168, 1, 1].into()));
+        // No clients are trusted by default.
+        assert!(!Config::default().is_trusted([192, 168, 1, 1].into()));
+    }
+
+    #[test]
+    fn proxied_addrs() {
+        let target = Target::new(([10, 0, 0, 2], 5432), ([10, 0, 0, 1], 4143));
+
+        let addrs = Addrs {
+            inner: target.clone(),
+            header: Some(proxied("192.0.2.1:56324", "198.51.100.1:443")),
+        };
+        let Remote(ClientAddr(client)) = addrs.param();
+        assert_eq!(client, "192.0.2.1:56324".parse().unwrap());
+        // The header's destination never replaces the socket's original
+        // destination address.
+        let OrigDstAddr(dst) = addrs.param();
+        assert_eq!(dst, ([10, 0, 0, 1], 4143).into());
+
+        let addrs = Addrs {
+            inner: target,
+            header: Some(Header::Local),
+        };
+        let Remote(ClientAddr(client)) = addrs.param();
+        assert_eq!(client, ([10, 0, 0, 2], 5432).into());
+        let OrigDstAddr(dst) = addrs.param();
+        assert_eq!(dst, ([10, 0, 0, 1], 4143).into());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn trusted_clients() {
+        let config = Config {
+            required_ports: vec![4143..=4143],
+            allowed_ports: vec![80..=80],
+            trusted_networks: vec!["10.0.0.0/8".parse().unwrap()],
+            timeout: time::Duration::from_secs(10),
+        };
+        let header = b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nhello";
+
+        for port in [80, 4143] {
+            let target = Target::new(([10, 0, 0, 2], 5432), ([10, 0, 0, 1], port));
+            let (Remote(ClientAddr(client)), OrigDstAddr(dst), rest) =
+                accept(&config, target, header)
+                    .await
+                    .expect("connection must be accepted");
+            assert_eq!(client, "192.0.2.1:56324".parse().unwrap());
+            assert_eq!(dst, ([10, 0, 0, 1], port).into());
+            assert_eq!(rest, b"hello");
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn untrusted_clients() {
+        let config = Config {
+            required_ports: vec![4143..=4143],
+            allowed_ports: vec![80..=80],
+            trusted_networks: vec!["10.0.0.0/8".parse().unwrap()],
+            timeout: time::Duration::from_secs(10),
+        };
+        let header = b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nhello";
+
+        // Headers from untrusted clients are not read on allowed ports, so
+        // the client's socket address is used and the header is passed
+        // through unmodified.
+        let target = Target::new(([192, 168, 1, 1], 5432), ([10, 0, 0, 1], 80));
+        let (Remote(ClientAddr(client)), OrigDstAddr(dst), rest) = accept(&config, target, header)
+            .await
+            .expect("connection must be accepted");
+        assert_eq!(client, ([192, 168, 1, 1], 5432).into());
+        assert_eq!(dst, ([10, 0, 0, 1], 80).into());
+        assert_eq!(rest, header);
+
+        // Untrusted clients are refused on required ports.
+        let target = Target::new(([192, 168, 1, 1], 5432), ([10, 0, 0, 1], 4143));
+        assert!(accept(&config, target, &[]).await.is_err());
+
+        // No clients are trusted when no networks are configured.
+        let config = Config {
+            trusted_networks: vec![],
+            ..config
+        };
+        let target = Target::new(([10, 0, 0, 2], 5432), ([10, 0, 0, 1], 4143));
+        assert!(accept(&config, target, &[]).await.is_err());
+    }
+
+    #[test]
+    fn absent() {
+        let (hdr, rest) = decode_all(b"GET / HTTP/1.1\r\n").unwrap();
+        assert_eq!(hdr, None);
+        assert_eq!(&rest[..], b"GET / HTTP/1.1\r\n");
+
+        let (hdr, _) = decode_all(b"\x16\x03\x01\x02\x00\x01").unwrap();
+        assert_eq!(hdr, None);
+    }
+}
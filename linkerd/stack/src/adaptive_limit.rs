@@ -0,0 +1,425 @@
+//! An adaptive concurrency limit.
+//!
+//! The limit is adjusted with an additive-increase/multiplicative-decrease
+//! (AIMD) policy driven by response latency: when a response's latency exceeds
+//! the baseline latency by more than the configured tolerance, the limit is
+//! decreased multiplicatively (at most once per round-trip); otherwise, while
+//! the limit is being utilized, it is increased by roughly one request per
+//! round-trip.
+//!
+//! The baseline tracks the lowest observed latency immediately and decays
+//! slowly towards higher latencies, so that a sustained change in latency
+//! eventually becomes the new baseline.
+
+use crate::{layer, ExtractParam, LoadShedError, NewService, Service};
+use linkerd_error::Error;
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Weak},
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AdaptiveLimitConfig {
+    /// The limit before any latencies have been observed.
+    pub initial: usize,
+
+    /// The lowest value to which the limit may be decreased.
+    pub min: usize,
+
+    /// The highest value to which the limit may be increased.
+    pub max: usize,
+
+    /// The ratio of a response's latency to the baseline latency above which
+    /// the limit is decreased.
+    pub tolerance: f64,
+
+    /// The factor by which the limit is multiplied when it is decreased.
+    pub backoff: f64,
+
+    /// The time over which the baseline latency decays towards higher
+    /// observed latencies.
+    pub baseline_decay: Duration,
+}
+
+/// A shared, adaptive concurrency limit.
+///
+/// Clones share the same limit, so a single `AdaptiveLimit` may be used to
+/// limit all of the services for a given target.
+#[derive(Clone, Debug)]
+pub struct AdaptiveLimit(Arc<Mutex<State>>);
+
+/// A reference to an [`AdaptiveLimit`] that does not keep it alive.
+#[derive(Clone, Debug)]
+pub struct WeakAdaptiveLimit(Weak<Mutex<State>>);
+
+/// Fails requests with a [`LoadShedError`] when the number of in-flight
+/// requests exceeds an [`AdaptiveLimit`].
+///
+/// Requests are considered in-flight until their response future completes.
+#[derive(Clone, Debug)]
+pub struct AdaptiveConcurrencyLimit<S> {
+    inner: S,
+    limit: AdaptiveLimit,
+}
+
+/// Builds [`AdaptiveConcurrencyLimit`] services with an [`AdaptiveLimit`]
+/// extracted from each target.
+#[derive(Clone, Debug)]
+pub struct NewAdaptiveConcurrencyLimit<X, N> {
+    inner: N,
+    extract: X,
+}
+
+#[pin_project(project = ResponseFutureProj)]
+#[derive(Debug)]
+pub enum ResponseFuture<F> {
+    Limited {
+        #[pin]
+        inner: F,
+        in_flight: Option<InFlight>,
+    },
+    Shed,
+}
+
+#[derive(Debug)]
+pub struct InFlight {
+    limit: AdaptiveLimit,
+    start: Instant,
+}
+
+#[derive(Debug)]
+struct State {
+    config: AdaptiveLimitConfig,
+    limit: f64,
+    in_flight: usize,
+    baseline: Option<(f64, Instant)>,
+    last_decrease: Option<Instant>,
+}
+
+// === impl AdaptiveLimitConfig ===
+
+impl Default for AdaptiveLimitConfig {
+    fn default() -> Self {
+        Self {
+            initial: 100,
+            min: 1,
+            max: 10_000,
+            tolerance: 2.0,
+            backoff: 0.9,
+            baseline_decay: Duration::from_secs(10),
+        }
+    }
+}
+
+// === impl AdaptiveLimit ===
+
+impl AdaptiveLimit {
+    pub fn new(config: AdaptiveLimitConfig) -> Self {
+        let min = config.min.max(1);
+        let max = config.max.max(min);
+        let config = AdaptiveLimitConfig { min, max, ..config };
+        Self(Arc::new(Mutex::new(State {
+            limit: config.initial.clamp(min, max) as f64,
+            in_flight: 0,
+            baseline: None,
+            last_decrease: None,
+            config,
+        })))
+    }
+
+    /// Returns the current concurrency limit.
+    pub fn limit(&self) -> usize {
+        self.0.lock().limit as usize
+    }
+
+    /// Returns the number of requests currently in flight.
+    pub fn in_flight(&self) -> usize {
+        self.0.lock().in_flight
+    }
+
+    /// Returns a reference to this limit that does not keep it alive.
+    pub fn downgrade(&self) -> WeakAdaptiveLimit {
+        WeakAdaptiveLimit(Arc::downgrade(&self.0))
+    }
+
+    fn try_acquire(&self) -> Option<InFlight> {
+        let mut state = self.0.lock();
+        if state.in_flight >= state.limit as usize {
+            return None;
+        }
+        state.in_flight += 1;
+        Some(InFlight {
+            limit: self.clone(),
+            start: Instant::now(),
+        })
+    }
+}
+
+// === impl WeakAdaptiveLimit ===
+
+impl WeakAdaptiveLimit {
+    /// Returns the limit, if it has not been dropped.
+    pub fn upgrade(&self) -> Option<AdaptiveLimit> {
+        self.0.upgrade().map(AdaptiveLimit)
+    }
+}
+
+// === impl State ===
+
+impl State {
+    fn record(&mut self, start: Instant, now: Instant) {
+        let latency = now.saturating_duration_since(start).as_secs_f64();
+
+        let baseline = match self.baseline {
+            Some((baseline, updated)) if latency >= baseline => {
+                // Decay the baseline towards the observed latency, weighted by
+                // the time since it was last updated.
+                let elapsed = now.saturating_duration_since(updated).as_secs_f64();
+                let decay = self.config.baseline_decay.as_secs_f64();
+                let weight = if decay > 0.0 {
+                    (-elapsed / decay).exp()
+                } else {
+                    0.0
+                };
+                self.baseline = Some((baseline * weight + latency * (1.0 - weight), now));
+                baseline
+            }
+            _ => {
+                self.baseline = Some((latency, now));
+                latency
+            }
+        };
+
+        let min = self.config.min as f64;
+        let max = self.config.max as f64;
+        if latency > baseline * self.config.tolerance {
+            // Only decrease the limit once per round-trip so that responses
+            // to requests issued before the last decrease don't compound it.
+            if self.last_decrease.map(|t| start > t).unwrap_or(true) {
+                self.limit = (self.limit * self.config.backoff).floor().max(min);
+                self.last_decrease = Some(now);
+                tracing::debug!(limit = self.limit, latency, baseline, "Decreased limit");
+            }
+        } else if self.in_flight * 2 >= self.limit as usize {
+            // Only increase the limit while it is being utilized.
+            self.limit = (self.limit + 1.0 / self.limit).min(max);
+            tracing::trace!(limit = self.limit, latency, baseline, "Increased limit");
+        }
+    }
+}
+
+// === impl InFlight ===
+
+impl InFlight {
+    fn complete(self) {
+        self.limit.0.lock().record(self.start, Instant::now());
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.limit.0.lock().in_flight -= 1;
+    }
+}
+
+// === impl AdaptiveConcurrencyLimit ===
+
+impl<S> AdaptiveConcurrencyLimit<S> {
+    pub fn new(limit: AdaptiveLimit, inner: S) -> Self {
+        Self { inner, limit }
+    }
+
+    pub fn layer(limit: AdaptiveLimit) -> impl layer::Layer<S, Service = Self> + Clone {
+        layer::mk(move |inner| Self::new(limit.clone(), inner))
+    }
+}
+
+impl<S, Req> Service<Req> for AdaptiveConcurrencyLimit<S>
+where
+    S: Service<Req>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = ResponseFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        match self.limit.try_acquire() {
+            Some(in_flight) => ResponseFuture::Limited {
+                inner: self.inner.call(req),
+                in_flight: Some(in_flight),
+            },
+            None => {
+                tracing::debug!(limit = self.limit.limit(), "Shedding load");
+                ResponseFuture::Shed
+            }
+        }
+    }
+}
+
+// === impl NewAdaptiveConcurrencyLimit ===
+
+impl<X: Clone, N> NewAdaptiveConcurrencyLimit<X, N> {
+    pub fn new(extract: X, inner: N) -> Self {
+        Self { inner, extract }
+    }
+
+    pub fn layer_via(extract: X) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self::new(extract.clone(), inner))
+    }
+}
+
+impl<N> NewAdaptiveConcurrencyLimit<(), N> {
+    pub fn layer() -> impl layer::Layer<N, Service = Self> + Clone {
+        Self::layer_via(())
+    }
+}
+
+impl<T, X, N> NewService<T> for NewAdaptiveConcurrencyLimit<X, N>
+where
+    X: ExtractParam<AdaptiveLimit, T>,
+    N: NewService<T>,
+{
+    type Service = AdaptiveConcurrencyLimit<N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let limit = self.extract.extract_param(&target);
+        let inner = self.inner.new_service(target);
+        AdaptiveConcurrencyLimit::new(limit, inner)
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, T, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<Error>,
+{
+    type Output = Result<T, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let (inner, in_flight) = match self.project() {
+            ResponseFutureProj::Limited { inner, in_flight } => (inner, in_flight),
+            ResponseFutureProj::Shed => return Poll::Ready(Err(LoadShedError(()).into())),
+        };
+
+        let res = futures::ready!(inner.poll(cx));
+        if let Some(in_flight) = in_flight.take() {
+            // Only successful responses inform the limit, since errors may
+            // be returned without incurring the latency of a response.
+            if res.is_ok() {
+                in_flight.complete();
+            }
+        }
+        Poll::Ready(res.map_err(Into::into))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_test::assert_ready_ok;
+    use tower_test::mock::{self, Spawn};
+
+    fn state(initial: usize) -> State {
+        let config = AdaptiveLimitConfig {
+            initial,
+            ..Default::default()
+        };
+        Arc::try_unwrap(AdaptiveLimit::new(config).0)
+            .expect("must be unique")
+            .into_inner()
+    }
+
+    #[test]
+    fn increases_when_utilized() {
+        let mut state = state(10);
+        let start = Instant::now();
+
+        // The limit is not increased while it is underutilized.
+        state.in_flight = 1;
+        state.record(start, start + Duration::from_millis(10));
+        assert_eq!(state.limit as usize, 10);
+
+        state.in_flight = 10;
+        for _ in 0..11 {
+            state.record(start, start + Duration::from_millis(10));
+        }
+        assert_eq!(state.limit as usize, 11);
+    }
+
+    #[test]
+    fn decreases_once_per_round_trip() {
+        let mut state = state(100);
+        let start = Instant::now();
+        state.in_flight = 100;
+        state.record(start, start + Duration::from_millis(10));
+
+        // Responses to requests issued before the decrease don't compound it.
+        let now = start + Duration::from_millis(100);
+        state.record(start, now);
+        assert_eq!(state.limit as usize, 90);
+        state.record(start, now);
+        assert_eq!(state.limit as usize, 90);
+
+        let start = now + Duration::from_millis(1);
+        state.record(start, start + Duration::from_millis(100));
+        assert_eq!(state.limit as usize, 81);
+    }
+
+    #[test]
+    fn baseline_decays() {
+        let mut state = state(10);
+        let start = Instant::now();
+        state.record(start, start + Duration::from_millis(10));
+
+        // A lower latency becomes the baseline immediately.
+        let start = start + Duration::from_secs(1);
+        state.record(start, start + Duration::from_millis(5));
+        assert_eq!(state.baseline.unwrap().0, 0.005);
+
+        // A higher latency is only incorporated gradually.
+        let start = start + Duration::from_secs(10);
+        state.record(start, start + Duration::from_millis(15));
+        let (baseline, _) = state.baseline.unwrap();
+        assert!(baseline > 0.005 && baseline < 0.015, "{baseline}");
+    }
+
+    #[tokio::test]
+    async fn sheds_load() {
+        let _trace = linkerd_tracing::test::trace_init();
+        let (service, mut handle) = mock::pair::<(), ()>();
+        let limit = AdaptiveLimit::new(AdaptiveLimitConfig {
+            initial: 1,
+            ..Default::default()
+        });
+        let mut service = Spawn::new(AdaptiveConcurrencyLimit::new(limit.clone(), service));
+
+        handle.allow(2);
+        assert_ready_ok!(service.poll_ready());
+        let call0 = service.call(());
+        assert_eq!(limit.in_flight(), 1);
+
+        // The second request exceeds the limit.
+        assert_ready_ok!(service.poll_ready());
+        let err = service.call(()).await.expect_err("should shed load");
+        assert!(err.is::<LoadShedError>());
+
+        let (_, rsp) = handle.next_request().await.expect("must receive request");
+        rsp.send_response(());
+        call0.await.expect("should succeed");
+        assert_eq!(limit.in_flight(), 0);
+    }
+}
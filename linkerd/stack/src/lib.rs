@@ -3,6 +3,7 @@
 #![deny(rust_2018_idioms, clippy::disallowed_methods, clippy::disallowed_types)]
 #![forbid(unsafe_code)]
 
+mod adaptive_limit;
 mod arc_new_service;
 mod box_future;
 mod box_service;
@@ -31,6 +32,10 @@ mod unwrap_or;
 mod watch;
 
 pub use self::{
+    adaptive_limit::{
+        AdaptiveConcurrencyLimit, AdaptiveLimit, AdaptiveLimitConfig, NewAdaptiveConcurrencyLimit,
+        WeakAdaptiveLimit,
+    },
     arc_new_service::ArcNewService,
     box_future::BoxFuture,
     box_service::{BoxService, BoxServiceLayer},
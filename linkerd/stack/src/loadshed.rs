@@ -15,7 +15,7 @@ pub struct LoadShed<S> {
 /// An error representing that a service is shedding load.
 #[derive(Debug, Error)]
 #[error("service unavailable")]
-pub struct LoadShedError(());
+pub struct LoadShedError(pub(crate) ());
 
 // === impl LoadShed ===
 
@@ -1,5 +1,6 @@
 mod client_hello;
 
+pub use self::client_hello::{parse_sni, Incomplete};
 use crate::{NegotiatedProtocol, ServerId};
 use bytes::BytesMut;
 use futures::prelude::*;
@@ -20,8 +21,10 @@ use tokio::time::{self, Duration};
 use tracing::{debug, trace, warn};
 
 /// A newtype for remote client idenities.
+///
+/// Clients may be identified by a DNS-like name or by a SPIFFE ID.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
-pub struct ClientId(pub id::Name);
+pub struct ClientId(pub id::Id);
 
 /// Indicates a server-side connection's TLS status.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -251,20 +254,26 @@ where
 
 impl From<id::Name> for ClientId {
     fn from(n: id::Name) -> Self {
-        Self(n)
+        Self(id::Id::Dns(n))
     }
 }
 
-impl From<ClientId> for id::Name {
-    fn from(ClientId(name): ClientId) -> id::Name {
-        name
+impl From<id::SpiffeId> for ClientId {
+    fn from(id: id::SpiffeId) -> Self {
+        Self(id::Id::Spiffe(id))
+    }
+}
+
+impl From<ClientId> for id::Id {
+    fn from(ClientId(id): ClientId) -> id::Id {
+        id
     }
 }
 
 impl Deref for ClientId {
-    type Target = id::Name;
+    type Target = id::Id;
 
-    fn deref(&self) -> &id::Name {
+    fn deref(&self) -> &id::Id {
         &self.0
     }
 }
@@ -276,9 +285,9 @@ impl fmt::Display for ClientId {
 }
 
 impl FromStr for ClientId {
-    type Err = id::InvalidName;
+    type Err = id::InvalidId;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        id::Name::from_str(s).map(Self)
+        id::Id::from_str(s).map(Self)
     }
 }
 